@@ -5,13 +5,18 @@ pub mod echo;
 #[cfg(feature = "kvstore-app")]
 pub mod kvstore;
 
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+};
+
 use tendermint_proto::v0_37::abci::{
     request::Value, response, response_process_proposal, Request, RequestApplySnapshotChunk,
     RequestBeginBlock, RequestCheckTx, RequestDeliverTx, RequestEcho, RequestEndBlock, RequestInfo,
     RequestInitChain, RequestLoadSnapshotChunk, RequestOfferSnapshot, RequestPrepareProposal,
     RequestProcessProposal, RequestQuery, Response, ResponseApplySnapshotChunk, ResponseBeginBlock,
     ResponseCheckTx, ResponseCommit, ResponseDeliverTx, ResponseEcho, ResponseEndBlock,
-    ResponseFlush, ResponseInfo, ResponseInitChain, ResponseListSnapshots,
+    ResponseException, ResponseFlush, ResponseInfo, ResponseInitChain, ResponseListSnapshots,
     ResponseLoadSnapshotChunk, ResponseOfferSnapshot, ResponsePrepareProposal,
     ResponseProcessProposal, ResponseQuery,
 };
@@ -112,29 +117,9 @@ pub trait Application: Send + Clone + 'static {
     ///
     /// This method is introduced in ABCI++.
     fn prepare_proposal(&self, request: RequestPrepareProposal) -> ResponsePrepareProposal {
-        // Per the ABCI++ spec: if the size of RequestPrepareProposal.txs is
-        // greater than RequestPrepareProposal.max_tx_bytes, the Application
-        // MUST remove transactions to ensure that the
-        // RequestPrepareProposal.max_tx_bytes limit is respected by those
-        // transactions returned in ResponsePrepareProposal.txs.
-        let RequestPrepareProposal {
-            mut txs,
-            max_tx_bytes,
-            ..
-        } = request;
-        let max_tx_bytes: usize = max_tx_bytes.try_into().unwrap_or(0);
-        let mut total_tx_bytes: usize = txs
-            .iter()
-            .map(|tx| tx.len())
-            .fold(0, |acc, len| acc.saturating_add(len));
-        while total_tx_bytes > max_tx_bytes {
-            if let Some(tx) = txs.pop() {
-                total_tx_bytes = total_tx_bytes.saturating_sub(tx.len());
-            } else {
-                break;
-            }
+        ResponsePrepareProposal {
+            txs: truncate_proposal_txs(request.txs, request.max_tx_bytes),
         }
-        ResponsePrepareProposal { txs }
     }
 
     /// A stage where the application can accept or reject the proposed block.
@@ -149,6 +134,30 @@ pub trait Application: Send + Clone + 'static {
     }
 }
 
+/// The default `PrepareProposal` behavior shared by the various application
+/// traits: removes transactions off the end of the list until their total size
+/// no longer exceeds `max_tx_bytes`.
+pub(crate) fn truncate_proposal_txs<T: AsRef<[u8]>>(mut txs: Vec<T>, max_tx_bytes: i64) -> Vec<T> {
+    // Per the ABCI++ spec: if the size of RequestPrepareProposal.txs is
+    // greater than RequestPrepareProposal.max_tx_bytes, the Application
+    // MUST remove transactions to ensure that the
+    // RequestPrepareProposal.max_tx_bytes limit is respected by those
+    // transactions returned in ResponsePrepareProposal.txs.
+    let max_tx_bytes: usize = max_tx_bytes.try_into().unwrap_or(0);
+    let mut total_tx_bytes: usize = txs
+        .iter()
+        .map(|tx| tx.as_ref().len())
+        .fold(0, |acc, len| acc.saturating_add(len));
+    while total_tx_bytes > max_tx_bytes {
+        if let Some(tx) = txs.pop() {
+            total_tx_bytes = total_tx_bytes.saturating_sub(tx.as_ref().len());
+        } else {
+            break;
+        }
+    }
+    txs
+}
+
 /// Provides a mechanism for the [`Server`] to execute incoming requests while
 /// expecting the correct response types.
 pub trait RequestDispatcher {
@@ -158,11 +167,18 @@ pub trait RequestDispatcher {
 }
 
 // Implement `RequestDispatcher` for all `Application`s.
+//
+// Requests without a value, as well as panics raised by the application, are
+// answered with a `ResponseException`.
 impl<A: Application> RequestDispatcher for A {
     fn handle(&self, request: Request) -> Response {
         tracing::debug!("Incoming request: {:?}", request);
-        Response {
-            value: Some(match request.value.unwrap() {
+        let value = match request.value {
+            Some(value) => value,
+            None => return exception("empty request"),
+        };
+        catch_panic(|| Response {
+            value: Some(match value {
                 Value::Echo(req) => response::Value::Echo(self.echo(req)),
                 Value::Flush(_) => response::Value::Flush(self.flush()),
                 Value::Info(req) => response::Value::Info(self.info(req)),
@@ -190,6 +206,32 @@ impl<A: Application> RequestDispatcher for A {
                     response::Value::ProcessProposal(self.process_proposal(req))
                 },
             }),
-        }
+        })
     }
 }
+
+/// Produce a `ResponseException` carrying the given error.
+pub(crate) fn exception(error: impl Into<String>) -> Response {
+    Response {
+        value: Some(response::Value::Exception(ResponseException {
+            error: error.into(),
+        })),
+    }
+}
+
+/// Handle a request, answering it with a `ResponseException` should the
+/// handler panic.
+pub(crate) fn catch_panic<F: FnOnce() -> Response>(handle: F) -> Response {
+    panic::catch_unwind(AssertUnwindSafe(handle)).unwrap_or_else(|payload| panicked(&*payload))
+}
+
+/// Produce the `ResponseException` answering a request whose handler
+/// panicked with the given payload.
+pub(crate) fn panicked(payload: &(dyn Any + Send)) -> Response {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause");
+    exception(format!("application panicked: {message}"))
+}
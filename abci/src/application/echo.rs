@@ -1,9 +1,18 @@
 //! Trivial ABCI echo application
 
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
 use crate::Application;
+#[cfg(feature = "async")]
+use crate::AsyncApplication;
 
 /// Trivial echo application, mainly for testing purposes.
 #[derive(Clone, Default)]
 pub struct EchoApp;
 
 impl Application for EchoApp {}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncApplication for EchoApp {}
@@ -1,20 +1,53 @@
 //! In-memory key/value store ABCI application.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, VecDeque},
+    mem,
+    ops::Bound,
     sync::mpsc::{channel, Receiver, Sender},
 };
 
-use bytes::BytesMut;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tendermint::{
+    crypto::{default::Sha256, Sha256 as _},
+    merkle::{
+        self,
+        proof::{ProofOp, ProofOps},
+        MerkleHash,
+    },
+};
 use tendermint_proto::v0_37::abci::{
-    Event, EventAttribute, RequestCheckTx, RequestDeliverTx, RequestInfo, RequestQuery,
-    ResponseCheckTx, ResponseCommit, ResponseDeliverTx, ResponseInfo, ResponseQuery,
+    response_apply_snapshot_chunk, response_offer_snapshot, Event, EventAttribute,
+    RequestApplySnapshotChunk, RequestCheckTx, RequestDeliverTx, RequestInfo,
+    RequestLoadSnapshotChunk, RequestOfferSnapshot, RequestQuery, ResponseApplySnapshotChunk,
+    ResponseCheckTx, ResponseCommit, ResponseDeliverTx, ResponseInfo, ResponseListSnapshots,
+    ResponseLoadSnapshotChunk, ResponseOfferSnapshot, ResponseQuery, Snapshot,
 };
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+use crate::{Application, Error};
+
+/// The only snapshot format produced and understood by the key/value store:
+/// the store's entries, sorted by key, each encoded as a length-prefixed key
+/// followed by a length-prefixed value.
+const SNAPSHOT_FORMAT: u32 = 1;
+
+/// The default maximum size of a snapshot chunk, in bytes.
+const DEFAULT_SNAPSHOT_CHUNK_SIZE: usize = 1024 * 1024;
 
-use crate::{codec::MAX_VARINT_LENGTH, Application, Error};
+/// The number of most recent snapshots retained by the key/value store.
+const SNAPSHOT_KEEP_RECENT: usize = 2;
 
-/// In-memory, hashmap-backed key/value store ABCI application.
+/// The size of the SHA256 hash of each chunk, as listed in the snapshot's
+/// metadata.
+const CHUNK_HASH_LENGTH: usize = 32;
+
+/// In-memory key/value store ABCI application.
+///
+/// The application hash committed to by this application is the root of a
+/// simple Merkle tree, whose leaves are the store's entries sorted by key (see
+/// [`merkle::value_leaf`]). Queries with `prove` set are answered with a
+/// proof of the value against that root, as of the last commit.
 ///
 /// This structure effectively just serves as a handle to the actual key/value
 /// store - the [`KeyValueStoreDriver`].
@@ -88,6 +121,24 @@ impl KeyValueStoreApp {
         channel_recv(&result_rx)
     }
 
+    /// Attempt to retrieve the value associated with the given key as of the
+    /// last commit, along with a proof of that value against the committed
+    /// application hash if the key exists.
+    pub fn get_with_proof<K: AsRef<str>>(
+        &self,
+        key: K,
+    ) -> Result<(i64, Option<(String, ProofOps)>), Error> {
+        let (result_tx, result_rx) = channel();
+        channel_send(
+            &self.cmd_tx,
+            Command::GetWithProof {
+                key: key.as_ref().to_string(),
+                result_tx,
+            },
+        )?;
+        channel_recv(&result_rx)
+    }
+
     /// Attempt to set the value associated with the given key.
     ///
     /// Optionally returns any pre-existing value associated with the given
@@ -136,8 +187,16 @@ impl Application for KeyValueStoreApp {
             Err(e) => panic!("Failed to interpret key as UTF-8: {e}"),
         };
         debug!("Attempting to get key: {}", key);
-        match self.get(key) {
-            Ok((height, value_opt)) => match value_opt {
+        let result = if request.prove {
+            self.get_with_proof(key).map(|(height, entry)| match entry {
+                Some((value, proof_ops)) => (height, Some(value), Some(proof_ops.into())),
+                None => (height, None, None),
+            })
+        } else {
+            self.get(key).map(|(height, value)| (height, value, None))
+        };
+        match result {
+            Ok((height, value_opt, proof_ops)) => match value_opt {
                 Some(value) => ResponseQuery {
                     code: 0,
                     log: "exists".to_string(),
@@ -145,7 +204,7 @@ impl Application for KeyValueStoreApp {
                     index: 0,
                     key: request.data,
                     value: value.into_bytes().into(),
-                    proof_ops: None,
+                    proof_ops,
                     height,
                     codespace: "".to_string(),
                 },
@@ -229,27 +288,125 @@ impl Application for KeyValueStoreApp {
             retain_height: height - 1,
         }
     }
+
+    fn list_snapshots(&self) -> ResponseListSnapshots {
+        let (result_tx, result_rx) = channel();
+        channel_send(&self.cmd_tx, Command::ListSnapshots { result_tx }).unwrap();
+        ResponseListSnapshots {
+            snapshots: channel_recv(&result_rx).unwrap(),
+        }
+    }
+
+    fn offer_snapshot(&self, request: RequestOfferSnapshot) -> ResponseOfferSnapshot {
+        let snapshot = match request.snapshot {
+            Some(snapshot) => snapshot,
+            None => {
+                return ResponseOfferSnapshot {
+                    result: response_offer_snapshot::Result::Reject as i32,
+                }
+            },
+        };
+        let (result_tx, result_rx) = channel();
+        channel_send(
+            &self.cmd_tx,
+            Command::OfferSnapshot {
+                snapshot,
+                app_hash: request.app_hash,
+                result_tx,
+            },
+        )
+        .unwrap();
+        ResponseOfferSnapshot {
+            result: channel_recv(&result_rx).unwrap() as i32,
+        }
+    }
+
+    fn load_snapshot_chunk(&self, request: RequestLoadSnapshotChunk) -> ResponseLoadSnapshotChunk {
+        let (result_tx, result_rx) = channel();
+        channel_send(
+            &self.cmd_tx,
+            Command::LoadSnapshotChunk {
+                height: request.height,
+                format: request.format,
+                index: request.chunk,
+                result_tx,
+            },
+        )
+        .unwrap();
+        ResponseLoadSnapshotChunk {
+            chunk: channel_recv(&result_rx).unwrap(),
+        }
+    }
+
+    fn apply_snapshot_chunk(
+        &self,
+        request: RequestApplySnapshotChunk,
+    ) -> ResponseApplySnapshotChunk {
+        let (result_tx, result_rx) = channel();
+        channel_send(
+            &self.cmd_tx,
+            Command::ApplySnapshotChunk {
+                index: request.index,
+                chunk: request.chunk,
+                sender: request.sender,
+                result_tx,
+            },
+        )
+        .unwrap();
+        channel_recv(&result_rx).unwrap()
+    }
 }
 
 /// Manages key/value store state.
+///
+/// When configured with a snapshot interval, the driver takes a snapshot of
+/// the store every time the height reaches a multiple of that interval,
+/// retaining the most recent ones for serving to peers through state sync.
+/// Snapshots are split into chunks, the SHA256 hashes of which are listed in
+/// the snapshot's metadata, so that every chunk can be verified as it is
+/// applied.
 #[derive(Debug)]
 pub struct KeyValueStoreDriver {
-    store: HashMap<String, String>,
+    uncommitted: BTreeMap<String, String>,
+    committed: MerkleMap,
     height: i64,
     app_hash: Vec<u8>,
+    snapshot_interval: u64,
+    snapshot_chunk_size: usize,
+    snapshots: VecDeque<StoredSnapshot>,
+    restore: Option<Restore>,
     cmd_rx: Receiver<Command>,
 }
 
 impl KeyValueStoreDriver {
     fn new(cmd_rx: Receiver<Command>) -> Self {
         Self {
-            store: HashMap::new(),
+            uncommitted: BTreeMap::new(),
+            committed: MerkleMap::default(),
             height: 0,
-            app_hash: vec![0_u8; MAX_VARINT_LENGTH],
+            app_hash: MerkleMap::default().root.to_vec(),
+            snapshot_interval: 0,
+            snapshot_chunk_size: DEFAULT_SNAPSHOT_CHUNK_SIZE,
+            snapshots: VecDeque::new(),
+            restore: None,
             cmd_rx,
         }
     }
 
+    /// Take a snapshot of the store every `interval` blocks. An interval of
+    /// 0 (the default) disables snapshots.
+    pub fn with_snapshot_interval(mut self, interval: u64) -> Self {
+        self.snapshot_interval = interval;
+        self
+    }
+
+    /// Split snapshots into chunks of at most `chunk_size` bytes each
+    /// (1 MiB by default).
+    pub fn with_snapshot_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.snapshot_chunk_size = chunk_size.max(1);
+        self
+    }
+
     /// Run the driver in the current thread (blocking).
     pub fn run(mut self) -> Result<(), Error> {
         loop {
@@ -260,9 +417,13 @@ impl KeyValueStoreDriver {
                 },
                 Command::Get { key, result_tx } => {
                     debug!("Getting value for \"{}\"", key);
+                    channel_send(&result_tx, (self.height, self.get(&key).cloned()))?;
+                },
+                Command::GetWithProof { key, result_tx } => {
+                    debug!("Getting value and proof for \"{}\"", key);
                     channel_send(
                         &result_tx,
-                        (self.height, self.store.get(&key).map(Clone::clone)),
+                        (self.height, self.committed.get_with_proof(&key)),
                     )?;
                 },
                 Command::Set {
@@ -271,22 +432,304 @@ impl KeyValueStoreDriver {
                     result_tx,
                 } => {
                     debug!("Setting \"{}\" = \"{}\"", key, value);
-                    channel_send(&result_tx, self.store.insert(key, value))?;
+                    let previous = self.get(&key).cloned();
+                    self.uncommitted.insert(key, value);
+                    channel_send(&result_tx, previous)?;
                 },
                 Command::Commit { result_tx } => self.commit(result_tx)?,
+                Command::ListSnapshots { result_tx } => channel_send(
+                    &result_tx,
+                    self.snapshots.iter().map(|s| s.snapshot.clone()).collect(),
+                )?,
+                Command::LoadSnapshotChunk {
+                    height,
+                    format,
+                    index,
+                    result_tx,
+                } => channel_send(&result_tx, self.load_snapshot_chunk(height, format, index))?,
+                Command::OfferSnapshot {
+                    snapshot,
+                    app_hash,
+                    result_tx,
+                } => channel_send(&result_tx, self.offer_snapshot(snapshot, app_hash))?,
+                Command::ApplySnapshotChunk {
+                    index,
+                    chunk,
+                    sender,
+                    result_tx,
+                } => channel_send(&result_tx, self.apply_snapshot_chunk(index, chunk, sender))?,
             }
         }
     }
 
+    /// The latest value associated with `key`, committed or not.
+    fn get(&self, key: &str) -> Option<&String> {
+        self.uncommitted
+            .get(key)
+            .or_else(|| self.committed.get(key))
+    }
+
     fn commit(&mut self, result_tx: Sender<(i64, Vec<u8>)>) -> Result<(), Error> {
-        // As in the Go-based key/value store, simply encode the number of
-        // items as the "app hash"
-        let mut app_hash = BytesMut::with_capacity(MAX_VARINT_LENGTH);
-        prost::encoding::encode_varint(self.store.len() as u64, &mut app_hash);
-        self.app_hash = app_hash.to_vec();
+        self.committed.update(mem::take(&mut self.uncommitted));
+        self.app_hash = self.committed.root.to_vec();
         self.height += 1;
+        if (self.height as u64).checked_rem(self.snapshot_interval) == Some(0) {
+            self.take_snapshot();
+        }
         channel_send(&result_tx, (self.height, self.app_hash.clone()))
     }
+
+    fn take_snapshot(&mut self) {
+        let state = encode_state(self.committed.iter());
+        let chunks = if state.is_empty() {
+            vec![Bytes::new()]
+        } else {
+            state
+                .chunks(self.snapshot_chunk_size)
+                .map(Bytes::copy_from_slice)
+                .collect::<Vec<_>>()
+        };
+        let metadata = chunks.iter().flat_map(Sha256::digest).collect::<Vec<u8>>();
+        let snapshot = Snapshot {
+            height: self.height as u64,
+            format: SNAPSHOT_FORMAT,
+            chunks: chunks.len() as u32,
+            hash: Sha256::digest(&state).to_vec().into(),
+            metadata: metadata.into(),
+        };
+        info!(
+            "Took snapshot at height {} ({} chunks)",
+            snapshot.height, snapshot.chunks
+        );
+        self.snapshots
+            .push_back(StoredSnapshot { snapshot, chunks });
+        while self.snapshots.len() > SNAPSHOT_KEEP_RECENT {
+            self.snapshots.pop_front();
+        }
+    }
+
+    fn load_snapshot_chunk(&self, height: u64, format: u32, index: u32) -> Bytes {
+        self.snapshots
+            .iter()
+            .find(|s| s.snapshot.height == height && s.snapshot.format == format)
+            .and_then(|s| s.chunks.get(index as usize))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn offer_snapshot(
+        &mut self,
+        snapshot: Snapshot,
+        app_hash: Bytes,
+    ) -> response_offer_snapshot::Result {
+        if snapshot.format != SNAPSHOT_FORMAT {
+            return response_offer_snapshot::Result::RejectFormat;
+        }
+        if snapshot.chunks == 0
+            || snapshot.metadata.len() != snapshot.chunks as usize * CHUNK_HASH_LENGTH
+        {
+            return response_offer_snapshot::Result::Reject;
+        }
+        info!(
+            "Restoring snapshot at height {} ({} chunks)",
+            snapshot.height, snapshot.chunks
+        );
+        self.restore = Some(Restore {
+            chunks: vec![None; snapshot.chunks as usize],
+            snapshot,
+            app_hash,
+        });
+        response_offer_snapshot::Result::Accept
+    }
+
+    fn apply_snapshot_chunk(
+        &mut self,
+        index: u32,
+        chunk: Bytes,
+        sender: String,
+    ) -> ResponseApplySnapshotChunk {
+        use response_apply_snapshot_chunk::Result;
+
+        let response = |result: Result| ResponseApplySnapshotChunk {
+            result: result as i32,
+            ..Default::default()
+        };
+        let restore = match self.restore.as_mut() {
+            Some(restore) => restore,
+            None => return response(Result::Abort),
+        };
+        let expected_hash = match restore
+            .snapshot
+            .metadata
+            .chunks(CHUNK_HASH_LENGTH)
+            .nth(index as usize)
+        {
+            Some(hash) => hash,
+            None => return response(Result::RejectSnapshot),
+        };
+        if Sha256::digest(&chunk).as_slice() != expected_hash {
+            warn!(
+                "Snapshot chunk {} from \"{}\" has an invalid hash",
+                index, sender
+            );
+            return ResponseApplySnapshotChunk {
+                result: Result::Retry as i32,
+                refetch_chunks: vec![index],
+                reject_senders: vec![sender],
+            };
+        }
+        restore.chunks[index as usize] = Some(chunk);
+        if restore.chunks.iter().any(Option::is_none) {
+            return response(Result::Accept);
+        }
+
+        // All chunks have been received: restore the store, and make sure
+        // it matches the trusted application hash.
+        let restore = self.restore.take().unwrap();
+        let state = restore
+            .chunks
+            .into_iter()
+            .flatten()
+            .fold(BytesMut::new(), |mut state, chunk| {
+                state.put(chunk);
+                state
+            })
+            .freeze();
+        if Sha256::digest(&state).as_slice() != restore.snapshot.hash {
+            warn!("Restored snapshot does not match its hash");
+            return response(Result::RejectSnapshot);
+        }
+        let store = match decode_state(state) {
+            Some(store) => store,
+            None => {
+                warn!("Failed to decode restored snapshot");
+                return response(Result::RejectSnapshot);
+            },
+        };
+        let restored = MerkleMap::new(store);
+        if restored.root.as_slice() != restore.app_hash {
+            warn!("Restored snapshot does not match the trusted application hash");
+            return response(Result::RejectSnapshot);
+        }
+        info!("Restored snapshot at height {}", restore.snapshot.height);
+        self.uncommitted.clear();
+        self.height = restore.snapshot.height as i64;
+        self.app_hash = restored.root.to_vec();
+        self.committed = restored;
+        response(Result::Accept)
+    }
+}
+
+/// A snapshot taken by this driver, along with its chunks.
+#[derive(Debug)]
+struct StoredSnapshot {
+    snapshot: Snapshot,
+    chunks: Vec<Bytes>,
+}
+
+/// The progress of restoring a snapshot offered to this driver.
+#[derive(Debug)]
+struct Restore {
+    snapshot: Snapshot,
+    app_hash: Bytes,
+    chunks: Vec<Option<Bytes>>,
+}
+
+/// The committed entries of the store, as leaves of a simple Merkle tree.
+///
+/// Each entry is kept along with the hash of its leaf, such that a commit
+/// only hashes the leaves of the entries written since the previous one.
+/// Proofs are computed from the hashes of the leaves when queried.
+#[derive(Debug)]
+struct MerkleMap {
+    entries: BTreeMap<String, (String, merkle::Hash)>,
+    leaf_hashes: Vec<merkle::Hash>,
+    root: merkle::Hash,
+}
+
+impl MerkleMap {
+    fn new(entries: BTreeMap<String, String>) -> Self {
+        let mut map = Self {
+            entries: BTreeMap::new(),
+            leaf_hashes: Vec::new(),
+            root: Sha256::default().empty_hash(),
+        };
+        map.update(entries);
+        map
+    }
+
+    /// Apply the given writes, and compute the new root.
+    fn update(&mut self, writes: BTreeMap<String, String>) {
+        if writes.is_empty() {
+            return;
+        }
+        let mut hasher = Sha256::default();
+        for (key, value) in writes {
+            let leaf = merkle::value_leaf::<Sha256>(key.as_bytes(), value.as_bytes());
+            let leaf_hash = hasher.leaf_hash(&leaf);
+            self.entries.insert(key, (value, leaf_hash));
+        }
+        self.leaf_hashes = self.entries.values().map(|(_, hash)| *hash).collect();
+        self.root = hasher.hash_leaf_hashes(&self.leaf_hashes);
+    }
+
+    fn get(&self, key: &str) -> Option<&String> {
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    fn get_with_proof(&self, key: &str) -> Option<(String, ProofOps)> {
+        let value = self.get(key)?.clone();
+        let index = self
+            .entries
+            .range::<str, _>((Bound::Unbounded, Bound::Excluded(key)))
+            .count();
+        let proof = merkle::proof_from_leaf_hashes::<Sha256>(&self.leaf_hashes, index)?;
+        Some((
+            value,
+            ProofOps {
+                ops: vec![ProofOp::value(key.as_bytes().to_vec(), proof)],
+            },
+        ))
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.entries.iter().map(|(key, (value, _))| (key, value))
+    }
+}
+
+impl Default for MerkleMap {
+    fn default() -> Self {
+        Self::new(BTreeMap::new())
+    }
+}
+
+fn encode_state<'a>(entries: impl Iterator<Item = (&'a String, &'a String)>) -> Vec<u8> {
+    let mut state = Vec::new();
+    for (key, value) in entries {
+        for field in [key, value] {
+            prost::encoding::encode_varint(field.len() as u64, &mut state);
+            state.put_slice(field.as_bytes());
+        }
+    }
+    state
+}
+
+fn decode_state(mut state: Bytes) -> Option<BTreeMap<String, String>> {
+    let mut store = BTreeMap::new();
+    while state.has_remaining() {
+        let key = decode_field(&mut state)?;
+        let value = decode_field(&mut state)?;
+        store.insert(key, value);
+    }
+    Some(store)
+}
+
+fn decode_field(state: &mut Bytes) -> Option<String> {
+    let len = prost::encoding::decode_varint(state).ok()? as usize;
+    if state.remaining() < len {
+        return None;
+    }
+    String::from_utf8(state.split_to(len).to_vec()).ok()
 }
 
 #[derive(Debug, Clone)]
@@ -298,6 +741,11 @@ enum Command {
         key: String,
         result_tx: Sender<(i64, Option<String>)>,
     },
+    /// Get the committed value associated with `key`, along with its proof.
+    GetWithProof {
+        key: String,
+        result_tx: Sender<(i64, Option<(String, ProofOps)>)>,
+    },
     /// Set the value of `key` to to `value`.
     Set {
         key: String,
@@ -307,6 +755,29 @@ enum Command {
     /// Commit the current state of the application, which involves recomputing
     /// the application's hash.
     Commit { result_tx: Sender<(i64, Vec<u8>)> },
+    /// List the snapshots available for state sync.
+    ListSnapshots { result_tx: Sender<Vec<Snapshot>> },
+    /// Load a chunk of one of the available snapshots.
+    LoadSnapshotChunk {
+        height: u64,
+        format: u32,
+        index: u32,
+        result_tx: Sender<Bytes>,
+    },
+    /// Start restoring the given snapshot, which must result in the given
+    /// application hash.
+    OfferSnapshot {
+        snapshot: Snapshot,
+        app_hash: Bytes,
+        result_tx: Sender<response_offer_snapshot::Result>,
+    },
+    /// Apply a chunk of the snapshot being restored.
+    ApplySnapshotChunk {
+        index: u32,
+        chunk: Bytes,
+        sender: String,
+        result_tx: Sender<ResponseApplySnapshotChunk>,
+    },
 }
 
 fn channel_send<T>(tx: &Sender<T>, value: T) -> Result<(), Error> {
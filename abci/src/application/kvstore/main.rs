@@ -19,6 +19,11 @@ struct Opt {
     #[structopt(short, long, default_value = "1048576")]
     read_buf_size: usize,
 
+    /// Take a snapshot of the store for state sync every this many blocks (0
+    /// disables snapshots).
+    #[structopt(long, default_value = "0")]
+    snapshot_interval: u64,
+
     /// Increase output logging verbosity to DEBUG level.
     #[structopt(short, long)]
     verbose: bool,
@@ -40,6 +45,7 @@ fn main() {
     tracing_subscriber::fmt().with_max_level(log_level).init();
 
     let (app, driver) = KeyValueStoreApp::new();
+    let driver = driver.with_snapshot_interval(opt.snapshot_interval);
     let server = ServerBuilder::new(opt.read_buf_size)
         .bind(format!("{}:{}", opt.host, opt.port), app)
         .unwrap();
@@ -0,0 +1,198 @@
+//! Asynchronous ABCI application interface.
+
+use std::panic::AssertUnwindSafe;
+
+use async_trait::async_trait;
+use futures::FutureExt;
+use tendermint_proto::v0_37::abci::{
+    request::Value, response, response_process_proposal, Request, RequestApplySnapshotChunk,
+    RequestBeginBlock, RequestCheckTx, RequestDeliverTx, RequestEcho, RequestEndBlock, RequestInfo,
+    RequestInitChain, RequestLoadSnapshotChunk, RequestOfferSnapshot, RequestPrepareProposal,
+    RequestProcessProposal, RequestQuery, Response, ResponseApplySnapshotChunk, ResponseBeginBlock,
+    ResponseCheckTx, ResponseCommit, ResponseDeliverTx, ResponseEcho, ResponseEndBlock,
+    ResponseFlush, ResponseInfo, ResponseInitChain, ResponseListSnapshots,
+    ResponseLoadSnapshotChunk, ResponseOfferSnapshot, ResponsePrepareProposal,
+    ResponseProcessProposal, ResponseQuery,
+};
+
+use crate::application::{exception, panicked, truncate_proposal_txs};
+
+/// An asynchronous ABCI application.
+///
+/// This mirrors the [`Application`] trait, but all of its methods are
+/// `async`. Applications are cloned for each incoming connection to the
+/// [`AsyncServer`], and each connection is served by its own task, so it is
+/// up to the application developer to manage shared state between these
+/// clones of their application.
+///
+/// [`Application`]: crate::Application
+/// [`AsyncServer`]: crate::AsyncServer
+#[async_trait]
+pub trait AsyncApplication: Send + Sync + Clone + 'static {
+    /// Echo back the same message as provided in the request.
+    async fn echo(&self, request: RequestEcho) -> ResponseEcho {
+        ResponseEcho {
+            message: request.message,
+        }
+    }
+
+    /// Provide information about the ABCI application.
+    async fn info(&self, _request: RequestInfo) -> ResponseInfo {
+        Default::default()
+    }
+
+    /// Called once upon genesis.
+    async fn init_chain(&self, _request: RequestInitChain) -> ResponseInitChain {
+        Default::default()
+    }
+
+    /// Query the application for data at the current or past height.
+    async fn query(&self, _request: RequestQuery) -> ResponseQuery {
+        Default::default()
+    }
+
+    /// Check the given transaction before putting it into the local mempool.
+    async fn check_tx(&self, _request: RequestCheckTx) -> ResponseCheckTx {
+        Default::default()
+    }
+
+    /// Signals the beginning of a new block, prior to any `DeliverTx` calls.
+    async fn begin_block(&self, _request: RequestBeginBlock) -> ResponseBeginBlock {
+        Default::default()
+    }
+
+    /// Apply a transaction to the application's state.
+    async fn deliver_tx(&self, _request: RequestDeliverTx) -> ResponseDeliverTx {
+        Default::default()
+    }
+
+    /// Signals the end of a block.
+    async fn end_block(&self, _request: RequestEndBlock) -> ResponseEndBlock {
+        Default::default()
+    }
+
+    /// Signals that messages queued on the client should be flushed to the server.
+    async fn flush(&self) -> ResponseFlush {
+        ResponseFlush {}
+    }
+
+    /// Commit the current state at the current height.
+    async fn commit(&self) -> ResponseCommit {
+        Default::default()
+    }
+
+    /// Used during state sync to discover available snapshots on peers.
+    async fn list_snapshots(&self) -> ResponseListSnapshots {
+        Default::default()
+    }
+
+    /// Called when bootstrapping the node using state sync.
+    async fn offer_snapshot(&self, _request: RequestOfferSnapshot) -> ResponseOfferSnapshot {
+        Default::default()
+    }
+
+    /// Used during state sync to retrieve chunks of snapshots from peers.
+    async fn load_snapshot_chunk(
+        &self,
+        _request: RequestLoadSnapshotChunk,
+    ) -> ResponseLoadSnapshotChunk {
+        Default::default()
+    }
+
+    /// Apply the given snapshot chunk to the application's state.
+    async fn apply_snapshot_chunk(
+        &self,
+        _request: RequestApplySnapshotChunk,
+    ) -> ResponseApplySnapshotChunk {
+        Default::default()
+    }
+
+    /// A stage where the application can modify the list of transactions
+    /// in the preliminary proposal.
+    ///
+    /// The default implementation behaves as that of
+    /// [`Application::prepare_proposal`](crate::Application::prepare_proposal).
+    ///
+    /// This method is introduced in ABCI++.
+    async fn prepare_proposal(&self, request: RequestPrepareProposal) -> ResponsePrepareProposal {
+        ResponsePrepareProposal {
+            txs: truncate_proposal_txs(request.txs, request.max_tx_bytes),
+        }
+    }
+
+    /// A stage where the application can accept or reject the proposed block.
+    ///
+    /// The default implementation returns the status value of `ACCEPT`.
+    ///
+    /// This method is introduced in ABCI++.
+    async fn process_proposal(&self, _request: RequestProcessProposal) -> ResponseProcessProposal {
+        ResponseProcessProposal {
+            status: response_process_proposal::ProposalStatus::Accept as i32,
+        }
+    }
+}
+
+/// Provides a mechanism for the [`AsyncServer`] to execute incoming requests
+/// while expecting the correct response types.
+///
+/// [`AsyncServer`]: crate::AsyncServer
+#[async_trait]
+pub trait AsyncRequestDispatcher {
+    /// Executes the relevant application method based on the type of the
+    /// request, and produces the corresponding response.
+    async fn handle(&self, request: Request) -> Response;
+}
+
+// Implement `AsyncRequestDispatcher` for all `AsyncApplication`s.
+//
+// Requests without a value, as well as panics raised by the application, are
+// answered with a `ResponseException`.
+#[async_trait]
+impl<A: AsyncApplication> AsyncRequestDispatcher for A {
+    async fn handle(&self, request: Request) -> Response {
+        tracing::debug!("Incoming request: {:?}", request);
+        let value = match request.value {
+            Some(value) => value,
+            None => return exception("empty request"),
+        };
+        AssertUnwindSafe(async move {
+            Response {
+                value: Some(match value {
+                    Value::Echo(req) => response::Value::Echo(self.echo(req).await),
+                    Value::Flush(_) => response::Value::Flush(self.flush().await),
+                    Value::Info(req) => response::Value::Info(self.info(req).await),
+                    Value::InitChain(req) => response::Value::InitChain(self.init_chain(req).await),
+                    Value::Query(req) => response::Value::Query(self.query(req).await),
+                    Value::BeginBlock(req) => {
+                        response::Value::BeginBlock(self.begin_block(req).await)
+                    },
+                    Value::CheckTx(req) => response::Value::CheckTx(self.check_tx(req).await),
+                    Value::DeliverTx(req) => response::Value::DeliverTx(self.deliver_tx(req).await),
+                    Value::EndBlock(req) => response::Value::EndBlock(self.end_block(req).await),
+                    Value::Commit(_) => response::Value::Commit(self.commit().await),
+                    Value::ListSnapshots(_) => {
+                        response::Value::ListSnapshots(self.list_snapshots().await)
+                    },
+                    Value::OfferSnapshot(req) => {
+                        response::Value::OfferSnapshot(self.offer_snapshot(req).await)
+                    },
+                    Value::LoadSnapshotChunk(req) => {
+                        response::Value::LoadSnapshotChunk(self.load_snapshot_chunk(req).await)
+                    },
+                    Value::ApplySnapshotChunk(req) => {
+                        response::Value::ApplySnapshotChunk(self.apply_snapshot_chunk(req).await)
+                    },
+                    Value::PrepareProposal(req) => {
+                        response::Value::PrepareProposal(self.prepare_proposal(req).await)
+                    },
+                    Value::ProcessProposal(req) => {
+                        response::Value::ProcessProposal(self.process_proposal(req).await)
+                    },
+                }),
+            }
+        })
+        .catch_unwind()
+        .await
+        .unwrap_or_else(|payload| panicked(&*payload))
+    }
+}
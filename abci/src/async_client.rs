@@ -0,0 +1,345 @@
+//! Pipelined asynchronous ABCI client.
+
+use std::future::Future;
+
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use prost::Message;
+use tendermint_proto::{
+    v0_34::abci as pb34,
+    v0_37::abci::{
+        request, response, Request, RequestApplySnapshotChunk, RequestBeginBlock, RequestCheckTx,
+        RequestCommit, RequestDeliverTx, RequestEcho, RequestEndBlock, RequestFlush, RequestInfo,
+        RequestInitChain, RequestListSnapshots, RequestLoadSnapshotChunk, RequestOfferSnapshot,
+        RequestPrepareProposal, RequestProcessProposal, RequestQuery, Response,
+        ResponseApplySnapshotChunk, ResponseBeginBlock, ResponseCheckTx, ResponseCommit,
+        ResponseDeliverTx, ResponseEcho, ResponseEndBlock, ResponseFlush, ResponseInfo,
+        ResponseInitChain, ResponseListSnapshots, ResponseLoadSnapshotChunk, ResponseOfferSnapshot,
+        ResponsePrepareProposal, ResponseProcessProposal, ResponseQuery,
+    },
+};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::codec::Framed;
+use tracing::{debug, error};
+
+use crate::{
+    codec::AsyncCodec,
+    protocol::{request_to_v0_34, response_from_v0_34, ProtocolVersion},
+    transport::AsyncStream,
+    Error,
+};
+
+/// Delivers the outcome of a request to its caller.
+type Responder = oneshot::Sender<Result<Response, Error>>;
+
+/// Pipelined asynchronous ABCI client.
+///
+/// Like the clients used by Tendermint itself, this client does not wait for
+/// the response to a request before sending the next one. Requests are
+/// queued as soon as the corresponding method is called, in the order of
+/// these calls, and buffered until a [`flush`](AsyncClient::flush) request
+/// is queued (or until the write buffer fills up). The server answers
+/// requests in order, and each response is delivered to the future returned
+/// for its request.
+///
+/// **Note:** the futures returned by this client only resolve once their
+/// request has been flushed, so make sure to queue a `flush` request after
+/// each batch of requests.
+///
+/// The client can be cloned in order to queue requests from multiple tasks
+/// over the same connection.
+#[derive(Clone)]
+pub struct AsyncClient {
+    requests: mpsc::UnboundedSender<(Request, Responder)>,
+}
+
+macro_rules! perform {
+    ($self:expr, $type:ident, $req:expr) => {{
+        let response = $self.perform(request::Value::$type($req));
+        async move {
+            match response.await? {
+                response::Value::$type(r) => Ok(r),
+                r => Err(Error::unexpected_server_response_type(
+                    stringify!($type).to_string(),
+                    r,
+                )),
+            }
+        }
+    }};
+}
+
+impl AsyncClient {
+    /// Serve the client over the given stream by way of two background
+    /// tasks: one writing requests, the other reading responses.
+    ///
+    /// Must be called from within a [`tokio`] runtime.
+    pub(crate) fn new(
+        stream: AsyncStream,
+        read_buf_size: usize,
+        version: ProtocolVersion,
+        max_frame_size: usize,
+    ) -> Self {
+        let (requests, queued) = mpsc::unbounded_channel();
+        match version {
+            ProtocolVersion::V0_34 => spawn_connection(
+                Framed::with_capacity(
+                    stream,
+                    AsyncCodec::<pb34::Response, pb34::Request>::new(version, max_frame_size),
+                    read_buf_size,
+                ),
+                queued,
+                request_to_v0_34,
+                |response| response_from_v0_34(response).map_err(Error::decode),
+            ),
+            ProtocolVersion::V0_37 => spawn_connection(
+                Framed::with_capacity(
+                    stream,
+                    AsyncCodec::<Response, Request>::new(version, max_frame_size),
+                    read_buf_size,
+                ),
+                queued,
+                Ok,
+                Ok,
+            ),
+        }
+        Self { requests }
+    }
+
+    /// Ask the ABCI server to echo back a message.
+    pub fn echo(
+        &self,
+        req: RequestEcho,
+    ) -> impl Future<Output = Result<ResponseEcho, Error>> + Send + 'static {
+        perform!(self, Echo, req)
+    }
+
+    /// Request information about the ABCI application.
+    pub fn info(
+        &self,
+        req: RequestInfo,
+    ) -> impl Future<Output = Result<ResponseInfo, Error>> + Send + 'static {
+        perform!(self, Info, req)
+    }
+
+    /// To be called once upon genesis.
+    pub fn init_chain(
+        &self,
+        req: RequestInitChain,
+    ) -> impl Future<Output = Result<ResponseInitChain, Error>> + Send + 'static {
+        perform!(self, InitChain, req)
+    }
+
+    /// Query the application for data at the current or past height.
+    pub fn query(
+        &self,
+        req: RequestQuery,
+    ) -> impl Future<Output = Result<ResponseQuery, Error>> + Send + 'static {
+        perform!(self, Query, req)
+    }
+
+    /// Check the given transaction before putting it into the local mempool.
+    pub fn check_tx(
+        &self,
+        req: RequestCheckTx,
+    ) -> impl Future<Output = Result<ResponseCheckTx, Error>> + Send + 'static {
+        perform!(self, CheckTx, req)
+    }
+
+    /// Signal the beginning of a new block, prior to any `DeliverTx` calls.
+    pub fn begin_block(
+        &self,
+        req: RequestBeginBlock,
+    ) -> impl Future<Output = Result<ResponseBeginBlock, Error>> + Send + 'static {
+        perform!(self, BeginBlock, req)
+    }
+
+    /// Apply a transaction to the application's state.
+    pub fn deliver_tx(
+        &self,
+        req: RequestDeliverTx,
+    ) -> impl Future<Output = Result<ResponseDeliverTx, Error>> + Send + 'static {
+        perform!(self, DeliverTx, req)
+    }
+
+    /// Signal the end of a block.
+    pub fn end_block(
+        &self,
+        req: RequestEndBlock,
+    ) -> impl Future<Output = Result<ResponseEndBlock, Error>> + Send + 'static {
+        perform!(self, EndBlock, req)
+    }
+
+    /// Send all queued requests to the server.
+    ///
+    /// The returned future resolves once the server has handled all requests
+    /// queued before this one.
+    pub fn flush(&self) -> impl Future<Output = Result<ResponseFlush, Error>> + Send + 'static {
+        perform!(self, Flush, RequestFlush {})
+    }
+
+    /// Commit the current state at the current height.
+    pub fn commit(&self) -> impl Future<Output = Result<ResponseCommit, Error>> + Send + 'static {
+        perform!(self, Commit, RequestCommit {})
+    }
+
+    /// Used during state sync to discover available snapshots on peers.
+    pub fn list_snapshots(
+        &self,
+    ) -> impl Future<Output = Result<ResponseListSnapshots, Error>> + Send + 'static {
+        perform!(self, ListSnapshots, RequestListSnapshots {})
+    }
+
+    /// Called when bootstrapping the node using state sync.
+    pub fn offer_snapshot(
+        &self,
+        req: RequestOfferSnapshot,
+    ) -> impl Future<Output = Result<ResponseOfferSnapshot, Error>> + Send + 'static {
+        perform!(self, OfferSnapshot, req)
+    }
+
+    /// Used during state sync to retrieve chunks of snapshots from peers.
+    pub fn load_snapshot_chunk(
+        &self,
+        req: RequestLoadSnapshotChunk,
+    ) -> impl Future<Output = Result<ResponseLoadSnapshotChunk, Error>> + Send + 'static {
+        perform!(self, LoadSnapshotChunk, req)
+    }
+
+    /// Apply the given snapshot chunk to the application's state.
+    pub fn apply_snapshot_chunk(
+        &self,
+        req: RequestApplySnapshotChunk,
+    ) -> impl Future<Output = Result<ResponseApplySnapshotChunk, Error>> + Send + 'static {
+        perform!(self, ApplySnapshotChunk, req)
+    }
+
+    /// Ask the application to prepare a block proposal.
+    ///
+    /// This method is introduced in ABCI++, and is not supported by v0.34
+    /// servers.
+    pub fn prepare_proposal(
+        &self,
+        req: RequestPrepareProposal,
+    ) -> impl Future<Output = Result<ResponsePrepareProposal, Error>> + Send + 'static {
+        perform!(self, PrepareProposal, req)
+    }
+
+    /// Ask the application to accept or reject a block proposal.
+    ///
+    /// This method is introduced in ABCI++, and is not supported by v0.34
+    /// servers.
+    pub fn process_proposal(
+        &self,
+        req: RequestProcessProposal,
+    ) -> impl Future<Output = Result<ResponseProcessProposal, Error>> + Send + 'static {
+        perform!(self, ProcessProposal, req)
+    }
+
+    /// Queue the given request, producing a future resolving to its
+    /// response.
+    fn perform(
+        &self,
+        req: request::Value,
+    ) -> impl Future<Output = Result<response::Value, Error>> + Send + 'static {
+        let (responder, response) = oneshot::channel();
+        let queued = self
+            .requests
+            .send((Request { value: Some(req) }, responder))
+            .is_ok();
+        async move {
+            if !queued {
+                return Err(Error::server_connection_terminated());
+            }
+            let res = response
+                .await
+                .map_err(|_| Error::server_connection_terminated())??;
+            match res.value {
+                Some(response::Value::Exception(e)) => Err(Error::response_exception(e.error)),
+                Some(value) => Ok(value),
+                None => Err(Error::malformed_server_response()),
+            }
+        }
+    }
+}
+
+fn spawn_connection<I, O>(
+    framed: Framed<AsyncStream, AsyncCodec<I, O>>,
+    queued: mpsc::UnboundedReceiver<(Request, Responder)>,
+    encode: fn(Request) -> Result<O, Error>,
+    decode: fn(I) -> Result<Response, Error>,
+) where
+    I: Message + Default + Send + 'static,
+    O: Message + Send + 'static,
+{
+    let (sink, stream) = framed.split();
+    let (pending, awaiting) = mpsc::unbounded_channel();
+    tokio::spawn(write_requests(sink, queued, pending, encode));
+    tokio::spawn(read_responses(stream, awaiting, decode));
+}
+
+async fn write_requests<I, O>(
+    mut sink: SplitSink<Framed<AsyncStream, AsyncCodec<I, O>>, O>,
+    mut queued: mpsc::UnboundedReceiver<(Request, Responder)>,
+    pending: mpsc::UnboundedSender<Responder>,
+    encode: fn(Request) -> Result<O, Error>,
+) where
+    I: Message + Default,
+    O: Message,
+{
+    while let Some((request, responder)) = queued.recv().await {
+        let flush = matches!(request.value, Some(request::Value::Flush(_)));
+        let outgoing = match encode(request) {
+            Ok(outgoing) => outgoing,
+            Err(e) => {
+                let _ = responder.send(Err(e));
+                continue;
+            },
+        };
+        // The responder is handed to the reading task before its request is
+        // sent, such that it is always available once the response arrives.
+        if pending.send(responder).is_err() {
+            break;
+        }
+        let result = if flush {
+            sink.send(outgoing).await
+        } else {
+            sink.feed(outgoing).await
+        };
+        if let Err(e) = result {
+            error!("Failed to send request to ABCI server: {:?}", e);
+            break;
+        }
+    }
+    debug!("Closing connection to ABCI server");
+    let _ = sink.close().await;
+}
+
+async fn read_responses<I, O>(
+    mut stream: SplitStream<Framed<AsyncStream, AsyncCodec<I, O>>>,
+    mut awaiting: mpsc::UnboundedReceiver<Responder>,
+    decode: fn(I) -> Result<Response, Error>,
+) where
+    I: Message + Default,
+{
+    while let Some(incoming) = stream.next().await {
+        let response = incoming.and_then(decode);
+        let failed = response.is_err();
+        match awaiting.try_recv() {
+            Ok(responder) => {
+                let _ = responder.send(response);
+            },
+            Err(_) => {
+                error!("Received unexpected response from ABCI server");
+                break;
+            },
+        }
+        if failed {
+            // We cannot tell where the next response begins
+            break;
+        }
+    }
+    // Dropping the remaining responders informs their callers that the
+    // connection has been terminated.
+}
@@ -0,0 +1,232 @@
+//! Asynchronous ABCI application server interface.
+
+use std::{future::Future, sync::Arc};
+
+use futures::{SinkExt, StreamExt};
+use prost::Message;
+use tendermint_config::net;
+use tendermint_proto::v0_34::abci as pb34;
+use tokio::task::JoinSet;
+use tokio_util::{codec::Framed, sync::CancellationToken};
+use tracing::{error, info};
+
+use crate::{
+    async_application::AsyncRequestDispatcher,
+    codec::{AsyncCodec, AsyncServerCodec},
+    error::Error,
+    protocol::{request_from_v0_34, response_to_v0_34, ProtocolVersion},
+    server::{local_addr, FailureCount},
+    transport::{AsyncListener, AsyncStream},
+    AsyncApplication,
+};
+
+/// A [`tokio`]-based TCP or Unix domain socket server for serving a specific
+/// asynchronous ABCI application.
+///
+/// Each incoming connection is handled in a separate task. The ABCI
+/// application is cloned for access in each task. It is up to the application
+/// developer to manage shared state across these different tasks.
+///
+/// Constructed through [`ServerBuilder::bind_async`] or
+/// [`ServerBuilder::bind_address_async`].
+///
+/// [`ServerBuilder::bind_async`]: crate::ServerBuilder::bind_async
+/// [`ServerBuilder::bind_address_async`]: crate::ServerBuilder::bind_address_async
+pub struct AsyncServer<App> {
+    app: App,
+    listener: AsyncListener,
+    address: net::Address,
+    read_buf_size: usize,
+    protocol_version: ProtocolVersion,
+    max_frame_size: usize,
+    failures: FailureCount,
+    shutdown: CancellationToken,
+}
+
+impl<App: AsyncApplication> AsyncServer<App> {
+    pub(crate) fn new(
+        app: App,
+        listener: AsyncListener,
+        address: net::Address,
+        read_buf_size: usize,
+        protocol_version: ProtocolVersion,
+        max_frame_size: usize,
+    ) -> Self {
+        Self {
+            app,
+            listener,
+            address,
+            read_buf_size,
+            protocol_version,
+            max_frame_size,
+            failures: FailureCount::default(),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Listen for incoming connections until shut down through a
+    /// [`ShutdownHandle`].
+    ///
+    /// Once shutdown has been signalled, the server stops accepting new
+    /// connections and waits for each existing connection to finish handling
+    /// its current request before returning.
+    pub async fn listen(self) -> Result<(), Error> {
+        let mut connections = JoinSet::new();
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                Some(_) = connections.join_next(), if !connections.is_empty() => (),
+                accepted = self.listener.accept() => {
+                    let (stream, addr) = accepted?;
+                    info!("Incoming connection from: {}", addr);
+                    self.spawn_client_handler(&mut connections, stream, addr);
+                },
+            }
+        }
+        info!("ABCI server shutting down");
+        while connections.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    /// Getter for this server's local address.
+    ///
+    /// For TCP servers, this is of the form `host:port`, whereas servers
+    /// bound to a Unix domain socket produce a `unix://` URI.
+    pub fn local_addr(&self) -> String {
+        local_addr(&self.address)
+    }
+
+    /// Getter for the address to which this server is bound.
+    pub fn address(&self) -> net::Address {
+        self.address.clone()
+    }
+
+    /// Obtain a handle through which this server can be shut down.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle(self.shutdown.clone())
+    }
+
+    /// Obtain the counter of requests this server has failed to handle.
+    pub fn failure_count(&self) -> FailureCount {
+        self.failures.clone()
+    }
+
+    fn spawn_client_handler(
+        &self,
+        connections: &mut JoinSet<()>,
+        stream: AsyncStream,
+        addr: String,
+    ) {
+        let app = Arc::new(self.app.clone());
+        let read_buf_size = self.read_buf_size;
+        let version = self.protocol_version;
+        let max_frame_size = self.max_frame_size;
+        let shutdown = self.shutdown.clone();
+        let failures = self.failures.clone();
+        let peer = addr.clone();
+        match version {
+            // Requests and responses failing translation are answered with
+            // an exception too, hence the v0.34 response is the one recorded.
+            ProtocolVersion::V0_34 => connections.spawn(Self::handle_client(
+                Framed::with_capacity(
+                    stream,
+                    AsyncCodec::<pb34::Request, pb34::Response>::new(version, max_frame_size),
+                    read_buf_size,
+                ),
+                addr,
+                shutdown,
+                move |request| {
+                    let app = app.clone();
+                    let failures = failures.clone();
+                    let peer = peer.clone();
+                    async move {
+                        let response = match request_from_v0_34(request) {
+                            Ok(request) => response_to_v0_34(app.handle(request).await),
+                            Err(response) => response,
+                        };
+                        failures.record_v0_34(&peer, &response);
+                        response
+                    }
+                },
+            )),
+            ProtocolVersion::V0_37 => connections.spawn(Self::handle_client(
+                Framed::with_capacity(
+                    stream,
+                    AsyncServerCodec::new(version, max_frame_size),
+                    read_buf_size,
+                ),
+                addr,
+                shutdown,
+                move |request| {
+                    let app = app.clone();
+                    let failures = failures.clone();
+                    let peer = peer.clone();
+                    async move {
+                        let response = app.handle(request).await;
+                        failures.record(&peer, &response);
+                        response
+                    }
+                },
+            )),
+        };
+    }
+
+    async fn handle_client<I, O, F, Fut>(
+        mut framed: Framed<AsyncStream, AsyncCodec<I, O>>,
+        addr: String,
+        shutdown: CancellationToken,
+        handle: F,
+    ) where
+        I: Message + Default,
+        O: Message,
+        F: Fn(I) -> Fut,
+        Fut: Future<Output = O>,
+    {
+        info!("Listening for incoming requests from {}", addr);
+        loop {
+            let request = tokio::select! {
+                _ = shutdown.cancelled() => {
+                    info!("Closing connection to client {}", addr);
+                    return;
+                },
+                request = framed.next() => match request {
+                    Some(Ok(r)) => r,
+                    Some(Err(e)) => {
+                        error!(
+                            "Failed to read incoming request from client {}: {:?}",
+                            addr, e
+                        );
+                        return;
+                    },
+                    None => {
+                        info!("Client {} terminated stream", addr);
+                        return;
+                    },
+                },
+            };
+            let response = handle(request).await;
+            if let Err(e) = framed.send(response).await {
+                error!("Failed sending response to client {}: {:?}", addr, e);
+                return;
+            }
+        }
+    }
+}
+
+/// A handle through which an [`AsyncServer`] can be signalled to shut down
+/// gracefully.
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle(CancellationToken);
+
+impl ShutdownHandle {
+    /// Signal the server to stop accepting connections and to close its
+    /// existing connections.
+    pub fn shutdown(&self) {
+        self.0.cancel()
+    }
+
+    /// Whether shutdown has already been signalled.
+    pub fn is_shutdown(&self) -> bool {
+        self.0.is_cancelled()
+    }
+}
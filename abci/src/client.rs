@@ -2,17 +2,30 @@
 
 use std::net::{TcpStream, ToSocketAddrs};
 
-use tendermint_proto::v0_37::abci::{
-    request, response, Request, RequestApplySnapshotChunk, RequestBeginBlock, RequestCheckTx,
-    RequestCommit, RequestDeliverTx, RequestEcho, RequestEndBlock, RequestFlush, RequestInfo,
-    RequestInitChain, RequestListSnapshots, RequestLoadSnapshotChunk, RequestOfferSnapshot,
-    RequestQuery, ResponseApplySnapshotChunk, ResponseBeginBlock, ResponseCheckTx, ResponseCommit,
-    ResponseDeliverTx, ResponseEcho, ResponseEndBlock, ResponseFlush, ResponseInfo,
-    ResponseInitChain, ResponseListSnapshots, ResponseLoadSnapshotChunk, ResponseOfferSnapshot,
-    ResponseQuery,
+use tendermint_config::net;
+use tendermint_proto::{
+    v0_34::abci as pb34,
+    v0_37::abci::{
+        request, response, Request, RequestApplySnapshotChunk, RequestBeginBlock, RequestCheckTx,
+        RequestCommit, RequestDeliverTx, RequestEcho, RequestEndBlock, RequestFlush, RequestInfo,
+        RequestInitChain, RequestListSnapshots, RequestLoadSnapshotChunk, RequestOfferSnapshot,
+        RequestQuery, ResponseApplySnapshotChunk, ResponseBeginBlock, ResponseCheckTx,
+        ResponseCommit, ResponseDeliverTx, ResponseEcho, ResponseEndBlock, ResponseFlush,
+        ResponseInfo, ResponseInitChain, ResponseListSnapshots, ResponseLoadSnapshotChunk,
+        ResponseOfferSnapshot, ResponseQuery,
+    },
 };
 
-use crate::{codec::ClientCodec, Error};
+#[cfg(feature = "grpc")]
+use crate::GrpcClient;
+use crate::{
+    codec::{ClientCodec, Codec, DEFAULT_MAX_FRAME_SIZE},
+    protocol::{request_to_v0_34, response_from_v0_34, ProtocolVersion},
+    transport::Stream,
+    Error,
+};
+#[cfg(feature = "async")]
+use crate::{transport::AsyncStream, AsyncClient};
 
 /// The size of the read buffer for the client in its receiving of responses
 /// from the server.
@@ -21,21 +34,128 @@ pub const DEFAULT_CLIENT_READ_BUF_SIZE: usize = 1024;
 /// Builder for a blocking ABCI client.
 pub struct ClientBuilder {
     read_buf_size: usize,
+    protocol_version: ProtocolVersion,
+    max_frame_size: usize,
 }
 
 impl ClientBuilder {
     /// Builder constructor.
     pub fn new(read_buf_size: usize) -> Self {
-        Self { read_buf_size }
+        Self {
+            read_buf_size,
+            protocol_version: ProtocolVersion::default(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Select the version of the ABCI wire protocol spoken by the client,
+    /// which must match that of the server it connects to.
+    ///
+    /// Defaults to [`ProtocolVersion::V0_37`].
+    pub fn protocol_version(mut self, version: ProtocolVersion) -> Self {
+        self.protocol_version = version;
+        self
+    }
+
+    /// Limit the size of incoming responses to the given number of bytes.
+    ///
+    /// Responses announced to be larger produce an error before being
+    /// buffered. Defaults to 128MB.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
     }
 
-    /// Client constructor that attempts to connect to the given network
+    /// Client constructor that attempts to connect to the given TCP network
     /// address.
     pub fn connect<A: ToSocketAddrs>(self, addr: A) -> Result<Client, Error> {
         let stream = TcpStream::connect(addr).map_err(Error::io)?;
-        Ok(Client {
-            codec: ClientCodec::new(stream, self.read_buf_size),
-        })
+        Ok(self.client(Stream::Tcp(stream)))
+    }
+
+    /// Client constructor that attempts to connect to the given TCP or Unix
+    /// domain socket address.
+    pub fn connect_address(self, addr: net::Address) -> Result<Client, Error> {
+        let stream = Stream::connect(&addr)?;
+        Ok(self.client(stream))
+    }
+
+    /// Constructor for a pipelined asynchronous client, which attempts to
+    /// connect to the given TCP network address.
+    ///
+    /// Must be called from within a [`tokio`] runtime.
+    #[cfg(feature = "async")]
+    pub async fn connect_async<A>(self, addr: A) -> Result<AsyncClient, Error>
+    where
+        A: tokio::net::ToSocketAddrs,
+    {
+        let stream = tokio::net::TcpStream::connect(addr)
+            .await
+            .map_err(Error::io)?;
+        Ok(self.async_client(AsyncStream::Tcp(stream)))
+    }
+
+    /// Constructor for a pipelined asynchronous client, which attempts to
+    /// connect to the given TCP or Unix domain socket address.
+    ///
+    /// Must be called from within a [`tokio`] runtime.
+    #[cfg(feature = "async")]
+    pub async fn connect_address_async(self, addr: net::Address) -> Result<AsyncClient, Error> {
+        let stream = AsyncStream::connect(&addr).await?;
+        Ok(self.async_client(stream))
+    }
+
+    /// Constructor for a gRPC client, which attempts to connect to the
+    /// `ABCIApplication` gRPC service at the given TCP network address.
+    ///
+    /// The read buffer size, protocol version and maximum frame size only
+    /// apply to the socket protocol, and are ignored. Must be called from
+    /// within a [`tokio`] runtime.
+    #[cfg(feature = "grpc")]
+    pub async fn connect_grpc<A>(self, addr: A) -> Result<GrpcClient, Error>
+    where
+        A: tokio::net::ToSocketAddrs,
+    {
+        let addr = tokio::net::lookup_host(addr)
+            .await
+            .map_err(Error::io)?
+            .next()
+            .ok_or_else(|| {
+                Error::io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "address resolved to nothing",
+                ))
+            })?;
+        GrpcClient::connect(addr.to_string()).await
+    }
+
+    #[cfg(feature = "async")]
+    fn async_client(self, stream: AsyncStream) -> AsyncClient {
+        AsyncClient::new(
+            stream,
+            self.read_buf_size,
+            self.protocol_version,
+            self.max_frame_size,
+        )
+    }
+
+    fn client(self, stream: Stream) -> Client {
+        let version = self.protocol_version;
+        let codec = match version {
+            ProtocolVersion::V0_34 => ClientCodecs::V0_34(Codec::new(
+                stream,
+                self.read_buf_size,
+                version,
+                self.max_frame_size,
+            )),
+            ProtocolVersion::V0_37 => ClientCodecs::V0_37(ClientCodec::new(
+                stream,
+                self.read_buf_size,
+                version,
+                self.max_frame_size,
+            )),
+        };
+        Client { codec }
     }
 }
 
@@ -43,13 +163,23 @@ impl Default for ClientBuilder {
     fn default() -> Self {
         Self {
             read_buf_size: DEFAULT_CLIENT_READ_BUF_SIZE,
+            protocol_version: ProtocolVersion::default(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
         }
     }
 }
 
 /// Blocking ABCI client.
+///
+/// Requests and responses are always expressed in terms of the v0.37
+/// protocol, and are translated when speaking to a v0.34 server.
 pub struct Client {
-    codec: ClientCodec<TcpStream>,
+    codec: ClientCodecs,
+}
+
+enum ClientCodecs {
+    V0_34(Codec<Stream, pb34::Response, pb34::Request>),
+    V0_37(ClientCodec<Stream>),
 }
 
 macro_rules! perform {
@@ -143,11 +273,22 @@ impl Client {
     }
 
     fn perform(&mut self, req: request::Value) -> Result<response::Value, Error> {
-        self.codec.send(Request { value: Some(req) })?;
-        let res = self
-            .codec
-            .next()
-            .ok_or_else(Error::server_connection_terminated)??;
+        let req = Request { value: Some(req) };
+        let res = match &mut self.codec {
+            ClientCodecs::V0_34(codec) => {
+                codec.send(request_to_v0_34(req)?)?;
+                let res = codec
+                    .next()
+                    .ok_or_else(Error::server_connection_terminated)??;
+                response_from_v0_34(res).map_err(Error::decode)?
+            },
+            ClientCodecs::V0_37(codec) => {
+                codec.send(req)?;
+                codec
+                    .next()
+                    .ok_or_else(Error::server_connection_terminated)??
+            },
+        };
         res.value.ok_or_else(Error::malformed_server_response)
     }
 }
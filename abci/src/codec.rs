@@ -13,12 +13,17 @@ use bytes::{Buf, BufMut, BytesMut};
 use prost::Message;
 use tendermint_proto::v0_37::abci::{Request, Response};
 
-use crate::error::Error;
+use crate::{error::Error, protocol::ProtocolVersion};
 
 /// The maximum number of bytes we expect in a varint. We use this to check if
 /// we're encountering a decoding error for a varint.
 pub const MAX_VARINT_LENGTH: usize = 16;
 
+/// The default maximum size of an incoming message (128MB), which leaves
+/// ample room for requests carrying the largest blocks permitted by
+/// Tendermint (100MB).
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 128 * 1024 * 1024;
+
 /// The server receives incoming requests, and sends outgoing responses.
 pub type ServerCodec<S> = Codec<S, Request, Response>;
 
@@ -35,6 +40,10 @@ pub struct Codec<S, I, O> {
     // Fixed-length read window
     read_window: Vec<u8>,
     write_buf: BytesMut,
+    // Determines the framing of messages on the wire
+    version: ProtocolVersion,
+    // Maximum size of incoming messages
+    max_frame_size: usize,
     _incoming: PhantomData<I>,
     _outgoing: PhantomData<O>,
 }
@@ -46,12 +55,22 @@ where
     O: Message,
 {
     /// Constructor.
-    pub fn new(stream: S, read_buf_size: usize) -> Self {
+    ///
+    /// Incoming messages larger than `max_frame_size` bytes are rejected as
+    /// soon as their length prefix has been read.
+    pub fn new(
+        stream: S,
+        read_buf_size: usize,
+        version: ProtocolVersion,
+        max_frame_size: usize,
+    ) -> Self {
         Self {
             stream,
             read_buf: BytesMut::new(),
             read_window: vec![0_u8; read_buf_size],
             write_buf: BytesMut::new(),
+            version,
+            max_frame_size,
             _incoming: Default::default(),
             _outgoing: Default::default(),
         }
@@ -69,7 +88,11 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             // Try to decode an incoming message from our buffer first
-            match decode_length_delimited::<I>(&mut self.read_buf) {
+            match decode_length_delimited::<I>(
+                &mut self.read_buf,
+                self.version,
+                self.max_frame_size,
+            ) {
                 Ok(Some(incoming)) => return Some(Ok(incoming)),
                 Err(e) => return Some(Err(e)),
                 _ => (), // not enough data to decode a message, let's continue.
@@ -98,7 +121,7 @@ where
 {
     /// Send a message using this codec.
     pub fn send(&mut self, message: O) -> Result<(), Error> {
-        encode_length_delimited(message, &mut self.write_buf)?;
+        encode_length_delimited(message, &mut self.write_buf, self.version)?;
         while !self.write_buf.is_empty() {
             let bytes_written = self
                 .stream
@@ -120,8 +143,73 @@ where
     }
 }
 
-/// Encode the given message with a length prefix.
-pub fn encode_length_delimited<M, B>(message: M, mut dst: &mut B) -> Result<(), Error>
+/// An asynchronous counterpart to [`Codec`] for use with [`tokio_util`]'s
+/// framing utilities.
+///
+/// Unlike [`Codec`], this does not own the underlying stream. Wrap the stream
+/// in a [`tokio_util::codec::Framed`] to obtain a `Stream` of `I` and a `Sink`
+/// of `O`.
+#[cfg(feature = "async")]
+pub struct AsyncCodec<I, O> {
+    version: ProtocolVersion,
+    max_frame_size: usize,
+    _incoming: PhantomData<I>,
+    _outgoing: PhantomData<O>,
+}
+
+/// The asynchronous server receives incoming requests, and sends outgoing
+/// responses.
+#[cfg(feature = "async")]
+pub type AsyncServerCodec = AsyncCodec<Request, Response>;
+
+#[cfg(feature = "async")]
+impl<I, O> AsyncCodec<I, O> {
+    /// Constructor.
+    ///
+    /// Incoming messages larger than `max_frame_size` bytes are rejected as
+    /// soon as their length prefix has been read.
+    pub fn new(version: ProtocolVersion, max_frame_size: usize) -> Self {
+        Self {
+            version,
+            max_frame_size,
+            _incoming: Default::default(),
+            _outgoing: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I, O> tokio_util::codec::Decoder for AsyncCodec<I, O>
+where
+    I: Message + Default,
+{
+    type Item = I;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        decode_length_delimited(src, self.version, self.max_frame_size)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I, O> tokio_util::codec::Encoder<O> for AsyncCodec<I, O>
+where
+    O: Message,
+{
+    type Error = Error;
+
+    fn encode(&mut self, message: O, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        encode_length_delimited(message, dst, self.version)
+    }
+}
+
+/// Encode the given message with a length prefix, as framed by the given
+/// protocol version.
+pub fn encode_length_delimited<M, B>(
+    message: M,
+    mut dst: &mut B,
+    version: ProtocolVersion,
+) -> Result<(), Error>
 where
     M: Message,
     B: BufMut,
@@ -130,13 +218,26 @@ where
     message.encode(&mut buf).map_err(Error::encode)?;
 
     let buf = buf.freeze();
-    prost::encoding::encode_varint(buf.len() as u64, &mut dst);
+    let len = match version {
+        // Tendermint Core v0.34 uses a signed, zigzag-encoded varint
+        ProtocolVersion::V0_34 => zigzag_encode(buf.len() as i64),
+        ProtocolVersion::V0_37 => buf.len() as u64,
+    };
+    prost::encoding::encode_varint(len, &mut dst);
     dst.put(buf);
     Ok(())
 }
 
-/// Attempt to decode a message of type `M` from the given source buffer.
-pub fn decode_length_delimited<M>(src: &mut BytesMut) -> Result<Option<M>, Error>
+/// Attempt to decode a message of type `M` from the given source buffer, as
+/// framed by the given protocol version.
+///
+/// Fails as soon as the length prefix announces a message larger than
+/// `max_frame_size` bytes, without waiting for the message itself.
+pub fn decode_length_delimited<M>(
+    src: &mut BytesMut,
+    version: ProtocolVersion,
+    max_frame_size: usize,
+) -> Result<Option<M>, Error>
 where
     M: Message + Default,
 {
@@ -148,6 +249,16 @@ where
         Err(_) if src_len <= MAX_VARINT_LENGTH => return Ok(None),
         Err(e) => return Err(Error::decode(e)),
     };
+    let encoded_len = match version {
+        ProtocolVersion::V0_34 => match zigzag_decode(encoded_len) {
+            len if len < 0 => return Err(Error::negative_message_length(len)),
+            len => len as u64,
+        },
+        ProtocolVersion::V0_37 => encoded_len,
+    };
+    if encoded_len > max_frame_size as u64 {
+        return Err(Error::frame_too_large(encoded_len, max_frame_size));
+    }
     let remaining = tmp.remaining() as u64;
     if remaining < encoded_len {
         // We don't have enough data yet to decode the entire message
@@ -164,3 +275,11 @@ where
         Ok(Some(res))
     }
 }
+
+fn zigzag_encode(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn zigzag_decode(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
@@ -0,0 +1,187 @@
+//! ABCI application interface based on the validated domain types from the
+//! [`tendermint`] crate.
+
+use tendermint::{
+    abci::{request, response},
+    v0_37::abci::{Request, Response},
+};
+use tendermint_proto::v0_37::abci as pb;
+use tracing::error;
+
+use crate::application::{catch_panic, truncate_proposal_txs, RequestDispatcher};
+
+/// An ABCI application whose methods take and return the domain types
+/// defined in [`tendermint::abci`], rather than raw Protobuf messages.
+///
+/// Incoming requests are validated while being converted into their domain
+/// types. Requests failing this validation never reach the application, and
+/// are answered with a `ResponseException` instead.
+///
+/// In order to serve such an application, wrap it in a [`DomainAdapter`].
+pub trait DomainApplication: Send + Clone + 'static {
+    /// Echo back the same message as provided in the request.
+    fn echo(&self, request: request::Echo) -> response::Echo {
+        response::Echo {
+            message: request.message,
+        }
+    }
+
+    /// Provide information about the ABCI application.
+    fn info(&self, _request: request::Info) -> response::Info {
+        Default::default()
+    }
+
+    /// Called once upon genesis.
+    fn init_chain(&self, _request: request::InitChain) -> response::InitChain {
+        Default::default()
+    }
+
+    /// Query the application for data at the current or past height.
+    fn query(&self, _request: request::Query) -> response::Query {
+        Default::default()
+    }
+
+    /// Check the given transaction before putting it into the local mempool.
+    fn check_tx(&self, _request: request::CheckTx) -> response::CheckTx {
+        Default::default()
+    }
+
+    /// Signals the beginning of a new block, prior to any `DeliverTx` calls.
+    fn begin_block(&self, _request: request::BeginBlock) -> response::BeginBlock {
+        Default::default()
+    }
+
+    /// Apply a transaction to the application's state.
+    fn deliver_tx(&self, _request: request::DeliverTx) -> response::DeliverTx {
+        Default::default()
+    }
+
+    /// Signals the end of a block.
+    fn end_block(&self, _request: request::EndBlock) -> response::EndBlock {
+        Default::default()
+    }
+
+    /// Signals that messages queued on the client should be flushed to the
+    /// server.
+    fn flush(&self) {}
+
+    /// Commit the current state at the current height.
+    fn commit(&self) -> response::Commit {
+        Default::default()
+    }
+
+    /// Used during state sync to discover available snapshots on peers.
+    fn list_snapshots(&self) -> response::ListSnapshots {
+        Default::default()
+    }
+
+    /// Called when bootstrapping the node using state sync.
+    fn offer_snapshot(&self, _request: request::OfferSnapshot) -> response::OfferSnapshot {
+        Default::default()
+    }
+
+    /// Used during state sync to retrieve chunks of snapshots from peers.
+    fn load_snapshot_chunk(
+        &self,
+        _request: request::LoadSnapshotChunk,
+    ) -> response::LoadSnapshotChunk {
+        Default::default()
+    }
+
+    /// Apply the given snapshot chunk to the application's state.
+    fn apply_snapshot_chunk(
+        &self,
+        _request: request::ApplySnapshotChunk,
+    ) -> response::ApplySnapshotChunk {
+        Default::default()
+    }
+
+    /// A stage where the application can modify the list of transactions
+    /// in the preliminary proposal.
+    ///
+    /// The default implementation behaves as that of
+    /// [`Application::prepare_proposal`](crate::Application::prepare_proposal).
+    ///
+    /// This method is introduced in ABCI++.
+    fn prepare_proposal(&self, request: request::PrepareProposal) -> response::PrepareProposal {
+        response::PrepareProposal {
+            txs: truncate_proposal_txs(request.txs, request.max_tx_bytes),
+        }
+    }
+
+    /// A stage where the application can accept or reject the proposed block.
+    ///
+    /// The default implementation accepts the proposal.
+    ///
+    /// This method is introduced in ABCI++.
+    fn process_proposal(&self, _request: request::ProcessProposal) -> response::ProcessProposal {
+        response::ProcessProposal::Accept
+    }
+}
+
+/// Allows a [`DomainApplication`] to be served by the ABCI [`Server`].
+///
+/// The adapter takes care of converting incoming Protobuf requests into
+/// their domain types and the application's responses back into Protobuf
+/// messages.
+///
+/// [`Server`]: crate::Server
+#[derive(Debug, Clone, Default)]
+pub struct DomainAdapter<A>(A);
+
+impl<A: DomainApplication> DomainAdapter<A> {
+    /// Wrap the given application.
+    pub fn new(app: A) -> Self {
+        Self(app)
+    }
+
+    /// Access the wrapped application.
+    pub fn inner(&self) -> &A {
+        &self.0
+    }
+
+    fn dispatch(&self, request: Request) -> Response {
+        let app = &self.0;
+        match request {
+            Request::Echo(req) => Response::Echo(app.echo(req)),
+            Request::Flush => {
+                app.flush();
+                Response::Flush
+            },
+            Request::Info(req) => Response::Info(app.info(req)),
+            Request::InitChain(req) => Response::InitChain(app.init_chain(req)),
+            Request::Query(req) => Response::Query(app.query(req)),
+            Request::BeginBlock(req) => Response::BeginBlock(app.begin_block(req)),
+            Request::CheckTx(req) => Response::CheckTx(app.check_tx(req)),
+            Request::DeliverTx(req) => Response::DeliverTx(app.deliver_tx(req)),
+            Request::EndBlock(req) => Response::EndBlock(app.end_block(req)),
+            Request::Commit => Response::Commit(app.commit()),
+            Request::ListSnapshots => Response::ListSnapshots(app.list_snapshots()),
+            Request::OfferSnapshot(req) => Response::OfferSnapshot(app.offer_snapshot(req)),
+            Request::LoadSnapshotChunk(req) => {
+                Response::LoadSnapshotChunk(app.load_snapshot_chunk(req))
+            },
+            Request::ApplySnapshotChunk(req) => {
+                Response::ApplySnapshotChunk(app.apply_snapshot_chunk(req))
+            },
+            Request::PrepareProposal(req) => Response::PrepareProposal(app.prepare_proposal(req)),
+            Request::ProcessProposal(req) => Response::ProcessProposal(app.process_proposal(req)),
+        }
+    }
+}
+
+impl<A: DomainApplication> RequestDispatcher for DomainAdapter<A> {
+    fn handle(&self, request: pb::Request) -> pb::Response {
+        tracing::debug!("Incoming request: {:?}", request);
+        match Request::try_from(request) {
+            Ok(request) => catch_panic(|| self.dispatch(request).into()),
+            Err(e) => {
+                error!("Failed to convert incoming request: {}", e);
+                Response::Exception(response::Exception {
+                    error: format!("invalid request: {e}"),
+                })
+                .into()
+            },
+        }
+    }
+}
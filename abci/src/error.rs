@@ -17,12 +17,29 @@ define_error! {
             [ DisplayError<prost::DecodeError> ]
             | _ | { "error encoding protocol buffer" },
 
+        NegativeMessageLength
+            { length: i64 }
+            | e | { format_args!("negative message length prefix: {}", e.length) },
+
+        FrameTooLarge
+            {
+                size: u64,
+                max: usize,
+            }
+            | e | {
+                format_args!("message of {0} bytes exceeds maximum frame size of {1} bytes",
+                    e.size, e.max)
+            },
+
         ServerConnectionTerminated
             | _ | { "server connection terminated" },
 
         MalformedServerResponse
             | _ | { "malformed server response" },
 
+        UnsupportedRequest
+            | _ | { "request not supported by the ABCI v0.34 protocol" },
+
         UnexpectedServerResponseType
             {
                 expected: String,
@@ -33,6 +50,37 @@ define_error! {
                     e.expected, e.got)
             },
 
+        ResponseException
+            { error: String }
+            | e | { format_args!("server responded with an exception: {}", e.error) },
+
+        InvalidResponse
+            [ tendermint::Error ]
+            | _ | { "invalid response" },
+
+        InvalidGenesis
+            [ tendermint::Error ]
+            | _ | { "invalid genesis" },
+
+        SerializeAppState
+            { detail: String }
+            | e | { format_args!("error serializing the genesis application state: {}", e.detail) },
+
+        ProposalRejected
+            { height: tendermint::block::Height }
+            | e | { format_args!("proposal for block {} rejected by the application", e.height) },
+
+        GrpcTransport
+            { detail: String }
+            | e | { format_args!("gRPC transport error: {}", e.detail) },
+
+        GrpcStatus
+            { detail: String }
+            | e | { format_args!("gRPC request failed: {}", e.detail) },
+
+        TruncatedRecording
+            | _ | { "recorded session ends in the middle of a record" },
+
         ChannelSend
             | _ | { "channel send error" },
 
@@ -42,6 +90,12 @@ define_error! {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::io(e)
+    }
+}
+
 impl Error {
     pub fn send<T>(_e: std::sync::mpsc::SendError<T>) -> Error {
         Error::channel_send()
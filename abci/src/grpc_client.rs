@@ -0,0 +1,180 @@
+//! ABCI client over gRPC.
+
+use tendermint_proto::v0_37::abci::{
+    RequestApplySnapshotChunk, RequestBeginBlock, RequestCheckTx, RequestCommit, RequestDeliverTx,
+    RequestEcho, RequestEndBlock, RequestFlush, RequestInfo, RequestInitChain,
+    RequestListSnapshots, RequestLoadSnapshotChunk, RequestOfferSnapshot, RequestPrepareProposal,
+    RequestProcessProposal, RequestQuery, ResponseApplySnapshotChunk, ResponseBeginBlock,
+    ResponseCheckTx, ResponseCommit, ResponseDeliverTx, ResponseEcho, ResponseEndBlock,
+    ResponseFlush, ResponseInfo, ResponseInitChain, ResponseListSnapshots,
+    ResponseLoadSnapshotChunk, ResponseOfferSnapshot, ResponsePrepareProposal,
+    ResponseProcessProposal, ResponseQuery,
+};
+use tonic::{
+    client::Grpc,
+    codec::ProstCodec,
+    codegen::http::uri::PathAndQuery,
+    transport::{Channel, Endpoint},
+    Code,
+};
+
+use crate::{grpc_server::SERVICE_NAME, Error};
+
+/// ABCI client over gRPC, as used by Tendermint nodes configured with
+/// `abci = "grpc"`.
+///
+/// The client can be cloned in order to issue concurrent requests over the
+/// same channel.
+#[derive(Clone, Debug)]
+pub struct GrpcClient {
+    inner: Grpc<Channel>,
+}
+
+macro_rules! perform {
+    ($self:expr, $method:ident, $req:expr) => {
+        $self
+            .unary(
+                concat!("/tendermint.abci.ABCIApplication/", stringify!($method)),
+                $req,
+            )
+            .await
+    };
+}
+
+impl GrpcClient {
+    /// Connect to the gRPC server at the given address, of the form
+    /// `host:port`.
+    pub(crate) async fn connect(addr: String) -> Result<Self, Error> {
+        let channel = Endpoint::from_shared(format!("http://{addr}"))
+            .map_err(|e| Error::grpc_transport(e.to_string()))?
+            .connect()
+            .await
+            .map_err(|e| Error::grpc_transport(e.to_string()))?;
+        Ok(Self {
+            inner: Grpc::new(channel),
+        })
+    }
+
+    /// Ask the ABCI server to echo back a message.
+    pub async fn echo(&mut self, req: RequestEcho) -> Result<ResponseEcho, Error> {
+        perform!(self, Echo, req)
+    }
+
+    /// Request information about the ABCI application.
+    pub async fn info(&mut self, req: RequestInfo) -> Result<ResponseInfo, Error> {
+        perform!(self, Info, req)
+    }
+
+    /// To be called once upon genesis.
+    pub async fn init_chain(&mut self, req: RequestInitChain) -> Result<ResponseInitChain, Error> {
+        perform!(self, InitChain, req)
+    }
+
+    /// Query the application for data at the current or past height.
+    pub async fn query(&mut self, req: RequestQuery) -> Result<ResponseQuery, Error> {
+        perform!(self, Query, req)
+    }
+
+    /// Check the given transaction before putting it into the local mempool.
+    pub async fn check_tx(&mut self, req: RequestCheckTx) -> Result<ResponseCheckTx, Error> {
+        perform!(self, CheckTx, req)
+    }
+
+    /// Signal the beginning of a new block, prior to any `DeliverTx` calls.
+    pub async fn begin_block(
+        &mut self,
+        req: RequestBeginBlock,
+    ) -> Result<ResponseBeginBlock, Error> {
+        perform!(self, BeginBlock, req)
+    }
+
+    /// Apply a transaction to the application's state.
+    pub async fn deliver_tx(&mut self, req: RequestDeliverTx) -> Result<ResponseDeliverTx, Error> {
+        perform!(self, DeliverTx, req)
+    }
+
+    /// Signal the end of a block.
+    pub async fn end_block(&mut self, req: RequestEndBlock) -> Result<ResponseEndBlock, Error> {
+        perform!(self, EndBlock, req)
+    }
+
+    /// Flush the application's pending responses.
+    pub async fn flush(&mut self) -> Result<ResponseFlush, Error> {
+        perform!(self, Flush, RequestFlush {})
+    }
+
+    /// Commit the current state at the current height.
+    pub async fn commit(&mut self) -> Result<ResponseCommit, Error> {
+        perform!(self, Commit, RequestCommit {})
+    }
+
+    /// Used during state sync to discover available snapshots on peers.
+    pub async fn list_snapshots(&mut self) -> Result<ResponseListSnapshots, Error> {
+        perform!(self, ListSnapshots, RequestListSnapshots {})
+    }
+
+    /// Called when bootstrapping the node using state sync.
+    pub async fn offer_snapshot(
+        &mut self,
+        req: RequestOfferSnapshot,
+    ) -> Result<ResponseOfferSnapshot, Error> {
+        perform!(self, OfferSnapshot, req)
+    }
+
+    /// Used during state sync to retrieve chunks of snapshots from peers.
+    pub async fn load_snapshot_chunk(
+        &mut self,
+        req: RequestLoadSnapshotChunk,
+    ) -> Result<ResponseLoadSnapshotChunk, Error> {
+        perform!(self, LoadSnapshotChunk, req)
+    }
+
+    /// Apply the given snapshot chunk to the application's state.
+    pub async fn apply_snapshot_chunk(
+        &mut self,
+        req: RequestApplySnapshotChunk,
+    ) -> Result<ResponseApplySnapshotChunk, Error> {
+        perform!(self, ApplySnapshotChunk, req)
+    }
+
+    /// Ask the application to prepare a block proposal.
+    pub async fn prepare_proposal(
+        &mut self,
+        req: RequestPrepareProposal,
+    ) -> Result<ResponsePrepareProposal, Error> {
+        perform!(self, PrepareProposal, req)
+    }
+
+    /// Ask the application to accept or reject a block proposal.
+    pub async fn process_proposal(
+        &mut self,
+        req: RequestProcessProposal,
+    ) -> Result<ResponseProcessProposal, Error> {
+        perform!(self, ProcessProposal, req)
+    }
+
+    async fn unary<Req, Res>(&mut self, path: &'static str, req: Req) -> Result<Res, Error>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Res: prost::Message + Default + Send + Sync + 'static,
+    {
+        debug_assert!(path[1..].starts_with(SERVICE_NAME));
+        self.inner
+            .ready()
+            .await
+            .map_err(|e| Error::grpc_transport(e.to_string()))?;
+        self.inner
+            .unary(
+                tonic::Request::new(req),
+                PathAndQuery::from_static(path),
+                ProstCodec::default(),
+            )
+            .await
+            .map(tonic::Response::into_inner)
+            .map_err(|status| match status.code() {
+                // Exceptions raised by the application
+                Code::Unknown => Error::response_exception(status.message().to_string()),
+                _ => Error::grpc_status(status.to_string()),
+            })
+    }
+}
@@ -0,0 +1,226 @@
+//! ABCI application server interface over gRPC.
+
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    task::{Context, Poll},
+};
+
+use futures::stream;
+use tendermint_proto::v0_37::abci::{request, response, Request};
+use tokio::net::TcpListener;
+use tonic::{
+    codec::ProstCodec,
+    codegen::{empty_body, http, Body, BoxFuture, Service, StdError},
+    server::{Grpc, NamedService, UnaryService},
+    transport::Server,
+    Status,
+};
+use tracing::{error, info};
+
+use crate::{Error, RequestDispatcher};
+
+/// The fully qualified name of the ABCI gRPC service.
+pub(crate) const SERVICE_NAME: &str = "tendermint.abci.ABCIApplication";
+
+/// Exposes an ABCI application as the `ABCIApplication` gRPC service, for
+/// use with a [`tonic`] server.
+///
+/// Every request is handled on [`tokio`]'s blocking thread pool, by a clone
+/// of the application. Exceptions raised by the application are reported
+/// with the [`Unknown`](tonic::Code::Unknown) status code.
+#[derive(Clone, Debug)]
+pub struct GrpcService<App> {
+    app: App,
+}
+
+impl<App> GrpcService<App>
+where
+    App: RequestDispatcher + Clone + Send + 'static,
+{
+    /// Serve the given application.
+    pub fn new(app: App) -> Self {
+        Self { app }
+    }
+
+    fn unary<B, Req, Res>(
+        &self,
+        request: http::Request<B>,
+        wrap: fn(Req) -> request::Value,
+        unwrap: fn(response::Value) -> Result<Res, response::Value>,
+    ) -> BoxFuture<http::Response<tonic::body::BoxBody>, Infallible>
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+        Req: prost::Message + Default + Send + 'static,
+        Res: prost::Message + Send + Sync + 'static,
+    {
+        let method = Method {
+            app: self.app.clone(),
+            wrap,
+            unwrap,
+        };
+        Box::pin(async move {
+            let mut grpc = Grpc::new(ProstCodec::<Res, Req>::default());
+            Ok(grpc.unary(method, request).await)
+        })
+    }
+}
+
+/// Answers the requests of a single gRPC method.
+struct Method<App, Req, Res> {
+    app: App,
+    wrap: fn(Req) -> request::Value,
+    unwrap: fn(response::Value) -> Result<Res, response::Value>,
+}
+
+impl<App, Req, Res> UnaryService<Req> for Method<App, Req, Res>
+where
+    App: RequestDispatcher + Clone + Send + 'static,
+    Req: Send + 'static,
+    Res: Send + 'static,
+{
+    type Response = Res;
+    type Future = BoxFuture<tonic::Response<Res>, Status>;
+
+    fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+        let app = self.app.clone();
+        let request = Request {
+            value: Some((self.wrap)(request.into_inner())),
+        };
+        let unwrap = self.unwrap;
+        Box::pin(async move {
+            let response = tokio::task::spawn_blocking(move || app.handle(request))
+                .await
+                .map_err(|e| Status::internal(format!("request handler failed: {e}")))?;
+            match response.value {
+                Some(response::Value::Exception(e)) => Err(Status::unknown(e.error)),
+                Some(value) => unwrap(value).map(tonic::Response::new).map_err(|value| {
+                    error!("Application produced unexpected response: {:?}", value);
+                    Status::internal("unexpected response type")
+                }),
+                None => Err(Status::internal("empty response")),
+            }
+        })
+    }
+}
+
+/// Route the request with the given path to the corresponding method.
+macro_rules! route {
+    ($self:expr, $request:expr, $($method:ident),+ $(,)?) => {{
+        let path = $request.uri().path().to_string();
+        match path
+            .strip_prefix('/')
+            .and_then(|path| path.strip_prefix(SERVICE_NAME))
+            .and_then(|path| path.strip_prefix('/'))
+        {
+            $(
+                Some(stringify!($method)) => $self.unary(
+                    $request,
+                    request::Value::$method,
+                    |value| match value {
+                        response::Value::$method(r) => Ok(r),
+                        value => Err(value),
+                    },
+                ),
+            )+
+            _ => Box::pin(async move {
+                // Unimplemented method
+                Ok(http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", "12")
+                    .header("content-type", "application/grpc")
+                    .body(empty_body())
+                    .unwrap())
+            }),
+        }
+    }};
+}
+
+impl<App, B> Service<http::Request<B>> for GrpcService<App>
+where
+    App: RequestDispatcher + Clone + Send + 'static,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        route!(
+            self,
+            request,
+            Echo,
+            Flush,
+            Info,
+            DeliverTx,
+            CheckTx,
+            Query,
+            Commit,
+            InitChain,
+            BeginBlock,
+            EndBlock,
+            ListSnapshots,
+            OfferSnapshot,
+            LoadSnapshotChunk,
+            ApplySnapshotChunk,
+            PrepareProposal,
+            ProcessProposal,
+        )
+    }
+}
+
+impl<App> NamedService for GrpcService<App> {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+/// A gRPC server for serving a specific ABCI application.
+///
+/// Unlike the socket protocol, the gRPC protocol does not distinguish
+/// between Tendermint's connections to the application: all requests are
+/// served concurrently, by clones of the application.
+pub struct GrpcServer<App> {
+    service: GrpcService<App>,
+    listener: TcpListener,
+    local_addr: SocketAddr,
+}
+
+impl<App> GrpcServer<App>
+where
+    App: RequestDispatcher + Clone + Send + 'static,
+{
+    pub(crate) fn new(app: App, listener: TcpListener) -> Result<Self, Error> {
+        let local_addr = listener.local_addr().map_err(Error::io)?;
+        info!("ABCI gRPC server running at {}", local_addr);
+        Ok(Self {
+            service: GrpcService::new(app),
+            listener,
+            local_addr,
+        })
+    }
+
+    /// Getter for this server's local address, of the form `host:port`.
+    pub fn local_addr(&self) -> String {
+        self.local_addr.to_string()
+    }
+
+    /// Serve requests until the server fails.
+    ///
+    /// Must be called from within a [`tokio`] runtime.
+    pub async fn listen(self) -> Result<(), Error> {
+        let incoming = stream::unfold(self.listener, |listener| async move {
+            let accepted = listener.accept().await.map(|(stream, _)| stream);
+            Some((accepted, listener))
+        });
+        Server::builder()
+            .add_service(self.service)
+            .serve_with_incoming(incoming)
+            .await
+            .map_err(|e| Error::grpc_transport(e.to_string()))
+    }
+}
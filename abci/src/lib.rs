@@ -3,11 +3,29 @@
 //! [Tendermint]: https://tendermint.com
 
 mod application;
+#[cfg(feature = "async")]
+mod async_application;
+#[cfg(all(feature = "async", feature = "client"))]
+mod async_client;
+#[cfg(feature = "async")]
+mod async_server;
 #[cfg(feature = "client")]
 mod client;
 mod codec;
+mod domain_application;
 pub mod error;
+#[cfg(all(feature = "grpc", feature = "client"))]
+mod grpc_client;
+#[cfg(feature = "grpc")]
+mod grpc_server;
+pub mod middleware;
+#[cfg(feature = "mock-node")]
+mod mock_node;
+mod protocol;
 mod server;
+mod service;
+mod session;
+mod transport;
 
 // Common exports
 // Example applications
@@ -15,8 +33,24 @@ mod server;
 pub use application::echo::EchoApp;
 #[cfg(feature = "kvstore-app")]
 pub use application::kvstore::{KeyValueStoreApp, KeyValueStoreDriver};
-pub use application::Application;
+pub use application::{Application, RequestDispatcher};
+#[cfg(feature = "async")]
+pub use async_application::AsyncApplication;
+#[cfg(all(feature = "async", feature = "client"))]
+pub use async_client::AsyncClient;
+#[cfg(feature = "async")]
+pub use async_server::{AsyncServer, ShutdownHandle};
 #[cfg(feature = "client")]
 pub use client::{Client, ClientBuilder};
+pub use domain_application::{DomainAdapter, DomainApplication};
 pub use error::Error;
-pub use server::{Server, ServerBuilder};
+#[cfg(all(feature = "grpc", feature = "client"))]
+pub use grpc_client::GrpcClient;
+#[cfg(feature = "grpc")]
+pub use grpc_server::{GrpcServer, GrpcService};
+#[cfg(feature = "mock-node")]
+pub use mock_node::{ExecutedBlock, MockNode};
+pub use protocol::ProtocolVersion;
+pub use server::{FailureCount, Server, ServerBuilder};
+pub use service::{Consensus, Info, Mempool, ServiceDispatcher, Services, Snapshot};
+pub use session::{replay_session, Divergence, SessionReader, SessionRecorder};
@@ -0,0 +1,148 @@
+//! Middleware wrapping ABCI applications.
+//!
+//! Much like [tower] layers, each middleware wraps a [`RequestDispatcher`]
+//! (including any [`Application`](crate::Application)) into another
+//! dispatcher, which may inspect, time, answer or pass on every request. A
+//! [`Layer`] produces such a wrapper, and layers can be stacked through a
+//! [`MiddlewareBuilder`]:
+//!
+//! ```rust
+//! use tendermint_abci::{
+//!     middleware::{MetricsLayer, MiddlewareBuilder, ReadOnlyLayer, TraceLayer},
+//!     Application, RequestDispatcher,
+//! };
+//! use tendermint_proto::v0_37::abci::{request, Request, RequestDeliverTx};
+//!
+//! #[derive(Clone)]
+//! struct MyApp;
+//!
+//! impl Application for MyApp {}
+//!
+//! let metrics = MetricsLayer::new();
+//! let app = MiddlewareBuilder::new()
+//!     .layer(TraceLayer)
+//!     .layer(metrics.clone())
+//!     .layer(ReadOnlyLayer)
+//!     .build(MyApp);
+//!
+//! // The read-only guard answers the request, which is traced and measured
+//! // by the outer layers.
+//! app.handle(Request {
+//!     value: Some(request::Value::DeliverTx(RequestDeliverTx::default())),
+//! });
+//! assert_eq!(metrics.metrics().get("deliver_tx").exceptions, 1);
+//! ```
+//!
+//! [tower]: https://docs.rs/tower
+
+mod metrics;
+mod rate_limit;
+mod read_only;
+mod trace;
+
+pub use metrics::{MethodMetrics, MethodStats, Metrics, MetricsLayer};
+pub use rate_limit::{CheckTxRateLimit, CheckTxRateLimitLayer};
+pub use read_only::{ReadOnly, ReadOnlyLayer};
+use tendermint_proto::v0_37::abci::{request::Value, Request};
+pub use trace::{Trace, TraceLayer};
+
+use crate::RequestDispatcher;
+
+/// Wraps a [`RequestDispatcher`] into another one.
+pub trait Layer<D> {
+    /// The wrapping dispatcher.
+    type Dispatcher: RequestDispatcher;
+
+    /// Wrap the given dispatcher.
+    fn layer(&self, inner: D) -> Self::Dispatcher;
+}
+
+/// The layer which leaves dispatchers untouched.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Identity;
+
+impl<D: RequestDispatcher> Layer<D> for Identity {
+    type Dispatcher = D;
+
+    fn layer(&self, inner: D) -> D {
+        inner
+    }
+}
+
+/// Two layers, one wrapping the other.
+#[derive(Clone, Debug)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<D, Inner, Outer> Layer<D> for Stack<Inner, Outer>
+where
+    Inner: Layer<D>,
+    Outer: Layer<Inner::Dispatcher>,
+{
+    type Dispatcher = Outer::Dispatcher;
+
+    fn layer(&self, inner: D) -> Self::Dispatcher {
+        self.outer.layer(self.inner.layer(inner))
+    }
+}
+
+/// Stacks layers around an application.
+///
+/// Layers see requests in the order in which they were added: the first
+/// layer is the outermost one.
+#[derive(Clone, Debug, Default)]
+pub struct MiddlewareBuilder<L> {
+    layer: L,
+}
+
+impl MiddlewareBuilder<Identity> {
+    /// Start with an empty stack of layers.
+    pub fn new() -> Self {
+        Self { layer: Identity }
+    }
+}
+
+impl<L> MiddlewareBuilder<L> {
+    /// Add the given layer, which wraps all layers added after it.
+    pub fn layer<T>(self, layer: T) -> MiddlewareBuilder<Stack<T, L>> {
+        MiddlewareBuilder {
+            layer: Stack {
+                inner: layer,
+                outer: self.layer,
+            },
+        }
+    }
+
+    /// Wrap the given application in the stacked layers.
+    pub fn build<D>(&self, app: D) -> L::Dispatcher
+    where
+        L: Layer<D>,
+    {
+        self.layer.layer(app)
+    }
+}
+
+/// The name of the ABCI method invoked by the given request.
+pub(crate) fn method_name(request: &Request) -> &'static str {
+    match &request.value {
+        Some(Value::Echo(_)) => "echo",
+        Some(Value::Flush(_)) => "flush",
+        Some(Value::Info(_)) => "info",
+        Some(Value::InitChain(_)) => "init_chain",
+        Some(Value::Query(_)) => "query",
+        Some(Value::BeginBlock(_)) => "begin_block",
+        Some(Value::CheckTx(_)) => "check_tx",
+        Some(Value::DeliverTx(_)) => "deliver_tx",
+        Some(Value::EndBlock(_)) => "end_block",
+        Some(Value::Commit(_)) => "commit",
+        Some(Value::ListSnapshots(_)) => "list_snapshots",
+        Some(Value::OfferSnapshot(_)) => "offer_snapshot",
+        Some(Value::LoadSnapshotChunk(_)) => "load_snapshot_chunk",
+        Some(Value::ApplySnapshotChunk(_)) => "apply_snapshot_chunk",
+        Some(Value::PrepareProposal(_)) => "prepare_proposal",
+        Some(Value::ProcessProposal(_)) => "process_proposal",
+        None => "unknown",
+    }
+}
@@ -0,0 +1,124 @@
+//! Per-method request metrics.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tendermint_proto::v0_37::abci::{response, Request, Response};
+
+use super::{method_name, Layer};
+use crate::RequestDispatcher;
+
+/// Statistics about the requests made to a single ABCI method.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MethodStats {
+    /// The number of requests handled.
+    pub count: u64,
+    /// The number of requests answered with an exception.
+    pub exceptions: u64,
+    /// The total time spent handling requests.
+    pub total_latency: Duration,
+    /// The longest time spent handling a single request.
+    pub max_latency: Duration,
+}
+
+impl MethodStats {
+    /// The average time spent handling a request.
+    pub fn mean_latency(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(self.total_latency.as_secs_f64() / self.count as f64)
+        }
+    }
+}
+
+/// A handle to the metrics collected by [`Metrics`] dispatchers, which is
+/// shared by all of its clones.
+#[derive(Clone, Debug, Default)]
+pub struct MethodMetrics(Arc<Mutex<BTreeMap<&'static str, MethodStats>>>);
+
+impl MethodMetrics {
+    /// The statistics of the method with the given name (e.g. `check_tx`).
+    pub fn get(&self, method: &str) -> MethodStats {
+        self.lock().get(method).copied().unwrap_or_default()
+    }
+
+    /// The statistics of all methods invoked so far, by method name.
+    pub fn snapshot(&self) -> BTreeMap<&'static str, MethodStats> {
+        self.lock().clone()
+    }
+
+    fn record(&self, method: &'static str, latency: Duration, exception: bool) {
+        let mut metrics = self.lock();
+        let stats = metrics.entry(method).or_default();
+        stats.count += 1;
+        stats.exceptions += u64::from(exception);
+        stats.total_latency += latency;
+        stats.max_latency = stats.max_latency.max(latency);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<&'static str, MethodStats>> {
+        // Metrics are updated atomically, so they remain consistent even if
+        // the lock has been poisoned
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Produces [`Metrics`] dispatchers, all of which record their metrics
+/// through the same [`MethodMetrics`].
+#[derive(Clone, Debug, Default)]
+pub struct MetricsLayer {
+    metrics: MethodMetrics,
+}
+
+impl MetricsLayer {
+    /// Constructor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The metrics recorded by the dispatchers produced by this layer.
+    pub fn metrics(&self) -> MethodMetrics {
+        self.metrics.clone()
+    }
+}
+
+impl<D: RequestDispatcher> Layer<D> for MetricsLayer {
+    type Dispatcher = Metrics<D>;
+
+    fn layer(&self, inner: D) -> Metrics<D> {
+        Metrics {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+/// Counts the requests handled by the wrapped dispatcher, and measures their
+/// latency, per ABCI method.
+#[derive(Clone, Debug)]
+pub struct Metrics<D> {
+    inner: D,
+    metrics: MethodMetrics,
+}
+
+impl<D> Metrics<D> {
+    /// The metrics recorded by this dispatcher.
+    pub fn metrics(&self) -> MethodMetrics {
+        self.metrics.clone()
+    }
+}
+
+impl<D: RequestDispatcher> RequestDispatcher for Metrics<D> {
+    fn handle(&self, request: Request) -> Response {
+        let method = method_name(&request);
+        let start = Instant::now();
+        let response = self.inner.handle(request);
+        let exception = matches!(response.value, Some(response::Value::Exception(_)));
+        self.metrics.record(method, start.elapsed(), exception);
+        response
+    }
+}
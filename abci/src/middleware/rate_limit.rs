@@ -0,0 +1,123 @@
+//! Rate limiting of `CheckTx` requests.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use tendermint_proto::v0_37::abci::{
+    request, response, CheckTxType, Request, Response, ResponseCheckTx,
+};
+
+use super::Layer;
+use crate::RequestDispatcher;
+
+/// The code with which rate-limited transactions are rejected by default.
+const DEFAULT_REJECTION_CODE: u32 = 1;
+
+/// Produces [`CheckTxRateLimit`] dispatchers, all of which draw from the same
+/// budget.
+#[derive(Clone, Debug)]
+pub struct CheckTxRateLimitLayer {
+    bucket: Arc<Mutex<TokenBucket>>,
+    code: u32,
+}
+
+impl CheckTxRateLimitLayer {
+    /// Admit `rate` new transactions per second on average, and up to
+    /// `burst` transactions at once.
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(TokenBucket::new(rate, burst))),
+            code: DEFAULT_REJECTION_CODE,
+        }
+    }
+
+    /// Reject rate-limited transactions with the given (non-zero) code.
+    pub fn rejection_code(mut self, code: u32) -> Self {
+        self.code = code;
+        self
+    }
+}
+
+impl<D: RequestDispatcher> Layer<D> for CheckTxRateLimitLayer {
+    type Dispatcher = CheckTxRateLimit<D>;
+
+    fn layer(&self, inner: D) -> CheckTxRateLimit<D> {
+        CheckTxRateLimit {
+            inner,
+            bucket: self.bucket.clone(),
+            code: self.code,
+        }
+    }
+}
+
+/// Limits the rate at which new transactions are checked by the wrapped
+/// dispatcher.
+///
+/// Transactions exceeding the limit are rejected with a non-zero code, such
+/// that Tendermint keeps them out of its mempool, rather than with an
+/// exception. Re-checks of transactions already in the mempool are never
+/// limited.
+#[derive(Clone, Debug)]
+pub struct CheckTxRateLimit<D> {
+    inner: D,
+    bucket: Arc<Mutex<TokenBucket>>,
+    code: u32,
+}
+
+impl<D: RequestDispatcher> RequestDispatcher for CheckTxRateLimit<D> {
+    fn handle(&self, request: Request) -> Response {
+        if let Some(request::Value::CheckTx(check_tx)) = &request.value {
+            let admitted = check_tx.r#type == CheckTxType::Recheck as i32
+                || self
+                    .bucket
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .try_acquire();
+            if !admitted {
+                return Response {
+                    value: Some(response::Value::CheckTx(ResponseCheckTx {
+                        code: self.code,
+                        log: "rate limit exceeded".to_string(),
+                        ..Default::default()
+                    })),
+                };
+            }
+        }
+        self.inner.handle(request)
+    }
+}
+
+/// Holds up to `capacity` tokens, refilled at `rate` tokens per second.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            capacity: f64::from(burst),
+            rate,
+            tokens: f64::from(burst),
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Rejection of state-changing requests.
+
+use tendermint_proto::v0_37::abci::{request::Value, Request, Response};
+
+use super::{method_name, Layer};
+use crate::{application::exception, RequestDispatcher};
+
+/// Produces [`ReadOnly`] dispatchers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReadOnlyLayer;
+
+impl<D: RequestDispatcher> Layer<D> for ReadOnlyLayer {
+    type Dispatcher = ReadOnly<D>;
+
+    fn layer(&self, inner: D) -> ReadOnly<D> {
+        ReadOnly { inner }
+    }
+}
+
+/// Answers requests which would change the application's state with an
+/// exception, rather than passing them on to the wrapped dispatcher.
+///
+/// The rejected requests are those of the consensus connection (from
+/// `InitChain` to `Commit`), as well as `OfferSnapshot` and
+/// `ApplySnapshotChunk`, which restore the application's state from a
+/// snapshot. Queries, transaction checks and the serving of snapshots are
+/// passed on.
+#[derive(Clone, Debug)]
+pub struct ReadOnly<D> {
+    inner: D,
+}
+
+impl<D: RequestDispatcher> RequestDispatcher for ReadOnly<D> {
+    fn handle(&self, request: Request) -> Response {
+        match request.value {
+            Some(
+                Value::InitChain(_)
+                | Value::PrepareProposal(_)
+                | Value::ProcessProposal(_)
+                | Value::BeginBlock(_)
+                | Value::DeliverTx(_)
+                | Value::EndBlock(_)
+                | Value::Commit(_)
+                | Value::OfferSnapshot(_)
+                | Value::ApplySnapshotChunk(_),
+            ) => exception(format!(
+                "{} rejected: the application is read-only",
+                method_name(&request)
+            )),
+            _ => self.inner.handle(request),
+        }
+    }
+}
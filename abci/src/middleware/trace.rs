@@ -0,0 +1,50 @@
+//! Tracing of requests and responses.
+
+use std::time::Instant;
+
+use tendermint_proto::v0_37::abci::{response, Request, Response};
+use tracing::{debug, trace, warn};
+
+use super::{method_name, Layer};
+use crate::RequestDispatcher;
+
+/// Produces [`Trace`] dispatchers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TraceLayer;
+
+impl<D: RequestDispatcher> Layer<D> for TraceLayer {
+    type Dispatcher = Trace<D>;
+
+    fn layer(&self, inner: D) -> Trace<D> {
+        Trace { inner }
+    }
+}
+
+/// Logs every request handled by the wrapped dispatcher, along with the time
+/// taken to handle it.
+///
+/// Timings are logged at the `DEBUG` level, and failures at the `WARN`
+/// level, whereas the (potentially large) contents of requests and responses
+/// are only logged at the `TRACE` level.
+#[derive(Clone, Debug)]
+pub struct Trace<D> {
+    inner: D,
+}
+
+impl<D: RequestDispatcher> RequestDispatcher for Trace<D> {
+    fn handle(&self, request: Request) -> Response {
+        let method = method_name(&request);
+        trace!("Handling {} request: {:?}", method, request);
+        let start = Instant::now();
+        let response = self.inner.handle(request);
+        let elapsed = start.elapsed();
+        match &response.value {
+            Some(response::Value::Exception(e)) => {
+                warn!("{} request failed after {:?}: {}", method, elapsed, e.error)
+            },
+            _ => debug!("{} request handled in {:?}", method, elapsed),
+        }
+        trace!("Responding to {} request: {:?}", method, response);
+        response
+    }
+}
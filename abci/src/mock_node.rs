@@ -0,0 +1,407 @@
+//! A mock Tendermint node, which executes blocks against an ABCI application
+//! without any consensus or networking.
+//!
+//! The [`MockNode`] initializes the application from a [`Genesis`], and
+//! subsequently drives the full lifecycle of each block (`PrepareProposal`,
+//! `ProcessProposal`, `BeginBlock`, `DeliverTx`, `EndBlock` and `Commit`),
+//! all of it in-process. This allows application logic to be tested without
+//! a Tendermint binary.
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use prost::Message;
+use serde::Serialize;
+use tendermint::{
+    abci::{
+        request, response,
+        types::{CommitInfo, Validator, VoteInfo},
+    },
+    account, block,
+    consensus::Params,
+    crypto::{default::Sha256, Sha256 as _},
+    merkle::simple_hash_from_byte_vectors,
+    validator, AppHash, Genesis, Hash, Time,
+};
+use tendermint_proto::v0_37::{abci as pb, types::HashedParams};
+
+use crate::{application::RequestDispatcher, Error};
+
+/// The block protocol version implemented by Tendermint Core v0.34 and v0.37.
+const BLOCK_PROTOCOL_VERSION: u64 = 11;
+
+/// Perform a request of the given type against the application, and convert
+/// the response into its domain type.
+macro_rules! call {
+    ($app:expr, $type:ident, $req:expr) => {
+        match $app
+            .handle(pb::Request {
+                value: Some(pb::request::Value::$type($req.into())),
+            })
+            .value
+        {
+            Some(pb::response::Value::$type(r)) => r.try_into().map_err(Error::invalid_response),
+            Some(pb::response::Value::Exception(e)) => Err(Error::response_exception(e.error)),
+            Some(r) => Err(Error::unexpected_server_response_type(
+                stringify!($type).to_string(),
+                r,
+            )),
+            None => Err(Error::malformed_server_response()),
+        }
+    };
+}
+
+/// The outcome of executing a single block.
+#[derive(Clone, Debug)]
+pub struct ExecutedBlock {
+    /// The header of the block.
+    pub header: block::Header,
+    /// The transactions included in the block, as prepared by the
+    /// application.
+    pub txs: Vec<Bytes>,
+    /// The results of executing each of the block's transactions.
+    pub deliver_txs: Vec<response::DeliverTx>,
+    /// The application's response to the end of the block, including its
+    /// validator updates.
+    pub end_block: response::EndBlock,
+    /// The application hash after committing the block.
+    pub app_hash: AppHash,
+}
+
+/// Executes blocks against an ABCI application, as a Tendermint node would.
+///
+/// Every block is proposed by a validator of the current set (in turn), and
+/// signed by all validators of the previous set. Validator updates returned
+/// by the application at height `H` take effect at height `H + 2`, and
+/// consensus parameter updates at height `H + 1`.
+///
+/// Block headers carry genuine validator set, consensus parameter,
+/// transaction and result hashes, but no commits or evidence.
+pub struct MockNode<App> {
+    app: App,
+    chain_id: tendermint::chain::Id,
+    consensus_params: Params,
+    // The validator sets of the last, the current and the next height
+    last_validators: validator::Set,
+    validators: validator::Set,
+    next_validators: validator::Set,
+    // The height and time of the next block
+    height: block::Height,
+    time: Time,
+    last_block_id: Option<block::Id>,
+    last_results_hash: Option<Hash>,
+    app_hash: AppHash,
+    app_hashes: Vec<AppHash>,
+}
+
+impl<App: RequestDispatcher> MockNode<App> {
+    /// Initialize the given application by way of an `InitChain` request
+    /// carrying the given genesis data.
+    pub fn new<AppState>(app: App, genesis: &Genesis<AppState>) -> Result<Self, Error>
+    where
+        AppState: Serialize,
+    {
+        let app_state_bytes = serde_json::to_vec(&genesis.app_state)
+            .map_err(|e| Error::serialize_app_state(e.to_string()))?
+            .into();
+        let initial_height =
+            block::Height::try_from(genesis.initial_height).map_err(Error::invalid_genesis)?;
+        let response: response::InitChain = call!(
+            app,
+            InitChain,
+            request::InitChain {
+                time: genesis.genesis_time,
+                chain_id: genesis.chain_id.to_string(),
+                consensus_params: genesis.consensus_params.clone(),
+                validators: genesis
+                    .validators
+                    .iter()
+                    .map(|v| validator::Update {
+                        pub_key: v.pub_key,
+                        power: v.power,
+                    })
+                    .collect(),
+                app_state_bytes,
+                initial_height,
+            }
+        )?;
+
+        // The application may override the genesis validators, consensus
+        // parameters and application hash.
+        let validators = if response.validators.is_empty() {
+            validator::Set::new(genesis.validators.clone(), None)
+        } else {
+            apply_updates(&validator::Set::new(vec![], None), &response.validators)
+        };
+        let app_hash = if response.app_hash.as_ref().is_empty() {
+            genesis.app_hash.clone()
+        } else {
+            response.app_hash
+        };
+        Ok(Self {
+            app,
+            chain_id: genesis.chain_id.clone(),
+            consensus_params: response
+                .consensus_params
+                .unwrap_or_else(|| genesis.consensus_params.clone()),
+            last_validators: validator::Set::new(vec![], None),
+            validators: validators.clone(),
+            next_validators: validators,
+            height: initial_height,
+            time: genesis.genesis_time,
+            last_block_id: None,
+            last_results_hash: None,
+            app_hash,
+            app_hashes: vec![],
+        })
+    }
+
+    /// Execute the given transactions, in blocks containing at most
+    /// `txs_per_block` transactions each.
+    pub fn execute<I>(&mut self, txs: I, txs_per_block: usize) -> Result<Vec<ExecutedBlock>, Error>
+    where
+        I: IntoIterator<Item = Bytes>,
+    {
+        let mut txs = txs.into_iter().peekable();
+        let mut blocks = vec![];
+        while txs.peek().is_some() {
+            let block_txs = txs.by_ref().take(txs_per_block.max(1)).collect();
+            blocks.push(self.execute_block(block_txs)?);
+        }
+        Ok(blocks)
+    }
+
+    /// Propose, execute and commit a single block containing the given
+    /// transactions, subject to the application's `PrepareProposal` and
+    /// `ProcessProposal` decisions.
+    pub fn execute_block(&mut self, txs: Vec<Bytes>) -> Result<ExecutedBlock, Error> {
+        let height = self.height;
+        let proposer_address = self.proposer_address();
+        let last_commit_info = self.last_commit_info();
+        let next_validators_hash = self.next_validators.hash();
+
+        let prepared: response::PrepareProposal = call!(
+            self.app,
+            PrepareProposal,
+            request::PrepareProposal {
+                max_tx_bytes: self.consensus_params.block.max_bytes as i64,
+                txs,
+                local_last_commit: Some(last_commit_info.clone()),
+                misbehavior: vec![],
+                height,
+                time: self.time,
+                next_validators_hash,
+                proposer_address,
+            }
+        )?;
+        let txs = prepared.txs;
+        let header = self.header(&txs, proposer_address);
+        let hash = header.hash();
+
+        let processed: response::ProcessProposal = call!(
+            self.app,
+            ProcessProposal,
+            request::ProcessProposal {
+                txs: txs.clone(),
+                proposed_last_commit: Some(last_commit_info.clone()),
+                misbehavior: vec![],
+                hash,
+                height,
+                time: self.time,
+                next_validators_hash,
+                proposer_address,
+            }
+        )?;
+        if processed != response::ProcessProposal::Accept {
+            return Err(Error::proposal_rejected(height));
+        }
+
+        let _: response::BeginBlock = call!(
+            self.app,
+            BeginBlock,
+            request::BeginBlock {
+                hash,
+                header: header.clone(),
+                last_commit_info,
+                byzantine_validators: vec![],
+            }
+        )?;
+        let deliver_txs = txs
+            .iter()
+            .map(|tx| call!(self.app, DeliverTx, request::DeliverTx { tx: tx.clone() }))
+            .collect::<Result<Vec<response::DeliverTx>, Error>>()?;
+        let end_block: response::EndBlock = call!(
+            self.app,
+            EndBlock,
+            request::EndBlock {
+                height: height.into()
+            }
+        )?;
+        let commit: response::Commit = call!(self.app, Commit, pb::RequestCommit {})?;
+        let app_hash = AppHash::try_from(commit.data).map_err(Error::invalid_response)?;
+
+        // Prepare for the next block
+        let next_validators = apply_updates(&self.next_validators, &end_block.validator_updates);
+        self.last_validators = std::mem::replace(
+            &mut self.validators,
+            std::mem::replace(&mut self.next_validators, next_validators),
+        );
+        if let Some(params) = &end_block.consensus_param_updates {
+            self.consensus_params = params.clone();
+        }
+        self.height = height.increment();
+        self.time = (self.time + Duration::from_secs(1)).map_err(Error::invalid_response)?;
+        self.last_block_id = Some(block::Id {
+            hash,
+            part_set_header: Default::default(),
+        });
+        self.last_results_hash = Some(results_hash(&deliver_txs));
+        self.app_hash = app_hash.clone();
+        self.app_hashes.push(app_hash.clone());
+
+        Ok(ExecutedBlock {
+            header,
+            txs,
+            deliver_txs,
+            end_block,
+            app_hash,
+        })
+    }
+
+    /// The height of the next block to be executed.
+    pub fn height(&self) -> block::Height {
+        self.height
+    }
+
+    /// The application hash resulting from the last committed block (or
+    /// from genesis, if no block has been committed yet).
+    pub fn app_hash(&self) -> &AppHash {
+        &self.app_hash
+    }
+
+    /// The application hashes resulting from each committed block, in order
+    /// of height.
+    pub fn app_hashes(&self) -> &[AppHash] {
+        &self.app_hashes
+    }
+
+    /// The validator set for the next block to be executed.
+    pub fn validators(&self) -> &validator::Set {
+        &self.validators
+    }
+
+    /// The validator set for the block after the next one.
+    pub fn next_validators(&self) -> &validator::Set {
+        &self.next_validators
+    }
+
+    /// The consensus parameters for the next block to be executed.
+    pub fn consensus_params(&self) -> &Params {
+        &self.consensus_params
+    }
+
+    /// Access the application.
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    /// The validators of the current set take turns proposing blocks.
+    fn proposer_address(&self) -> account::Id {
+        let validators = self.validators.validators();
+        if validators.is_empty() {
+            return account::Id::new([0; 20]);
+        }
+        validators[(self.height.value() % validators.len() as u64) as usize].address
+    }
+
+    /// All validators of the previous set sign each block.
+    fn last_commit_info(&self) -> CommitInfo {
+        CommitInfo {
+            round: Default::default(),
+            votes: self
+                .last_validators
+                .validators()
+                .iter()
+                .map(|v| VoteInfo {
+                    validator: Validator {
+                        address: v.address.as_bytes().try_into().unwrap(),
+                        power: v.power,
+                    },
+                    signed_last_block: true,
+                })
+                .collect(),
+        }
+    }
+
+    fn header(&self, txs: &[Bytes], proposer_address: account::Id) -> block::Header {
+        let tx_hashes: Vec<Vec<u8>> = txs.iter().map(|tx| Sha256::digest(tx).to_vec()).collect();
+        block::Header {
+            version: block::header::Version {
+                block: BLOCK_PROTOCOL_VERSION,
+                app: self
+                    .consensus_params
+                    .version
+                    .as_ref()
+                    .map(|v| v.app)
+                    .unwrap_or_default(),
+            },
+            chain_id: self.chain_id.clone(),
+            height: self.height,
+            time: self.time,
+            last_block_id: self.last_block_id,
+            last_commit_hash: None,
+            data_hash: Some(Hash::Sha256(simple_hash_from_byte_vectors::<Sha256>(
+                &tx_hashes,
+            ))),
+            validators_hash: self.validators.hash(),
+            next_validators_hash: self.next_validators.hash(),
+            consensus_hash: consensus_hash(&self.consensus_params),
+            app_hash: self.app_hash.clone(),
+            last_results_hash: self.last_results_hash,
+            evidence_hash: None,
+            proposer_address,
+        }
+    }
+}
+
+/// Apply the given validator updates to the given validator set, where a
+/// voting power of zero removes a validator.
+fn apply_updates(set: &validator::Set, updates: &[validator::Update]) -> validator::Set {
+    let mut validators = set.validators().clone();
+    for update in updates {
+        let address = account::Id::from(update.pub_key);
+        validators.retain(|v| v.address != address);
+        if update.power.value() > 0 {
+            validators.push(validator::Info::new(update.pub_key, update.power));
+        }
+    }
+    validator::Set::new(validators, None)
+}
+
+/// The hash of the consensus parameters, as included in block headers.
+fn consensus_hash(params: &Params) -> Hash {
+    let hashed = HashedParams {
+        block_max_bytes: params.block.max_bytes as i64,
+        block_max_gas: params.block.max_gas,
+    };
+    Hash::Sha256(Sha256::digest(hashed.encode_to_vec()))
+}
+
+/// The Merkle root of the deterministic parts of the given transaction
+/// results, as included in the header of the subsequent block.
+fn results_hash(results: &[response::DeliverTx]) -> Hash {
+    let results: Vec<Vec<u8>> = results
+        .iter()
+        .map(|r| {
+            pb::ResponseDeliverTx {
+                code: r.code.into(),
+                data: r.data.clone(),
+                gas_wanted: r.gas_wanted,
+                gas_used: r.gas_used,
+                ..Default::default()
+            }
+            .encode_to_vec()
+        })
+        .collect();
+    Hash::Sha256(simple_hash_from_byte_vectors::<Sha256>(&results))
+}
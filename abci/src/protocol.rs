@@ -0,0 +1,105 @@
+//! ABCI wire protocol versions.
+//!
+//! Applications are always implemented in terms of the latest (v0.37)
+//! protocol. Requests received from Tendermint Core v0.34 nodes are
+//! translated into their v0.37 counterparts before being dispatched to the
+//! application, and the application's responses are translated back.
+
+use prost::Message;
+use tendermint_proto::{v0_34::abci as pb34, v0_37::abci as pb};
+use tracing::{debug, error};
+
+/// The version of the ABCI wire protocol spoken by a server or client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ProtocolVersion {
+    /// The protocol spoken by Tendermint Core v0.34.
+    ///
+    /// Messages are framed with a signed (zigzag-encoded) varint length
+    /// prefix. `SetOption` requests are answered with a default, successful
+    /// response without reaching the application, and the ABCI++ methods
+    /// (`PrepareProposal` and `ProcessProposal`) are never invoked.
+    V0_34,
+    /// The protocol spoken by Tendermint Core v0.37.
+    ///
+    /// Messages are framed with an unsigned varint length prefix.
+    #[default]
+    V0_37,
+}
+
+/// Translate an incoming v0.34 request into its v0.37 counterpart.
+///
+/// Requests that need no further handling by the application, as well as
+/// requests that cannot be translated, produce the v0.34 response to be sent
+/// back to the client as the error value.
+pub(crate) fn request_from_v0_34(request: pb34::Request) -> Result<pb::Request, pb34::Response> {
+    if let Some(pb34::request::Value::SetOption(req)) = &request.value {
+        debug!("Ignoring SetOption request: {:?}", req);
+        return Err(pb34::Response {
+            value: Some(pb34::response::Value::SetOption(Default::default())),
+        });
+    }
+    transcode(&request).map_err(|e| {
+        error!("Failed to translate v0.34 request: {}", e);
+        exception_v0_34(format!("failed to translate request: {e}"))
+    })
+}
+
+/// Translate the application's (v0.37) response into its v0.34 counterpart.
+pub(crate) fn response_to_v0_34(response: pb::Response) -> pb34::Response {
+    match transcode::<_, pb34::Response>(&response) {
+        // Responses to ABCI++ methods do not exist in v0.34, and therefore
+        // decode as an empty response.
+        Ok(pb34::Response { value: None }) => {
+            exception_v0_34("response not supported by ABCI v0.34".to_string())
+        },
+        Ok(response) => response,
+        Err(e) => {
+            error!("Failed to translate v0.37 response: {}", e);
+            exception_v0_34(format!("failed to translate response: {e}"))
+        },
+    }
+}
+
+/// Translate an outgoing v0.37 request into its v0.34 counterpart.
+///
+/// Requests introduced in ABCI++ have no v0.34 counterpart, and are refused
+/// rather than being sent as an empty request.
+#[cfg(feature = "client")]
+pub(crate) fn request_to_v0_34(request: pb::Request) -> Result<pb34::Request, crate::Error> {
+    match transcode::<_, pb34::Request>(&request).map_err(crate::Error::decode)? {
+        pb34::Request { value: None } if request.value.is_some() => {
+            Err(crate::Error::unsupported_request())
+        },
+        transcoded => Ok(transcoded),
+    }
+}
+
+/// Translate an incoming v0.34 response into its v0.37 counterpart.
+#[cfg(feature = "client")]
+pub(crate) fn response_from_v0_34(
+    response: pb34::Response,
+) -> Result<pb::Response, prost::DecodeError> {
+    transcode(&response)
+}
+
+fn exception_v0_34(error: String) -> pb34::Response {
+    pb34::Response {
+        value: Some(pb34::response::Value::Exception(pb34::ResponseException {
+            error,
+        })),
+    }
+}
+
+/// Convert a message between protocol versions by re-encoding it.
+///
+/// For every method present in both protocol versions, the v0.34 and v0.37
+/// messages share their field numbers and wire types. The only differences
+/// are in fields that have been added, and in event attributes, which have
+/// changed from `bytes` to `string` (requiring valid UTF-8).
+fn transcode<A, B>(message: &A) -> Result<B, prost::DecodeError>
+where
+    A: Message,
+    B: Message + Default,
+{
+    B::decode(message.encode_to_vec().as_slice())
+}
@@ -1,13 +1,33 @@
 //! ABCI application server interface.
 
 use std::{
-    net::{TcpListener, TcpStream, ToSocketAddrs},
+    net::{TcpListener, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     thread,
 };
 
+use prost::Message;
+use tendermint_config::net;
+use tendermint_proto::{
+    v0_34::abci as pb34,
+    v0_37::abci::{response::Value, Response},
+};
 use tracing::{error, info};
 
-use crate::{application::RequestDispatcher, codec::ServerCodec, error::Error, Application};
+#[cfg(feature = "grpc")]
+use crate::GrpcServer;
+use crate::{
+    application::RequestDispatcher,
+    codec::{Codec, ServerCodec, DEFAULT_MAX_FRAME_SIZE},
+    error::Error,
+    protocol::{request_from_v0_34, response_to_v0_34, ProtocolVersion},
+    transport::{tcp_address, Listener, Stream},
+};
+#[cfg(feature = "async")]
+use crate::{transport::AsyncListener, AsyncApplication, AsyncServer};
 
 /// The size of the read buffer for each incoming connection to the ABCI
 /// server (1MB).
@@ -16,6 +36,8 @@ pub const DEFAULT_SERVER_READ_BUF_SIZE: usize = 1024 * 1024;
 /// Allows us to configure and construct an ABCI server.
 pub struct ServerBuilder {
     read_buf_size: usize,
+    protocol_version: ProtocolVersion,
+    max_frame_size: usize,
 }
 
 impl ServerBuilder {
@@ -25,28 +47,159 @@ impl ServerBuilder {
     /// incoming data from the client. This needs to be tuned for your
     /// application.
     pub fn new(read_buf_size: usize) -> Self {
-        Self { read_buf_size }
+        Self {
+            read_buf_size,
+            protocol_version: ProtocolVersion::default(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Select the version of the ABCI wire protocol spoken by the server,
+    /// which must match that of the Tendermint node connecting to it.
+    ///
+    /// Defaults to [`ProtocolVersion::V0_37`].
+    pub fn protocol_version(mut self, version: ProtocolVersion) -> Self {
+        self.protocol_version = version;
+        self
+    }
+
+    /// Limit the size of incoming requests to the given number of bytes.
+    ///
+    /// A connection whose client announces a larger request is closed before
+    /// the request is buffered. Defaults to 128MB.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
     }
 
     /// Constructor for an ABCI server.
     ///
-    /// Binds the server to the given address. You must subsequently call the
-    /// [`Server::listen`] method in order for incoming connections' requests
-    /// to be routed to the specified ABCI application.
+    /// Binds the server to the given TCP address. You must subsequently call
+    /// the [`Server::listen`] method in order for incoming connections'
+    /// requests to be routed to the specified ABCI application.
     pub fn bind<Addr, App>(self, addr: Addr, app: App) -> Result<Server<App>, Error>
     where
         Addr: ToSocketAddrs,
-        App: Application,
+        App: RequestDispatcher + Clone + Send + 'static,
     {
         let listener = TcpListener::bind(addr).map_err(Error::io)?;
-        let local_addr = listener.local_addr().map_err(Error::io)?.to_string();
-        info!("ABCI server running at {}", local_addr);
-        Ok(Server {
+        let address = tcp_address(listener.local_addr().map_err(Error::io)?);
+        Ok(self.server(Listener::Tcp(listener), address, app))
+    }
+
+    /// Constructor for an ABCI server bound to either a TCP or a Unix domain
+    /// socket address.
+    ///
+    /// Any stale socket file left behind at the given Unix domain socket path
+    /// is removed prior to binding. You must subsequently call the
+    /// [`Server::listen`] method in order for incoming connections' requests
+    /// to be routed to the specified ABCI application.
+    pub fn bind_address<App>(self, addr: net::Address, app: App) -> Result<Server<App>, Error>
+    where
+        App: RequestDispatcher + Clone + Send + 'static,
+    {
+        let listener = Listener::bind(&addr)?;
+        let address = listener.local_address()?;
+        Ok(self.server(listener, address, app))
+    }
+
+    fn server<App>(self, listener: Listener, address: net::Address, app: App) -> Server<App> {
+        info!("ABCI server running at {}", address);
+        Server {
             app,
             listener,
-            local_addr,
+            address,
             read_buf_size: self.read_buf_size,
-        })
+            protocol_version: self.protocol_version,
+            max_frame_size: self.max_frame_size,
+            failures: FailureCount::default(),
+        }
+    }
+
+    /// Constructor for an asynchronous ABCI server.
+    ///
+    /// Binds the server to the given TCP address. You must subsequently call
+    /// the [`AsyncServer::listen`] method from within a [`tokio`] runtime in
+    /// order for incoming connections' requests to be routed to the specified
+    /// ABCI application.
+    #[cfg(feature = "async")]
+    pub async fn bind_async<Addr, App>(
+        self,
+        addr: Addr,
+        app: App,
+    ) -> Result<AsyncServer<App>, Error>
+    where
+        Addr: tokio::net::ToSocketAddrs,
+        App: AsyncApplication,
+    {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(Error::io)?;
+        let address = tcp_address(listener.local_addr().map_err(Error::io)?);
+        Ok(self.async_server(AsyncListener::Tcp(listener), address, app))
+    }
+
+    /// Constructor for an asynchronous ABCI server bound to either a TCP or a
+    /// Unix domain socket address.
+    ///
+    /// Any stale socket file left behind at the given Unix domain socket path
+    /// is removed prior to binding. You must subsequently call the
+    /// [`AsyncServer::listen`] method from within a [`tokio`] runtime in
+    /// order for incoming connections' requests to be routed to the specified
+    /// ABCI application.
+    #[cfg(feature = "async")]
+    pub async fn bind_address_async<App>(
+        self,
+        addr: net::Address,
+        app: App,
+    ) -> Result<AsyncServer<App>, Error>
+    where
+        App: AsyncApplication,
+    {
+        let listener = AsyncListener::bind(&addr).await?;
+        let address = listener.local_address()?;
+        Ok(self.async_server(listener, address, app))
+    }
+
+    /// Constructor for an ABCI server exposing the application as the
+    /// `ABCIApplication` gRPC service.
+    ///
+    /// Binds the server to the given TCP address. You must subsequently call
+    /// the [`GrpcServer::listen`] method from within a [`tokio`] runtime in
+    /// order for incoming requests to be routed to the specified ABCI
+    /// application. The read buffer size, protocol version and maximum frame
+    /// size only apply to the socket protocol, and are ignored.
+    #[cfg(feature = "grpc")]
+    pub async fn bind_grpc<Addr, App>(self, addr: Addr, app: App) -> Result<GrpcServer<App>, Error>
+    where
+        Addr: tokio::net::ToSocketAddrs,
+        App: RequestDispatcher + Clone + Send + 'static,
+    {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(Error::io)?;
+        GrpcServer::new(app, listener)
+    }
+
+    #[cfg(feature = "async")]
+    fn async_server<App>(
+        self,
+        listener: AsyncListener,
+        address: net::Address,
+        app: App,
+    ) -> AsyncServer<App>
+    where
+        App: AsyncApplication,
+    {
+        info!("ABCI server running at {}", address);
+        AsyncServer::new(
+            app,
+            listener,
+            address,
+            self.read_buf_size,
+            self.protocol_version,
+            self.max_frame_size,
+        )
     }
 }
 
@@ -54,47 +207,118 @@ impl Default for ServerBuilder {
     fn default() -> Self {
         Self {
             read_buf_size: DEFAULT_SERVER_READ_BUF_SIZE,
+            protocol_version: ProtocolVersion::default(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
         }
     }
 }
 
-/// A TCP-based server for serving a specific ABCI application.
+/// A TCP- or Unix domain socket-based server for serving a specific ABCI
+/// application.
+///
+/// Any [`Application`] can be served directly, whereas a
+/// [`DomainApplication`] needs to be wrapped in a [`DomainAdapter`] first.
 ///
 /// Each incoming connection is handled in a separate thread. The ABCI
 /// application is cloned for access in each thread. It is up to the
 /// application developer to manage shared state across these different
 /// threads.
+///
+/// Requests which the application fails to handle, e.g. because they are
+/// malformed or because the application panics, are answered with a
+/// `ResponseException`, logged and counted (see [`Server::failure_count`]).
+///
+/// [`Application`]: crate::Application
+/// [`DomainApplication`]: crate::DomainApplication
+/// [`DomainAdapter`]: crate::DomainAdapter
 pub struct Server<App> {
     app: App,
-    listener: TcpListener,
-    local_addr: String,
+    listener: Listener,
+    address: net::Address,
     read_buf_size: usize,
+    protocol_version: ProtocolVersion,
+    max_frame_size: usize,
+    failures: FailureCount,
 }
 
-impl<App: Application> Server<App> {
+impl<App> Server<App>
+where
+    App: RequestDispatcher + Clone + Send + 'static,
+{
     /// Initiate a blocking listener for incoming connections.
     pub fn listen(self) -> Result<(), Error> {
         loop {
-            let (stream, addr) = self.listener.accept().map_err(Error::io)?;
-            let addr = addr.to_string();
+            let (stream, addr) = self.listener.accept()?;
             info!("Incoming connection from: {}", addr);
             self.spawn_client_handler(stream, addr);
         }
     }
 
     /// Getter for this server's local address.
+    ///
+    /// For TCP servers, this is of the form `host:port`, whereas servers
+    /// bound to a Unix domain socket produce a `unix://` URI.
     pub fn local_addr(&self) -> String {
-        self.local_addr.clone()
+        local_addr(&self.address)
     }
 
-    fn spawn_client_handler(&self, stream: TcpStream, addr: String) {
+    /// Getter for the address to which this server is bound.
+    pub fn address(&self) -> net::Address {
+        self.address.clone()
+    }
+
+    /// Obtain the counter of requests this server has failed to handle.
+    pub fn failure_count(&self) -> FailureCount {
+        self.failures.clone()
+    }
+
+    fn spawn_client_handler(&self, stream: Stream, addr: String) {
         let app = self.app.clone();
         let read_buf_size = self.read_buf_size;
-        let _ = thread::spawn(move || Self::handle_client(stream, addr, app, read_buf_size));
+        let version = self.protocol_version;
+        let max_frame_size = self.max_frame_size;
+        let failures = self.failures.clone();
+        let _ = thread::spawn(move || {
+            match version {
+                // Requests and responses failing translation are answered
+                // with an exception too, hence the v0.34 response is the one
+                // recorded.
+                ProtocolVersion::V0_34 => Self::handle_client(
+                    Codec::<_, pb34::Request, pb34::Response>::new(
+                        stream,
+                        read_buf_size,
+                        version,
+                        max_frame_size,
+                    ),
+                    &addr,
+                    |request| {
+                        let response = match request_from_v0_34(request) {
+                            Ok(request) => response_to_v0_34(app.handle(request)),
+                            Err(response) => response,
+                        };
+                        failures.record_v0_34(&addr, &response);
+                        response
+                    },
+                ),
+                ProtocolVersion::V0_37 => Self::handle_client(
+                    ServerCodec::new(stream, read_buf_size, version, max_frame_size),
+                    &addr,
+                    |request| {
+                        let response = app.handle(request);
+                        failures.record(&addr, &response);
+                        response
+                    },
+                ),
+            }
+        });
     }
 
-    fn handle_client(stream: TcpStream, addr: String, app: App, read_buf_size: usize) {
-        let mut codec = ServerCodec::new(stream, read_buf_size);
+    fn handle_client<I, O, F>(mut codec: Codec<Stream, I, O>, addr: &str, handle: F)
+    where
+        I: Message + Default,
+        O: Message,
+        F: Fn(I) -> O,
+    {
         info!("Listening for incoming requests from {}", addr);
         loop {
             let request = match codec.next() {
@@ -113,7 +337,7 @@ impl<App: Application> Server<App> {
                     return;
                 },
             };
-            let response = app.handle(request);
+            let response = handle(request);
             if let Err(e) = codec.send(response) {
                 error!("Failed sending response to client {}: {:?}", addr, e);
                 return;
@@ -121,3 +345,46 @@ impl<App: Application> Server<App> {
         }
     }
 }
+
+/// Describe the given server address in the form produced by
+/// [`Server::local_addr`].
+pub(crate) fn local_addr(address: &net::Address) -> String {
+    match address {
+        net::Address::Tcp { host, port, .. } => format!("{host}:{port}"),
+        net::Address::Unix { .. } => address.to_string(),
+    }
+}
+
+/// Counts the requests that a server has failed to handle, i.e. that have
+/// been answered with a `ResponseException`.
+///
+/// The count is shared by all clones of a counter.
+#[derive(Debug, Clone, Default)]
+pub struct FailureCount(Arc<AtomicU64>);
+
+impl FailureCount {
+    /// The number of requests that have failed so far.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Log and count the given response, should it signal a failure.
+    pub(crate) fn record(&self, addr: &str, response: &Response) {
+        if let Some(Value::Exception(e)) = &response.value {
+            self.record_exception(addr, &e.error);
+        }
+    }
+
+    /// Log and count the given ABCI v0.34 response, should it signal a
+    /// failure.
+    pub(crate) fn record_v0_34(&self, addr: &str, response: &pb34::Response) {
+        if let Some(pb34::response::Value::Exception(e)) = &response.value {
+            self.record_exception(addr, &e.error);
+        }
+    }
+
+    fn record_exception(&self, addr: &str, error: &str) {
+        error!("Failed to handle request from client {}: {}", addr, error);
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,320 @@
+//! Per-connection ABCI service interfaces.
+//!
+//! Tendermint opens four distinct connections to an ABCI application, each
+//! of which carries a different category of requests (see
+//! [`MethodKind`]). Rather than implementing a single [`Application`] that
+//! handles all of them, an application can implement one service trait per
+//! connection, and have them served through a [`ServiceDispatcher`]:
+//!
+//! * [`Consensus`] handles block execution. Its requests are serialized across all connections,
+//!   which is why its methods take `&mut self`.
+//! * [`Mempool`] checks transactions for inclusion in the mempool.
+//! * [`Info`] answers queries about the application's state. Its requests are handled concurrently.
+//! * [`Snapshot`] serves and restores state sync snapshots. Like consensus, its requests are
+//!   serialized.
+//!
+//! [`Application`]: crate::Application
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tendermint::{
+    abci::{request, response, MethodKind},
+    v0_37::abci::{Request, Response},
+};
+use tendermint_proto::v0_37::abci as pb;
+use tracing::{debug, error};
+
+use crate::application::{catch_panic, truncate_proposal_txs, RequestDispatcher};
+
+/// The service handling requests received over the consensus connection.
+///
+/// Requests are never handled concurrently.
+pub trait Consensus: Send + 'static {
+    /// Called once upon genesis.
+    fn init_chain(&mut self, _request: request::InitChain) -> response::InitChain {
+        Default::default()
+    }
+
+    /// A stage where the application can modify the list of transactions
+    /// in the preliminary proposal.
+    ///
+    /// The default implementation behaves as that of
+    /// [`Application::prepare_proposal`](crate::Application::prepare_proposal).
+    fn prepare_proposal(&mut self, request: request::PrepareProposal) -> response::PrepareProposal {
+        response::PrepareProposal {
+            txs: truncate_proposal_txs(request.txs, request.max_tx_bytes),
+        }
+    }
+
+    /// A stage where the application can accept or reject the proposed block.
+    ///
+    /// The default implementation accepts the proposal.
+    fn process_proposal(
+        &mut self,
+        _request: request::ProcessProposal,
+    ) -> response::ProcessProposal {
+        response::ProcessProposal::Accept
+    }
+
+    /// Signals the beginning of a new block, prior to any `DeliverTx` calls.
+    fn begin_block(&mut self, _request: request::BeginBlock) -> response::BeginBlock {
+        Default::default()
+    }
+
+    /// Apply a transaction to the application's state.
+    fn deliver_tx(&mut self, _request: request::DeliverTx) -> response::DeliverTx {
+        Default::default()
+    }
+
+    /// Signals the end of a block.
+    fn end_block(&mut self, _request: request::EndBlock) -> response::EndBlock {
+        Default::default()
+    }
+
+    /// Commit the current state at the current height.
+    fn commit(&mut self) -> response::Commit {
+        Default::default()
+    }
+}
+
+/// The service handling requests received over the mempool connection.
+pub trait Mempool: Send + Sync + 'static {
+    /// Check the given transaction before putting it into the local mempool.
+    fn check_tx(&self, _request: request::CheckTx) -> response::CheckTx {
+        Default::default()
+    }
+}
+
+/// The service handling requests received over the info (query) connection.
+///
+/// Requests may be handled concurrently.
+pub trait Info: Send + Sync + 'static {
+    /// Provide information about the ABCI application.
+    fn info(&self, _request: request::Info) -> response::Info {
+        Default::default()
+    }
+
+    /// Query the application for data at the current or past height.
+    fn query(&self, _request: request::Query) -> response::Query {
+        Default::default()
+    }
+}
+
+/// The service handling requests received over the snapshot connection.
+///
+/// Requests are never handled concurrently.
+pub trait Snapshot: Send + 'static {
+    /// Used during state sync to discover available snapshots on peers.
+    fn list_snapshots(&mut self) -> response::ListSnapshots {
+        Default::default()
+    }
+
+    /// Called when bootstrapping the node using state sync.
+    fn offer_snapshot(&mut self, _request: request::OfferSnapshot) -> response::OfferSnapshot {
+        Default::default()
+    }
+
+    /// Used during state sync to retrieve chunks of snapshots from peers.
+    fn load_snapshot_chunk(
+        &mut self,
+        _request: request::LoadSnapshotChunk,
+    ) -> response::LoadSnapshotChunk {
+        Default::default()
+    }
+
+    /// Apply the given snapshot chunk to the application's state.
+    fn apply_snapshot_chunk(
+        &mut self,
+        _request: request::ApplySnapshotChunk,
+    ) -> response::ApplySnapshotChunk {
+        Default::default()
+    }
+}
+
+/// The set of services making up an ABCI application.
+pub struct Services<C, M, I, S> {
+    consensus: Arc<Mutex<C>>,
+    mempool: Arc<M>,
+    info: Arc<I>,
+    snapshot: Arc<Mutex<S>>,
+}
+
+impl<C, M, I, S> Services<C, M, I, S>
+where
+    C: Consensus,
+    M: Mempool,
+    I: Info,
+    S: Snapshot,
+{
+    /// Combine the given services.
+    pub fn new(consensus: C, mempool: M, info: I, snapshot: S) -> Self {
+        Self {
+            consensus: Arc::new(Mutex::new(consensus)),
+            mempool: Arc::new(mempool),
+            info: Arc::new(info),
+            snapshot: Arc::new(Mutex::new(snapshot)),
+        }
+    }
+
+    /// Produce a dispatcher that serves each connection according to the
+    /// first (non-`Flush`, non-`Echo`) request received over it.
+    ///
+    /// This is what should be handed to a [`Server`](crate::Server) serving
+    /// a Tendermint node.
+    pub fn dispatcher(&self) -> ServiceDispatcher<C, M, I, S> {
+        ServiceDispatcher {
+            services: self.clone(),
+            fixed: None,
+            detected: Mutex::new(None),
+        }
+    }
+
+    /// Produce a dispatcher that serves every connection as one of the given
+    /// kind, and answers requests of any other kind with an exception.
+    ///
+    /// Passing [`MethodKind::Flush`] is equivalent to calling
+    /// [`Services::dispatcher`].
+    pub fn connection(&self, kind: MethodKind) -> ServiceDispatcher<C, M, I, S> {
+        ServiceDispatcher {
+            fixed: Some(kind).filter(|kind| *kind != MethodKind::Flush),
+            ..self.dispatcher()
+        }
+    }
+}
+
+impl<C, M, I, S> Clone for Services<C, M, I, S> {
+    fn clone(&self) -> Self {
+        Self {
+            consensus: self.consensus.clone(),
+            mempool: self.mempool.clone(),
+            info: self.info.clone(),
+            snapshot: self.snapshot.clone(),
+        }
+    }
+}
+
+/// Routes the requests received over a connection to the corresponding
+/// service.
+///
+/// The [`Server`](crate::Server) clones its application for each incoming
+/// connection. Every clone of a dispatcher starts out as a fresh connection,
+/// whose kind is yet to be determined (unless fixed through
+/// [`Services::connection`]).
+pub struct ServiceDispatcher<C, M, I, S> {
+    services: Services<C, M, I, S>,
+    fixed: Option<MethodKind>,
+    detected: Mutex<Option<MethodKind>>,
+}
+
+impl<C, M, I, S> Clone for ServiceDispatcher<C, M, I, S> {
+    fn clone(&self) -> Self {
+        Self {
+            services: self.services.clone(),
+            fixed: self.fixed,
+            detected: Mutex::new(None),
+        }
+    }
+}
+
+impl<C, M, I, S> ServiceDispatcher<C, M, I, S>
+where
+    C: Consensus,
+    M: Mempool,
+    I: Info,
+    S: Snapshot,
+{
+    /// Establish the kind of this connection, which the given request must
+    /// belong to.
+    fn admit(&self, kind: MethodKind) -> Result<(), String> {
+        let expected = match self.fixed {
+            Some(expected) => expected,
+            None => {
+                let mut detected = lock(&self.detected)?;
+                *detected.get_or_insert_with(|| {
+                    debug!("Serving connection as {:?} connection", kind);
+                    kind
+                })
+            },
+        };
+        if kind == expected {
+            Ok(())
+        } else {
+            Err(format!(
+                "unexpected {kind:?} request over {expected:?} connection"
+            ))
+        }
+    }
+
+    fn dispatch(&self, request: Request) -> Result<Response, String> {
+        // Flush and Echo requests are served over every connection.
+        if !matches!(request, Request::Flush | Request::Echo(_)) {
+            self.admit(request.kind())?;
+        }
+        let Services {
+            consensus,
+            mempool,
+            info,
+            snapshot,
+        } = &self.services;
+        Ok(match request {
+            Request::Flush => Response::Flush,
+            Request::Echo(req) => Response::Echo(response::Echo {
+                message: req.message,
+            }),
+            Request::InitChain(req) => Response::InitChain(lock(consensus)?.init_chain(req)),
+            Request::PrepareProposal(req) => {
+                Response::PrepareProposal(lock(consensus)?.prepare_proposal(req))
+            },
+            Request::ProcessProposal(req) => {
+                Response::ProcessProposal(lock(consensus)?.process_proposal(req))
+            },
+            Request::BeginBlock(req) => Response::BeginBlock(lock(consensus)?.begin_block(req)),
+            Request::DeliverTx(req) => Response::DeliverTx(lock(consensus)?.deliver_tx(req)),
+            Request::EndBlock(req) => Response::EndBlock(lock(consensus)?.end_block(req)),
+            Request::Commit => Response::Commit(lock(consensus)?.commit()),
+            Request::CheckTx(req) => Response::CheckTx(mempool.check_tx(req)),
+            Request::Info(req) => Response::Info(info.info(req)),
+            Request::Query(req) => Response::Query(info.query(req)),
+            Request::ListSnapshots => Response::ListSnapshots(lock(snapshot)?.list_snapshots()),
+            Request::OfferSnapshot(req) => {
+                Response::OfferSnapshot(lock(snapshot)?.offer_snapshot(req))
+            },
+            Request::LoadSnapshotChunk(req) => {
+                Response::LoadSnapshotChunk(lock(snapshot)?.load_snapshot_chunk(req))
+            },
+            Request::ApplySnapshotChunk(req) => {
+                Response::ApplySnapshotChunk(lock(snapshot)?.apply_snapshot_chunk(req))
+            },
+        })
+    }
+}
+
+impl<C, M, I, S> RequestDispatcher for ServiceDispatcher<C, M, I, S>
+where
+    C: Consensus,
+    M: Mempool,
+    I: Info,
+    S: Snapshot,
+{
+    fn handle(&self, request: pb::Request) -> pb::Response {
+        tracing::debug!("Incoming request: {:?}", request);
+        catch_panic(|| {
+            let response = Request::try_from(request)
+                .map_err(|e| format!("invalid request: {e}"))
+                .and_then(|request| self.dispatch(request))
+                .unwrap_or_else(|error| {
+                    error!("Failed to handle incoming request: {}", error);
+                    Response::Exception(response::Exception { error })
+                });
+            response.into()
+        })
+    }
+}
+
+/// Lock the given service, failing if a previous request panicked while
+/// holding the lock.
+fn lock<T>(mutex: &Mutex<T>) -> Result<MutexGuard<'_, T>, String> {
+    mutex
+        .lock()
+        .map_err(|_| "service unavailable after an earlier failure".to_string())
+}
@@ -0,0 +1,231 @@
+//! Recording and replaying of ABCI sessions.
+//!
+//! A [`SessionRecorder`] wraps an application, and logs every request it
+//! handles along with the corresponding response. Each record consists of two
+//! length-prefixed Protobuf messages (the request, followed by the response),
+//! framed as on the wire by the v0.37 ABCI protocol.
+//!
+//! Recorded sessions can be read back through a [`SessionReader`], and
+//! replayed against another application through [`replay_session`], in order
+//! to find out whether both applications behave identically.
+
+use std::{
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use bytes::BytesMut;
+use prost::Message;
+use tendermint_proto::v0_37::abci::{request, Request, Response};
+use tracing::error;
+
+use crate::{
+    codec::{decode_length_delimited, encode_length_delimited, DEFAULT_MAX_FRAME_SIZE},
+    Error, ProtocolVersion, RequestDispatcher,
+};
+
+/// The protocol version determining the framing of recorded messages.
+const RECORDING_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::V0_37;
+
+/// Size of the window through which recordings are read.
+const READ_WINDOW_SIZE: usize = 64 * 1024;
+
+/// Records every request handled by the wrapped application, along with the
+/// corresponding response, to the given log.
+///
+/// The recorder is shared by all of its clones, such that the requests
+/// received over all of the server's connections end up in the same log, in
+/// the order in which they were answered. Failing to write to the log does
+/// not affect the application: the error is logged, and the response is
+/// returned as usual.
+///
+/// ## Example
+/// ```rust,no_run
+/// use std::fs::File;
+///
+/// use tendermint_abci::{Application, ServerBuilder, SessionRecorder};
+///
+/// #[derive(Clone)]
+/// struct MyApp;
+///
+/// impl Application for MyApp {}
+///
+/// let log = File::create("abci-session.log").unwrap();
+/// let server = ServerBuilder::default()
+///     .bind("127.0.0.1:26658", SessionRecorder::new(MyApp, log))
+///     .unwrap();
+/// server.listen().unwrap();
+/// ```
+pub struct SessionRecorder<D, W> {
+    inner: D,
+    log: Arc<Mutex<W>>,
+}
+
+impl<D, W: Write> SessionRecorder<D, W> {
+    /// Record the requests handled by `inner` to `log`.
+    pub fn new(inner: D, log: W) -> Self {
+        Self {
+            inner,
+            log: Arc::new(Mutex::new(log)),
+        }
+    }
+
+    /// Access the wrapped application.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    fn record(&self, request: Request, response: Response) -> Result<(), Error> {
+        let mut buf = BytesMut::new();
+        encode_length_delimited(request, &mut buf, RECORDING_PROTOCOL_VERSION)?;
+        encode_length_delimited(response, &mut buf, RECORDING_PROTOCOL_VERSION)?;
+        // A poisoned lock only means that another write failed halfway
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        log.write_all(&buf).map_err(Error::io)?;
+        log.flush().map_err(Error::io)
+    }
+}
+
+impl<D: Clone, W> Clone for SessionRecorder<D, W> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            log: self.log.clone(),
+        }
+    }
+}
+
+impl<D, W> RequestDispatcher for SessionRecorder<D, W>
+where
+    D: RequestDispatcher,
+    W: Write,
+{
+    fn handle(&self, request: Request) -> Response {
+        let response = self.inner.handle(request.clone());
+        if let Err(e) = self.record(request, response.clone()) {
+            error!("Failed to record ABCI request: {}", e);
+        }
+        response
+    }
+}
+
+/// Reads the requests and responses recorded by a [`SessionRecorder`].
+///
+/// Iterating over a reader produces the recorded `(request, response)`
+/// pairs, in order.
+pub struct SessionReader<R> {
+    reader: R,
+    read_buf: BytesMut,
+    read_window: Vec<u8>,
+    failed: bool,
+}
+
+impl<R: Read> SessionReader<R> {
+    /// Read the recording from the given reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            read_buf: BytesMut::new(),
+            read_window: vec![0_u8; READ_WINDOW_SIZE],
+            failed: false,
+        }
+    }
+
+    /// Read the next message, or `None` if the recording ends cleanly before
+    /// it.
+    fn read_message<M: Message + Default>(&mut self) -> Result<Option<M>, Error> {
+        loop {
+            if let Some(message) = decode_length_delimited(
+                &mut self.read_buf,
+                RECORDING_PROTOCOL_VERSION,
+                DEFAULT_MAX_FRAME_SIZE,
+            )? {
+                return Ok(Some(message));
+            }
+            let bytes_read = self
+                .reader
+                .read(self.read_window.as_mut())
+                .map_err(Error::io)?;
+            if bytes_read == 0 {
+                return if self.read_buf.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(Error::truncated_recording())
+                };
+            }
+            self.read_buf
+                .extend_from_slice(&self.read_window[..bytes_read]);
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<(Request, Response)>, Error> {
+        let request = match self.read_message()? {
+            Some(request) => request,
+            None => return Ok(None),
+        };
+        match self.read_message()? {
+            Some(response) => Ok(Some((request, response))),
+            None => Err(Error::truncated_recording()),
+        }
+    }
+}
+
+impl<R: Read> Iterator for SessionReader<R> {
+    type Item = Result<(Request, Response), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let record = self.read_record();
+        // We cannot tell where the next record begins
+        self.failed = record.is_err();
+        record.transpose()
+    }
+}
+
+/// The first response of a replayed session which differs from the recorded
+/// one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// The position of the request within the session, starting from 0.
+    pub index: usize,
+    /// The request answered differently.
+    pub request: Request,
+    /// The response found in the recording.
+    pub recorded: Response,
+    /// The response produced by the application during the replay.
+    pub replayed: Response,
+}
+
+/// Replay the recorded session against the given application, and report
+/// the first response which differs from the recorded one, if any.
+///
+/// All recorded requests are handed to the application, in order, up to the
+/// first divergence. The responses to `Echo`, `Flush` and `Info` requests are
+/// not compared, since these legitimately differ between versions of an
+/// application, whereas all other responses (including application hashes,
+/// transaction results and validator updates) must match exactly.
+pub fn replay_session<R, D>(recording: R, app: &D) -> Result<Option<Divergence>, Error>
+where
+    R: Read,
+    D: RequestDispatcher,
+{
+    for (index, record) in SessionReader::new(recording).enumerate() {
+        let (request, recorded) = record?;
+        let replayed = app.handle(request.clone());
+        let compared = !matches!(
+            request.value,
+            Some(request::Value::Echo(_) | request::Value::Flush(_) | request::Value::Info(_))
+        );
+        if compared && replayed != recorded {
+            return Ok(Some(Divergence {
+                index,
+                request,
+                recorded,
+                replayed,
+            }));
+        }
+    }
+    Ok(None)
+}
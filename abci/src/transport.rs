@@ -0,0 +1,359 @@
+//! Stream-oriented transports over which the ABCI socket protocol is served.
+//!
+//! Both TCP and (on Unix platforms) Unix domain sockets are supported, as
+//! described by a [`net::Address`].
+
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+};
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+};
+
+use tendermint_config::net;
+use tracing::info;
+
+use crate::error::Error;
+
+/// A listener bound to either a TCP or a Unix domain socket address.
+pub(crate) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Bind to the given address.
+    ///
+    /// When binding to a Unix domain socket, any stale socket file left
+    /// behind at the given path by a previous server is removed first.
+    pub(crate) fn bind(addr: &net::Address) -> Result<Self, Error> {
+        match addr {
+            net::Address::Tcp { host, port, .. } => {
+                TcpListener::bind((resolvable_host(host), *port))
+                    .map(Self::Tcp)
+                    .map_err(Error::io)
+            },
+            #[cfg(unix)]
+            net::Address::Unix { path } => {
+                remove_stale_socket(Path::new(path))?;
+                UnixListener::bind(path).map(Self::Unix).map_err(Error::io)
+            },
+            #[cfg(not(unix))]
+            net::Address::Unix { .. } => Err(unix_unsupported()),
+        }
+    }
+
+    /// Accept an incoming connection, along with a description of the peer
+    /// for logging purposes.
+    pub(crate) fn accept(&self) -> Result<(Stream, String), Error> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, addr) = listener.accept().map_err(Error::io)?;
+                Ok((Stream::Tcp(stream), addr.to_string()))
+            },
+            #[cfg(unix)]
+            Self::Unix(listener) => {
+                let (stream, addr) = listener.accept().map_err(Error::io)?;
+                Ok((Stream::Unix(stream), format!("{addr:?}")))
+            },
+        }
+    }
+
+    /// The address to which this listener is bound.
+    pub(crate) fn local_address(&self) -> Result<net::Address, Error> {
+        match self {
+            Self::Tcp(listener) => Ok(tcp_address(listener.local_addr().map_err(Error::io)?)),
+            #[cfg(unix)]
+            Self::Unix(listener) => Ok(unix_address(
+                listener.local_addr().map_err(Error::io)?.as_pathname(),
+            )),
+        }
+    }
+}
+
+/// A connected stream over either TCP or a Unix domain socket.
+pub(crate) enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    /// Connect to the given address.
+    #[cfg(feature = "client")]
+    pub(crate) fn connect(addr: &net::Address) -> Result<Self, Error> {
+        match addr {
+            net::Address::Tcp { host, port, .. } => {
+                TcpStream::connect((resolvable_host(host), *port))
+                    .map(Self::Tcp)
+                    .map_err(Error::io)
+            },
+            #[cfg(unix)]
+            net::Address::Unix { path } => {
+                UnixStream::connect(path).map(Self::Unix).map_err(Error::io)
+            },
+            #[cfg(not(unix))]
+            net::Address::Unix { .. } => Err(unix_unsupported()),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Describe the given socket address as a TCP [`net::Address`].
+pub(crate) fn tcp_address(addr: SocketAddr) -> net::Address {
+    let host = match addr {
+        SocketAddr::V4(addr) => addr.ip().to_string(),
+        // IPv6 hosts need to be enclosed in brackets in order to be
+        // distinguishable from the port number.
+        SocketAddr::V6(addr) => format!("[{}]", addr.ip()),
+    };
+    net::Address::Tcp {
+        peer_id: None,
+        host,
+        port: addr.port(),
+    }
+}
+
+/// Strip the brackets enclosing IPv6 hosts in [`net::Address`]es so that they
+/// can be resolved.
+pub(crate) fn resolvable_host(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+/// Describe the given socket path as a Unix domain socket [`net::Address`].
+///
+/// Unnamed sockets are described by an empty path.
+#[cfg(unix)]
+pub(crate) fn unix_address(path: Option<&Path>) -> net::Address {
+    net::Address::Unix {
+        path: path
+            .map(|path| path.display().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Remove the socket file at the given path if no server is listening on it
+/// any longer.
+///
+/// Files that are not sockets are left untouched, in which case binding will
+/// subsequently fail.
+#[cfg(unix)]
+pub(crate) fn remove_stale_socket(path: &Path) -> Result<(), Error> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(Error::io(e)),
+    };
+    if !metadata.file_type().is_socket() {
+        return Ok(());
+    }
+    if UnixStream::connect(path).is_ok() {
+        return Err(Error::io(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("socket {} is already in use", path.display()),
+        )));
+    }
+    info!("Removing stale socket file {}", path.display());
+    fs::remove_file(path).map_err(Error::io)
+}
+
+#[cfg(not(unix))]
+fn unix_unsupported() -> Error {
+    Error::io(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix domain sockets are not supported on this platform",
+    ))
+}
+
+#[cfg(feature = "async")]
+pub(crate) use self::asynchronous::{AsyncListener, AsyncStream};
+
+#[cfg(feature = "async")]
+mod asynchronous {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use tendermint_config::net;
+    #[cfg(unix)]
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::{
+        io::{AsyncRead, AsyncWrite, ReadBuf},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::{resolvable_host, tcp_address};
+    use crate::error::Error;
+
+    /// An asynchronous listener bound to either a TCP or a Unix domain socket
+    /// address.
+    pub(crate) enum AsyncListener {
+        Tcp(TcpListener),
+        #[cfg(unix)]
+        Unix(UnixListener),
+    }
+
+    impl AsyncListener {
+        /// Bind to the given address.
+        ///
+        /// As with the blocking listener, any stale socket file is removed
+        /// before binding to a Unix domain socket.
+        pub(crate) async fn bind(addr: &net::Address) -> Result<Self, Error> {
+            match addr {
+                net::Address::Tcp { host, port, .. } => {
+                    TcpListener::bind((resolvable_host(host), *port))
+                        .await
+                        .map(Self::Tcp)
+                        .map_err(Error::io)
+                },
+                #[cfg(unix)]
+                net::Address::Unix { path } => {
+                    super::remove_stale_socket(std::path::Path::new(path))?;
+                    UnixListener::bind(path).map(Self::Unix).map_err(Error::io)
+                },
+                #[cfg(not(unix))]
+                net::Address::Unix { .. } => Err(super::unix_unsupported()),
+            }
+        }
+
+        /// Accept an incoming connection, along with a description of the
+        /// peer for logging purposes.
+        pub(crate) async fn accept(&self) -> Result<(AsyncStream, String), Error> {
+            match self {
+                Self::Tcp(listener) => {
+                    let (stream, addr) = listener.accept().await.map_err(Error::io)?;
+                    Ok((AsyncStream::Tcp(stream), addr.to_string()))
+                },
+                #[cfg(unix)]
+                Self::Unix(listener) => {
+                    let (stream, addr) = listener.accept().await.map_err(Error::io)?;
+                    Ok((AsyncStream::Unix(stream), format!("{addr:?}")))
+                },
+            }
+        }
+
+        /// The address to which this listener is bound.
+        pub(crate) fn local_address(&self) -> Result<net::Address, Error> {
+            match self {
+                Self::Tcp(listener) => Ok(tcp_address(listener.local_addr().map_err(Error::io)?)),
+                #[cfg(unix)]
+                Self::Unix(listener) => Ok(super::unix_address(
+                    listener.local_addr().map_err(Error::io)?.as_pathname(),
+                )),
+            }
+        }
+    }
+
+    /// An asynchronous connected stream over either TCP or a Unix domain
+    /// socket.
+    pub(crate) enum AsyncStream {
+        Tcp(TcpStream),
+        #[cfg(unix)]
+        Unix(UnixStream),
+    }
+
+    impl AsyncStream {
+        /// Connect to the given address.
+        #[cfg(feature = "client")]
+        pub(crate) async fn connect(addr: &net::Address) -> Result<Self, Error> {
+            match addr {
+                net::Address::Tcp { host, port, .. } => {
+                    TcpStream::connect((resolvable_host(host), *port))
+                        .await
+                        .map(Self::Tcp)
+                        .map_err(Error::io)
+                },
+                #[cfg(unix)]
+                net::Address::Unix { path } => UnixStream::connect(path)
+                    .await
+                    .map(Self::Unix)
+                    .map_err(Error::io),
+                #[cfg(not(unix))]
+                net::Address::Unix { .. } => Err(super::unix_unsupported()),
+            }
+        }
+    }
+
+    impl AsyncRead for AsyncStream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+                #[cfg(unix)]
+                Self::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+            }
+        }
+    }
+
+    impl AsyncWrite for AsyncStream {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+                #[cfg(unix)]
+                Self::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+            }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+                #[cfg(unix)]
+                Self::Unix(stream) => Pin::new(stream).poll_flush(cx),
+            }
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+                #[cfg(unix)]
+                Self::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+            }
+        }
+    }
+}
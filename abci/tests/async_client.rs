@@ -0,0 +1,116 @@
+//! Integration tests for the pipelined asynchronous ABCI client.
+
+#[cfg(all(feature = "async", feature = "client", feature = "echo-app"))]
+mod async_client_integration {
+    use std::thread;
+
+    use futures::future::try_join_all;
+    use tendermint_abci::{
+        error::ErrorDetail, Application, ClientBuilder, EchoApp, ProtocolVersion, ServerBuilder,
+    };
+    use tendermint_proto::v0_37::abci::{RequestCheckTx, RequestEcho, ResponseCheckTx};
+
+    /// Rejects transactions reading "panic" in the most unfortunate way.
+    #[derive(Clone)]
+    struct PanickingApp;
+
+    impl Application for PanickingApp {
+        fn check_tx(&self, request: RequestCheckTx) -> ResponseCheckTx {
+            if request.tx == "panic" {
+                panic!("deliberate panic");
+            }
+            Default::default()
+        }
+    }
+
+    fn serve<App>(version: ProtocolVersion, app: App) -> String
+    where
+        App: Application,
+    {
+        let server = ServerBuilder::default()
+            .protocol_version(version)
+            .bind("127.0.0.1:0", app)
+            .unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || server.listen());
+        server_addr
+    }
+
+    async fn pipelined_echoes(version: ProtocolVersion) {
+        let server_addr = serve(version, EchoApp);
+        let client = ClientBuilder::default()
+            .protocol_version(version)
+            .connect_async(server_addr)
+            .await
+            .unwrap();
+
+        let echoes = (0..1000)
+            .map(|i| {
+                client.echo(RequestEcho {
+                    message: i.to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+        client.flush().await.unwrap();
+
+        let responses = try_join_all(echoes).await.unwrap();
+        for (i, response) in responses.into_iter().enumerate() {
+            assert_eq!(response.message, i.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn pipelined_requests() {
+        pipelined_echoes(ProtocolVersion::V0_37).await;
+    }
+
+    #[tokio::test]
+    async fn pipelined_requests_over_v0_34() {
+        pipelined_echoes(ProtocolVersion::V0_34).await;
+    }
+
+    #[tokio::test]
+    async fn exception_produces_typed_error() {
+        let server_addr = serve(ProtocolVersion::V0_37, PanickingApp);
+        let client = ClientBuilder::default()
+            .connect_async(server_addr)
+            .await
+            .unwrap();
+
+        let rejected = client.check_tx(RequestCheckTx {
+            tx: "panic".into(),
+            r#type: 0,
+        });
+        let accepted = client.check_tx(RequestCheckTx::default());
+        client.flush().await.unwrap();
+
+        match rejected.await.unwrap_err().detail() {
+            ErrorDetail::ResponseException(e) => {
+                assert_eq!(e.error, "application panicked: deliberate panic")
+            },
+            e => panic!("unexpected error: {e:?}"),
+        }
+        assert_eq!(accepted.await.unwrap().code, 0);
+    }
+
+    #[tokio::test]
+    async fn concurrent_callers_share_connection() {
+        let server_addr = serve(ProtocolVersion::V0_37, EchoApp);
+        let client = ClientBuilder::default()
+            .connect_async(server_addr)
+            .await
+            .unwrap();
+
+        let tasks = (0..10).map(|i| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let echo = client.echo(RequestEcho {
+                    message: i.to_string(),
+                });
+                client.flush().await.unwrap();
+                assert_eq!(echo.await.unwrap().message, i.to_string());
+            })
+        });
+        try_join_all(tasks).await.unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+//! Integration tests for the asynchronous ABCI server.
+
+#[cfg(all(feature = "async", feature = "client", feature = "echo-app"))]
+mod async_echo_app_integration {
+    use tendermint_abci::{ClientBuilder, EchoApp, ProtocolVersion, ServerBuilder};
+    #[cfg(unix)]
+    use tendermint_config::net;
+    use tendermint_proto::v0_37::abci::RequestEcho;
+
+    #[tokio::test]
+    async fn echo() {
+        let server = ServerBuilder::default()
+            .bind_async("127.0.0.1:0", EchoApp)
+            .await
+            .unwrap();
+        let server_addr = server.local_addr();
+        let shutdown = server.shutdown_handle();
+        let server_task = tokio::spawn(server.listen());
+
+        let response = tokio::task::spawn_blocking(move || {
+            let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+            client
+                .echo(RequestEcho {
+                    message: "Hello ABCI!".to_string(),
+                })
+                .unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.message, "Hello ABCI!");
+
+        shutdown.shutdown();
+        server_task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn echo_over_v0_34() {
+        let server = ServerBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .bind_async("127.0.0.1:0", EchoApp)
+            .await
+            .unwrap();
+        let server_addr = server.local_addr();
+        let shutdown = server.shutdown_handle();
+        let server_task = tokio::spawn(server.listen());
+
+        let response = tokio::task::spawn_blocking(move || {
+            let mut client = ClientBuilder::default()
+                .protocol_version(ProtocolVersion::V0_34)
+                .connect(server_addr)
+                .unwrap();
+            client
+                .echo(RequestEcho {
+                    message: "Hello ABCI!".to_string(),
+                })
+                .unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.message, "Hello ABCI!");
+
+        shutdown.shutdown();
+        server_task.await.unwrap().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn echo_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!(
+            "tendermint-abci-async-echo-{}.sock",
+            std::process::id()
+        ));
+        let server = ServerBuilder::default()
+            .bind_address_async(
+                net::Address::Unix {
+                    path: path.display().to_string(),
+                },
+                EchoApp,
+            )
+            .await
+            .unwrap();
+        let server_addr = server.address();
+        let shutdown = server.shutdown_handle();
+        let server_task = tokio::spawn(server.listen());
+
+        let response = tokio::task::spawn_blocking(move || {
+            let mut client = ClientBuilder::default()
+                .connect_address(server_addr)
+                .unwrap();
+            client
+                .echo(RequestEcho {
+                    message: "Hello ABCI!".to_string(),
+                })
+                .unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.message, "Hello ABCI!");
+
+        shutdown.shutdown();
+        server_task.await.unwrap().unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_open_connections() {
+        let server = ServerBuilder::default()
+            .bind_async("127.0.0.1:0", EchoApp)
+            .await
+            .unwrap();
+        let server_addr = server.local_addr();
+        let shutdown = server.shutdown_handle();
+        let server_task = tokio::spawn(server.listen());
+
+        // Keep the connection open while the server shuts down
+        let client = tokio::task::spawn_blocking(move || {
+            ClientBuilder::default().connect(server_addr).unwrap()
+        })
+        .await
+        .unwrap();
+
+        shutdown.shutdown();
+        server_task.await.unwrap().unwrap();
+        drop(client);
+    }
+}
@@ -0,0 +1,96 @@
+//! Integration tests for applications built on the `tendermint` domain types.
+
+#[cfg(feature = "client")]
+mod domain_app_integration {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use tendermint::abci::{request, response, Code};
+    use tendermint_abci::{
+        error::ErrorDetail, ClientBuilder, DomainAdapter, DomainApplication, ServerBuilder,
+    };
+    use tendermint_proto::v0_37::abci::{
+        response::Value, RequestBeginBlock, RequestCheckTx, RequestEcho,
+    };
+
+    /// Accepts only non-empty transactions, and counts those it accepts.
+    #[derive(Clone, Default)]
+    struct CountingApp {
+        accepted: Arc<AtomicU64>,
+    }
+
+    impl DomainApplication for CountingApp {
+        fn check_tx(&self, request: request::CheckTx) -> response::CheckTx {
+            if request.tx.is_empty() {
+                return response::CheckTx {
+                    code: Code::from(1),
+                    log: "empty transaction".to_string(),
+                    ..Default::default()
+                };
+            }
+            self.accepted.fetch_add(1, Ordering::SeqCst);
+            Default::default()
+        }
+    }
+
+    #[test]
+    fn domain_types() {
+        let app = CountingApp::default();
+        let server = ServerBuilder::default()
+            .bind("127.0.0.1:0", DomainAdapter::new(app.clone()))
+            .unwrap();
+        let server_addr = server.local_addr();
+        let _ = std::thread::spawn(move || server.listen());
+        let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+
+        let response = client
+            .echo(RequestEcho {
+                message: "Hello ABCI!".to_string(),
+            })
+            .unwrap();
+        assert_eq!(response.message, "Hello ABCI!");
+
+        let response = client
+            .check_tx(RequestCheckTx {
+                tx: "test-tx".into(),
+                r#type: 0,
+            })
+            .unwrap();
+        assert_eq!(response.code, 0);
+        let response = client.check_tx(RequestCheckTx::default()).unwrap();
+        assert_eq!(response.code, 1);
+        assert_eq!(response.log, "empty transaction");
+        assert_eq!(app.accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn invalid_request_produces_exception() {
+        let server = ServerBuilder::default()
+            .bind("127.0.0.1:0", DomainAdapter::new(CountingApp::default()))
+            .unwrap();
+        let server_addr = server.local_addr();
+        let _ = std::thread::spawn(move || server.listen());
+        let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+
+        // A `BeginBlock` request without a header fails validation.
+        let err = client
+            .begin_block(RequestBeginBlock::default())
+            .unwrap_err();
+        match err.detail() {
+            ErrorDetail::UnexpectedServerResponseType(e) => {
+                assert!(matches!(e.got, Value::Exception(_)))
+            },
+            e => panic!("unexpected error: {e:?}"),
+        }
+
+        // The connection remains usable afterwards.
+        let response = client
+            .echo(RequestEcho {
+                message: "Still there?".to_string(),
+            })
+            .unwrap();
+        assert_eq!(response.message, "Still there?");
+    }
+}
@@ -3,6 +3,7 @@
 #[cfg(all(feature = "client", feature = "echo-app"))]
 mod echo_app_integration {
     use tendermint_abci::{ClientBuilder, EchoApp, ServerBuilder};
+    use tendermint_config::net;
     use tendermint_proto::v0_37::abci::RequestEcho;
 
     #[test]
@@ -21,4 +22,42 @@ mod echo_app_integration {
             .unwrap();
         assert_eq!(response.message, "Hello ABCI!");
     }
+
+    #[test]
+    fn echo_over_tcp_address() {
+        echo_over("tcp://127.0.0.1:0".parse().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn echo_over_unix_socket() {
+        let path =
+            std::env::temp_dir().join(format!("tendermint-abci-echo-{}.sock", std::process::id()));
+        // Leave a stale socket file behind, which the server needs to clean
+        // up in order to bind.
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        echo_over(net::Address::Unix {
+            path: path.display().to_string(),
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn echo_over(addr: net::Address) {
+        let server = ServerBuilder::default()
+            .bind_address(addr, EchoApp)
+            .unwrap();
+        let server_addr = server.address();
+        let _ = std::thread::spawn(move || server.listen());
+        let mut client = ClientBuilder::default()
+            .connect_address(server_addr)
+            .unwrap();
+
+        let response = client
+            .echo(RequestEcho {
+                message: "Hello ABCI!".to_string(),
+            })
+            .unwrap();
+        assert_eq!(response.message, "Hello ABCI!");
+    }
 }
@@ -0,0 +1,145 @@
+//! Integration tests for the ABCI gRPC server and client.
+
+#[cfg(all(
+    feature = "grpc",
+    feature = "client",
+    feature = "echo-app",
+    feature = "kvstore-app"
+))]
+mod grpc_integration {
+    use std::thread;
+
+    use futures::future::try_join_all;
+    use tendermint_abci::{
+        error::ErrorDetail, Application, ClientBuilder, EchoApp, KeyValueStoreApp,
+        RequestDispatcher, ServerBuilder,
+    };
+    use tendermint_proto::v0_37::abci::{
+        RequestCheckTx, RequestDeliverTx, RequestEcho, RequestInfo, RequestQuery, ResponseCheckTx,
+    };
+
+    /// Raises an exception for transactions reading "fail".
+    #[derive(Clone)]
+    struct FailingApp;
+
+    impl Application for FailingApp {
+        fn check_tx(&self, request: RequestCheckTx) -> ResponseCheckTx {
+            if request.tx == "fail" {
+                panic!("deliberate failure");
+            }
+            Default::default()
+        }
+    }
+
+    async fn serve<App>(app: App) -> String
+    where
+        App: RequestDispatcher + Clone + Send + 'static,
+    {
+        let server = ServerBuilder::default()
+            .bind_grpc("127.0.0.1:0", app)
+            .await
+            .unwrap();
+        let server_addr = server.local_addr();
+        tokio::spawn(server.listen());
+        server_addr
+    }
+
+    #[tokio::test]
+    async fn echo() {
+        let server_addr = serve(EchoApp).await;
+        let mut client = ClientBuilder::default()
+            .connect_grpc(server_addr)
+            .await
+            .unwrap();
+
+        let response = client
+            .echo(RequestEcho {
+                message: "Hello ABCI!".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.message, "Hello ABCI!");
+        client.flush().await.unwrap();
+
+        // Requests may be issued concurrently by clones of the client.
+        let echoes = (0..100).map(|i| {
+            let mut client = client.clone();
+            async move {
+                client
+                    .echo(RequestEcho {
+                        message: i.to_string(),
+                    })
+                    .await
+            }
+        });
+        let responses = try_join_all(echoes).await.unwrap();
+        for (i, response) in responses.into_iter().enumerate() {
+            assert_eq!(response.message, i.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn kvstore() {
+        let (app, driver) = KeyValueStoreApp::new();
+        thread::spawn(move || driver.run());
+        let server_addr = serve(app).await;
+        let mut client = ClientBuilder::default()
+            .connect_grpc(server_addr)
+            .await
+            .unwrap();
+
+        let info = client.info(RequestInfo::default()).await.unwrap();
+        assert_eq!(info.last_block_height, 0);
+
+        client
+            .deliver_tx(RequestDeliverTx {
+                tx: "test-key=test-value".into(),
+            })
+            .await
+            .unwrap();
+        let commit = client.commit().await.unwrap();
+        assert_ne!(commit.data, info.last_block_app_hash);
+
+        let response = client
+            .query(RequestQuery {
+                data: "test-key".into(),
+                path: "".to_string(),
+                height: 0,
+                prove: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.value, "test-value".as_bytes());
+
+        let info = client.info(RequestInfo::default()).await.unwrap();
+        assert_eq!(info.last_block_height, 1);
+        assert_eq!(info.last_block_app_hash, commit.data);
+    }
+
+    #[tokio::test]
+    async fn exceptions_are_reported() {
+        let server_addr = serve(FailingApp).await;
+        let mut client = ClientBuilder::default()
+            .connect_grpc(server_addr)
+            .await
+            .unwrap();
+
+        let err = client
+            .check_tx(RequestCheckTx {
+                tx: "fail".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err.detail(), ErrorDetail::ResponseException(_)));
+
+        // The server keeps serving requests.
+        client
+            .check_tx(RequestCheckTx {
+                tx: "pass".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+}
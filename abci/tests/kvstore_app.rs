@@ -4,8 +4,17 @@
 mod kvstore_app_integration {
     use std::thread;
 
-    use tendermint_abci::{ClientBuilder, KeyValueStoreApp, ServerBuilder};
-    use tendermint_proto::v0_37::abci::{RequestDeliverTx, RequestEcho, RequestQuery};
+    use tendermint::{
+        crypto::default::Sha256,
+        merkle::{self, proof::ProofOps},
+    };
+    use tendermint_abci::{Client, ClientBuilder, KeyValueStoreApp, ServerBuilder};
+    use tendermint_config::net;
+    use tendermint_proto::v0_37::abci::{
+        response_apply_snapshot_chunk, response_offer_snapshot, RequestApplySnapshotChunk,
+        RequestDeliverTx, RequestEcho, RequestInfo, RequestLoadSnapshotChunk, RequestOfferSnapshot,
+        RequestQuery,
+    };
 
     #[test]
     fn happy_path() {
@@ -16,6 +25,41 @@ mod kvstore_app_integration {
         thread::spawn(move || server.listen());
 
         let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+        deliver_and_query(&mut client);
+    }
+
+    #[test]
+    fn happy_path_over_tcp_address() {
+        happy_path_over("tcp://127.0.0.1:0".parse().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn happy_path_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!(
+            "tendermint-abci-kvstore-{}.sock",
+            std::process::id()
+        ));
+        happy_path_over(net::Address::Unix {
+            path: path.display().to_string(),
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn happy_path_over(addr: net::Address) {
+        let (app, driver) = KeyValueStoreApp::new();
+        let server = ServerBuilder::default().bind_address(addr, app).unwrap();
+        let server_addr = server.address();
+        thread::spawn(move || driver.run());
+        thread::spawn(move || server.listen());
+
+        let mut client = ClientBuilder::default()
+            .connect_address(server_addr)
+            .unwrap();
+        deliver_and_query(&mut client);
+    }
+
+    fn deliver_and_query(client: &mut Client) {
         let res = client
             .echo(RequestEcho {
                 message: "Hello ABCI!".to_string(),
@@ -39,5 +83,214 @@ mod kvstore_app_integration {
             })
             .unwrap();
         assert_eq!(res.value, "test-value".as_bytes());
+        assert!(res.proof_ops.is_none());
+    }
+
+    #[test]
+    fn proven_query() {
+        let (app, driver) = KeyValueStoreApp::new();
+        let server = ServerBuilder::default().bind("127.0.0.1:0", app).unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || driver.run());
+        thread::spawn(move || server.listen());
+        let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+
+        for tx in ["a=1", "b=2", "c=3"] {
+            client
+                .deliver_tx(RequestDeliverTx { tx: tx.into() })
+                .unwrap();
+        }
+        let app_hash: merkle::Hash = client.commit().unwrap().data.as_ref().try_into().unwrap();
+        // Uncommitted changes are not reflected in proven queries.
+        client
+            .deliver_tx(RequestDeliverTx { tx: "b=4".into() })
+            .unwrap();
+
+        let res = client
+            .query(RequestQuery {
+                data: "b".into(),
+                prove: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.value, "2".as_bytes());
+        assert_eq!(res.height, 1);
+        let proof_ops = ProofOps::try_from(res.proof_ops.unwrap()).unwrap();
+        proof_ops
+            .verify_value::<Sha256>(&app_hash, &[b"b".as_slice()], b"2")
+            .unwrap();
+        assert!(proof_ops
+            .verify_value::<Sha256>(&app_hash, &[b"b".as_slice()], b"4")
+            .is_err());
+
+        let res = client
+            .query(RequestQuery {
+                data: "d".into(),
+                prove: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(res.value.is_empty());
+        assert!(res.proof_ops.is_none());
+    }
+
+    /// Serves a key/value store taking a snapshot every other block, split
+    /// into chunks of 16 bytes.
+    fn connect_to_snapshotting_kvstore() -> Client {
+        let (app, driver) = KeyValueStoreApp::new();
+        let driver = driver
+            .with_snapshot_interval(2)
+            .with_snapshot_chunk_size(16);
+        let server = ServerBuilder::default().bind("127.0.0.1:0", app).unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || driver.run());
+        thread::spawn(move || server.listen());
+        ClientBuilder::default().connect(server_addr).unwrap()
+    }
+
+    #[test]
+    fn state_sync() {
+        let mut source = connect_to_snapshotting_kvstore();
+        let mut app_hashes = vec![];
+        for height in 1..=3 {
+            for i in 0..3 {
+                source
+                    .deliver_tx(RequestDeliverTx {
+                        tx: format!("key{height}{i}=value{height}{i}").into(),
+                    })
+                    .unwrap();
+            }
+            app_hashes.push(source.commit().unwrap().data);
+        }
+
+        // Only the snapshot at height 2 has been taken.
+        let snapshots = source.list_snapshots().unwrap().snapshots;
+        assert_eq!(snapshots.len(), 1);
+        let snapshot = snapshots[0].clone();
+        assert_eq!(snapshot.height, 2);
+        assert!(snapshot.chunks > 1);
+
+        let chunks = (0..snapshot.chunks)
+            .map(|chunk| {
+                source
+                    .load_snapshot_chunk(RequestLoadSnapshotChunk {
+                        height: snapshot.height,
+                        format: snapshot.format,
+                        chunk,
+                    })
+                    .unwrap()
+                    .chunk
+            })
+            .collect::<Vec<_>>();
+
+        let mut target = connect_to_snapshotting_kvstore();
+        let offered = target
+            .offer_snapshot(RequestOfferSnapshot {
+                snapshot: Some(snapshot.clone()),
+                app_hash: app_hashes[1].clone(),
+            })
+            .unwrap();
+        assert_eq!(
+            offered.result,
+            response_offer_snapshot::Result::Accept as i32
+        );
+
+        // A corrupted chunk is refetched, and its sender rejected.
+        let applied = target
+            .apply_snapshot_chunk(RequestApplySnapshotChunk {
+                index: 0,
+                chunk: "corrupted".into(),
+                sender: "mallory".to_string(),
+            })
+            .unwrap();
+        assert_eq!(
+            applied.result,
+            response_apply_snapshot_chunk::Result::Retry as i32
+        );
+        assert_eq!(applied.refetch_chunks, vec![0]);
+        assert_eq!(applied.reject_senders, vec!["mallory".to_string()]);
+
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let applied = target
+                .apply_snapshot_chunk(RequestApplySnapshotChunk {
+                    index: index as u32,
+                    chunk,
+                    sender: "alice".to_string(),
+                })
+                .unwrap();
+            assert_eq!(
+                applied.result,
+                response_apply_snapshot_chunk::Result::Accept as i32
+            );
+        }
+
+        let info = target.info(RequestInfo::default()).unwrap();
+        assert_eq!(info.last_block_height, 2);
+        assert_eq!(info.last_block_app_hash, app_hashes[1]);
+        let res = target
+            .query(RequestQuery {
+                data: "key21".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.value, "value21".as_bytes());
+        let res = target
+            .query(RequestQuery {
+                data: "key31".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(res.value.is_empty());
+    }
+
+    #[test]
+    fn state_sync_rejects_mismatching_app_hash() {
+        let mut source = connect_to_snapshotting_kvstore();
+        for _ in 0..2 {
+            source
+                .deliver_tx(RequestDeliverTx { tx: "a=b".into() })
+                .unwrap();
+            source.commit().unwrap();
+        }
+        let snapshot = source.list_snapshots().unwrap().snapshots[0].clone();
+        assert_eq!(snapshot.chunks, 1);
+        let chunk = source
+            .load_snapshot_chunk(RequestLoadSnapshotChunk {
+                height: snapshot.height,
+                format: snapshot.format,
+                chunk: 0,
+            })
+            .unwrap()
+            .chunk;
+
+        let mut target = connect_to_snapshotting_kvstore();
+        let offered = target
+            .offer_snapshot(RequestOfferSnapshot {
+                snapshot: Some(snapshot),
+                app_hash: "not the app hash".into(),
+            })
+            .unwrap();
+        assert_eq!(
+            offered.result,
+            response_offer_snapshot::Result::Accept as i32
+        );
+        let applied = target
+            .apply_snapshot_chunk(RequestApplySnapshotChunk {
+                index: 0,
+                chunk,
+                sender: "alice".to_string(),
+            })
+            .unwrap();
+        assert_eq!(
+            applied.result,
+            response_apply_snapshot_chunk::Result::RejectSnapshot as i32
+        );
+        assert_eq!(
+            target
+                .info(RequestInfo::default())
+                .unwrap()
+                .last_block_height,
+            0
+        );
     }
 }
@@ -0,0 +1,120 @@
+//! Integration tests for the enforcement of the maximum frame size.
+
+#[cfg(all(feature = "client", feature = "echo-app"))]
+mod max_frame_size_integration {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use prost::{encoding::encode_varint, Message};
+    use tendermint_abci::{
+        error::ErrorDetail, ClientBuilder, EchoApp, ProtocolVersion, ServerBuilder,
+    };
+    use tendermint_proto::v0_37::abci::{request, Request, RequestEcho};
+
+    const MAX_FRAME_SIZE: usize = 64;
+
+    fn serve(version: ProtocolVersion) -> String {
+        let server = ServerBuilder::default()
+            .protocol_version(version)
+            .max_frame_size(MAX_FRAME_SIZE)
+            .bind("127.0.0.1:0", EchoApp)
+            .unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || server.listen());
+        server_addr
+    }
+
+    fn echo_request(message: &str) -> Request {
+        Request {
+            value: Some(request::Value::Echo(RequestEcho {
+                message: message.to_string(),
+            })),
+        }
+    }
+
+    /// Sends the given raw bytes to the server, and asserts that the server
+    /// closes the connection without responding.
+    fn assert_rejected(server_addr: &str, bytes: &[u8]) {
+        let mut stream = TcpStream::connect(server_addr).unwrap();
+        stream.write_all(bytes).unwrap();
+        let mut buf = Vec::new();
+        // Either a clean shutdown or a reset is acceptable, as long as
+        // nothing is sent back.
+        let _ = stream.read_to_end(&mut buf);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn server_rejects_crafted_length_prefix() {
+        let server_addr = serve(ProtocolVersion::V0_37);
+
+        // Announce a message of 2^62 bytes, without sending any of it.
+        let mut prefix = Vec::new();
+        encode_varint(1 << 62, &mut prefix);
+        assert_rejected(&server_addr, &prefix);
+
+        // A message just over the limit is rejected as well.
+        let mut prefix = Vec::new();
+        encode_varint(MAX_FRAME_SIZE as u64 + 1, &mut prefix);
+        assert_rejected(&server_addr, &prefix);
+
+        // Whereas the server keeps serving other connections.
+        let mut client = ClientBuilder::default().connect(&server_addr).unwrap();
+        let response = client
+            .echo(RequestEcho {
+                message: "Hello ABCI!".to_string(),
+            })
+            .unwrap();
+        assert_eq!(response.message, "Hello ABCI!");
+    }
+
+    #[test]
+    fn server_rejects_oversized_request() {
+        let server_addr = serve(ProtocolVersion::V0_37);
+        let request = echo_request(&"a".repeat(MAX_FRAME_SIZE));
+        assert_rejected(&server_addr, &request.encode_length_delimited_to_vec());
+    }
+
+    #[test]
+    fn server_rejects_crafted_v0_34_length_prefix() {
+        let server_addr = serve(ProtocolVersion::V0_34);
+
+        // The zigzag encoding of 2^61 bytes.
+        let mut prefix = Vec::new();
+        encode_varint(1 << 62, &mut prefix);
+        assert_rejected(&server_addr, &prefix);
+    }
+
+    #[test]
+    fn client_rejects_crafted_length_prefix() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Wait for the request, and answer with an oversized prefix
+            let mut buf = [0u8; 64];
+            let _ = stream.read(&mut buf).unwrap();
+            let mut prefix = Vec::new();
+            encode_varint(u64::MAX >> 1, &mut prefix);
+            stream.write_all(&prefix).unwrap();
+            // Keep the connection open until the client hangs up
+            let _ = stream.read(&mut buf);
+        });
+
+        let mut client = ClientBuilder::default()
+            .max_frame_size(MAX_FRAME_SIZE)
+            .connect(server_addr)
+            .unwrap();
+        let err = client.echo(RequestEcho::default()).unwrap_err();
+        match err.detail() {
+            ErrorDetail::FrameTooLarge(e) => {
+                assert_eq!(e.size, u64::MAX >> 1);
+                assert_eq!(e.max, MAX_FRAME_SIZE);
+            },
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+}
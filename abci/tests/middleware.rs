@@ -0,0 +1,143 @@
+//! Integration tests for the middleware wrapping ABCI applications.
+
+mod middleware_integration {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use tendermint_abci::{
+        middleware::{
+            CheckTxRateLimitLayer, MetricsLayer, MiddlewareBuilder, ReadOnlyLayer, TraceLayer,
+        },
+        Application, RequestDispatcher,
+    };
+    use tendermint_proto::v0_37::abci::{
+        request, response, CheckTxType, Request, RequestCheckTx, RequestDeliverTx, RequestQuery,
+        Response, ResponseCheckTx, ResponseDeliverTx,
+    };
+
+    /// Counts the transactions it gets to see.
+    #[derive(Clone, Default)]
+    struct CountingApp {
+        checked: Arc<AtomicU64>,
+        delivered: Arc<AtomicU64>,
+    }
+
+    impl Application for CountingApp {
+        fn check_tx(&self, _request: RequestCheckTx) -> ResponseCheckTx {
+            self.checked.fetch_add(1, Ordering::SeqCst);
+            Default::default()
+        }
+
+        fn deliver_tx(&self, _request: RequestDeliverTx) -> ResponseDeliverTx {
+            self.delivered.fetch_add(1, Ordering::SeqCst);
+            Default::default()
+        }
+    }
+
+    fn check_tx(r#type: CheckTxType) -> Request {
+        Request {
+            value: Some(request::Value::CheckTx(RequestCheckTx {
+                tx: "tx".into(),
+                r#type: r#type as i32,
+            })),
+        }
+    }
+
+    fn deliver_tx() -> Request {
+        Request {
+            value: Some(request::Value::DeliverTx(RequestDeliverTx {
+                tx: "tx".into(),
+            })),
+        }
+    }
+
+    fn query() -> Request {
+        Request {
+            value: Some(request::Value::Query(RequestQuery::default())),
+        }
+    }
+
+    fn check_tx_code(response: Response) -> u32 {
+        match response.value {
+            Some(response::Value::CheckTx(r)) => r.code,
+            r => panic!("unexpected response: {r:?}"),
+        }
+    }
+
+    #[test]
+    fn metrics_count_requests_per_method() {
+        let metrics = MetricsLayer::new();
+        let app = MiddlewareBuilder::new()
+            .layer(TraceLayer)
+            .layer(metrics.clone())
+            .build(CountingApp::default());
+
+        for _ in 0..3 {
+            app.handle(check_tx(CheckTxType::New));
+        }
+        app.handle(deliver_tx());
+        app.handle(Request { value: None });
+
+        let stats = metrics.metrics().snapshot();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats["check_tx"].count, 3);
+        assert_eq!(stats["check_tx"].exceptions, 0);
+        assert!(stats["check_tx"].max_latency <= stats["check_tx"].total_latency);
+        assert_eq!(stats["deliver_tx"].count, 1);
+        assert_eq!(stats["unknown"].exceptions, 1);
+        assert_eq!(metrics.metrics().get("commit").count, 0);
+    }
+
+    #[test]
+    fn check_tx_rate_limit() {
+        let app = CountingApp::default();
+        let limited = MiddlewareBuilder::new()
+            .layer(CheckTxRateLimitLayer::new(0.001, 2).rejection_code(42))
+            .build(app.clone());
+        // Clones, serving other connections, share the same budget.
+        let other = limited.clone();
+
+        assert_eq!(check_tx_code(limited.handle(check_tx(CheckTxType::New))), 0);
+        assert_eq!(check_tx_code(other.handle(check_tx(CheckTxType::New))), 0);
+        assert_eq!(
+            check_tx_code(limited.handle(check_tx(CheckTxType::New))),
+            42
+        );
+        assert_eq!(check_tx_code(other.handle(check_tx(CheckTxType::New))), 42);
+        assert_eq!(app.checked.load(Ordering::SeqCst), 2);
+
+        // Re-checks and other requests are never limited.
+        assert_eq!(
+            check_tx_code(limited.handle(check_tx(CheckTxType::Recheck))),
+            0
+        );
+        limited.handle(deliver_tx());
+        assert_eq!(app.checked.load(Ordering::SeqCst), 3);
+        assert_eq!(app.delivered.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn read_only_guard_rejects_state_changes() {
+        let app = CountingApp::default();
+        let guarded = MiddlewareBuilder::new()
+            .layer(ReadOnlyLayer)
+            .build(app.clone());
+
+        match guarded.handle(deliver_tx()).value {
+            Some(response::Value::Exception(e)) => {
+                assert_eq!(e.error, "deliver_tx rejected: the application is read-only")
+            },
+            r => panic!("unexpected response: {r:?}"),
+        }
+        assert_eq!(app.delivered.load(Ordering::SeqCst), 0);
+
+        assert!(matches!(
+            guarded.handle(query()).value,
+            Some(response::Value::Query(_))
+        ));
+        assert_eq!(check_tx_code(guarded.handle(check_tx(CheckTxType::New))), 0);
+        assert_eq!(app.checked.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,150 @@
+//! Integration tests for executing blocks through the mock node.
+
+#[cfg(all(feature = "mock-node", feature = "kvstore-app"))]
+mod mock_node_integration {
+    use std::thread;
+
+    use bytes::Bytes;
+    use tendermint::{
+        abci::{request, response},
+        crypto::{default::Sha256, ed25519::SigningKey},
+        merkle::{self, proof::ProofOps},
+        validator, vote, Genesis, PublicKey,
+    };
+    use tendermint_abci::{
+        DomainAdapter, DomainApplication, KeyValueStoreApp, MockNode, RequestDispatcher,
+    };
+    use tendermint_proto::v0_37::abci as pb;
+
+    const GENESIS: &str = r#"{
+        "genesis_time": "2023-02-27T07:13:03.391799721Z",
+        "chain_id": "mock-chain",
+        "initial_height": "1",
+        "consensus_params": {
+            "block": { "max_bytes": "22020096", "max_gas": "-1" },
+            "evidence": {
+                "max_age_duration": "172800000000000",
+                "max_age_num_blocks": "100000",
+                "max_bytes": "1048576"
+            },
+            "validator": { "pub_key_types": ["ed25519"] },
+            "version": { "app": "0" }
+        },
+        "validators": [{
+            "address": "DD8A65495B6240145764A74E78CF203D51510371",
+            "name": "",
+            "power": "10",
+            "pub_key": {
+                "type": "tendermint/PubKeyEd25519",
+                "value": "OYpM2RXHEO1/R3jJRhAbjY8JhvjTBbiNJKBStEKu12s="
+            }
+        }],
+        "app_hash": "",
+        "app_state": {}
+    }"#;
+
+    fn genesis() -> Genesis {
+        serde_json::from_str(GENESIS).unwrap()
+    }
+
+    #[test]
+    fn kvstore_blocks() {
+        let (app, driver) = KeyValueStoreApp::new();
+        thread::spawn(move || driver.run());
+        let mut node = MockNode::new(app, &genesis()).unwrap();
+
+        let txs = (0..5).map(|i| Bytes::from(format!("key{i}=value{i}")));
+        let blocks = node.execute(txs, 2).unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[2].txs.len(), 1);
+        assert_eq!(node.height().value(), 4);
+        assert_eq!(node.app_hashes().len(), 3);
+        assert_eq!(node.app_hash(), &blocks[2].app_hash);
+
+        // Each header commits to the application hash of the previous block.
+        assert_eq!(blocks[1].header.app_hash, blocks[0].app_hash);
+        assert_eq!(
+            blocks[1].header.last_block_id.unwrap().hash,
+            blocks[0].header.hash()
+        );
+
+        let response = node.app().handle(pb::Request {
+            value: Some(pb::request::Value::Query(pb::RequestQuery {
+                data: "key3".into(),
+                prove: true,
+                ..Default::default()
+            })),
+        });
+        let query = match response.value {
+            Some(pb::response::Value::Query(r)) => r,
+            r => panic!("unexpected response: {r:?}"),
+        };
+        assert_eq!(query.value, "value3".as_bytes());
+
+        // The proof holds against the application hash found in the header
+        // of the next block.
+        let next = node.execute_block(vec![]).unwrap();
+        let root: merkle::Hash = next.header.app_hash.as_bytes().try_into().unwrap();
+        let proof_ops = ProofOps::try_from(query.proof_ops.unwrap()).unwrap();
+        proof_ops
+            .verify_value::<Sha256>(&root, &[b"key3".as_slice()], b"value3")
+            .unwrap();
+        assert!(proof_ops
+            .verify_value::<Sha256>(&root, &[b"key3".as_slice()], b"value4")
+            .is_err());
+    }
+
+    /// Adds a validator, whose public key is given by the first transaction
+    /// of a block, with a voting power of 5.
+    #[derive(Clone, Default)]
+    struct StakingApp {
+        added: std::sync::Arc<std::sync::Mutex<Vec<PublicKey>>>,
+    }
+
+    impl DomainApplication for StakingApp {
+        fn deliver_tx(&self, request: request::DeliverTx) -> response::DeliverTx {
+            let key = PublicKey::from_raw_ed25519(&request.tx).unwrap();
+            self.added.lock().unwrap().push(key);
+            Default::default()
+        }
+
+        fn end_block(&self, _request: request::EndBlock) -> response::EndBlock {
+            response::EndBlock {
+                validator_updates: self
+                    .added
+                    .lock()
+                    .unwrap()
+                    .drain(..)
+                    .map(|pub_key| validator::Update {
+                        pub_key,
+                        power: vote::Power::from(5_u32),
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[test]
+    fn validator_updates_take_effect_two_blocks_later() {
+        let mut node =
+            MockNode::new(DomainAdapter::new(StakingApp::default()), &genesis()).unwrap();
+        let key = SigningKey::try_from(&[7_u8; 32][..])
+            .unwrap()
+            .verification_key();
+        let genesis_validators_hash = node.validators().hash();
+
+        let first = node
+            .execute_block(vec![Bytes::copy_from_slice(key.as_bytes())])
+            .unwrap();
+        assert_eq!(first.end_block.validator_updates.len(), 1);
+        assert_eq!(node.validators().validators().len(), 1);
+        assert_eq!(node.next_validators().validators().len(), 2);
+        assert_eq!(node.next_validators().total_voting_power().value(), 15);
+
+        let second = node.execute_block(vec![]).unwrap();
+        assert_eq!(second.header.validators_hash, genesis_validators_hash);
+        assert_ne!(second.header.next_validators_hash, genesis_validators_hash);
+        assert_eq!(node.validators().validators().len(), 2);
+    }
+}
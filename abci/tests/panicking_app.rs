@@ -0,0 +1,221 @@
+//! Integration tests for the isolation of failing requests.
+
+#[cfg(feature = "client")]
+mod panicking_app_integration {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use prost::Message;
+    use tendermint_abci::{
+        error::ErrorDetail, Application, ClientBuilder, ProtocolVersion, RequestDispatcher,
+        ServerBuilder,
+    };
+    use tendermint_proto::{
+        v0_34::abci as pb34,
+        v0_37::abci::{
+            response::Value, Request, RequestCheckTx, RequestEcho, Response, ResponseCheckTx,
+        },
+    };
+
+    /// Panics upon checking a transaction reading "panic".
+    #[derive(Clone)]
+    struct PanickingApp;
+
+    impl Application for PanickingApp {
+        fn check_tx(&self, request: RequestCheckTx) -> ResponseCheckTx {
+            if request.tx == "panic" {
+                panic!("deliberate panic");
+            }
+            Default::default()
+        }
+    }
+
+    fn exception(response: Response) -> String {
+        match response.value {
+            Some(Value::Exception(e)) => e.error,
+            r => panic!("unexpected response: {r:?}"),
+        }
+    }
+
+    #[test]
+    fn dispatcher_catches_panics() {
+        let response = PanickingApp.handle(Request {
+            value: Some(tendermint_proto::v0_37::abci::request::Value::CheckTx(
+                RequestCheckTx {
+                    tx: "panic".into(),
+                    r#type: 0,
+                },
+            )),
+        });
+        assert_eq!(
+            exception(response),
+            "application panicked: deliberate panic"
+        );
+
+        let response = PanickingApp.handle(Request { value: None });
+        assert_eq!(exception(response), "empty request");
+    }
+
+    #[test]
+    fn panic_produces_exception() {
+        let server = ServerBuilder::default()
+            .bind("127.0.0.1:0", PanickingApp)
+            .unwrap();
+        let server_addr = server.local_addr();
+        let failures = server.failure_count();
+        let _ = std::thread::spawn(move || server.listen());
+        let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+
+        let err = client
+            .check_tx(RequestCheckTx {
+                tx: "panic".into(),
+                r#type: 0,
+            })
+            .unwrap_err();
+        match err.detail() {
+            ErrorDetail::UnexpectedServerResponseType(e) => {
+                assert!(matches!(e.got, Value::Exception(_)))
+            },
+            e => panic!("unexpected error: {e:?}"),
+        }
+        assert_eq!(failures.get(), 1);
+
+        // The connection survives the panic.
+        let response = client
+            .echo(RequestEcho {
+                message: "Still there?".to_string(),
+            })
+            .unwrap();
+        assert_eq!(response.message, "Still there?");
+        client.check_tx(RequestCheckTx::default()).unwrap();
+        assert_eq!(failures.get(), 1);
+    }
+
+    #[test]
+    fn empty_request_produces_exception() {
+        let server = ServerBuilder::default()
+            .bind("127.0.0.1:0", PanickingApp)
+            .unwrap();
+        let server_addr = server.local_addr();
+        let failures = server.failure_count();
+        let _ = std::thread::spawn(move || server.listen());
+
+        let mut stream = TcpStream::connect(server_addr).unwrap();
+        stream
+            .write_all(&Request { value: None }.encode_length_delimited_to_vec())
+            .unwrap();
+        assert_eq!(exception(read_response(&mut stream)), "empty request");
+        assert_eq!(failures.get(), 1);
+    }
+
+    #[test]
+    fn v0_34_failures_are_counted() {
+        let server = ServerBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .bind("127.0.0.1:0", PanickingApp)
+            .unwrap();
+        let server_addr = server.local_addr();
+        let failures = server.failure_count();
+        let _ = std::thread::spawn(move || server.listen());
+
+        let mut client = ClientBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .connect(server_addr.clone())
+            .unwrap();
+        client
+            .check_tx(RequestCheckTx {
+                tx: "panic".into(),
+                r#type: 0,
+            })
+            .unwrap_err();
+        assert_eq!(failures.get(), 1);
+        client.check_tx(RequestCheckTx::default()).unwrap();
+        assert_eq!(failures.get(), 1);
+
+        let mut stream = TcpStream::connect(server_addr).unwrap();
+        stream
+            .write_all(&pb34::Request { value: None }.encode_length_delimited_to_vec())
+            .unwrap();
+        let response =
+            pb34::Response::decode(read_frame(&mut stream, ProtocolVersion::V0_34).as_slice())
+                .unwrap();
+        match response.value {
+            Some(pb34::response::Value::Exception(e)) => assert_eq!(e.error, "empty request"),
+            r => panic!("unexpected response: {r:?}"),
+        }
+        assert_eq!(failures.get(), 2);
+    }
+
+    fn read_response(stream: &mut TcpStream) -> Response {
+        Response::decode(read_frame(stream, ProtocolVersion::V0_37).as_slice()).unwrap()
+    }
+
+    fn read_frame(stream: &mut TcpStream, version: ProtocolVersion) -> Vec<u8> {
+        let mut len = 0u64;
+        let mut shift = 0;
+        loop {
+            let mut byte = [0u8];
+            stream.read_exact(&mut byte).unwrap();
+            len |= u64::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        if version == ProtocolVersion::V0_34 {
+            // Lengths are zigzag-encoded, and never negative.
+            len >>= 1;
+        }
+        let mut buf = vec![0u8; len as usize];
+        stream.read_exact(&mut buf).unwrap();
+        buf
+    }
+}
+
+#[cfg(all(feature = "async", feature = "client"))]
+mod async_panicking_app_integration {
+    use async_trait::async_trait;
+    use tendermint_abci::{AsyncApplication, ClientBuilder, ServerBuilder};
+    use tendermint_proto::v0_37::abci::{RequestCheckTx, RequestEcho, ResponseCheckTx};
+
+    #[derive(Clone)]
+    struct PanickingApp;
+
+    #[async_trait]
+    impl AsyncApplication for PanickingApp {
+        async fn check_tx(&self, _request: RequestCheckTx) -> ResponseCheckTx {
+            panic!("deliberate panic");
+        }
+    }
+
+    #[tokio::test]
+    async fn panic_produces_exception() {
+        let server = ServerBuilder::default()
+            .bind_async("127.0.0.1:0", PanickingApp)
+            .await
+            .unwrap();
+        let server_addr = server.local_addr();
+        let failures = server.failure_count();
+        let shutdown = server.shutdown_handle();
+        let server_task = tokio::spawn(server.listen());
+
+        let response = tokio::task::spawn_blocking(move || {
+            let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+            client.check_tx(RequestCheckTx::default()).unwrap_err();
+            client
+                .echo(RequestEcho {
+                    message: "Still there?".to_string(),
+                })
+                .unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.message, "Still there?");
+        assert_eq!(failures.get(), 1);
+
+        shutdown.shutdown();
+        server_task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+//! Integration tests for serving the ABCI v0.34 wire protocol.
+
+#[cfg(all(feature = "client", feature = "echo-app", feature = "kvstore-app"))]
+mod protocol_v0_34_integration {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        thread,
+    };
+
+    use prost::Message;
+    use tendermint_abci::{
+        ClientBuilder, EchoApp, KeyValueStoreApp, ProtocolVersion, ServerBuilder,
+    };
+    use tendermint_proto::{
+        v0_34::abci as pb34,
+        v0_37::abci::{RequestDeliverTx, RequestEcho, RequestQuery},
+    };
+
+    #[test]
+    fn echo() {
+        let server = ServerBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .bind("127.0.0.1:0", EchoApp)
+            .unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || server.listen());
+
+        let mut client = ClientBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .connect(server_addr)
+            .unwrap();
+        let response = client
+            .echo(RequestEcho {
+                message: "Hello ABCI!".to_string(),
+            })
+            .unwrap();
+        assert_eq!(response.message, "Hello ABCI!");
+    }
+
+    #[test]
+    fn kvstore() {
+        let (app, driver) = KeyValueStoreApp::new();
+        let server = ServerBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .bind("127.0.0.1:0", app)
+            .unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || driver.run());
+        thread::spawn(move || server.listen());
+
+        let mut client = ClientBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .connect(server_addr)
+            .unwrap();
+        let response = client
+            .deliver_tx(RequestDeliverTx {
+                tx: "test-key=test-value".into(),
+            })
+            .unwrap();
+        // Event attributes must survive the translation between versions.
+        assert_eq!(response.events[0].attributes[0].key, "key");
+        assert_eq!(response.events[0].attributes[0].value, "test-key");
+        client.commit().unwrap();
+
+        let response = client
+            .query(RequestQuery {
+                data: "test-key".into(),
+                path: "".to_string(),
+                height: 0,
+                prove: false,
+            })
+            .unwrap();
+        assert_eq!(response.value, "test-value".as_bytes());
+    }
+
+    #[test]
+    fn set_option_is_acknowledged() {
+        let server = ServerBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .bind("127.0.0.1:0", EchoApp)
+            .unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || server.listen());
+
+        let mut stream = TcpStream::connect(server_addr).unwrap();
+        let request = pb34::Request {
+            value: Some(pb34::request::Value::SetOption(pb34::RequestSetOption {
+                key: "some-key".to_string(),
+                value: "some-value".to_string(),
+            })),
+        };
+        write_v0_34(&mut stream, &request);
+
+        let response: pb34::Response = read_v0_34(&mut stream);
+        assert!(matches!(
+            response.value,
+            Some(pb34::response::Value::SetOption(_))
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn prepare_proposal_is_refused() {
+        use tendermint_abci::error::ErrorDetail;
+        use tendermint_proto::v0_37::abci::RequestPrepareProposal;
+
+        let server = ServerBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .bind("127.0.0.1:0", EchoApp)
+            .unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || server.listen());
+
+        let client = ClientBuilder::default()
+            .protocol_version(ProtocolVersion::V0_34)
+            .connect_async(server_addr)
+            .await
+            .unwrap();
+        let err = client
+            .prepare_proposal(RequestPrepareProposal::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err.detail(), ErrorDetail::UnsupportedRequest(_)));
+
+        // Nothing has been sent, so the connection remains usable.
+        let echo = client.echo(RequestEcho {
+            message: "Hello ABCI!".to_string(),
+        });
+        client.flush().await.unwrap();
+        assert_eq!(echo.await.unwrap().message, "Hello ABCI!");
+    }
+
+    /// Write a message prefixed by its zigzag-encoded length, as done by
+    /// Tendermint Core v0.34.
+    fn write_v0_34<M: Message>(stream: &mut TcpStream, message: &M) {
+        let mut buf = Vec::new();
+        prost::encoding::encode_varint((message.encoded_len() as u64) << 1, &mut buf);
+        message.encode(&mut buf).unwrap();
+        stream.write_all(&buf).unwrap();
+    }
+
+    fn read_v0_34<M: Message + Default>(stream: &mut TcpStream) -> M {
+        let mut len = 0u64;
+        let mut shift = 0;
+        loop {
+            let mut byte = [0u8];
+            stream.read_exact(&mut byte).unwrap();
+            len |= u64::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        let mut buf = vec![0u8; (len >> 1) as usize];
+        stream.read_exact(&mut buf).unwrap();
+        M::decode(buf.as_slice()).unwrap()
+    }
+}
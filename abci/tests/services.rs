@@ -0,0 +1,134 @@
+//! Integration tests for applications built from per-connection services.
+
+#[cfg(feature = "client")]
+mod services_integration {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use bytes::Bytes;
+    use tendermint::abci::{request, response, MethodKind};
+    use tendermint_abci::{
+        error::ErrorDetail, Client, ClientBuilder, Consensus, Info, Mempool, ServerBuilder,
+        Services, Snapshot,
+    };
+    use tendermint_proto::v0_37::abci::{
+        response::Value, RequestCheckTx, RequestDeliverTx, RequestEcho, RequestInfo, RequestQuery,
+    };
+
+    /// Counts delivered transactions, making the count visible to queries
+    /// once committed.
+    struct Counter {
+        delivered: u64,
+        committed: Arc<AtomicU64>,
+    }
+
+    impl Consensus for Counter {
+        fn deliver_tx(&mut self, _request: request::DeliverTx) -> response::DeliverTx {
+            self.delivered += 1;
+            Default::default()
+        }
+
+        fn commit(&mut self) -> response::Commit {
+            self.committed.store(self.delivered, Ordering::SeqCst);
+            Default::default()
+        }
+    }
+
+    struct CounterQuery {
+        committed: Arc<AtomicU64>,
+    }
+
+    impl Info for CounterQuery {
+        fn query(&self, _request: request::Query) -> response::Query {
+            response::Query {
+                value: Bytes::from(self.committed.load(Ordering::SeqCst).to_string()),
+                ..Default::default()
+            }
+        }
+    }
+
+    struct AcceptAll;
+
+    impl Mempool for AcceptAll {}
+
+    struct NoSnapshots;
+
+    impl Snapshot for NoSnapshots {}
+
+    fn services() -> Services<Counter, AcceptAll, CounterQuery, NoSnapshots> {
+        let committed = Arc::new(AtomicU64::new(0));
+        Services::new(
+            Counter {
+                delivered: 0,
+                committed: committed.clone(),
+            },
+            AcceptAll,
+            CounterQuery { committed },
+            NoSnapshots,
+        )
+    }
+
+    fn query(client: &mut Client) -> Vec<u8> {
+        client
+            .query(RequestQuery::default())
+            .unwrap()
+            .value
+            .to_vec()
+    }
+
+    fn assert_exception<T: std::fmt::Debug>(result: Result<T, tendermint_abci::Error>) {
+        match result.unwrap_err().detail() {
+            ErrorDetail::UnexpectedServerResponseType(e) => {
+                assert!(matches!(e.got, Value::Exception(_)))
+            },
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn connections_are_told_apart_by_first_request() {
+        let server = ServerBuilder::default()
+            .bind("127.0.0.1:0", services().dispatcher())
+            .unwrap();
+        let server_addr = server.local_addr();
+        let _ = std::thread::spawn(move || server.listen());
+        let mut consensus = ClientBuilder::default().connect(&server_addr).unwrap();
+        let mut mempool = ClientBuilder::default().connect(&server_addr).unwrap();
+        let mut info = ClientBuilder::default().connect(&server_addr).unwrap();
+
+        // Echo and Flush do not determine the kind of a connection.
+        consensus.echo(RequestEcho::default()).unwrap();
+        consensus.flush().unwrap();
+
+        consensus.deliver_tx(RequestDeliverTx::default()).unwrap();
+        consensus.deliver_tx(RequestDeliverTx::default()).unwrap();
+        assert_eq!(query(&mut info), b"0");
+        consensus.commit().unwrap();
+        assert_eq!(query(&mut info), b"2");
+
+        let response = mempool.check_tx(RequestCheckTx::default()).unwrap();
+        assert_eq!(response.code, 0);
+
+        // Requests of other kinds are rejected, without closing the
+        // connection.
+        assert_exception(consensus.check_tx(RequestCheckTx::default()));
+        assert_exception(mempool.info(RequestInfo::default()));
+        assert_exception(info.commit());
+        assert_eq!(query(&mut info), b"2");
+    }
+
+    #[test]
+    fn connection_kind_can_be_fixed() {
+        let server = ServerBuilder::default()
+            .bind("127.0.0.1:0", services().connection(MethodKind::Info))
+            .unwrap();
+        let server_addr = server.local_addr();
+        let _ = std::thread::spawn(move || server.listen());
+        let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+
+        assert_exception(client.deliver_tx(RequestDeliverTx::default()));
+        assert_eq!(query(&mut client), b"0");
+    }
+}
@@ -0,0 +1,106 @@
+//! Integration tests for recording and replaying ABCI sessions.
+
+#[cfg(all(feature = "client", feature = "kvstore-app"))]
+mod session_integration {
+    use std::{fs::File, path::PathBuf, thread};
+
+    use tendermint_abci::{
+        error::ErrorDetail, replay_session, ClientBuilder, KeyValueStoreApp, ServerBuilder,
+        SessionReader, SessionRecorder,
+    };
+    use tendermint_proto::v0_37::abci::{request, response, RequestDeliverTx, RequestInfo};
+
+    fn log_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "tendermint-abci-session-{}-{}.log",
+            name,
+            std::process::id()
+        ))
+    }
+
+    /// Records a session of two blocks against the key/value store.
+    fn record(path: &PathBuf) {
+        let (app, driver) = KeyValueStoreApp::new();
+        let recorder = SessionRecorder::new(app, File::create(path).unwrap());
+        let server = ServerBuilder::default()
+            .bind("127.0.0.1:0", recorder)
+            .unwrap();
+        let server_addr = server.local_addr();
+        thread::spawn(move || driver.run());
+        thread::spawn(move || server.listen());
+
+        let mut client = ClientBuilder::default().connect(server_addr).unwrap();
+        client.info(RequestInfo::default()).unwrap();
+        for block in [["a=1", "b=2"], ["c=3", "a=4"]] {
+            for tx in block {
+                client
+                    .deliver_tx(RequestDeliverTx { tx: tx.into() })
+                    .unwrap();
+            }
+            client.commit().unwrap();
+        }
+        drop(client);
+    }
+
+    #[test]
+    fn replaying_identical_app_does_not_diverge() {
+        let path = log_path("identical");
+        record(&path);
+
+        let records = SessionReader::new(File::open(&path).unwrap())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 7);
+        assert!(matches!(
+            records[6].0.value,
+            Some(request::Value::Commit(_))
+        ));
+        assert!(matches!(
+            records[6].1.value,
+            Some(response::Value::Commit(_))
+        ));
+
+        let (app, driver) = KeyValueStoreApp::new();
+        thread::spawn(move || driver.run());
+        let divergence = replay_session(File::open(&path).unwrap(), &app).unwrap();
+        assert_eq!(divergence, None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replay_reports_first_divergent_response() {
+        let path = log_path("divergent");
+        record(&path);
+
+        // A store which already holds an entry commits to a different
+        // application hash.
+        let (app, driver) = KeyValueStoreApp::new();
+        thread::spawn(move || driver.run());
+        app.set("z", "0").unwrap();
+        let divergence = replay_session(File::open(&path).unwrap(), &app)
+            .unwrap()
+            .unwrap();
+        assert_eq!(divergence.index, 3);
+        match (divergence.recorded.value, divergence.replayed.value) {
+            (Some(response::Value::Commit(recorded)), Some(response::Value::Commit(replayed))) => {
+                assert_ne!(recorded.data, replayed.data)
+            },
+            r => panic!("unexpected responses: {r:?}"),
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn truncated_recording_is_reported() {
+        let path = log_path("truncated");
+        record(&path);
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.pop();
+
+        let (app, driver) = KeyValueStoreApp::new();
+        thread::spawn(move || driver.run());
+        let err = replay_session(bytes.as_slice(), &app).unwrap_err();
+        assert!(matches!(err.detail(), ErrorDetail::TruncatedRecording(_)));
+        std::fs::remove_file(path).unwrap();
+    }
+}
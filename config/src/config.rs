@@ -5,6 +5,7 @@
 //! - `config.toml`: `config::TendermintConfig`
 //! - `node_key.rs`: `config::node_key::NodeKey`
 //! - `priv_validator_key.rs`: `config::priv_validator_key::PrivValidatorKey`
+//! - `priv_validator_state.rs`: `config::priv_validator_state::PrivValidatorState`
 
 use alloc::collections::{btree_map, BTreeMap};
 use core::{fmt, str::FromStr};
@@ -16,7 +17,7 @@ use std::{
 use serde::{de, de::Error as _, ser, Deserialize, Serialize};
 use tendermint::{genesis::Genesis, node, Moniker, Timeout};
 
-use crate::{net, node_key::NodeKey, prelude::*, Error};
+use crate::{net, node_key::NodeKey, prelude::*, priv_validator_state::PrivValidatorState, Error};
 
 /// Tendermint `config.toml` file
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
@@ -139,6 +140,15 @@ impl TendermintConfig {
         let path = home.as_ref().join(&self.node_key_file);
         NodeKey::load_json_file(&path)
     }
+
+    /// Load `priv_validator_state.json` file from the configured location
+    pub fn load_priv_validator_state(
+        &self,
+        home: impl AsRef<Path>,
+    ) -> Result<PrivValidatorState, Error> {
+        let path = home.as_ref().join(&self.priv_validator_state_file);
+        PrivValidatorState::load_json_file(&path)
+    }
 }
 
 /// Database backend
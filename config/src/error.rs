@@ -34,5 +34,31 @@ define_error! {
         Tendermint
             [ TendermintError ]
             |_| { format_args!("tendermint error") },
+
+        SignBytes
+            [ DisplayOnly<tendermint_proto::Error> ]
+            |_| { format_args!("failed to encode the bytes to sign") },
+
+        SignStateRegression
+            {
+                last: String,
+                requested: String,
+            }
+            |e| {
+                format_args!("refusing to sign at height/round/step {}, lower than the last signed {}",
+                    e.requested, e.last)
+            },
+
+        MissingSignBytes
+            { hrs: String }
+            |e| {
+                format_args!("no signed bytes kept for the last height/round/step {}", e.hrs)
+            },
+
+        DoubleSign
+            { hrs: String }
+            |e| {
+                format_args!("refusing to sign conflicting data at height/round/step {}", e.hrs)
+            },
     }
 }
@@ -23,8 +23,10 @@ mod error;
 mod node_key;
 mod prelude;
 mod priv_validator_key;
+mod priv_validator_state;
 
 pub use config::*;
 pub use error::*;
 pub use node_key::NodeKey;
 pub use priv_validator_key::PrivValidatorKey;
+pub use priv_validator_state::{PrivValidatorState, SignCheck};
@@ -0,0 +1,261 @@
+//! Validator signing state
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+use tendermint::{
+    block, chain,
+    proposal::{CanonicalProposal, Proposal},
+    vote::{self, CanonicalVote, Vote},
+    Time,
+};
+use tendermint_proto::{
+    serializers::bytes::{base64string, hexstring},
+    v0_37::types::{CanonicalProposal as RawCanonicalProposal, CanonicalVote as RawCanonicalVote},
+    Protobuf,
+};
+
+use crate::{error::Error, prelude::*};
+
+/// State of the last message signed by a validator, kept in
+/// `priv_validator_state.json` to prevent double signing
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PrivValidatorState {
+    /// Height of the last signed message
+    pub height: block::Height,
+
+    /// Round of the last signed message
+    #[serde(with = "round_number")]
+    pub round: block::Round,
+
+    /// Step of the last signed message (see [`PrivValidatorState::STEP_PROPOSE`] and others)
+    pub step: i8,
+
+    /// Signature of the last signed message
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "base64string")]
+    pub signature: Vec<u8>,
+
+    /// Signed bytes of the last signed message
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "hexstring")]
+    pub signbytes: Vec<u8>,
+}
+
+impl Default for PrivValidatorState {
+    /// State before anything is signed, as written by `tendermint init`
+    fn default() -> Self {
+        Self {
+            height: 0_u32.into(),
+            round: block::Round::default(),
+            step: 0,
+            signature: Vec::new(),
+            signbytes: Vec::new(),
+        }
+    }
+}
+
+/// Outcome of checking a message against a [`PrivValidatorState`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignCheck {
+    /// The message is for a later height, round or step, and can be signed
+    New,
+
+    /// The message is the last signed one, whose signature can be reused
+    Same,
+
+    /// The message is the last signed one apart from its timestamp: the last
+    /// signature can be reused, along with the timestamp it covers
+    SameExceptTimestamp(Time),
+}
+
+impl PrivValidatorState {
+    /// Step of a signed proposal
+    pub const STEP_PROPOSE: i8 = 1;
+
+    /// Step of a signed prevote
+    pub const STEP_PREVOTE: i8 = 2;
+
+    /// Step of a signed precommit
+    pub const STEP_PRECOMMIT: i8 = 3;
+
+    /// Parse `priv_validator_state.json`
+    pub fn parse_json<T: AsRef<str>>(json_string: T) -> Result<Self, Error> {
+        serde_json::from_str(json_string.as_ref()).map_err(Error::serde_json)
+    }
+
+    /// Load `priv_validator_state.json` from a file
+    pub fn load_json_file<P>(path: &P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let json_string = fs::read_to_string(path)
+            .map_err(|e| Error::file_io(format!("{}", path.as_ref().display()), e))?;
+
+        Self::parse_json(json_string)
+    }
+
+    /// Save `priv_validator_state.json` to a file, atomically replacing its
+    /// previous content
+    pub fn save_json_file<P>(&self, path: &P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        // Distinguishes the temporary files of concurrent saves.
+        static SAVE_COUNT: AtomicU64 = AtomicU64::new(0);
+
+        let path = path.as_ref();
+        let json_string = serde_json::to_string_pretty(self).map_err(Error::serde_json)?;
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(format!(
+            ".{}.{}.tmp",
+            process::id(),
+            SAVE_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_path = Path::new(&tmp_path);
+
+        // The content must be on disk before the rename makes it visible, lest
+        // a crash leave an empty or truncated state behind.
+        let write = || {
+            let mut file = File::create(tmp_path)?;
+            file.write_all(json_string.as_bytes())?;
+            file.sync_all()
+        };
+        if let Err(e) = write().and_then(|()| fs::rename(tmp_path, path)) {
+            let _ = fs::remove_file(tmp_path);
+            return Err(Error::file_io(format!("{}", path.display()), e));
+        }
+
+        // Persist the rename itself. Directories cannot be opened as files on
+        // every platform, hence this is limited to Unix.
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            File::open(dir)
+                .and_then(|dir| dir.sync_all())
+                .map_err(|e| Error::file_io(format!("{}", dir.display()), e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Step of the given vote type
+    pub fn vote_step(vote_type: vote::Type) -> i8 {
+        match vote_type {
+            vote::Type::Prevote => Self::STEP_PREVOTE,
+            vote::Type::Precommit => Self::STEP_PRECOMMIT,
+        }
+    }
+
+    /// Check whether signing the given vote for the given chain would be a
+    /// double sign, returning an error if so
+    pub fn check_vote(&self, vote: &Vote, chain_id: &chain::Id) -> Result<SignCheck, Error> {
+        let step = Self::vote_step(vote.vote_type);
+        if !self.check_hrs(vote.height, vote.round, step)? {
+            return Ok(SignCheck::New);
+        }
+        let sign_bytes = vote
+            .to_signable_vec(chain_id.clone())
+            .map_err(Error::sign_bytes)?;
+        self.check_sign_bytes(&sign_bytes, || {
+            let mut last: CanonicalVote =
+                Protobuf::<RawCanonicalVote>::decode_length_delimited_vec(&self.signbytes).ok()?;
+            let mut new = CanonicalVote::new(vote.clone(), chain_id.clone());
+            let timestamp = last.timestamp.take()?;
+            new.timestamp = None;
+            (last == new).then_some(timestamp)
+        })
+    }
+
+    /// Check whether signing the given proposal for the given chain would be
+    /// a double sign, returning an error if so
+    pub fn check_proposal(
+        &self,
+        proposal: &Proposal,
+        chain_id: &chain::Id,
+    ) -> Result<SignCheck, Error> {
+        if !self.check_hrs(proposal.height, proposal.round, Self::STEP_PROPOSE)? {
+            return Ok(SignCheck::New);
+        }
+        let sign_bytes = proposal
+            .to_signable_vec(chain_id.clone())
+            .map_err(Error::sign_bytes)?;
+        self.check_sign_bytes(&sign_bytes, || {
+            let mut last: CanonicalProposal =
+                Protobuf::<RawCanonicalProposal>::decode_length_delimited_vec(&self.signbytes)
+                    .ok()?;
+            let mut new = CanonicalProposal::new(proposal.clone(), chain_id.clone());
+            let timestamp = last.timestamp.take()?;
+            new.timestamp = None;
+            (last == new).then_some(timestamp)
+        })
+    }
+
+    /// Check that the given height, round and step are not lower than the
+    /// last ones, returning whether they are the same
+    pub fn check_hrs(
+        &self,
+        height: block::Height,
+        round: block::Round,
+        step: i8,
+    ) -> Result<bool, Error> {
+        let last = (self.height, self.round, self.step);
+        let requested = (height, round, step);
+        if requested < last {
+            return Err(Error::sign_state_regression(
+                format_hrs(last),
+                format_hrs(requested),
+            ));
+        }
+        if requested == last && self.signbytes.is_empty() {
+            return Err(Error::missing_sign_bytes(format_hrs(last)));
+        }
+        Ok(requested == last)
+    }
+
+    /// Compare the bytes to sign at the last height, round and step with the
+    /// last signed ones
+    fn check_sign_bytes(
+        &self,
+        sign_bytes: &[u8],
+        last_timestamp_if_only_difference: impl FnOnce() -> Option<Time>,
+    ) -> Result<SignCheck, Error> {
+        if sign_bytes == self.signbytes {
+            return Ok(SignCheck::Same);
+        }
+        last_timestamp_if_only_difference()
+            .map(SignCheck::SameExceptTimestamp)
+            .ok_or_else(|| Error::double_sign(format_hrs((self.height, self.round, self.step))))
+    }
+}
+
+fn format_hrs((height, round, step): (block::Height, block::Round, i8)) -> String {
+    format!("{height}/{round}/{step}")
+}
+
+/// Serialize a round as a number, like Tendermint Core does
+mod round_number {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+    use tendermint::block;
+
+    pub fn serialize<S: Serializer>(
+        round: &block::Round,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(round.value())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<block::Round, D::Error> {
+        block::Round::try_from(u32::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
@@ -6,7 +6,7 @@ use std::{fs, path::PathBuf, time::Duration};
 
 #[cfg(test)]
 use pretty_assertions::assert_eq;
-use tendermint::node;
+use tendermint::{block, node, proposal, vote, Hash, Proposal, Time, Vote};
 use tendermint_config::*;
 
 /// Read a fixture file from the `support/config` directory
@@ -224,6 +224,126 @@ fn priv_validator_json_parser() {
     );
 }
 
+/// Parse an example `priv_validator_state.json` to a `PrivValidatorState` struct
+#[test]
+fn priv_validator_state_parser() {
+    let raw_state = read_fixture("priv_validator_state.json");
+    let state = PrivValidatorState::parse_json(raw_state).unwrap();
+    assert_eq!(state.height, block::Height::from(10_u32));
+    assert_eq!(state.round, block::Round::from(1_u16));
+    assert_eq!(state.step, PrivValidatorState::STEP_PRECOMMIT);
+    assert_eq!(state.signature, (0..64).collect::<Vec<u8>>());
+    assert_eq!(state.signbytes.len(), 115);
+
+    let initial = PrivValidatorState::parse_json(r#"{"height":"0","round":0,"step":0}"#).unwrap();
+    assert_eq!(initial, PrivValidatorState::default());
+}
+
+/// Check votes and proposals against the state in `priv_validator_state.json`
+#[test]
+fn priv_validator_state_double_sign_check() {
+    let state = PrivValidatorState::parse_json(read_fixture("priv_validator_state.json")).unwrap();
+    let chain_id = "test-chain".parse().unwrap();
+
+    let vote = signed_vote();
+    assert_eq!(state.check_vote(&vote, &chain_id).unwrap(), SignCheck::Same);
+
+    let later = Vote {
+        timestamp: Some("2023-01-01T00:00:05Z".parse().unwrap()),
+        ..vote.clone()
+    };
+    assert_eq!(
+        state.check_vote(&later, &chain_id).unwrap(),
+        SignCheck::SameExceptTimestamp("2023-01-01T00:00:00Z".parse().unwrap())
+    );
+
+    let nil = Vote {
+        block_id: None,
+        ..vote.clone()
+    };
+    let err = state.check_vote(&nil, &chain_id).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::DoubleSign(_)));
+    let other_chain = "other-chain".parse().unwrap();
+    let err = state.check_vote(&vote, &other_chain).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::DoubleSign(_)));
+
+    let prevote = Vote {
+        vote_type: vote::Type::Prevote,
+        ..vote.clone()
+    };
+    let err = state.check_vote(&prevote, &chain_id).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::SignStateRegression(_)));
+
+    let next_round = Vote {
+        round: 2_u16.into(),
+        block_id: None,
+        ..vote
+    };
+    assert_eq!(
+        state.check_vote(&next_round, &chain_id).unwrap(),
+        SignCheck::New
+    );
+
+    let proposal = Proposal {
+        msg_type: proposal::Type::Proposal,
+        height: 11_u32.into(),
+        round: 0_u16.into(),
+        pol_round: None,
+        block_id: None,
+        timestamp: Some(Time::unix_epoch()),
+        signature: None,
+    };
+    assert_eq!(
+        state.check_proposal(&proposal, &chain_id).unwrap(),
+        SignCheck::New
+    );
+    let old_proposal = Proposal {
+        height: 10_u32.into(),
+        round: 1_u16.into(),
+        ..proposal
+    };
+    let err = state.check_proposal(&old_proposal, &chain_id).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::SignStateRegression(_)));
+}
+
+/// Save a `PrivValidatorState` to a file, then load it again.
+#[test]
+fn priv_validator_state_save_and_load() {
+    let state = PrivValidatorState::parse_json(read_fixture("priv_validator_state.json")).unwrap();
+    let dir = std::env::temp_dir().join(format!("priv_validator_state-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("priv_validator_state.json");
+    state.save_json_file(&path).unwrap();
+    assert_eq!(PrivValidatorState::load_json_file(&path).unwrap(), state);
+
+    let next = PrivValidatorState {
+        height: 11_u32.into(),
+        ..PrivValidatorState::default()
+    };
+    next.save_json_file(&path).unwrap();
+    assert_eq!(PrivValidatorState::load_json_file(&path).unwrap(), next);
+
+    // No temporary file is left behind.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// The precommit whose signed bytes are in `priv_validator_state.json`
+fn signed_vote() -> Vote {
+    Vote {
+        vote_type: vote::Type::Precommit,
+        height: 10_u32.into(),
+        round: 1_u16.into(),
+        block_id: Some(block::Id {
+            hash: Hash::Sha256([1; 32]),
+            part_set_header: block::parts::Header::new(1, Hash::Sha256([2; 32])).unwrap(),
+        }),
+        timestamp: Some("2023-01-01T00:00:00Z".parse().unwrap()),
+        signature: None,
+        ..Vote::default()
+    }
+}
+
 /// Parse an example `config.toml` file to a `TendermintConfig` struct, then
 /// serialize it and parse again.
 #[test]
@@ -0,0 +1,22 @@
+//! Blocksync: download of the blocks of a chain from the peers the local node
+//! is connected to.
+//!
+//! Peers tell each other the range of blocks they have with `StatusResponse`
+//! messages, in response to `StatusRequest`s, and request blocks with
+//! `BlockRequest`, answered with `BlockResponse`, or `NoBlockResponse` if the
+//! block is not available. The [`BlocksyncClient`] downloads blocks in
+//! parallel from several peers, verifies them, and returns them in order.
+//!
+//! [Specification](https://github.com/tendermint/tendermint/blob/v0.37.x/spec/p2p/messages/block-sync.md)
+
+mod client;
+mod message;
+
+pub use self::{
+    client::{
+        BlocksyncClient, BlocksyncConfig, DEFAULT_MAX_PENDING_REQUESTS,
+        DEFAULT_MAX_PENDING_REQUESTS_PER_PEER, DEFAULT_REQUEST_TIMEOUT,
+        DEFAULT_STATUS_UPDATE_INTERVAL,
+    },
+    message::BlocksyncMessage,
+};
@@ -0,0 +1,627 @@
+//! Blocksync protocol handling, on the side of the node fetching blocks.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use prost::Message as _;
+use tendermint::{
+    block::{self, signed_header::SignedHeader},
+    chain,
+    crypto::{default::Sha256, Sha256 as _},
+    evidence::Evidence,
+    merkle, node, validator, Block, Hash,
+};
+use tendermint_light_client_verifier::operations::{
+    ProdVotingPowerCalculator, VotingPowerCalculator,
+};
+use tendermint_proto::{
+    google::protobuf::Timestamp,
+    v0_37::types::{CommitSig as RawCommitSig, DuplicateVoteEvidence as RawDuplicateVoteEvidence},
+};
+
+use super::BlocksyncMessage;
+use crate::{
+    error::{Error, ErrorDetail},
+    transport::{peer_id, transport_error, Connection, StreamId, StreamSend},
+};
+
+/// Default maximum number of blocks requested, or received and not yet
+/// returned, at any time.
+pub const DEFAULT_MAX_PENDING_REQUESTS: usize = 600;
+
+/// Default maximum number of blocks requested from the same peer at any
+/// time.
+pub const DEFAULT_MAX_PENDING_REQUESTS_PER_PEER: usize = 20;
+
+/// Default time to wait for a requested block, before dropping the peer.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::new(15, 0);
+
+/// Default interval between two status requests sent to the peers.
+pub const DEFAULT_STATUS_UPDATE_INTERVAL: Duration = Duration::new(10, 0);
+
+/// `0001-01-01T00:00:00Z`, the zero value of Go's `time.Time`.
+const ZERO_TIME: Timestamp = Timestamp {
+    seconds: -62_135_596_800,
+    nanos: 0,
+};
+
+/// Maximum time [`BlocksyncClient::next_block`] waits before checking
+/// whether requests timed out.
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Configuration of a [`BlocksyncClient`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlocksyncConfig {
+    /// Maximum number of blocks requested, or received and not yet returned,
+    /// at any time. This bounds the memory used by the client.
+    pub max_pending_requests: usize,
+    /// Maximum number of blocks requested from the same peer at any time.
+    pub max_pending_requests_per_peer: usize,
+    /// Time to wait for a requested block. Peers answering slower are
+    /// dropped from the pool.
+    pub request_timeout: Duration,
+    /// Interval between two status requests sent to the peers.
+    pub status_update_interval: Duration,
+}
+
+impl Default for BlocksyncConfig {
+    fn default() -> Self {
+        Self {
+            max_pending_requests: DEFAULT_MAX_PENDING_REQUESTS,
+            max_pending_requests_per_peer: DEFAULT_MAX_PENDING_REQUESTS_PER_PEER,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            status_update_interval: DEFAULT_STATUS_UPDATE_INTERVAL,
+        }
+    }
+}
+
+/// Sends a message to a peer over its blocksync stream.
+type SendFn = Box<dyn Fn(Vec<u8>) -> eyre::Result<()> + Send>;
+
+/// What the client tracks about a peer of the pool.
+struct Peer {
+    /// Lowest height of the blocks the peer has.
+    base: block::Height,
+    /// Highest height of the blocks the peer has.
+    height: block::Height,
+    /// Number of blocks requested from the peer and not received yet.
+    pending: usize,
+    send: SendFn,
+}
+
+/// Block requested from a peer.
+struct Request {
+    peer: node::Id,
+    sent_at: Instant,
+    /// The block, once received.
+    block: Option<Block>,
+}
+
+/// Peers and requests of the client.
+struct Pool {
+    /// Height of the next block to return.
+    height: block::Height,
+    /// Validator set of the next block to return.
+    validators: validator::Set,
+    /// Header of the last block returned.
+    last_header: Option<block::Header>,
+    peers: BTreeMap<node::Id, Peer>,
+    requests: BTreeMap<block::Height, Request>,
+    last_status_request: Instant,
+}
+
+/// Client of the blocksync protocol, downloading the blocks of a chain from
+/// the connected peers, for instance to archive them without access to the
+/// RPC of a node.
+///
+/// The peers are asked for the range of blocks they have, and the blocks are
+/// requested in parallel from the peers having them, while at most
+/// [`BlocksyncConfig::max_pending_requests`] blocks are in flight or waiting
+/// to be returned. Blocks are returned in order by
+/// [`BlocksyncClient::next_block`].
+///
+/// Each block is verified with the `last_commit` of the block following it:
+/// that commit must be for the block, and be signed by more than two thirds
+/// of the voting power of the validator set. The set is given for the first
+/// block; as blocks do not carry validator sets, changes are detected with
+/// the hashes in the headers. Once a verified header announces other
+/// validators for the next block, the new set must be given with
+/// [`BlocksyncClient::update_validators`]. Any other mismatch makes the block
+/// invalid.
+///
+/// The client is cheaply cloneable, and clones share the same pool of peers
+/// and blocks.
+#[derive(Clone)]
+pub struct BlocksyncClient {
+    pool: Arc<Mutex<Pool>>,
+    changed: Arc<Condvar>,
+    chain_id: chain::Id,
+    config: BlocksyncConfig,
+}
+
+impl BlocksyncClient {
+    /// Creates a client fetching the blocks of the given chain from the
+    /// given height, at which the validators are the given ones.
+    #[must_use]
+    pub fn new(
+        chain_id: chain::Id,
+        height: block::Height,
+        validators: validator::Set,
+        config: BlocksyncConfig,
+    ) -> Self {
+        Self {
+            pool: Arc::new(Mutex::new(Pool {
+                height,
+                validators,
+                last_header: None,
+                peers: BTreeMap::new(),
+                requests: BTreeMap::new(),
+                last_status_request: Instant::now(),
+            })),
+            changed: Arc::new(Condvar::new()),
+            chain_id,
+            config,
+        }
+    }
+
+    /// Returns the height of the next block [`Self::next_block`] returns.
+    #[must_use]
+    pub fn height(&self) -> block::Height {
+        self.pool().height
+    }
+
+    /// Returns whether the client caught up with its peers, i.e. the next
+    /// block is the highest one they have, which cannot be verified until
+    /// the next one is committed. Without peers, the client has not caught
+    /// up.
+    #[must_use]
+    pub fn is_caught_up(&self) -> bool {
+        let pool = self.pool();
+        pool.peers
+            .values()
+            .map(|peer| peer.height)
+            .max()
+            .is_some_and(|height| pool.height >= height)
+    }
+
+    /// Replaces the validator set of the next block, after
+    /// [`Self::next_block`] failed with [`ErrorDetail::ValidatorSetChanged`].
+    pub fn update_validators(&self, validators: validator::Set) {
+        self.pool().validators = validators;
+        self.changed.notify_all();
+    }
+
+    /// Adds a peer to the pool, which the given function sends messages to,
+    /// and asks it for the range of blocks it has.
+    ///
+    /// # Errors
+    ///
+    /// * if the status request cannot be sent
+    pub fn add_peer<F>(&self, peer: node::Id, send: F) -> Result<(), Error>
+    where
+        F: Fn(Vec<u8>) -> eyre::Result<()> + Send + 'static,
+    {
+        // The peer has no blocks until it tells otherwise.
+        let peer_state = Peer {
+            base: 0_u32.into(),
+            height: 0_u32.into(),
+            pending: 0,
+            send: Box::new(send),
+        };
+        let mut pool = self.pool();
+        let result = (peer_state.send)(BlocksyncMessage::StatusRequest.encode());
+        if result.is_ok() {
+            pool.peers.insert(peer, peer_state);
+        }
+        drop(pool);
+        result.map_err(transport_error)
+    }
+
+    /// Removes a peer from the pool. The blocks requested from it are
+    /// requested from other peers, while those it sent are kept.
+    pub fn remove_peer(&self, peer: &node::Id) {
+        let mut pool = self.pool();
+        pool.remove_peer(peer);
+        pool.schedule(&self.config);
+        drop(pool);
+        self.changed.notify_all();
+    }
+
+    /// Handles a message received from the given peer over the blocksync
+    /// stream, and returns the response to send back, if any.
+    ///
+    /// Blocks are only accepted from the peer they were requested from. The
+    /// client has no blocks to serve, and answers requests accordingly.
+    ///
+    /// # Errors
+    ///
+    /// * if the message is malformed
+    /// * if the peer is not in the pool
+    /// * if the peer reports an invalid range of blocks
+    /// * if the peer sends a block that was not requested from it
+    pub fn receive(&self, peer: &node::Id, msg: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let msg = BlocksyncMessage::decode(msg)?;
+        let mut pool = self.pool();
+        if !pool.peers.contains_key(peer) {
+            return Err(Error::unknown_blocksync_peer(*peer));
+        }
+        let response = match msg {
+            BlocksyncMessage::BlockRequest(height) => {
+                Some(BlocksyncMessage::NoBlockResponse(height).encode())
+            },
+            BlocksyncMessage::StatusRequest => Some(
+                BlocksyncMessage::StatusResponse {
+                    base: 0_u32.into(),
+                    height: 0_u32.into(),
+                }
+                .encode(),
+            ),
+            BlocksyncMessage::StatusResponse { base, height } => {
+                if base > height {
+                    return Err(Error::invalid_peer_status(*peer, base, height));
+                }
+                if let Some(state) = pool.peers.get_mut(peer) {
+                    state.base = base;
+                    state.height = height;
+                }
+                None
+            },
+            BlocksyncMessage::BlockResponse(block) => {
+                let height = block.header.height;
+                match pool.requests.get_mut(&height) {
+                    Some(request) if request.peer == *peer && request.block.is_none() => {
+                        request.block = Some(*block);
+                    },
+                    _ => return Err(Error::unsolicited_block(*peer, height)),
+                }
+                if let Some(state) = pool.peers.get_mut(peer) {
+                    state.pending = state.pending.saturating_sub(1);
+                }
+                None
+            },
+            BlocksyncMessage::NoBlockResponse(height) => {
+                let requested = pool
+                    .requests
+                    .get(&height)
+                    .is_some_and(|request| request.peer == *peer && request.block.is_none());
+                if requested {
+                    pool.requests.remove(&height);
+                    // Do not ask the peer again until it reports its range.
+                    if let Some(state) = pool.peers.get_mut(peer) {
+                        state.pending = state.pending.saturating_sub(1);
+                        state.height = state.height.min(
+                            block::Height::try_from(height.value().saturating_sub(1))
+                                .unwrap_or_default(),
+                        );
+                    }
+                }
+                None
+            },
+        };
+        pool.schedule(&self.config);
+        drop(pool);
+        self.changed.notify_all();
+        Ok(response)
+    }
+
+    /// Returns the next block, once it is received and verified, or `None`
+    /// if it is not within the given timeout.
+    ///
+    /// Meanwhile, blocks are requested from the peers, and the peers that
+    /// do not send requested blocks in time are dropped from the pool.
+    ///
+    /// # Errors
+    ///
+    /// * if the block fails verification: the peers that sent it and the next one are dropped from
+    ///   the pool, and the blocks requested again
+    /// * if the validator set changed, in which case the new one must be given with
+    ///   [`Self::update_validators`] to go on
+    pub fn next_block(&self, timeout: Duration) -> Result<Option<Block>, Error> {
+        let deadline = Instant::now() + timeout;
+        let mut pool = self.pool();
+        loop {
+            pool.tick(&self.config);
+            if let Some(result) = pool.pop_verified(&self.chain_id) {
+                pool.schedule(&self.config);
+                return result.map(Some);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            pool = self
+                .changed
+                .wait_timeout(pool, (deadline - now).min(TICK_INTERVAL))
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// Adds the peer at the other end of the given connection to the pool,
+    /// and runs the blocksync protocol over the connection on a separate
+    /// thread.
+    ///
+    /// The thread ends when the connection is closed, or with an error when
+    /// the peer misbehaves or was dropped from the pool, in which case the
+    /// caller should close the connection.
+    ///
+    /// # Errors
+    ///
+    /// * if the blocksync stream cannot be opened
+    /// * if the status request cannot be sent
+    pub fn serve<C>(&self, conn: &C) -> Result<JoinHandle<Result<(), Error>>, Error>
+    where
+        C: Connection<Error = Error>,
+        C::StreamRead: 'static,
+        C::StreamSend: Send + 'static,
+    {
+        let id = peer_id(conn.public_key()).ok_or_else(Error::invalid_key)?;
+        let (read, send) = conn.open_bidirectional(StreamId::BlockSync)?;
+        self.add_peer(id, move |msg| send.send(msg))?;
+
+        let client = self.clone();
+        Ok(thread::spawn(move || {
+            let result = client.run(&id, read);
+            client.remove_peer(&id);
+            result
+        }))
+    }
+
+    fn run<R>(&self, peer: &node::Id, read: R) -> Result<(), Error>
+    where
+        R: Iterator<Item = eyre::Result<Vec<u8>>>,
+    {
+        for msg in read {
+            let msg = msg.map_err(transport_error)?;
+            if let Some(response) = self.receive(peer, &msg)? {
+                self.pool().send(peer, response)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pool(&self) -> MutexGuard<'_, Pool> {
+        self.pool.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Pool {
+    /// Asks the peers for their status if it is time to, drops the peers
+    /// whose requests timed out, and requests more blocks.
+    fn tick(&mut self, config: &BlocksyncConfig) {
+        let now = Instant::now();
+        if now.duration_since(self.last_status_request) >= config.status_update_interval {
+            self.last_status_request = now;
+            let msg = BlocksyncMessage::StatusRequest.encode();
+            let failed: Vec<_> = self
+                .peers
+                .iter()
+                .filter(|(_, peer)| (peer.send)(msg.clone()).is_err())
+                .map(|(id, _)| *id)
+                .collect();
+            for id in &failed {
+                self.remove_peer(id);
+            }
+        }
+
+        let timed_out: Vec<_> = self
+            .requests
+            .values()
+            .filter(|request| {
+                request.block.is_none()
+                    && now.duration_since(request.sent_at) >= config.request_timeout
+            })
+            .map(|request| request.peer)
+            .collect();
+        for id in &timed_out {
+            self.remove_peer(id);
+        }
+        self.schedule(config);
+    }
+
+    /// Requests the blocks of the window that are not requested yet from
+    /// the least busy peers having them.
+    fn schedule(&mut self, config: &BlocksyncConfig) {
+        let Some(max_height) = self.peers.values().map(|peer| peer.height).max() else {
+            return;
+        };
+        let now = Instant::now();
+        let mut failed = Vec::new();
+        let mut height = self.height;
+        for _ in 0..config.max_pending_requests {
+            if height > max_height {
+                break;
+            }
+            if !self.requests.contains_key(&height) {
+                if let Some(id) = self.least_busy_peer(height, config, &failed) {
+                    if !self.request(id, height, now) {
+                        failed.push(id);
+                    }
+                }
+            }
+            height = height.increment();
+        }
+        for id in &failed {
+            self.remove_peer(id);
+        }
+    }
+
+    fn send(&self, peer: &node::Id, msg: Vec<u8>) -> Result<(), Error> {
+        let state = self
+            .peers
+            .get(peer)
+            .ok_or_else(|| Error::unknown_blocksync_peer(*peer))?;
+        (state.send)(msg).map_err(transport_error)
+    }
+
+    /// Returns the peer having the block at the given height with the
+    /// fewest pending requests, if any can take one more.
+    fn least_busy_peer(
+        &self,
+        height: block::Height,
+        config: &BlocksyncConfig,
+        exclude: &[node::Id],
+    ) -> Option<node::Id> {
+        self.peers
+            .iter()
+            .filter(|(id, peer)| {
+                peer.base <= height
+                    && height <= peer.height
+                    && peer.pending < config.max_pending_requests_per_peer
+                    && !exclude.contains(id)
+            })
+            .min_by_key(|(_, peer)| peer.pending)
+            .map(|(id, _)| *id)
+    }
+
+    /// Requests the block at the given height from the given peer, returning
+    /// whether the request could be sent.
+    fn request(&mut self, id: node::Id, height: block::Height, now: Instant) -> bool {
+        let Some(peer) = self.peers.get_mut(&id) else {
+            return false;
+        };
+        if (peer.send)(BlocksyncMessage::BlockRequest(height).encode()).is_err() {
+            return false;
+        }
+        peer.pending += 1;
+        self.requests.insert(
+            height,
+            Request {
+                peer: id,
+                sent_at: now,
+                block: None,
+            },
+        );
+        true
+    }
+
+    fn remove_peer(&mut self, peer: &node::Id) {
+        self.peers.remove(peer);
+        self.requests
+            .retain(|_, request| request.peer != *peer || request.block.is_some());
+    }
+
+    /// Returns the next block if it and the next one are received, and the
+    /// result of its verification.
+    fn pop_verified(&mut self, chain_id: &chain::Id) -> Option<Result<Block, Error>> {
+        let next_height = self.height.increment();
+        let first = self.requests.get(&self.height)?;
+        let second = self.requests.get(&next_height)?;
+        let (first_peer, second_peer) = (first.peer, second.peer);
+        let result = self.verify(first.block.as_ref()?, second.block.as_ref()?, chain_id);
+        match result {
+            Ok(()) => {
+                let block = self.requests.remove(&self.height)?.block?;
+                self.last_header = Some(block.header.clone());
+                self.height = next_height;
+                Some(Ok(block))
+            },
+            Err(e) if matches!(e.detail(), ErrorDetail::ValidatorSetChanged(_)) => Some(Err(e)),
+            Err(e) => {
+                // Either block may be the culprit.
+                self.requests.remove(&self.height);
+                self.requests.remove(&next_height);
+                self.remove_peer(&first_peer);
+                self.remove_peer(&second_peer);
+                Some(Err(e))
+            },
+        }
+    }
+
+    /// Verifies the next block with the last commit of the one after it.
+    fn verify(&self, first: &Block, second: &Block, chain_id: &chain::Id) -> Result<(), Error> {
+        let height = first.header.height;
+        let invalid = |detail: &str| Error::invalid_synced_block(height, detail.to_owned());
+        if first.header.chain_id != *chain_id {
+            return Err(invalid("block of another chain"));
+        }
+        check_body(first).map_err(invalid)?;
+        if let Some(last_header) = &self.last_header {
+            if first.header.last_block_id.map(|id| id.hash) != Some(last_header.hash()) {
+                return Err(invalid("block does not follow the previous one"));
+            }
+        }
+        if first.header.validators_hash != self.validators.hash() {
+            // Only a verified header vouches for a change of the validators.
+            return match &self.last_header {
+                Some(last_header)
+                    if last_header.next_validators_hash == first.header.validators_hash =>
+                {
+                    Err(Error::validator_set_changed(height))
+                },
+                _ => Err(invalid("unexpected hash of the validator set")),
+            };
+        }
+        let commit = second
+            .last_commit
+            .clone()
+            .ok_or_else(|| invalid("next block has no last commit"))?;
+        if commit.block_id.hash != first.header.hash() {
+            return Err(invalid(
+                "last commit of the next block is for another block",
+            ));
+        }
+        let signed_header =
+            SignedHeader::new(first.header.clone(), commit).map_err(|e| invalid(&e.to_string()))?;
+        ProdVotingPowerCalculator::default()
+            .check_signers_overlap(&signed_header, &self.validators)
+            .map_err(|e| invalid(&e.to_string()))
+    }
+}
+
+/// Checks the transactions, evidence and last commit of the block against the
+/// hashes of its header, which the commit of the next block authenticates.
+fn check_body(block: &Block) -> Result<(), &'static str> {
+    let header = &block.header;
+    let txs: Vec<_> = block
+        .data
+        .iter()
+        .map(|tx| Sha256::digest(tx).to_vec())
+        .collect();
+    if header.data_hash != Some(merkle_hash(&txs)) {
+        return Err("transactions do not match the data hash");
+    }
+
+    let mut evidence = Vec::new();
+    for ev in block.evidence.iter() {
+        match ev {
+            Evidence::DuplicateVote(ev) => {
+                evidence.push(RawDuplicateVoteEvidence::from(ev.clone()).encode_to_vec());
+            },
+            // Its content is not retained by `Evidence`, hence it cannot be
+            // checked.
+            Evidence::LightClientAttackEvidence => {
+                return Err("light client attack evidence cannot be verified")
+            },
+        }
+    }
+    if header.evidence_hash != Some(merkle_hash(&evidence)) {
+        return Err("evidence does not match the evidence hash");
+    }
+
+    let signatures: Vec<_> = block
+        .last_commit
+        .iter()
+        .flat_map(|commit| commit.signatures.iter())
+        .map(|sig| {
+            let mut raw = RawCommitSig::from(sig.clone());
+            // Go encodes the zero time of absent signatures.
+            if raw.timestamp.is_none() {
+                raw.timestamp = Some(ZERO_TIME);
+            }
+            raw.encode_to_vec()
+        })
+        .collect();
+    if header.last_commit_hash != Some(merkle_hash(&signatures)) {
+        return Err("last commit does not match the last commit hash");
+    }
+    Ok(())
+}
+
+fn merkle_hash(leaves: &[Vec<u8>]) -> Hash {
+    Hash::Sha256(merkle::simple_hash_from_byte_vectors::<Sha256>(leaves))
+}
@@ -0,0 +1,85 @@
+//! Messages of the blocksync protocol.
+
+use prost::Message as _;
+use tendermint::{block, Block};
+use tendermint_proto::v0_37::blocksync::{
+    message::Sum, BlockRequest, BlockResponse, Message, NoBlockResponse, StatusRequest,
+    StatusResponse,
+};
+
+use crate::error::Error;
+
+/// Message exchanged over the blocksync stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlocksyncMessage {
+    /// Asks the remote peer for the block at the given height.
+    BlockRequest(block::Height),
+    /// Tells the remote peer that the requested block is not available.
+    NoBlockResponse(block::Height),
+    /// Requested block.
+    BlockResponse(Box<Block>),
+    /// Asks the remote peer for the range of blocks it has.
+    StatusRequest,
+    /// Range of blocks the peer has, in response to a request or whenever it
+    /// changes.
+    StatusResponse {
+        /// Lowest height of the blocks the peer has.
+        base: block::Height,
+        /// Highest height of the blocks the peer has.
+        height: block::Height,
+    },
+}
+
+impl BlocksyncMessage {
+    /// Encodes the message as a `tendermint.blocksync.Message`.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let sum = match self {
+            Self::BlockRequest(height) => Sum::BlockRequest(BlockRequest {
+                height: (*height).into(),
+            }),
+            Self::NoBlockResponse(height) => Sum::NoBlockResponse(NoBlockResponse {
+                height: (*height).into(),
+            }),
+            Self::BlockResponse(block) => Sum::BlockResponse(BlockResponse {
+                block: Some(block.as_ref().clone().into()),
+            }),
+            Self::StatusRequest => Sum::StatusRequest(StatusRequest {}),
+            Self::StatusResponse { base, height } => Sum::StatusResponse(StatusResponse {
+                height: (*height).into(),
+                base: (*base).into(),
+            }),
+        };
+        Message { sum: Some(sum) }.encode_to_vec()
+    }
+
+    /// Decodes a `tendermint.blocksync.Message`.
+    ///
+    /// # Errors
+    ///
+    /// * if the message is malformed
+    /// * if a height is negative, or the block is invalid
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let sum = Message::decode(bytes)
+            .map_err(Error::blocksync_decode)?
+            .sum
+            .ok_or_else(Error::malformed_blocksync_message)?;
+        let height =
+            |height: i64| block::Height::try_from(height).map_err(Error::invalid_blocksync_message);
+        match sum {
+            Sum::BlockRequest(msg) => Ok(Self::BlockRequest(height(msg.height)?)),
+            Sum::NoBlockResponse(msg) => Ok(Self::NoBlockResponse(height(msg.height)?)),
+            Sum::BlockResponse(msg) => {
+                let block = msg.block.ok_or_else(Error::malformed_blocksync_message)?;
+                Block::try_from(block)
+                    .map(|block| Self::BlockResponse(Box::new(block)))
+                    .map_err(Error::invalid_blocksync_message)
+            },
+            Sum::StatusRequest(_) => Ok(Self::StatusRequest),
+            Sum::StatusResponse(msg) => Ok(Self::StatusResponse {
+                base: height(msg.base)?,
+                height: height(msg.height)?,
+            }),
+        }
+    }
+}
@@ -6,6 +6,7 @@
 
 use flex_error::{define_error, DisplayOnly};
 use prost::DecodeError;
+use tendermint::{block, chain, node};
 
 define_error! {
     Error {
@@ -57,7 +58,271 @@ define_error! {
 
         TransportClone
             { detail: String }
-            | e | { format_args!("failed to clone underlying transport: {}", e.detail) }
+            | e | { format_args!("failed to clone underlying transport: {}", e.detail) },
+
+        DuplicateChannel
+            { channel_id: u8 }
+            | e | { format_args!("channel {:#04x} is described more than once", e.channel_id) },
+
+        UnknownChannel
+            { channel_id: i32 }
+            | e | { format_args!("received a packet for unknown channel {}", e.channel_id) },
+
+        MessageTooLarge
+            {
+                channel_id: u8,
+                max: usize,
+            }
+            | e | {
+                format_args!("message received on channel {:#04x} exceeds maximum size of {} bytes",
+                    e.channel_id, e.max)
+            },
+
+        PacketTooLarge
+            {
+                size: usize,
+                max: usize,
+            }
+            | e | {
+                format_args!("packet of {} bytes exceeds maximum size of {} bytes",
+                    e.size, e.max)
+            },
+
+        PacketDecode
+            [ DisplayOnly<DecodeError> ]
+            | _ | { "malformed packet" },
+
+        MalformedPacket
+            | _ | { "malformed packet" },
+
+        PongTimeout
+            | _ | { "remote peer did not answer ping in time" },
+
+        ConnectionClosed
+            | _ | { "connection closed" },
+
+        NodeInfoDecode
+            [ DisplayOnly<DecodeError> ]
+            | _ | { "malformed node info" },
+
+        NodeInfoTooLarge
+            {
+                size: usize,
+                max: usize,
+            }
+            | e | {
+                format_args!("node info of {} bytes exceeds maximum size of {} bytes",
+                    e.size, e.max)
+            },
+
+        InvalidNodeInfo
+            [ DisplayOnly<tendermint::Error> ]
+            | _ | { "invalid node info" },
+
+        PeerIdMismatch
+            {
+                authenticated: node::Id,
+                claimed: node::Id,
+            }
+            | e | {
+                format_args!("peer authenticated as {} claims to be {}",
+                    e.authenticated, e.claimed)
+            },
+
+        EmptyMoniker
+            | _ | { "node info has an empty moniker" },
+
+        TooManyChannels
+            {
+                count: usize,
+                max: usize,
+            }
+            | e | {
+                format_args!("node info advertises {} channels, more than the maximum of {}",
+                    e.count, e.max)
+            },
+
+        BlockVersionMismatch
+            {
+                local: u64,
+                remote: u64,
+            }
+            | e | {
+                format_args!("incompatible block protocol versions: local {}, remote {}",
+                    e.local, e.remote)
+            },
+
+        NetworkMismatch
+            {
+                local: chain::Id,
+                remote: chain::Id,
+            }
+            | e | {
+                format_args!("peer is on network {}, not {}", e.remote, e.local)
+            },
+
+        NoCommonChannels
+            | _ | { "peer has no channel in common with the local node" },
+
+        UnexpectedPeerId
+            {
+                expected: node::Id,
+                authenticated: node::Id,
+            }
+            | e | {
+                format_args!("expected to connect to peer {}, but peer authenticated as {}",
+                    e.expected, e.authenticated)
+            },
+
+        NoAddress
+            | _ | { "no address to connect to" },
+
+        InvalidPeerAddress
+            { address: String }
+            | e | { format_args!("invalid peer address {}", e.address) },
+
+        PexDecode
+            [ DisplayOnly<DecodeError> ]
+            | _ | { "malformed PEX message" },
+
+        MalformedPexMessage
+            | _ | { "PEX message has no content" },
+
+        PexRequestTooFrequent
+            { peer: node::Id }
+            | e | { format_args!("peer {} sent PEX requests too frequently", e.peer) },
+
+        UnsolicitedPexAddrs
+            { peer: node::Id }
+            | e | { format_args!("peer {} sent PEX addresses that were not requested", e.peer) },
+
+        TooManyPexAddrs
+            {
+                count: usize,
+                max: usize,
+            }
+            | e | {
+                format_args!("PEX message carries {} addresses, more than the maximum of {}",
+                    e.count, e.max)
+            },
+
+        AddrBookParse
+            { detail: String }
+            | e | { format_args!("malformed address book: {}", e.detail) },
+
+        InvalidAddrBookKey
+            | _ | { "address book has an invalid key" },
+
+        Transport
+            { detail: String }
+            | e | { format_args!("transport error: {}", e.detail) },
+
+        PrivvalDecode
+            [ DisplayOnly<DecodeError> ]
+            | _ | { "malformed privval message" },
+
+        PrivvalMessageTooLarge
+            {
+                size: usize,
+                max: usize,
+            }
+            | e | {
+                format_args!("privval message of {} bytes exceeds maximum size of {} bytes",
+                    e.size, e.max)
+            },
+
+        MalformedPrivvalMessage
+            | _ | { "privval message has no content" },
+
+        UnexpectedPrivvalMessage
+            | _ | { "unexpected privval message" },
+
+        InvalidPrivvalMessage
+            [ DisplayOnly<tendermint::Error> ]
+            | _ | { "invalid privval message" },
+
+        UnsupportedAddress
+            { address: String }
+            | e | { format_args!("unsupported address {}", e.address) },
+
+        ChainIdMismatch
+            {
+                expected: chain::Id,
+                got: chain::Id,
+            }
+            | e | { format_args!("expected chain ID {}, got {}", e.expected, e.got) },
+
+        SignBytes
+            [ DisplayOnly<tendermint_proto::Error> ]
+            | _ | { "failed to encode the bytes to sign" },
+
+        PrivValidatorState
+            { detail: String }
+            | e | { format_args!("privval state error: {}", e.detail) },
+
+        RemoteSigner
+            {
+                code: i32,
+                description: String,
+            }
+            | e | { format_args!("remote signer error {}: {}", e.code, e.description) },
+
+        SignedDataMismatch
+            | _ | { "signer signed different data than requested" },
+
+        InvalidSignerSignature
+            | _ | { "signer returned an invalid signature" },
+
+        NonConformantSigner
+            { detail: String }
+            | e | { format_args!("signer does not conform to the protocol: it {}", e.detail) },
+
+        BlocksyncDecode
+            [ DisplayOnly<DecodeError> ]
+            | _ | { "malformed blocksync message" },
+
+        MalformedBlocksyncMessage
+            | _ | { "blocksync message has no content" },
+
+        InvalidBlocksyncMessage
+            [ DisplayOnly<tendermint::Error> ]
+            | _ | { "invalid blocksync message" },
+
+        InvalidPeerStatus
+            {
+                peer: node::Id,
+                base: block::Height,
+                height: block::Height,
+            }
+            | e | {
+                format_args!("peer {} reported an invalid range of blocks from {} to {}",
+                    e.peer, e.base, e.height)
+            },
+
+        UnsolicitedBlock
+            {
+                peer: node::Id,
+                height: block::Height,
+            }
+            | e | { format_args!("peer {} sent block {} that was not requested", e.peer, e.height) },
+
+        UnknownBlocksyncPeer
+            { peer: node::Id }
+            | e | { format_args!("peer {} is not part of the blocksync pool", e.peer) },
+
+        InvalidSyncedBlock
+            {
+                height: block::Height,
+                detail: String,
+            }
+            | e | { format_args!("block {} failed verification: {}", e.height, e.detail) },
+
+        ValidatorSetChanged
+            { height: block::Height }
+            | e | {
+                format_args!("validator set changed at height {}, the new set is needed to go on",
+                    e.height)
+            },
 
     }
 }
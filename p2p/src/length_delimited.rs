@@ -0,0 +1,51 @@
+//! Messages prefixed with their varint-encoded length, as written by
+//! `prost::Message::encode_length_delimited`.
+
+use std::{
+    io::{self, Read},
+    slice,
+};
+
+use prost::DecodeError;
+
+use crate::error::Error;
+
+/// Size of the longest varint encoding of a `u64`.
+const MAX_VARINT_SIZE: usize = 10;
+
+/// Reads the bytes of a length-prefixed message, or `None` if the reader is
+/// at its end.
+///
+/// An invalid prefix is reported with `decode_error`, and a message larger
+/// than `max_size` with `too_large`, given the size and the maximum.
+pub fn read_length_delimited<R: Read>(
+    reader: &mut R,
+    max_size: usize,
+    decode_error: fn(DecodeError) -> Error,
+    too_large: fn(usize, usize) -> Error,
+) -> Result<Option<Vec<u8>>, Error> {
+    let mut prefix = [0_u8; MAX_VARINT_SIZE];
+    let mut prefix_len = 0;
+    // An overlong prefix is left for `decode_length_delimiter` to reject.
+    while prefix_len < MAX_VARINT_SIZE {
+        let byte = &mut prefix[prefix_len];
+        match reader.read_exact(slice::from_mut(byte)) {
+            Err(e) if prefix_len == 0 && e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            },
+            result => result?,
+        }
+        prefix_len += 1;
+        if *byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let size = prost::decode_length_delimiter(&prefix[..prefix_len]).map_err(decode_error)?;
+    if size > max_size {
+        return Err(too_large(size, max_size));
+    }
+
+    let mut buf = vec![0; size];
+    reader.read_exact(&mut buf)?;
+    Ok(Some(buf))
+}
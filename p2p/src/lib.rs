@@ -23,6 +23,15 @@
     html_logo_url = "https://raw.githubusercontent.com/informalsystems/tendermint-rs/master/img/logo-tendermint-rs_3961x4001.png"
 )]
 
+#[cfg(feature = "blocksync")]
+pub mod blocksync;
 pub mod error;
+mod length_delimited;
+pub mod mconnection;
+pub mod node_info;
+#[cfg(feature = "pex")]
+pub mod pex;
+#[cfg(feature = "privval")]
+pub mod privval;
 pub mod secret_connection;
 pub mod transport;
@@ -0,0 +1,412 @@
+//! `MConnection`: Multiplexing of several channels over a single connection
+//! between peers.
+//!
+//! Messages sent over a channel are split into `PacketMsg` packets of at most
+//! [`MConnConfig::max_packet_msg_payload_size`] bytes, which are interleaved
+//! with the packets of other channels according to the channels' priorities,
+//! and reassembled by the remote peer. `PacketPing`/`PacketPong` packets keep
+//! the connection alive, and detect unresponsive peers. The rates at which
+//! data is sent and received are metered, and limited according to
+//! [`MConnConfig::send_rate`] and [`MConnConfig::recv_rate`].
+//!
+//! [Specification](https://github.com/tendermint/tendermint/blob/v0.37.x/spec/p2p/connection.md)
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+#[cfg(feature = "tendermint-config")]
+use tendermint_config::P2PConfig;
+use tendermint_std_ext::TryClone;
+
+pub use self::flowrate::FlowStatus;
+use self::flowrate::{Metered, Monitor};
+use crate::{error::Error, secret_connection::SecretConnection};
+
+mod flowrate;
+mod packet;
+mod recv;
+mod send;
+
+/// Identifier of a channel, unique within a connection.
+pub type ChannelId = u8;
+
+/// Default maximum size of the payload of a single `PacketMsg`.
+pub const DEFAULT_MAX_PACKET_MSG_PAYLOAD_SIZE: usize = 1024;
+
+/// Default interval between two pings.
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::new(60, 0);
+
+/// Default time to wait for a pong after sending a ping.
+pub const DEFAULT_PONG_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// Default maximum rate at which data is sent, in bytes per second.
+pub const DEFAULT_SEND_RATE: u64 = 5_120_000;
+
+/// Default maximum rate at which data is received, in bytes per second.
+pub const DEFAULT_RECV_RATE: u64 = 5_120_000;
+
+const DEFAULT_SEND_QUEUE_CAPACITY: usize = 1;
+const DEFAULT_RECV_QUEUE_CAPACITY: usize = 16;
+const DEFAULT_RECV_BUFFER_CAPACITY: usize = 4096;
+const DEFAULT_RECV_MESSAGE_CAPACITY: usize = 22_020_096;
+
+/// Configuration of an [`MConnection`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MConnConfig {
+    /// Maximum size of the payload of a single `PacketMsg`. Larger messages
+    /// are split into several packets.
+    pub max_packet_msg_payload_size: usize,
+    /// Interval between two pings sent to the remote peer.
+    pub ping_interval: Duration,
+    /// Time to wait for a pong after sending a ping, before considering the
+    /// remote peer unresponsive.
+    pub pong_timeout: Duration,
+    /// Maximum rate at which data is sent, in bytes per second. Zero means
+    /// no limit.
+    pub send_rate: u64,
+    /// Maximum rate at which data is received, in bytes per second. Zero
+    /// means no limit.
+    pub recv_rate: u64,
+}
+
+impl Default for MConnConfig {
+    fn default() -> Self {
+        Self {
+            max_packet_msg_payload_size: DEFAULT_MAX_PACKET_MSG_PAYLOAD_SIZE,
+            ping_interval: DEFAULT_PING_INTERVAL,
+            pong_timeout: DEFAULT_PONG_TIMEOUT,
+            send_rate: DEFAULT_SEND_RATE,
+            recv_rate: DEFAULT_RECV_RATE,
+        }
+    }
+}
+
+#[cfg(feature = "tendermint-config")]
+impl From<&P2PConfig> for MConnConfig {
+    fn from(config: &P2PConfig) -> Self {
+        Self {
+            max_packet_msg_payload_size: usize::try_from(config.max_packet_msg_payload_size)
+                .unwrap_or(usize::MAX),
+            send_rate: config.send_rate.bytes_per_sec(),
+            recv_rate: config.recv_rate.bytes_per_sec(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Statistics of the data flowing over an [`MConnection`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ConnectionStatus {
+    /// Statistics of the data sent.
+    pub send: FlowStatus,
+    /// Statistics of the data received.
+    pub recv: FlowStatus,
+}
+
+/// Description of a channel carried by an [`MConnection`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ChannelDescriptor {
+    /// Identifier of the channel.
+    pub id: ChannelId,
+    /// Relative priority of the channel. When several channels have messages
+    /// to send, each channel gets a share of the bandwidth proportional to its
+    /// priority. Must not be zero.
+    pub priority: u32,
+    /// Number of outgoing messages queued before sending blocks.
+    pub send_queue_capacity: usize,
+    /// Number of incoming messages queued before reading from the connection
+    /// pauses.
+    pub recv_queue_capacity: usize,
+    /// Initial capacity of the buffer in which incoming messages are
+    /// reassembled.
+    pub recv_buffer_capacity: usize,
+    /// Maximum size of an incoming message.
+    pub recv_message_capacity: usize,
+}
+
+impl ChannelDescriptor {
+    /// Describe the channel with the given identifier and priority, with
+    /// default queue and buffer capacities.
+    #[must_use]
+    pub const fn new(id: ChannelId, priority: u32) -> Self {
+        Self {
+            id,
+            priority,
+            send_queue_capacity: DEFAULT_SEND_QUEUE_CAPACITY,
+            recv_queue_capacity: DEFAULT_RECV_QUEUE_CAPACITY,
+            recv_buffer_capacity: DEFAULT_RECV_BUFFER_CAPACITY,
+            recv_message_capacity: DEFAULT_RECV_MESSAGE_CAPACITY,
+        }
+    }
+}
+
+/// Events handled by the sending routine.
+enum Signal {
+    /// A message was queued on one of the channels.
+    Wake,
+    /// The remote peer sent a ping.
+    SendPong,
+    /// The remote peer answered our ping.
+    PongReceived,
+    /// The receiving routine failed.
+    Failed(Error),
+    /// The connection is being stopped.
+    Stop,
+}
+
+/// Multiplexed connection to a remote peer.
+///
+/// The connection is served by two threads: one sends the messages queued on
+/// the channels (along with pings and pongs), and the other reassembles the
+/// incoming messages, which are handed out through the channels' receivers.
+/// The connection fails on the first I/O or protocol error, and stops when
+/// [`MConnection::stop`] is called or when the `MConnection` is dropped, at
+/// which point messages still queued for sending are discarded. The receiving
+/// thread runs until the underlying connection is closed.
+pub struct MConnection {
+    senders: BTreeMap<ChannelId, ChannelSender>,
+    receivers: BTreeMap<ChannelId, ChannelReceiver>,
+    signals: flume::Sender<Signal>,
+    send_routine: Option<JoinHandle<Result<(), Error>>>,
+    send_monitor: Arc<Monitor>,
+    recv_monitor: Arc<Monitor>,
+}
+
+impl MConnection {
+    /// Start multiplexing the given channels over the given reading and
+    /// writing halves of a connection.
+    ///
+    /// # Errors
+    ///
+    /// * if several channels share the same identifier
+    pub fn new<R, W>(
+        reader: R,
+        writer: W,
+        channels: &[ChannelDescriptor],
+        config: MConnConfig,
+    ) -> Result<Self, Error>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let (signal_tx, signal_rx) = flume::unbounded();
+        let mut senders = BTreeMap::new();
+        let mut receivers = BTreeMap::new();
+        let mut send_channels = Vec::with_capacity(channels.len());
+        let mut recv_channels = BTreeMap::new();
+        for desc in channels {
+            if senders.contains_key(&desc.id) {
+                return Err(Error::duplicate_channel(desc.id));
+            }
+            let (send_tx, send_rx) = flume::bounded(desc.send_queue_capacity);
+            let (recv_tx, recv_rx) = flume::bounded(desc.recv_queue_capacity);
+            senders.insert(
+                desc.id,
+                ChannelSender {
+                    id: desc.id,
+                    queue: send_tx,
+                    signals: signal_tx.clone(),
+                },
+            );
+            receivers.insert(
+                desc.id,
+                ChannelReceiver {
+                    id: desc.id,
+                    queue: recv_rx,
+                },
+            );
+            send_channels.push(send::Channel::new(desc, send_rx));
+            recv_channels.insert(desc.id, recv::Channel::new(desc, recv_tx));
+        }
+
+        let send_monitor = Arc::new(Monitor::new(config.send_rate));
+        let recv_monitor = Arc::new(Monitor::new(config.recv_rate));
+        let reader = Metered::new(reader, recv_monitor.clone());
+        let writer = Metered::new(writer, send_monitor.clone());
+
+        let recv_signals = signal_tx.clone();
+        thread::spawn(move || recv::run(reader, recv_channels, &recv_signals, config));
+        let send_routine =
+            thread::spawn(move || send::run(writer, send_channels, &signal_rx, config));
+
+        Ok(Self {
+            senders,
+            receivers,
+            signals: signal_tx,
+            send_routine: Some(send_routine),
+            send_monitor,
+            recv_monitor,
+        })
+    }
+
+    /// Start multiplexing the given channels over the given secret
+    /// connection, which is split into its sending and receiving halves.
+    ///
+    /// # Errors
+    ///
+    /// * if the connection cannot be split
+    /// * if several channels share the same identifier
+    pub fn from_secret_connection<IoHandler>(
+        conn: SecretConnection<IoHandler>,
+        channels: &[ChannelDescriptor],
+        config: MConnConfig,
+    ) -> Result<Self, Error>
+    where
+        IoHandler: TryClone + Read + Write + Send + 'static,
+        <IoHandler as TryClone>::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let (sender, receiver) = conn.split()?;
+        Self::new(receiver, sender, channels, config)
+    }
+
+    /// Returns a handle to send messages over the given channel, if the
+    /// connection carries it.
+    #[must_use]
+    pub fn sender(&self, id: ChannelId) -> Option<ChannelSender> {
+        self.senders.get(&id).cloned()
+    }
+
+    /// Returns a handle to receive messages from the given channel, if the
+    /// connection carries it.
+    ///
+    /// All receivers of a channel share the same queue: each message is
+    /// handed to only one of them.
+    #[must_use]
+    pub fn receiver(&self, id: ChannelId) -> Option<ChannelReceiver> {
+        self.receivers.get(&id).cloned()
+    }
+
+    /// Returns the statistics of the data sent and received so far.
+    #[must_use]
+    pub fn status(&self) -> ConnectionStatus {
+        ConnectionStatus {
+            send: self.send_monitor.status(),
+            recv: self.recv_monitor.status(),
+        }
+    }
+
+    /// Stops sending over the connection, and waits for the sending thread to
+    /// terminate.
+    ///
+    /// # Errors
+    ///
+    /// * if the connection failed before being stopped
+    pub fn stop(mut self) -> Result<(), Error> {
+        // The sending routine may already have terminated.
+        let _ = self.signals.send(Signal::Stop);
+        self.send_routine.take().map_or(Ok(()), |handle| {
+            handle
+                .join()
+                .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+        })
+    }
+}
+
+impl Drop for MConnection {
+    fn drop(&mut self) {
+        let _ = self.signals.send(Signal::Stop);
+    }
+}
+
+/// Sending end of a channel carried by an [`MConnection`].
+#[derive(Clone)]
+pub struct ChannelSender {
+    id: ChannelId,
+    queue: flume::Sender<Vec<u8>>,
+    signals: flume::Sender<Signal>,
+}
+
+impl ChannelSender {
+    /// Returns the identifier of the channel.
+    #[must_use]
+    pub const fn id(&self) -> ChannelId {
+        self.id
+    }
+
+    /// Queues the message for sending, blocking while the channel's send
+    /// queue is full.
+    ///
+    /// # Errors
+    ///
+    /// * if the connection was stopped, or failed
+    pub fn send(&self, msg: Vec<u8>) -> Result<(), Error> {
+        self.queue
+            .send(msg)
+            .map_err(|_| Error::connection_closed())?;
+        self.wake()
+    }
+
+    /// Queues the message for sending, unless the channel's send queue is
+    /// full. Returns whether the message was queued.
+    ///
+    /// # Errors
+    ///
+    /// * if the connection was stopped, or failed
+    pub fn try_send(&self, msg: Vec<u8>) -> Result<bool, Error> {
+        match self.queue.try_send(msg) {
+            Ok(()) => self.wake().map(|()| true),
+            Err(flume::TrySendError::Full(_)) => Ok(false),
+            Err(flume::TrySendError::Disconnected(_)) => Err(Error::connection_closed()),
+        }
+    }
+
+    fn wake(&self) -> Result<(), Error> {
+        self.signals
+            .send(Signal::Wake)
+            .map_err(|_| Error::connection_closed())
+    }
+}
+
+/// Receiving end of a channel carried by an [`MConnection`].
+///
+/// Iterating over a receiver produces the incoming messages, until the
+/// connection stops.
+#[derive(Clone)]
+pub struct ChannelReceiver {
+    id: ChannelId,
+    queue: flume::Receiver<Vec<u8>>,
+}
+
+impl ChannelReceiver {
+    /// Returns the identifier of the channel.
+    #[must_use]
+    pub const fn id(&self) -> ChannelId {
+        self.id
+    }
+
+    /// Waits for the next incoming message.
+    ///
+    /// # Errors
+    ///
+    /// * if the connection stopped receiving messages
+    pub fn recv(&self) -> Result<Vec<u8>, Error> {
+        self.queue.recv().map_err(|_| Error::connection_closed())
+    }
+
+    /// Waits for the next incoming message, for at most the given duration.
+    /// Returns `None` if no message came in.
+    ///
+    /// # Errors
+    ///
+    /// * if the connection stopped receiving messages
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<Vec<u8>>, Error> {
+        match self.queue.recv_timeout(timeout) {
+            Ok(msg) => Ok(Some(msg)),
+            Err(flume::RecvTimeoutError::Timeout) => Ok(None),
+            Err(flume::RecvTimeoutError::Disconnected) => Err(Error::connection_closed()),
+        }
+    }
+}
+
+impl Iterator for ChannelReceiver {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.queue.recv().ok()
+    }
+}
@@ -0,0 +1,213 @@
+//! Metering and throttling of the data flowing over a connection.
+
+use std::{
+    io::{self, Read, Write},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Duration of a slot of the sliding window over which the current rate is
+/// measured.
+const SLOT_DURATION: Duration = Duration::from_millis(100);
+
+/// Number of slots in the sliding window.
+const SLOT_COUNT: usize = 10;
+
+/// Duration of the sliding window.
+const WINDOW_DURATION: Duration = Duration::from_secs(1);
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Statistics of the data flowing in one direction of a connection.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FlowStatus {
+    /// Total number of bytes transferred.
+    pub bytes: u64,
+    /// Rate of transfer over the last second, in bytes per second.
+    pub current_rate: u64,
+    /// Rate of transfer since the connection was established, in bytes per
+    /// second.
+    pub average_rate: u64,
+    /// Maximum rate of transfer, in bytes per second, or zero if the rate is
+    /// not limited.
+    pub limit: u64,
+}
+
+/// Meters the data flowing in one direction, and limits its rate with a
+/// token bucket holding up to one second worth of data.
+pub struct Monitor {
+    limit: u64,
+    state: Mutex<State>,
+}
+
+struct State {
+    start: Instant,
+    bytes: u64,
+    /// Bytes transferred during each slot of the sliding window.
+    window: [u64; SLOT_COUNT],
+    /// Index of the current slot, since the start.
+    slot: u64,
+    tokens: u64,
+    last_refill: Instant,
+}
+
+impl Monitor {
+    /// Creates a monitor limiting the rate to the given number of bytes per
+    /// second, or not at all if zero.
+    pub fn new(limit: u64) -> Self {
+        let now = Instant::now();
+        Self {
+            limit,
+            state: Mutex::new(State {
+                start: now,
+                bytes: 0,
+                window: [0; SLOT_COUNT],
+                slot: 0,
+                tokens: limit,
+                last_refill: now,
+            }),
+        }
+    }
+
+    /// Returns the statistics of the data transferred so far.
+    pub fn status(&self) -> FlowStatus {
+        let now = Instant::now();
+        let mut state = self.state();
+        state.advance(now);
+        let elapsed = now.duration_since(state.start);
+        let window_bytes = state.window.iter().sum();
+        let status = FlowStatus {
+            bytes: state.bytes,
+            current_rate: rate(window_bytes, elapsed.clamp(SLOT_DURATION, WINDOW_DURATION)),
+            average_rate: rate(state.bytes, elapsed.max(SLOT_DURATION)),
+            limit: self.limit,
+        };
+        drop(state);
+        status
+    }
+
+    /// Records the transfer of the given number of bytes.
+    fn record(&self, bytes: usize) {
+        let bytes = u64::try_from(bytes).unwrap_or(u64::MAX);
+        let mut state = self.state();
+        state.advance(Instant::now());
+        state.bytes = state.bytes.saturating_add(bytes);
+        let slot = slot_index(state.slot);
+        state.window[slot] = state.window[slot].saturating_add(bytes);
+        drop(state);
+    }
+
+    /// Waits until the given number of bytes can be transferred within the
+    /// limit.
+    fn throttle(&self, bytes: usize) {
+        if self.limit == 0 {
+            return;
+        }
+        let bytes = u64::try_from(bytes).unwrap_or(u64::MAX);
+        let wait = self.state().take_tokens(bytes, self.limit, Instant::now());
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl State {
+    /// Moves the sliding window forward, clearing the slots elapsed since
+    /// the last transfer.
+    fn advance(&mut self, now: Instant) {
+        let slot = now.duration_since(self.start).as_nanos() / SLOT_DURATION.as_nanos();
+        let slot = u64::try_from(slot).unwrap_or(u64::MAX);
+        for elapsed in (self.slot.saturating_add(1)..=slot).take(SLOT_COUNT) {
+            self.window[slot_index(elapsed)] = 0;
+        }
+        self.slot = self.slot.max(slot);
+    }
+
+    /// Takes tokens from the bucket, going into debt if there are not
+    /// enough, and returns how long it takes to pay the debt back.
+    fn take_tokens(&mut self, bytes: u64, limit: u64, now: Instant) -> Duration {
+        // Only the time needed to produce whole tokens is consumed, so that
+        // no fraction of a token is lost.
+        let elapsed = now.saturating_duration_since(self.last_refill).as_nanos();
+        let refill = elapsed * u128::from(limit) / NANOS_PER_SEC;
+        if refill > 0 {
+            let refill = u64::try_from(refill).unwrap_or(u64::MAX);
+            self.tokens = self.tokens.saturating_add(refill).min(limit);
+            self.last_refill = if self.tokens == limit {
+                now
+            } else {
+                self.last_refill + duration(u128::from(refill), limit)
+            };
+        }
+
+        if let Some(tokens) = self.tokens.checked_sub(bytes) {
+            self.tokens = tokens;
+            return Duration::ZERO;
+        }
+        let debt = bytes - self.tokens;
+        self.tokens = 0;
+        let wait = duration(u128::from(debt), limit);
+        // The tokens produced while waiting pay the debt back.
+        self.last_refill = self.last_refill.max(now) + wait;
+        wait
+    }
+}
+
+/// Reader or writer metering, and throttling, the data flowing through it.
+pub struct Metered<T> {
+    inner: T,
+    monitor: Arc<Monitor>,
+}
+
+impl<T> Metered<T> {
+    pub const fn new(inner: T, monitor: Arc<Monitor>) -> Self {
+        Self { inner, monitor }
+    }
+}
+
+impl<T: Read> Read for Metered<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.monitor.record(size);
+        // The size of the data is only known once read, so the wait delays
+        // the next read instead.
+        self.monitor.throttle(size);
+        Ok(size)
+    }
+}
+
+impl<T: Write> Write for Metered<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.monitor.throttle(buf.len());
+        let size = self.inner.write(buf)?;
+        self.monitor.record(size);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Rate, in bytes per second, of the transfer of `bytes` during `elapsed`.
+fn rate(bytes: u64, elapsed: Duration) -> u64 {
+    let rate = u128::from(bytes) * NANOS_PER_SEC / elapsed.as_nanos().max(1);
+    u64::try_from(rate).unwrap_or(u64::MAX)
+}
+
+/// Time to transfer `bytes` at `limit` bytes per second.
+fn duration(bytes: u128, limit: u64) -> Duration {
+    let nanos = bytes * NANOS_PER_SEC / u128::from(limit);
+    let nanos = u64::try_from(nanos).unwrap_or(u64::MAX);
+    Duration::from_nanos(nanos)
+}
+
+fn slot_index(slot: u64) -> usize {
+    // The remainder is below `SLOT_COUNT`.
+    usize::try_from(slot % SLOT_COUNT as u64).unwrap_or_default()
+}
@@ -0,0 +1,39 @@
+//! Framing of packets.
+
+use std::io::{self, Read, Write};
+
+use prost::Message as _;
+use tendermint_proto::v0_37::p2p::{packet::Sum, Packet};
+
+use crate::{error::Error, length_delimited::read_length_delimited};
+
+/// Upper bound on the encoding overhead of a `PacketMsg`, on top of its
+/// payload.
+const MAX_PACKET_MSG_OVERHEAD: usize = 32;
+
+/// Maximum size of an encoded packet carrying at most the given payload.
+pub const fn max_packet_size(max_payload_size: usize) -> usize {
+    max_payload_size.saturating_add(MAX_PACKET_MSG_OVERHEAD)
+}
+
+/// Writes the packet, prefixed by its length.
+pub fn write_packet<W: Write>(writer: &mut W, sum: Sum) -> Result<(), Error> {
+    let packet = Packet { sum: Some(sum) };
+    writer.write_all(&packet.encode_length_delimited_to_vec())?;
+    Ok(())
+}
+
+/// Reads a length-prefixed packet of at most `max_size` bytes.
+pub fn read_packet<R: Read>(reader: &mut R, max_size: usize) -> Result<Sum, Error> {
+    let buf = read_length_delimited(
+        reader,
+        max_size,
+        Error::packet_decode,
+        Error::packet_too_large,
+    )?
+    .ok_or_else(|| Error::io(io::ErrorKind::UnexpectedEof.into()))?;
+    Packet::decode(buf.as_slice())
+        .map_err(Error::packet_decode)?
+        .sum
+        .ok_or_else(Error::malformed_packet)
+}
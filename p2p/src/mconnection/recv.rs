@@ -0,0 +1,91 @@
+//! Receiving routine of an `MConnection`.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Read},
+    mem,
+};
+
+use tendermint_proto::v0_37::p2p::{packet::Sum, PacketPing, PacketPong};
+
+use super::{packet, ChannelDescriptor, ChannelId, MConnConfig, Signal};
+use crate::error::Error;
+
+/// Reassembles the incoming messages of a channel.
+pub struct Channel {
+    id: ChannelId,
+    buffer: Vec<u8>,
+    buffer_capacity: usize,
+    message_capacity: usize,
+    queue: flume::Sender<Vec<u8>>,
+}
+
+impl Channel {
+    pub fn new(desc: &ChannelDescriptor, queue: flume::Sender<Vec<u8>>) -> Self {
+        Self {
+            id: desc.id,
+            buffer: Vec::with_capacity(desc.recv_buffer_capacity),
+            buffer_capacity: desc.recv_buffer_capacity,
+            message_capacity: desc.recv_message_capacity,
+            queue,
+        }
+    }
+
+    fn receive(&mut self, data: &[u8], eof: bool) -> Result<(), Error> {
+        if self.buffer.len().saturating_add(data.len()) > self.message_capacity {
+            return Err(Error::message_too_large(self.id, self.message_capacity));
+        }
+        self.buffer.extend_from_slice(data);
+        if eof {
+            let msg = mem::replace(&mut self.buffer, Vec::with_capacity(self.buffer_capacity));
+            // Nobody is interested in the channel's messages any more.
+            let _ = self.queue.send(msg);
+        }
+        Ok(())
+    }
+}
+
+/// Reads packets until the connection fails, and reports the failure to the
+/// sending routine.
+pub fn run<R: Read>(
+    reader: R,
+    mut channels: BTreeMap<ChannelId, Channel>,
+    signals: &flume::Sender<Signal>,
+    config: MConnConfig,
+) {
+    if let Err(e) = receive(reader, &mut channels, signals, config) {
+        // The sending routine may already have terminated.
+        let _ = signals.send(Signal::Failed(e));
+    }
+    // The channels' receivers only see the connection close once the failure
+    // is reported.
+    drop(channels);
+}
+
+fn receive<R: Read>(
+    reader: R,
+    channels: &mut BTreeMap<ChannelId, Channel>,
+    signals: &flume::Sender<Signal>,
+    config: MConnConfig,
+) -> Result<(), Error> {
+    let mut reader = BufReader::new(reader);
+    let max_packet_size = packet::max_packet_size(config.max_packet_msg_payload_size);
+    loop {
+        let signal = match packet::read_packet(&mut reader, max_packet_size)? {
+            Sum::PacketPing(PacketPing {}) => Signal::SendPong,
+            Sum::PacketPong(PacketPong {}) => Signal::PongReceived,
+            Sum::PacketMsg(msg) => {
+                ChannelId::try_from(msg.channel_id)
+                    .ok()
+                    .and_then(|id| channels.get_mut(&id))
+                    .ok_or_else(|| Error::unknown_channel(msg.channel_id))?
+                    .receive(&msg.data, msg.eof)?;
+                continue;
+            },
+        };
+        if signals.send(signal).is_err() {
+            // The sending routine terminated.
+            return Ok(());
+        }
+    }
+}
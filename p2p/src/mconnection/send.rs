@@ -0,0 +1,207 @@
+//! Sending routine of an `MConnection`.
+
+use std::{
+    cmp::{self, Ordering},
+    io::{BufWriter, Write},
+    time::{Duration, Instant},
+};
+
+use tendermint_proto::v0_37::p2p::{packet::Sum, PacketMsg, PacketPing, PacketPong};
+
+use super::{packet, ChannelDescriptor, ChannelId, MConnConfig, Signal};
+use crate::error::Error;
+
+/// Interval at which the amounts of data recently sent over each channel
+/// decay, such that priorities apply to the current traffic.
+const STATS_DECAY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Size of the buffer in which packets are gathered before being written to
+/// the connection.
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Splits the outgoing messages of a channel into packets.
+pub struct Channel {
+    id: ChannelId,
+    priority: u32,
+    queue: flume::Receiver<Vec<u8>>,
+    /// The message being sent, along with the size of its part already sent.
+    sending: Option<(Vec<u8>, usize)>,
+    recently_sent: u64,
+}
+
+impl Channel {
+    pub fn new(desc: &ChannelDescriptor, queue: flume::Receiver<Vec<u8>>) -> Self {
+        Self {
+            id: desc.id,
+            priority: desc.priority.max(1),
+            queue,
+            sending: None,
+            recently_sent: 0,
+        }
+    }
+
+    /// Returns whether the channel has data to send.
+    fn is_pending(&mut self) -> bool {
+        if self.sending.is_none() {
+            self.sending = self.queue.try_recv().ok().map(|msg| (msg, 0));
+        }
+        self.sending.is_some()
+    }
+
+    /// Compares the channels' shares of the recently used bandwidth, relative
+    /// to their priorities.
+    fn cmp_usage(&self, other: &Self) -> Ordering {
+        (u128::from(self.recently_sent) * u128::from(other.priority))
+            .cmp(&(u128::from(other.recently_sent) * u128::from(self.priority)))
+    }
+
+    /// Takes the next packet of the message being sent.
+    fn next_packet(&mut self, max_payload_size: usize) -> Option<PacketMsg> {
+        let (msg, sent) = self.sending.as_mut()?;
+        let end = cmp::min(msg.len(), sent.saturating_add(max_payload_size));
+        let data = msg[*sent..end].to_vec();
+        *sent = end;
+        let eof = end == msg.len();
+        if eof {
+            self.sending = None;
+        }
+        self.recently_sent = self
+            .recently_sent
+            .saturating_add(u64::try_from(data.len()).unwrap_or(u64::MAX));
+        Some(PacketMsg {
+            channel_id: i32::from(self.id),
+            eof,
+            data,
+        })
+    }
+}
+
+/// Sends packets until the connection is stopped, or fails.
+#[allow(clippy::needless_pass_by_value)]
+pub fn run<W: Write>(
+    writer: W,
+    channels: Vec<Channel>,
+    signals: &flume::Receiver<Signal>,
+    config: MConnConfig,
+) -> Result<(), Error> {
+    SendRoutine {
+        writer: BufWriter::with_capacity(WRITE_BUFFER_SIZE, writer),
+        channels,
+        config,
+        next_ping: Instant::now() + config.ping_interval,
+        pong_deadline: None,
+        next_decay: Instant::now() + STATS_DECAY_INTERVAL,
+    }
+    .run(signals)
+}
+
+struct SendRoutine<W: Write> {
+    writer: BufWriter<W>,
+    channels: Vec<Channel>,
+    config: MConnConfig,
+    next_ping: Instant,
+    pong_deadline: Option<Instant>,
+    next_decay: Instant,
+}
+
+impl<W: Write> SendRoutine<W> {
+    fn run(mut self, signals: &flume::Receiver<Signal>) -> Result<(), Error> {
+        loop {
+            // Handle pending signals without waiting.
+            loop {
+                match signals.try_recv() {
+                    Ok(signal) => {
+                        if !self.handle(signal)? {
+                            return Ok(());
+                        }
+                    },
+                    Err(flume::TryRecvError::Empty) => break,
+                    Err(flume::TryRecvError::Disconnected) => return self.flush(),
+                }
+            }
+            self.tick()?;
+            if self.send_packet_msg()? {
+                continue;
+            }
+
+            // Nothing left to send: wait for a signal, or for the next timer.
+            self.flush()?;
+            let deadline = self.pong_deadline.map_or(self.next_ping, |deadline| {
+                cmp::min(deadline, self.next_ping)
+            });
+            match signals.recv_deadline(deadline) {
+                Ok(signal) => {
+                    if !self.handle(signal)? {
+                        return Ok(());
+                    }
+                },
+                Err(flume::RecvTimeoutError::Timeout) => {},
+                Err(flume::RecvTimeoutError::Disconnected) => return self.flush(),
+            }
+        }
+    }
+
+    /// Handles the signal, and returns whether to keep running.
+    fn handle(&mut self, signal: Signal) -> Result<bool, Error> {
+        match signal {
+            Signal::Wake => {},
+            Signal::SendPong => {
+                packet::write_packet(&mut self.writer, Sum::PacketPong(PacketPong {}))?;
+            },
+            Signal::PongReceived => self.pong_deadline = None,
+            Signal::Failed(e) => return Err(e),
+            Signal::Stop => {
+                self.flush()?;
+                return Ok(false);
+            },
+        }
+        Ok(true)
+    }
+
+    /// Sends pings and decays statistics when due.
+    fn tick(&mut self) -> Result<(), Error> {
+        let now = Instant::now();
+        if matches!(self.pong_deadline, Some(deadline) if now >= deadline) {
+            return Err(Error::pong_timeout());
+        }
+        if now >= self.next_ping {
+            packet::write_packet(&mut self.writer, Sum::PacketPing(PacketPing {}))?;
+            self.writer.flush()?;
+            self.next_ping = now + self.config.ping_interval;
+            if self.pong_deadline.is_none() {
+                self.pong_deadline = Some(now + self.config.pong_timeout);
+            }
+        }
+        if now >= self.next_decay {
+            for channel in &mut self.channels {
+                channel.recently_sent = channel.recently_sent / 5 * 4;
+            }
+            self.next_decay = now + STATS_DECAY_INTERVAL;
+        }
+        Ok(())
+    }
+
+    /// Sends a packet of the pending channel which used the least bandwidth
+    /// relative to its priority, and returns whether there was one.
+    fn send_packet_msg(&mut self) -> Result<bool, Error> {
+        let max_payload_size = self.config.max_packet_msg_payload_size.max(1);
+        let packet = self
+            .channels
+            .iter_mut()
+            .filter_map(|channel| channel.is_pending().then_some(channel))
+            .min_by(|a, b| a.cmp_usage(b))
+            .and_then(|channel| channel.next_packet(max_payload_size));
+        match packet {
+            Some(packet) => {
+                packet::write_packet(&mut self.writer, Sum::PacketMsg(packet))?;
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
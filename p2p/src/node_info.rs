@@ -0,0 +1,129 @@
+//! Exchange of [`node::Info`] between peers, right after the secret
+//! connection handshake.
+//!
+//! Each peer sends its `DefaultNodeInfo` over the secret connection, then
+//! checks that the information received from the remote peer is valid,
+//! belongs to the authenticated peer, and is compatible with its own.
+//!
+//! [Specification](https://github.com/tendermint/tendermint/blob/v0.37.x/spec/p2p/peer.md#tendermint-version-handshake)
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Read, Write},
+};
+
+use prost::Message as _;
+use tendermint::node;
+use tendermint_proto::v0_37::p2p::DefaultNodeInfo;
+
+use crate::{
+    error::Error, length_delimited::read_length_delimited, secret_connection::SecretConnection,
+};
+
+/// Maximum size of an encoded `DefaultNodeInfo`.
+pub const MAX_NODE_INFO_SIZE: usize = 10240;
+
+/// Maximum number of channels a node may advertise.
+pub const MAX_NUM_CHANNELS: usize = 16;
+
+/// Sends the local node information over the connection, and receives the
+/// remote peer's one, which is then checked with [`validate`] and
+/// [`check_compatible`].
+///
+/// # Errors
+///
+/// * if sending or receiving the node information fails
+/// * if the remote node information is malformed or invalid
+/// * if the remote node identifier does not match the authenticated key
+/// * if the remote node is incompatible with the local one
+pub fn exchange<IoHandler: Read + Write + Send + Sync>(
+    conn: &mut SecretConnection<IoHandler>,
+    local: &node::Info,
+) -> Result<node::Info, Error> {
+    let raw = DefaultNodeInfo::from(local.clone());
+    conn.write_all(&raw.encode_length_delimited_to_vec())?;
+    conn.flush()?;
+
+    let remote = read_node_info(conn)?;
+    let authenticated = conn.remote_pubkey().peer_id();
+    if remote.id != authenticated {
+        return Err(Error::peer_id_mismatch(authenticated, remote.id));
+    }
+    validate(&remote)?;
+    check_compatible(local, &remote)?;
+    Ok(remote)
+}
+
+/// Checks that the node information is well-formed: the node has a moniker,
+/// and advertises a bounded number of distinct channels.
+///
+/// # Errors
+///
+/// * if the moniker is empty
+/// * if there are too many channels, or some are duplicated
+pub fn validate(info: &node::Info) -> Result<(), Error> {
+    if info.moniker.as_ref().trim().is_empty() {
+        return Err(Error::empty_moniker());
+    }
+    let channels = info.channels.ids();
+    if channels.len() > MAX_NUM_CHANNELS {
+        return Err(Error::too_many_channels(channels.len(), MAX_NUM_CHANNELS));
+    }
+    let mut seen = BTreeSet::new();
+    for &id in channels {
+        if !seen.insert(id) {
+            return Err(Error::duplicate_channel(id));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that two nodes can talk to each other: they run the same version
+/// of the block protocol, are part of the same network, and share at least
+/// one channel.
+///
+/// # Errors
+///
+/// * if the block protocol versions differ
+/// * if the networks differ
+/// * if the nodes have no channel in common
+pub fn check_compatible(local: &node::Info, remote: &node::Info) -> Result<(), Error> {
+    let (local_version, remote_version) = (&local.protocol_version, &remote.protocol_version);
+    if local_version.block != remote_version.block {
+        return Err(Error::block_version_mismatch(
+            local_version.block,
+            remote_version.block,
+        ));
+    }
+    if local.network != remote.network {
+        return Err(Error::network_mismatch(
+            local.network.clone(),
+            remote.network.clone(),
+        ));
+    }
+    // A node without any channel (e.g. a crawler) gets along with everyone.
+    let local_channels = local.channels.ids();
+    if !local_channels.is_empty()
+        && !local_channels
+            .iter()
+            .any(|&id| remote.channels.contains(id))
+    {
+        return Err(Error::no_common_channels());
+    }
+    Ok(())
+}
+
+/// Reads a length-prefixed `DefaultNodeInfo`.
+fn read_node_info<R: Read>(reader: &mut R) -> Result<node::Info, Error> {
+    let buf = read_length_delimited(
+        reader,
+        MAX_NODE_INFO_SIZE,
+        Error::node_info_decode,
+        Error::node_info_too_large,
+    )?
+    .ok_or_else(|| Error::io(io::ErrorKind::UnexpectedEof.into()))?;
+    DefaultNodeInfo::decode(buf.as_slice())
+        .map_err(Error::node_info_decode)?
+        .try_into()
+        .map_err(Error::invalid_node_info)
+}
@@ -0,0 +1,21 @@
+//! Peer exchange (PEX): discovery of peers through the peers the local node
+//! is connected to.
+//!
+//! Peers ask each other for addresses with `PexRequest` messages, and answer
+//! with `PexAddrs`. The addresses learned this way are kept in an
+//! [`AddrBook`], which in turn provides the candidates to dial when the local
+//! node needs more peers.
+//!
+//! [Specification](https://github.com/tendermint/tendermint/blob/v0.37.x/spec/p2p/messages/pex.md)
+
+mod addr_book;
+mod message;
+mod reactor;
+
+pub use self::{
+    addr_book::{
+        AddrBook, BucketType, KnownAddress, BUCKET_SIZE, NEW_BUCKET_COUNT, TRIED_BUCKET_COUNT,
+    },
+    message::{PeerAddress, PexMessage},
+    reactor::{PexConfig, PexReactor, DEFAULT_MAX_ADDRS_PER_MESSAGE, DEFAULT_MIN_REQUEST_INTERVAL},
+};
@@ -0,0 +1,481 @@
+//! Address book of known peers.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    fs, io,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tendermint::node;
+
+use super::PeerAddress;
+use crate::error::Error;
+
+/// Number of buckets of new addresses.
+pub const NEW_BUCKET_COUNT: usize = 256;
+
+/// Number of buckets of tried addresses.
+pub const TRIED_BUCKET_COUNT: usize = 64;
+
+/// Maximum number of addresses in a bucket.
+pub const BUCKET_SIZE: usize = 64;
+
+/// Number of new buckets the addresses of a group, learned from the same
+/// source group, spread over.
+const NEW_BUCKETS_PER_GROUP: u64 = 32;
+
+/// Number of tried buckets the addresses of a group spread over.
+const TRIED_BUCKETS_PER_GROUP: u64 = 8;
+
+/// Number of attempts after which a never reached address is bad.
+const MAX_ATTEMPTS_WITHOUT_SUCCESS: u32 = 3;
+
+/// Number of consecutive failed attempts after which an address is bad.
+const MAX_FAILED_ATTEMPTS: u32 = 10;
+
+/// Number of addresses below which the book needs more.
+const NEED_ADDRESS_THRESHOLD: usize = 1000;
+
+/// Minimum number of addresses in a selection, if known.
+const MIN_SELECTION_SIZE: usize = 32;
+
+/// Percentage of the known addresses in a selection.
+const SELECTION_PERCENT: usize = 23;
+
+/// Size of the secret key randomizing the placement of addresses.
+const KEY_SIZE: usize = 12;
+
+/// Kind of bucket an address is in.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BucketType {
+    /// The address was learned from another peer, and never successfully
+    /// dialed.
+    New,
+    /// A connection to the address was successful.
+    Tried,
+}
+
+/// What the address book knows about an address.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KnownAddress {
+    /// The address itself.
+    pub addr: PeerAddress,
+    /// The peer the address was learned from.
+    pub src: PeerAddress,
+    /// The kind of bucket the address is in.
+    pub bucket_type: BucketType,
+    /// Number of failed attempts to connect since the last success.
+    pub attempts: u32,
+    /// Time of the last attempt to connect, in seconds since the Unix epoch.
+    pub last_attempt: Option<u64>,
+    /// Time of the last successful connection, in seconds since the Unix
+    /// epoch.
+    pub last_success: Option<u64>,
+}
+
+impl KnownAddress {
+    /// Whether the address is not worth keeping.
+    #[must_use]
+    pub const fn is_bad(&self) -> bool {
+        (self.last_success.is_none() && self.attempts >= MAX_ATTEMPTS_WITHOUT_SUCCESS)
+            || self.attempts >= MAX_FAILED_ATTEMPTS
+    }
+}
+
+/// Address book of known peers.
+///
+/// Following Bitcoin's address manager, addresses are kept in two tables of
+/// buckets. Addresses learned from other peers go to the *new* buckets, and
+/// move to the *tried* buckets once the local node successfully connects to
+/// them. The bucket of an address is derived from the network groups of the
+/// address and of its source, keyed with a secret, so that a single source
+/// cannot flood the book with addresses of its choice. When a bucket is full,
+/// the worst new address is evicted, or the least recently successful tried
+/// address is moved back to the new buckets.
+#[derive(Clone, Debug)]
+pub struct AddrBook {
+    key: [u8; KEY_SIZE],
+    addrs: BTreeMap<node::Id, KnownAddress>,
+    new: Vec<BTreeSet<node::Id>>,
+    tried: Vec<BTreeSet<node::Id>>,
+    ours: BTreeSet<node::Id>,
+}
+
+/// Persisted form of an [`AddrBook`].
+#[derive(Deserialize, Serialize)]
+struct AddrBookJson {
+    key: String,
+    addrs: Vec<KnownAddress>,
+}
+
+impl Default for AddrBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AddrBook {
+    /// Creates an empty address book, with a random key.
+    #[must_use]
+    pub fn new() -> Self {
+        let mut key = [0_u8; KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        Self::with_key(key)
+    }
+
+    fn with_key(key: [u8; KEY_SIZE]) -> Self {
+        Self {
+            key,
+            addrs: BTreeMap::new(),
+            new: vec![BTreeSet::new(); NEW_BUCKET_COUNT],
+            tried: vec![BTreeSet::new(); TRIED_BUCKET_COUNT],
+            ours: BTreeSet::new(),
+        }
+    }
+
+    /// Loads the address book from the given file, or creates an empty one
+    /// if the file does not exist.
+    ///
+    /// # Errors
+    ///
+    /// * if the file cannot be read, or is malformed
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(Error::io(e)),
+        };
+        let json: AddrBookJson =
+            serde_json::from_str(&json).map_err(|e| Error::addr_book_parse(e.to_string()))?;
+        let key = decode_key(&json.key).ok_or_else(Error::invalid_addr_book_key)?;
+        let mut book = Self::with_key(key);
+        for known in json.addrs {
+            // Only the first entry of an identifier counts, as a second one
+            // would leave the former behind in its bucket.
+            if !book.addrs.contains_key(&known.addr.id) {
+                book.insert(known);
+            }
+        }
+        Ok(book)
+    }
+
+    /// Saves the address book to the given file, atomically replacing its
+    /// previous content.
+    ///
+    /// # Errors
+    ///
+    /// * if the file cannot be written
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let json = AddrBookJson {
+            key: self.key.iter().fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            }),
+            addrs: self.addrs.values().cloned().collect(),
+        };
+        let json = serde_json::to_string_pretty(&json)
+            .map_err(|e| Error::addr_book_parse(e.to_string()))?;
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Marks the given identifier as the local node's, whose addresses are
+    /// never added to the book.
+    pub fn add_our_id(&mut self, id: node::Id) {
+        self.remove(&id);
+        self.ours.insert(id);
+    }
+
+    /// Adds an address learned from the given source to the new buckets.
+    /// Returns whether the address was added.
+    ///
+    /// Addresses of the local node, and already known addresses, are
+    /// ignored.
+    pub fn add_address(&mut self, addr: PeerAddress, src: PeerAddress) -> bool {
+        if self.ours.contains(&addr.id) || self.addrs.contains_key(&addr.id) {
+            return false;
+        }
+        self.insert(KnownAddress {
+            addr,
+            src,
+            bucket_type: BucketType::New,
+            attempts: 0,
+            last_attempt: None,
+            last_success: None,
+        });
+        true
+    }
+
+    /// Records an attempt to connect to the peer with the given identifier.
+    /// The address is removed once it turns bad.
+    pub fn mark_attempt(&mut self, id: &node::Id) {
+        let bad = self.addrs.get_mut(id).is_some_and(|known| {
+            known.attempts = known.attempts.saturating_add(1);
+            known.last_attempt = Some(now());
+            known.is_bad()
+        });
+        if bad {
+            self.remove(id);
+        }
+    }
+
+    /// Records a successful connection to the peer with the given
+    /// identifier, moving its address to the tried buckets.
+    pub fn mark_good(&mut self, id: &node::Id) {
+        let Some(known) = self.addrs.get_mut(id) else {
+            return;
+        };
+        known.attempts = 0;
+        known.last_success = Some(now());
+        if known.bucket_type == BucketType::Tried {
+            return;
+        }
+        let known = known.clone();
+        self.unlink(&known);
+        self.insert(KnownAddress {
+            bucket_type: BucketType::Tried,
+            ..known
+        });
+    }
+
+    /// Removes the address of the peer with the given identifier.
+    pub fn remove(&mut self, id: &node::Id) {
+        if let Some(known) = self.addrs.remove(id) {
+            self.unlink(&known);
+        }
+    }
+
+    /// Returns what the book knows about the peer with the given identifier.
+    #[must_use]
+    pub fn get(&self, id: &node::Id) -> Option<&KnownAddress> {
+        self.addrs.get(id)
+    }
+
+    /// Number of known addresses.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.addrs.len()
+    }
+
+    /// Whether the book knows no address.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.addrs.is_empty()
+    }
+
+    /// Whether the book knows so few addresses that it should ask peers for
+    /// more.
+    #[must_use]
+    pub fn need_more_addrs(&self) -> bool {
+        self.addrs.len() < NEED_ADDRESS_THRESHOLD
+    }
+
+    /// Number of addresses in the new buckets.
+    #[must_use]
+    pub fn new_count(&self) -> usize {
+        self.new.iter().map(BTreeSet::len).sum()
+    }
+
+    /// Number of addresses in the tried buckets.
+    #[must_use]
+    pub fn tried_count(&self) -> usize {
+        self.tried.iter().map(BTreeSet::len).sum()
+    }
+
+    /// Picks a random address to dial, from the new buckets with a
+    /// probability of `bias_towards_new` percent (if any), or from the tried
+    /// buckets otherwise.
+    #[must_use]
+    pub fn pick_address(&self, bias_towards_new: u8) -> Option<PeerAddress> {
+        let (new_count, tried_count) = (self.new_count(), self.tried_count());
+        let from_new = match (new_count, tried_count) {
+            (0, 0) => return None,
+            (_, 0) => true,
+            (0, _) => false,
+            _ => OsRng.next_u32() % 100 < u32::from(bias_towards_new),
+        };
+        let (buckets, count) = if from_new {
+            (&self.new, new_count)
+        } else {
+            (&self.tried, tried_count)
+        };
+        let id = buckets.iter().flatten().nth(random_below(count))?;
+        self.addrs.get(id).map(|known| known.addr)
+    }
+
+    /// Returns a random selection of known addresses, to share with other
+    /// peers: about a quarter of the addresses, and at most `max` of them.
+    #[must_use]
+    pub fn selection(&self, max: usize) -> Vec<PeerAddress> {
+        let mut addrs: Vec<_> = self.addrs.values().map(|known| known.addr).collect();
+        let size = (addrs.len() * SELECTION_PERCENT / 100)
+            .max(MIN_SELECTION_SIZE.min(addrs.len()))
+            .min(max);
+        // Partial Fisher-Yates shuffle.
+        for i in 0..size {
+            let j = i + random_below(addrs.len() - i);
+            addrs.swap(i, j);
+        }
+        addrs.truncate(size);
+        addrs
+    }
+
+    /// Inserts the address in its bucket, making room if necessary.
+    fn insert(&mut self, known: KnownAddress) {
+        let id = known.addr.id;
+        match known.bucket_type {
+            BucketType::New => {
+                let index = self.new_bucket(&known);
+                if self.new[index].len() >= BUCKET_SIZE {
+                    self.evict_new(index);
+                }
+                self.new[index].insert(id);
+            },
+            BucketType::Tried => {
+                let index = self.tried_bucket(&known);
+                if self.tried[index].len() >= BUCKET_SIZE {
+                    self.demote_tried(index);
+                }
+                self.tried[index].insert(id);
+            },
+        }
+        self.addrs.insert(id, known);
+    }
+
+    /// Removes the address from its bucket.
+    fn unlink(&mut self, known: &KnownAddress) {
+        match known.bucket_type {
+            BucketType::New => {
+                let index = self.new_bucket(known);
+                self.new[index].remove(&known.addr.id);
+            },
+            BucketType::Tried => {
+                let index = self.tried_bucket(known);
+                self.tried[index].remove(&known.addr.id);
+            },
+        }
+    }
+
+    /// Evicts the worst address of a full new bucket: a bad one if any, or
+    /// else the one least recently attempted.
+    fn evict_new(&mut self, index: usize) {
+        let worst = self.new[index]
+            .iter()
+            .filter_map(|id| self.addrs.get(id))
+            .min_by_key(|known| (!known.is_bad(), known.last_attempt))
+            .map(|known| known.addr.id);
+        if let Some(id) = worst {
+            self.remove(&id);
+        }
+    }
+
+    /// Moves the least recently successful address of a full tried bucket
+    /// back to the new buckets.
+    fn demote_tried(&mut self, index: usize) {
+        let oldest = self.tried[index]
+            .iter()
+            .filter_map(|id| self.addrs.get(id))
+            .min_by_key(|known| known.last_success)
+            .cloned();
+        if let Some(known) = oldest {
+            self.remove(&known.addr.id);
+            self.insert(KnownAddress {
+                bucket_type: BucketType::New,
+                ..known
+            });
+        }
+    }
+
+    fn new_bucket(&self, known: &KnownAddress) -> usize {
+        let (group, src_group) = (group(&known.addr.addr), group(&known.src.addr));
+        let hash = self.hash(&[group.as_bytes(), src_group.as_bytes()]) % NEW_BUCKETS_PER_GROUP;
+        let hash = self.hash(&[src_group.as_bytes(), &hash.to_be_bytes()]);
+        bucket_index(hash, NEW_BUCKET_COUNT)
+    }
+
+    fn tried_bucket(&self, known: &KnownAddress) -> usize {
+        let addr = known.addr.to_string();
+        let hash = self.hash(&[addr.as_bytes()]) % TRIED_BUCKETS_PER_GROUP;
+        let group = group(&known.addr.addr);
+        let hash = self.hash(&[group.as_bytes(), &hash.to_be_bytes()]);
+        bucket_index(hash, TRIED_BUCKET_COUNT)
+    }
+
+    /// Hashes the given parts, keyed with the book's key.
+    fn hash(&self, parts: &[&[u8]]) -> u64 {
+        let mut hasher = Sha256::new();
+        hasher.update(self.key);
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .take(8)
+            .fold(0, |acc, &byte| (acc << 8) | u64::from(byte))
+    }
+}
+
+/// Network group of an address: addresses in the same group are likely
+/// under the control of the same entity.
+fn group(addr: &SocketAddr) -> String {
+    match addr.ip() {
+        IpAddr::V4(ip) if ip.is_loopback() || ip.is_private() || ip.is_link_local() => {
+            "local".to_string()
+        },
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            format!("{a}.{b}")
+        },
+        IpAddr::V6(ip) if ip.is_loopback() => "local".to_string(),
+        IpAddr::V6(ip) => {
+            let [a, b, ..] = ip.segments();
+            format!("{a:x}:{b:x}")
+        },
+    }
+}
+
+fn bucket_index(hash: u64, count: usize) -> usize {
+    // `count` is small, so that the remainder fits.
+    u64::try_from(count)
+        .ok()
+        .and_then(|count| usize::try_from(hash % count).ok())
+        .unwrap_or_default()
+}
+
+/// Returns a random number below `n`, which must not be zero.
+fn random_below(n: usize) -> usize {
+    // The modulo bias is negligible for the sizes at hand.
+    u64::try_from(n)
+        .ok()
+        .and_then(|n| usize::try_from(OsRng.next_u64() % n).ok())
+        .unwrap_or_default()
+}
+
+fn decode_key(hex: &str) -> Option<[u8; KEY_SIZE]> {
+    let mut key = [0_u8; KEY_SIZE];
+    if hex.len() != KEY_SIZE * 2 {
+        return None;
+    }
+    for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
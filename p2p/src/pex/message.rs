@@ -0,0 +1,126 @@
+//! Messages of the PEX protocol.
+
+use std::{
+    fmt::{self, Display},
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use prost::Message as _;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use tendermint::node;
+use tendermint_proto::v0_37::p2p::{message::Sum, Message, NetAddress, PexAddrs, PexRequest};
+
+use crate::error::Error;
+
+/// Address at which a peer can be dialed, along with its identifier.
+///
+/// Formatted as `id@ip:port`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PeerAddress {
+    /// Identifier of the peer.
+    pub id: node::Id,
+    /// IP address and port the peer listens on.
+    pub addr: SocketAddr,
+}
+
+impl Display for PeerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.id, self.addr)
+    }
+}
+
+impl FromStr for PeerAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::invalid_peer_address(s.to_string());
+        let (id, addr) = s.split_once('@').ok_or_else(invalid)?;
+        Ok(Self {
+            id: id.parse().map_err(|_| invalid())?,
+            addr: addr.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Serialize for PeerAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PeerAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl TryFrom<NetAddress> for PeerAddress {
+    type Error = Error;
+
+    fn try_from(value: NetAddress) -> Result<Self, Self::Error> {
+        let invalid =
+            || Error::invalid_peer_address(format!("{}@{}:{}", value.id, value.ip, value.port));
+        let id = value.id.parse().map_err(|_| invalid())?;
+        let ip = value.ip.parse::<IpAddr>().map_err(|_| invalid())?;
+        let port = u16::try_from(value.port).map_err(|_| invalid())?;
+        Ok(Self {
+            id,
+            addr: SocketAddr::new(ip, port),
+        })
+    }
+}
+
+impl From<PeerAddress> for NetAddress {
+    fn from(value: PeerAddress) -> Self {
+        Self {
+            id: value.id.to_string(),
+            ip: value.addr.ip().to_string(),
+            port: value.addr.port().into(),
+        }
+    }
+}
+
+/// Message exchanged over the PEX stream.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PexMessage {
+    /// Asks the remote peer for addresses of other peers.
+    Request,
+    /// Addresses of known peers, in response to a request.
+    Addrs(Vec<PeerAddress>),
+}
+
+impl PexMessage {
+    /// Encodes the message as a `tendermint.p2p.Message`.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let sum = match self {
+            Self::Request => Sum::PexRequest(PexRequest {}),
+            Self::Addrs(addrs) => Sum::PexAddrs(PexAddrs {
+                addrs: addrs.iter().copied().map(NetAddress::from).collect(),
+            }),
+        };
+        Message { sum: Some(sum) }.encode_to_vec()
+    }
+
+    /// Decodes a `tendermint.p2p.Message`.
+    ///
+    /// # Errors
+    ///
+    /// * if the message is malformed
+    /// * if one of the addresses is invalid
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        match Message::decode(bytes).map_err(Error::pex_decode)?.sum {
+            Some(Sum::PexRequest(_)) => Ok(Self::Request),
+            Some(Sum::PexAddrs(msg)) => msg
+                .addrs
+                .into_iter()
+                .map(PeerAddress::try_from)
+                .collect::<Result<_, _>>()
+                .map(Self::Addrs),
+            None => Err(Error::malformed_pex_message()),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! PEX protocol handling.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use tendermint::node;
+
+use super::{AddrBook, PeerAddress, PexMessage};
+use crate::{
+    error::Error,
+    transport::{
+        peer_id, transport_error, ConnectInfo, Connection, Endpoint, StreamId, StreamSend,
+    },
+};
+
+/// Default minimum interval between two requests of the same peer.
+pub const DEFAULT_MIN_REQUEST_INTERVAL: Duration = Duration::new(10, 0);
+
+/// Default maximum number of addresses in a `PexAddrs` message.
+pub const DEFAULT_MAX_ADDRS_PER_MESSAGE: usize = 250;
+
+/// Default probability, in percent, to dial a new address rather than a
+/// tried one.
+const DEFAULT_BIAS_TOWARDS_NEW: u8 = 30;
+
+/// Number of addresses picked from the book per requested dial candidate,
+/// before giving up.
+const PICK_ATTEMPTS_PER_CANDIDATE: usize = 3;
+
+/// Configuration of a [`PexReactor`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PexConfig {
+    /// Minimum interval between two requests of the same peer. Peers asking
+    /// more often are considered misbehaving.
+    pub min_request_interval: Duration,
+    /// Maximum number of addresses sent in, and accepted from, a `PexAddrs`
+    /// message.
+    pub max_addrs_per_message: usize,
+    /// Probability, in percent, to dial a new address rather than a tried
+    /// one.
+    pub bias_towards_new: u8,
+}
+
+impl Default for PexConfig {
+    fn default() -> Self {
+        Self {
+            min_request_interval: DEFAULT_MIN_REQUEST_INTERVAL,
+            max_addrs_per_message: DEFAULT_MAX_ADDRS_PER_MESSAGE,
+            bias_towards_new: DEFAULT_BIAS_TOWARDS_NEW,
+        }
+    }
+}
+
+/// What the reactor tracks about a connected peer.
+#[derive(Clone, Copy, Debug, Default)]
+struct PeerState {
+    /// When the peer last sent a request.
+    last_request: Option<Instant>,
+    /// Whether the local node asked the peer for addresses, and is waiting
+    /// for them.
+    awaiting_addrs: bool,
+}
+
+/// Handles the PEX stream of the connected peers, and feeds the address
+/// book.
+///
+/// The reactor is cheaply cloneable, and clones share the same address book
+/// and peer states.
+#[derive(Clone, Debug)]
+pub struct PexReactor {
+    book: Arc<Mutex<AddrBook>>,
+    peers: Arc<Mutex<BTreeMap<node::Id, PeerState>>>,
+    config: PexConfig,
+}
+
+impl PexReactor {
+    /// Creates a reactor feeding the given address book.
+    #[must_use]
+    pub fn new(book: AddrBook, config: PexConfig) -> Self {
+        Self {
+            book: Arc::new(Mutex::new(book)),
+            peers: Arc::new(Mutex::new(BTreeMap::new())),
+            config,
+        }
+    }
+
+    /// Locks the address book, e.g. to save it.
+    pub fn addr_book(&self) -> MutexGuard<'_, AddrBook> {
+        self.book.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Handles a message received from the given peer over the PEX stream,
+    /// and returns the response to send back, if any.
+    ///
+    /// Requests are answered with a random selection of known addresses.
+    /// Addresses are only accepted in response to a request made with
+    /// [`Self::request`], and are added to the address book.
+    ///
+    /// # Errors
+    ///
+    /// * if the message is malformed
+    /// * if the peer sends requests too frequently
+    /// * if the peer sends addresses that were not requested, or too many of them
+    pub fn receive(&self, peer: &PeerAddress, msg: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        match PexMessage::decode(msg)? {
+            PexMessage::Request => {
+                self.check_request_interval(&peer.id)?;
+                let addrs = self
+                    .addr_book()
+                    .selection(self.config.max_addrs_per_message);
+                Ok(Some(PexMessage::Addrs(addrs).encode()))
+            },
+            PexMessage::Addrs(addrs) => {
+                let awaiting_addrs = self
+                    .peers()
+                    .get_mut(&peer.id)
+                    .is_some_and(|state| std::mem::take(&mut state.awaiting_addrs));
+                if !awaiting_addrs {
+                    return Err(Error::unsolicited_pex_addrs(peer.id));
+                }
+                if addrs.len() > self.config.max_addrs_per_message {
+                    return Err(Error::too_many_pex_addrs(
+                        addrs.len(),
+                        self.config.max_addrs_per_message,
+                    ));
+                }
+                let mut book = self.addr_book();
+                for addr in addrs {
+                    book.add_address(addr, *peer);
+                }
+                drop(book);
+                Ok(None)
+            },
+        }
+    }
+
+    /// Returns a request for addresses to send to the given peer, whose
+    /// response [`Self::receive`] then accepts.
+    #[must_use]
+    pub fn request(&self, peer: &node::Id) -> Vec<u8> {
+        self.peers().entry(*peer).or_default().awaiting_addrs = true;
+        PexMessage::Request.encode()
+    }
+
+    /// Forgets the state of a disconnected peer.
+    pub fn remove_peer(&self, peer: &node::Id) {
+        self.peers().remove(peer);
+    }
+
+    /// Runs the PEX protocol over the given connection, on a separate
+    /// thread.
+    ///
+    /// The address the peer advertises, or else the one it connected from,
+    /// is added to the address book, and the peer is asked for addresses if
+    /// the book needs more. The thread ends when the connection is closed,
+    /// or with an error when the peer misbehaves, in which case the caller
+    /// should close the connection.
+    ///
+    /// # Errors
+    ///
+    /// * if the PEX stream cannot be opened
+    /// * if the request for addresses cannot be sent
+    pub fn serve<C>(&self, conn: &C) -> Result<JoinHandle<Result<(), Error>>, Error>
+    where
+        C: Connection<Error = Error>,
+        C::StreamRead: 'static,
+        C::StreamSend: Send + 'static,
+    {
+        let id = peer_id(conn.public_key()).ok_or_else(Error::invalid_key)?;
+        let addr = conn
+            .advertised_addrs()
+            .first()
+            .copied()
+            .unwrap_or_else(|| conn.remote_addr());
+        let peer = PeerAddress { id, addr };
+        self.addr_book().add_address(peer, peer);
+
+        let (read, send) = conn.open_bidirectional(StreamId::Pex)?;
+        if self.addr_book().need_more_addrs() {
+            send.send(self.request(&id)).map_err(transport_error)?;
+        }
+
+        let reactor = self.clone();
+        Ok(thread::spawn(move || {
+            let result = reactor.run(&peer, read, &send);
+            reactor.remove_peer(&peer.id);
+            result
+        }))
+    }
+
+    /// Picks up to `count` distinct addresses to dial from the address
+    /// book, leaving out the given peers (e.g. those already connected).
+    #[must_use]
+    pub fn dial_candidates(&self, count: usize, exclude: &[node::Id]) -> Vec<PeerAddress> {
+        let book = self.addr_book();
+        let mut picked = BTreeSet::new();
+        let mut candidates = Vec::with_capacity(count);
+        for _ in 0..count * PICK_ATTEMPTS_PER_CANDIDATE {
+            if candidates.len() >= count {
+                break;
+            }
+            let Some(addr) = book.pick_address(self.config.bias_towards_new) else {
+                break;
+            };
+            if !exclude.contains(&addr.id) && picked.insert(addr.id) {
+                candidates.push(addr);
+            }
+        }
+        drop(book);
+        candidates
+    }
+
+    /// Dials up to `count` candidates from the address book with the given
+    /// endpoint, and returns the connections that succeeded.
+    ///
+    /// Attempts are recorded in the address book, and the addresses of the
+    /// peers reached are marked as good.
+    pub fn ensure_peers<E>(
+        &self,
+        endpoint: &E,
+        connected: &[node::Id],
+        count: usize,
+    ) -> Vec<E::Connection>
+    where
+        E: Endpoint<SocketAddr>,
+    {
+        self.dial_candidates(count, connected)
+            .into_iter()
+            .filter_map(|candidate| {
+                self.addr_book().mark_attempt(&candidate.id);
+                let conn = endpoint
+                    .connect(ConnectInfo {
+                        addrs: candidate.addr,
+                        id: candidate.id,
+                    })
+                    .ok()?;
+                self.addr_book().mark_good(&candidate.id);
+                Some(conn)
+            })
+            .collect()
+    }
+
+    fn run<R, S>(&self, peer: &PeerAddress, read: R, send: &S) -> Result<(), Error>
+    where
+        R: Iterator<Item = eyre::Result<Vec<u8>>>,
+        S: StreamSend,
+    {
+        for msg in read {
+            let msg = msg.map_err(transport_error)?;
+            if let Some(response) = self.receive(peer, &msg)? {
+                send.send(response).map_err(transport_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_request_interval(&self, peer: &node::Id) -> Result<(), Error> {
+        let now = Instant::now();
+        let mut peers = self.peers();
+        let state = peers.entry(*peer).or_default();
+        if let Some(last_request) = state.last_request {
+            if now.duration_since(last_request) < self.config.min_request_interval {
+                return Err(Error::pex_request_too_frequent(*peer));
+            }
+        }
+        state.last_request = Some(now);
+        drop(peers);
+        Ok(())
+    }
+
+    fn peers(&self) -> MutexGuard<'_, BTreeMap<node::Id, PeerState>> {
+        self.peers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
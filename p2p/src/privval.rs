@@ -0,0 +1,24 @@
+//! Privval: the protocol between a node and the remote signer holding its
+//! validator key.
+//!
+//! The node listens on its `priv_validator_laddr`, to which the signer
+//! connects, either over TCP with a [`SecretConnection`], or over a Unix
+//! domain socket. The node then sends length-prefixed `tendermint.privval`
+//! requests, which the signer answers.
+//!
+//! [`SignerListener`] and [`SignerClient`] implement the node side of the
+//! protocol, and [`Signer`] the signer side.
+//!
+//! [`SecretConnection`]: crate::secret_connection::SecretConnection
+//!
+//! [Specification](https://github.com/tendermint/tendermint/blob/v0.37.x/spec/consensus/signing.md)
+
+mod client;
+mod message;
+mod signer;
+
+pub use self::{
+    client::{SignerClient, SignerListener},
+    message::{Request, Response, MAX_MESSAGE_SIZE},
+    signer::{Signer, SignerConnection},
+};
@@ -0,0 +1,380 @@
+//! Node side of the privval protocol.
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    time::Duration,
+};
+
+use tendermint::{
+    account, block, chain,
+    privval::RemoteSignerError,
+    proposal::{self, Proposal, SignProposalRequest},
+    public_key::{PubKeyRequest, PublicKey},
+    vote::{self, SignVoteRequest, Vote},
+    Hash, Signature, Time,
+};
+use tendermint_config::net;
+
+use super::{Request, Response, SignerConnection};
+use crate::{
+    error::{Error, ErrorDetail},
+    secret_connection::{self, SecretConnection, Version},
+};
+
+/// Listener on the `priv_validator_laddr` of a node, accepting the
+/// connection of its remote signer.
+pub struct SignerListener {
+    listener: Listener,
+    timeout: Duration,
+}
+
+enum Listener {
+    Tcp {
+        listener: TcpListener,
+        identity: Box<ed25519_consensus::SigningKey>,
+    },
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        path: PathBuf,
+    },
+}
+
+impl SignerListener {
+    /// Listens at the given address. Over TCP, the node authenticates with
+    /// the given identity key. Reads and writes on the accepted connections
+    /// time out after the given duration.
+    ///
+    /// # Errors
+    ///
+    /// * if the address cannot be bound
+    /// * if the address is a Unix socket, on a platform without them
+    pub fn bind(
+        address: &net::Address,
+        identity: ed25519_consensus::SigningKey,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let listener = match address {
+            net::Address::Tcp { host, port, .. } => Listener::Tcp {
+                listener: TcpListener::bind((host.as_str(), *port))?,
+                identity: Box::new(identity),
+            },
+            #[cfg(unix)]
+            net::Address::Unix { path } => Listener::Unix {
+                listener: UnixListener::bind(path)?,
+                path: path.into(),
+            },
+            #[cfg(not(unix))]
+            net::Address::Unix { .. } => {
+                return Err(Error::unsupported_address(address.to_string()))
+            },
+        };
+        Ok(Self { listener, timeout })
+    }
+
+    /// Returns the address the signer should connect to, including the
+    /// identifier of the node over TCP.
+    ///
+    /// # Errors
+    ///
+    /// * if the local address of the socket cannot be retrieved
+    pub fn local_addr(&self) -> Result<net::Address, Error> {
+        match &self.listener {
+            Listener::Tcp { listener, identity } => {
+                let addr = listener.local_addr()?;
+                Ok(net::Address::Tcp {
+                    peer_id: Some(secret_connection::PublicKey::from(identity.as_ref()).peer_id()),
+                    host: addr.ip().to_string(),
+                    port: addr.port(),
+                })
+            },
+            #[cfg(unix)]
+            Listener::Unix { path, .. } => Ok(net::Address::Unix {
+                path: path.to_string_lossy().into_owned(),
+            }),
+        }
+    }
+
+    /// Waits for a signer to connect.
+    ///
+    /// # Errors
+    ///
+    /// * if accepting the connection fails
+    /// * if the handshake fails
+    pub fn accept(&self) -> Result<SignerConnection, Error> {
+        match &self.listener {
+            Listener::Tcp { listener, identity } => {
+                let (stream, _) = listener.accept()?;
+                stream.set_read_timeout(Some(self.timeout))?;
+                stream.set_write_timeout(Some(self.timeout))?;
+                let conn = SecretConnection::new(stream, *identity.clone(), Version::V0_34)?;
+                Ok(SignerConnection::Tcp(Box::new(conn)))
+            },
+            #[cfg(unix)]
+            Listener::Unix { listener, .. } => {
+                let (stream, _) = listener.accept()?;
+                stream.set_read_timeout(Some(self.timeout))?;
+                stream.set_write_timeout(Some(self.timeout))?;
+                Ok(SignerConnection::Unix(stream))
+            },
+        }
+    }
+}
+
+/// Client of a remote signer, used by a node to get its votes and proposals
+/// signed.
+///
+/// Every signature returned by the signer is verified against the public
+/// key of the validator and the bytes to sign for the chain, so that the
+/// client can also be used to check that a third-party signer conforms to
+/// the protocol; see [`SignerClient::check_conformance`].
+pub struct SignerClient<C> {
+    conn: C,
+    chain_id: chain::Id,
+    public_key: PublicKey,
+    verification_key: ed25519_consensus::VerificationKey,
+}
+
+impl<C: Read + Write> SignerClient<C> {
+    /// Creates a client for the given chain over the connection, asking the
+    /// signer for the public key of the validator.
+    ///
+    /// # Errors
+    ///
+    /// * if reading or writing fails
+    /// * if the signer refuses the request, or answers something else
+    /// * if the public key is not supported
+    pub fn new(mut conn: C, chain_id: chain::Id) -> Result<Self, Error> {
+        let Response::PubKey(res) = request(
+            &mut conn,
+            Request::PubKey(PubKeyRequest {
+                chain_id: chain_id.clone(),
+            }),
+        )?
+        else {
+            return Err(Error::unexpected_privval_message());
+        };
+        let public_key = into_result(res.pub_key, res.error)?;
+        let verification_key = public_key
+            .ed25519()
+            .and_then(|key| ed25519_consensus::VerificationKey::try_from(key).ok())
+            .ok_or_else(Error::unsupported_key)?;
+        Ok(Self {
+            conn,
+            chain_id,
+            public_key,
+            verification_key,
+        })
+    }
+
+    /// Returns the public key of the validator.
+    #[must_use]
+    pub const fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    /// Checks that the signer is alive.
+    ///
+    /// # Errors
+    ///
+    /// * if reading or writing fails
+    /// * if the signer answers something else
+    pub fn ping(&mut self) -> Result<(), Error> {
+        match request(&mut self.conn, Request::Ping)? {
+            Response::Ping => Ok(()),
+            _ => Err(Error::unexpected_privval_message()),
+        }
+    }
+
+    /// Asks the signer to sign the given vote, and returns it signed. The
+    /// signer may change its timestamp, when signing the same vote again.
+    ///
+    /// # Errors
+    ///
+    /// * if reading or writing fails
+    /// * if the signer refuses to sign, or answers something else
+    /// * if the signer signed another vote, or the signature is invalid
+    pub fn sign_vote(&mut self, vote: &Vote) -> Result<Vote, Error> {
+        let Response::SignedVote(res) = request(
+            &mut self.conn,
+            Request::SignVote(SignVoteRequest {
+                vote: vote.clone(),
+                chain_id: self.chain_id.clone(),
+            }),
+        )?
+        else {
+            return Err(Error::unexpected_privval_message());
+        };
+        let signed = into_result(res.vote, res.error)?;
+        let requested = Vote {
+            timestamp: signed.timestamp,
+            signature: signed.signature.clone(),
+            ..vote.clone()
+        };
+        if signed != requested {
+            return Err(Error::signed_data_mismatch());
+        }
+        let sign_bytes = signed
+            .to_signable_vec(self.chain_id.clone())
+            .map_err(Error::sign_bytes)?;
+        self.verify(&sign_bytes, signed.signature.as_ref())?;
+        Ok(signed)
+    }
+
+    /// Asks the signer to sign the given proposal, and returns it signed.
+    /// The signer may change its timestamp, when signing the same proposal
+    /// again.
+    ///
+    /// # Errors
+    ///
+    /// * if reading or writing fails
+    /// * if the signer refuses to sign, or answers something else
+    /// * if the signer signed another proposal, or the signature is invalid
+    pub fn sign_proposal(&mut self, proposal: &Proposal) -> Result<Proposal, Error> {
+        let Response::SignedProposal(res) = request(
+            &mut self.conn,
+            Request::SignProposal(SignProposalRequest {
+                proposal: proposal.clone(),
+                chain_id: self.chain_id.clone(),
+            }),
+        )?
+        else {
+            return Err(Error::unexpected_privval_message());
+        };
+        let signed = into_result(res.proposal, res.error)?;
+        let requested = Proposal {
+            timestamp: signed.timestamp,
+            signature: signed.signature.clone(),
+            ..proposal.clone()
+        };
+        if signed != requested {
+            return Err(Error::signed_data_mismatch());
+        }
+        let sign_bytes = signed
+            .to_signable_vec(self.chain_id.clone())
+            .map_err(Error::sign_bytes)?;
+        self.verify(&sign_bytes, signed.signature.as_ref())?;
+        Ok(signed)
+    }
+
+    /// Checks that the signer conforms to the protocol, by asking it to sign
+    /// a proposal and votes at the given height, and checking that it signs
+    /// the same vote again, but refuses to double sign.
+    ///
+    /// The signer keeps the signed height, so this is meant to be run
+    /// against a test key, or at a height the chain will never reach.
+    ///
+    /// # Errors
+    ///
+    /// * if the signer does not conform to the protocol
+    /// * if reading or writing fails
+    pub fn check_conformance(&mut self, height: block::Height) -> Result<(), Error> {
+        self.ping()?;
+        let mut other_chain = self.chain_id.as_str().to_owned();
+        other_chain.push_str("-other");
+        match request(
+            &mut self.conn,
+            Request::PubKey(PubKeyRequest {
+                chain_id: other_chain
+                    .parse()
+                    .map_err(Error::invalid_privval_message)?,
+            }),
+        )? {
+            Response::PubKey(res) if res.error.is_some() => {},
+            Response::PubKey(_) => {
+                return Err(Error::non_conformant_signer(
+                    "returned its public key for another chain".to_owned(),
+                ))
+            },
+            _ => return Err(Error::unexpected_privval_message()),
+        }
+
+        let block_id = Some(block::Id {
+            hash: Hash::Sha256([0xab; 32]),
+            part_set_header: block::parts::Header::new(1, Hash::Sha256([0xcd; 32]))
+                .map_err(Error::invalid_privval_message)?,
+        });
+        self.sign_proposal(&Proposal {
+            msg_type: proposal::Type::Proposal,
+            height,
+            round: block::Round::default(),
+            pol_round: None,
+            block_id,
+            timestamp: Some(Time::now()),
+            signature: None,
+        })?;
+        self.sign_vote(&self.vote(vote::Type::Prevote, height, block_id))?;
+        let precommit = self.sign_vote(&self.vote(vote::Type::Precommit, height, block_id))?;
+
+        let again = self.sign_vote(&self.vote(vote::Type::Precommit, height, block_id))?;
+        if again.signature != precommit.signature {
+            return Err(Error::non_conformant_signer(
+                "returned another signature for the same precommit".to_owned(),
+            ));
+        }
+        self.expect_refusal(
+            &self.vote(vote::Type::Precommit, height, None),
+            "precommit for nil",
+        )?;
+        self.expect_refusal(
+            &self.vote(vote::Type::Prevote, height, block_id),
+            "prevote after the precommit",
+        )?;
+        Ok(())
+    }
+
+    /// Returns an unsigned vote of the validator at round zero.
+    fn vote(
+        &self,
+        vote_type: vote::Type,
+        height: block::Height,
+        block_id: Option<block::Id>,
+    ) -> Vote {
+        Vote {
+            vote_type,
+            height,
+            block_id,
+            timestamp: Some(Time::now()),
+            validator_address: account::Id::from(self.public_key),
+            signature: None,
+            ..Vote::default()
+        }
+    }
+
+    fn expect_refusal(&mut self, vote: &Vote, description: &str) -> Result<(), Error> {
+        match self.sign_vote(vote) {
+            Err(e) if matches!(e.detail(), ErrorDetail::RemoteSigner(_)) => Ok(()),
+            Err(e) => Err(e),
+            Ok(_) => Err(Error::non_conformant_signer(format!(
+                "signed a conflicting {description}"
+            ))),
+        }
+    }
+
+    fn verify(&self, sign_bytes: &[u8], signature: Option<&Signature>) -> Result<(), Error> {
+        let signature = signature
+            .and_then(|signature| ed25519_consensus::Signature::try_from(signature.as_bytes()).ok())
+            .ok_or_else(Error::invalid_signer_signature)?;
+        self.verification_key
+            .verify(&signature, sign_bytes)
+            .map_err(|_| Error::invalid_signer_signature())
+    }
+}
+
+fn request<C: Read + Write>(conn: &mut C, request: Request) -> Result<Response, Error> {
+    request.write_to(conn)?;
+    Response::read_from(conn)
+}
+
+/// Turns the content of a response into a result.
+fn into_result<T>(value: Option<T>, error: Option<RemoteSignerError>) -> Result<T, Error> {
+    match (value, error) {
+        (_, Some(e)) => Err(Error::remote_signer(e.code, e.description)),
+        (Some(value), None) => Ok(value),
+        (None, None) => Err(Error::malformed_privval_message()),
+    }
+}
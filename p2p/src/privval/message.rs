@@ -0,0 +1,151 @@
+//! Messages of the privval protocol.
+
+use std::io::{Read, Write};
+
+use prost::Message as _;
+use tendermint::{
+    proposal::{SignProposalRequest, SignedProposalResponse},
+    public_key::{PubKeyRequest, PubKeyResponse},
+    vote::{SignVoteRequest, SignedVoteResponse},
+};
+use tendermint_proto::v0_37::privval::{message::Sum, Message, PingRequest, PingResponse};
+
+use crate::{error::Error, length_delimited::read_length_delimited};
+
+/// Maximum size of an encoded privval message.
+pub const MAX_MESSAGE_SIZE: usize = 10240;
+
+/// Request from a node to its remote signer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Request {
+    /// Asks for the public key of the validator.
+    PubKey(PubKeyRequest),
+    /// Asks for a vote to be signed.
+    SignVote(SignVoteRequest),
+    /// Asks for a proposal to be signed.
+    SignProposal(SignProposalRequest),
+    /// Checks that the signer is alive.
+    Ping,
+}
+
+/// Response of a remote signer to a [`Request`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Response {
+    /// Public key of the validator.
+    PubKey(PubKeyResponse),
+    /// Signed vote.
+    SignedVote(SignedVoteResponse),
+    /// Signed proposal.
+    SignedProposal(SignedProposalResponse),
+    /// Answer to a ping.
+    Ping,
+}
+
+impl Request {
+    /// Reads a length-prefixed request. Returns `None` if the connection was
+    /// closed before the request started.
+    ///
+    /// # Errors
+    ///
+    /// * if reading fails
+    /// * if the message is malformed, or is not a request
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Option<Self>, Error> {
+        let Some(sum) = read_message(reader)? else {
+            return Ok(None);
+        };
+        let request = match sum {
+            Sum::PubKeyRequest(req) => {
+                Self::PubKey(req.try_into().map_err(Error::invalid_privval_message)?)
+            },
+            Sum::SignVoteRequest(req) => {
+                Self::SignVote(req.try_into().map_err(Error::invalid_privval_message)?)
+            },
+            Sum::SignProposalRequest(req) => {
+                Self::SignProposal(req.try_into().map_err(Error::invalid_privval_message)?)
+            },
+            Sum::PingRequest(PingRequest {}) => Self::Ping,
+            _ => return Err(Error::unexpected_privval_message()),
+        };
+        Ok(Some(request))
+    }
+
+    /// Writes the request, prefixed by its length.
+    ///
+    /// # Errors
+    ///
+    /// * if writing fails
+    pub fn write_to<W: Write>(self, writer: &mut W) -> Result<(), Error> {
+        let sum = match self {
+            Self::PubKey(req) => Sum::PubKeyRequest(req.into()),
+            Self::SignVote(req) => Sum::SignVoteRequest(req.into()),
+            Self::SignProposal(req) => Sum::SignProposalRequest(req.into()),
+            Self::Ping => Sum::PingRequest(PingRequest {}),
+        };
+        write_message(writer, sum)
+    }
+}
+
+impl Response {
+    /// Reads a length-prefixed response.
+    ///
+    /// # Errors
+    ///
+    /// * if reading fails, or the connection is closed
+    /// * if the message is malformed, or is not a response
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let response = match read_message(reader)?.ok_or_else(Error::connection_closed)? {
+            Sum::PubKeyResponse(res) => {
+                Self::PubKey(res.try_into().map_err(Error::invalid_privval_message)?)
+            },
+            Sum::SignedVoteResponse(res) => {
+                Self::SignedVote(res.try_into().map_err(Error::invalid_privval_message)?)
+            },
+            Sum::SignedProposalResponse(res) => {
+                Self::SignedProposal(res.try_into().map_err(Error::invalid_privval_message)?)
+            },
+            Sum::PingResponse(PingResponse {}) => Self::Ping,
+            _ => return Err(Error::unexpected_privval_message()),
+        };
+        Ok(response)
+    }
+
+    /// Writes the response, prefixed by its length.
+    ///
+    /// # Errors
+    ///
+    /// * if writing fails
+    pub fn write_to<W: Write>(self, writer: &mut W) -> Result<(), Error> {
+        let sum = match self {
+            Self::PubKey(res) => Sum::PubKeyResponse(res.into()),
+            Self::SignedVote(res) => Sum::SignedVoteResponse(res.into()),
+            Self::SignedProposal(res) => Sum::SignedProposalResponse(res.into()),
+            Self::Ping => Sum::PingResponse(PingResponse {}),
+        };
+        write_message(writer, sum)
+    }
+}
+
+fn write_message<W: Write>(writer: &mut W, sum: Sum) -> Result<(), Error> {
+    let msg = Message { sum: Some(sum) };
+    writer.write_all(&msg.encode_length_delimited_to_vec())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads a length-prefixed message, or `None` if the reader is at its end.
+fn read_message<R: Read>(reader: &mut R) -> Result<Option<Sum>, Error> {
+    let Some(buf) = read_length_delimited(
+        reader,
+        MAX_MESSAGE_SIZE,
+        Error::privval_decode,
+        Error::privval_message_too_large,
+    )?
+    else {
+        return Ok(None);
+    };
+    Message::decode(buf.as_slice())
+        .map_err(Error::privval_decode)?
+        .sum
+        .map(Some)
+        .ok_or_else(Error::malformed_privval_message)
+}
@@ -0,0 +1,332 @@
+//! Signer side of the privval protocol.
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    time::Duration,
+};
+
+use tendermint::{
+    block, chain,
+    private_key::PrivateKey,
+    privval::RemoteSignerError,
+    proposal::{SignProposalRequest, SignedProposalResponse},
+    public_key::{PubKeyRequest, PubKeyResponse, PublicKey},
+    vote::{SignVoteRequest, SignedVoteResponse},
+    Signature, Time,
+};
+use tendermint_config::{net, PrivValidatorState, SignCheck};
+
+use super::{Request, Response};
+use crate::{
+    error::Error,
+    secret_connection::{SecretConnection, Version},
+};
+
+/// Connection between a signer and a node.
+pub enum SignerConnection {
+    /// Connection over TCP, authenticated and encrypted.
+    Tcp(Box<SecretConnection<TcpStream>>),
+    /// Connection over a Unix domain socket.
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl SignerConnection {
+    /// Connects to the node listening at the given address, i.e. its
+    /// `priv_validator_laddr`.
+    ///
+    /// Over TCP, the signer authenticates with the given identity key, and
+    /// checks the identifier of the node if the address includes one.
+    ///
+    /// # Errors
+    ///
+    /// * if the node cannot be reached
+    /// * if the handshake fails, or the node is not the expected one
+    /// * if the address is a Unix socket, on a platform without them
+    pub fn connect(
+        address: &net::Address,
+        identity: ed25519_consensus::SigningKey,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        match address {
+            net::Address::Tcp {
+                peer_id,
+                host,
+                port,
+            } => {
+                let addr = (host.as_str(), *port)
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or_else(Error::no_address)?;
+                let stream = TcpStream::connect_timeout(&addr, timeout)?;
+                let conn = SecretConnection::new(stream, identity, Version::V0_34)?;
+                let authenticated = conn.remote_pubkey().peer_id();
+                match peer_id {
+                    Some(expected) if *expected != authenticated => {
+                        Err(Error::unexpected_peer_id(*expected, authenticated))
+                    },
+                    _ => Ok(Self::Tcp(Box::new(conn))),
+                }
+            },
+            #[cfg(unix)]
+            net::Address::Unix { path } => Ok(Self::Unix(UnixStream::connect(path)?)),
+            #[cfg(not(unix))]
+            net::Address::Unix { .. } => Err(Error::unsupported_address(address.to_string())),
+        }
+    }
+}
+
+impl Read for SignerConnection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(conn) => conn.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for SignerConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(conn) => conn.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(conn) => conn.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Remote signer, answering the requests of a node with a validator key.
+///
+/// Before signing a vote or a proposal, the signer checks it against the
+/// state of the last signature, and refuses to sign for a lower height,
+/// round or step, or to sign different data for the same ones. The new state
+/// is saved before the signature is sent back, so that the protection holds
+/// across restarts.
+pub struct Signer<C> {
+    conn: C,
+    signing_key: ed25519_consensus::SigningKey,
+    public_key: PublicKey,
+    chain_id: chain::Id,
+    state: PrivValidatorState,
+    state_file: PathBuf,
+}
+
+impl<C: Read + Write> Signer<C> {
+    /// Creates a signer answering requests for the given chain over the
+    /// connection, with the state of the last signature kept in the given
+    /// file.
+    ///
+    /// # Errors
+    ///
+    /// * if the private key is not supported
+    /// * if the state file exists, but cannot be loaded
+    pub fn new(
+        conn: C,
+        private_key: &PrivateKey,
+        chain_id: chain::Id,
+        state_file: impl Into<PathBuf>,
+    ) -> Result<Self, Error> {
+        let signing_key = private_key
+            .ed25519_signing_key()
+            .and_then(|key| ed25519_consensus::SigningKey::try_from(key.clone()).ok())
+            .ok_or_else(Error::unsupported_key)?;
+        let state_file = state_file.into();
+        let state = if state_file.exists() {
+            PrivValidatorState::load_json_file(&state_file)
+                .map_err(|e| Error::priv_validator_state(e.to_string()))?
+        } else {
+            PrivValidatorState::default()
+        };
+        Ok(Self {
+            conn,
+            signing_key,
+            public_key: private_key.public_key(),
+            chain_id,
+            state,
+            state_file,
+        })
+    }
+
+    /// Returns the state of the last signature.
+    #[must_use]
+    pub const fn state(&self) -> &PrivValidatorState {
+        &self.state
+    }
+
+    /// Answers requests until the node closes the connection.
+    ///
+    /// # Errors
+    ///
+    /// * if reading or writing fails
+    /// * if a request is malformed
+    pub fn serve(&mut self) -> Result<(), Error> {
+        while let Some(request) = Request::read_from(&mut self.conn)? {
+            self.handle(request).write_to(&mut self.conn)?;
+        }
+        Ok(())
+    }
+
+    /// Answers a single request.
+    pub fn handle(&mut self, request: Request) -> Response {
+        match request {
+            Request::PubKey(req) => Response::PubKey(self.pub_key(&req)),
+            Request::SignVote(req) => Response::SignedVote(self.sign_vote(req)),
+            Request::SignProposal(req) => Response::SignedProposal(self.sign_proposal(req)),
+            Request::Ping => Response::Ping,
+        }
+    }
+
+    fn pub_key(&self, req: &PubKeyRequest) -> PubKeyResponse {
+        match self.check_chain_id(&req.chain_id) {
+            Ok(()) => PubKeyResponse {
+                pub_key: Some(self.public_key),
+                error: None,
+            },
+            Err(e) => PubKeyResponse {
+                pub_key: None,
+                error: Some(remote_signer_error(&e)),
+            },
+        }
+    }
+
+    fn sign_vote(&mut self, req: SignVoteRequest) -> SignedVoteResponse {
+        let mut vote = req.vote;
+        let signed = self.check_chain_id(&req.chain_id).and_then(|()| {
+            vote.signature = None;
+            let check = self
+                .state
+                .check_vote(&vote, &req.chain_id)
+                .map_err(|e| Error::priv_validator_state(e.to_string()))?;
+            let sign_bytes = vote
+                .to_signable_vec(req.chain_id)
+                .map_err(Error::sign_bytes)?;
+            let step = PrivValidatorState::vote_step(vote.vote_type);
+            self.sign(vote.height, vote.round, step, sign_bytes, &check)
+        });
+        match signed {
+            Ok((signature, timestamp)) => {
+                vote.signature = Some(signature);
+                vote.timestamp = timestamp.or(vote.timestamp);
+                SignedVoteResponse {
+                    vote: Some(vote),
+                    error: None,
+                }
+            },
+            Err(e) => SignedVoteResponse {
+                vote: None,
+                error: Some(remote_signer_error(&e)),
+            },
+        }
+    }
+
+    fn sign_proposal(&mut self, req: SignProposalRequest) -> SignedProposalResponse {
+        let mut proposal = req.proposal;
+        let signed = self.check_chain_id(&req.chain_id).and_then(|()| {
+            proposal.signature = None;
+            let check = self
+                .state
+                .check_proposal(&proposal, &req.chain_id)
+                .map_err(|e| Error::priv_validator_state(e.to_string()))?;
+            let sign_bytes = proposal
+                .to_signable_vec(req.chain_id)
+                .map_err(Error::sign_bytes)?;
+            let step = PrivValidatorState::STEP_PROPOSE;
+            self.sign(proposal.height, proposal.round, step, sign_bytes, &check)
+        });
+        match signed {
+            Ok((signature, timestamp)) => {
+                proposal.signature = Some(signature);
+                proposal.timestamp = timestamp.or(proposal.timestamp);
+                SignedProposalResponse {
+                    proposal: Some(proposal),
+                    error: None,
+                }
+            },
+            Err(e) => SignedProposalResponse {
+                proposal: None,
+                error: Some(remote_signer_error(&e)),
+            },
+        }
+    }
+
+    /// Signs the given bytes, once checked against the state of the last
+    /// signature. When asked to sign the same message again, possibly with
+    /// another timestamp, returns the last signature, along with the
+    /// timestamp it covers.
+    fn sign(
+        &mut self,
+        height: block::Height,
+        round: block::Round,
+        step: i8,
+        sign_bytes: Vec<u8>,
+        check: &SignCheck,
+    ) -> Result<(Signature, Option<Time>), Error> {
+        match *check {
+            SignCheck::New => {},
+            SignCheck::Same => return Ok((self.last_signature()?, None)),
+            SignCheck::SameExceptTimestamp(timestamp) => {
+                return Ok((self.last_signature()?, Some(timestamp)))
+            },
+        }
+
+        let signature = self.signing_key.sign(&sign_bytes);
+        let state = PrivValidatorState {
+            height,
+            round,
+            step,
+            signature: signature.to_bytes().to_vec(),
+            signbytes: sign_bytes,
+        };
+        state
+            .save_json_file(&self.state_file)
+            .map_err(|e| Error::priv_validator_state(e.to_string()))?;
+        self.state = state;
+        Ok((signature.into(), None))
+    }
+
+    fn last_signature(&self) -> Result<Signature, Error> {
+        Signature::new(&self.state.signature)
+            .ok()
+            .flatten()
+            .ok_or_else(|| {
+                Error::priv_validator_state(
+                    tendermint_config::Error::missing_sign_bytes(format!(
+                        "{}/{}/{}",
+                        self.state.height, self.state.round, self.state.step
+                    ))
+                    .to_string(),
+                )
+            })
+    }
+
+    fn check_chain_id(&self, chain_id: &chain::Id) -> Result<(), Error> {
+        if *chain_id == self.chain_id {
+            Ok(())
+        } else {
+            Err(Error::chain_id_mismatch(
+                self.chain_id.clone(),
+                chain_id.clone(),
+            ))
+        }
+    }
+}
+
+fn remote_signer_error(e: &Error) -> RemoteSignerError {
+    RemoteSignerError {
+        code: 0,
+        description: e.detail().to_string(),
+    }
+}
@@ -29,10 +29,14 @@ pub use self::{
     protocol::Version,
     public_key::PublicKey,
 };
+#[cfg(feature = "async")]
+pub use self::async_io::{AsyncReceiver, AsyncSecretConnection, AsyncSender};
 use crate::error::Error;
 
 #[cfg(feature = "amino")]
 mod amino_types;
+#[cfg(feature = "async")]
+mod async_io;
 
 mod kdf;
 mod nonce;
@@ -533,22 +537,8 @@ fn encrypt_and_write<IoHandler: Write>(
     data: &[u8],
 ) -> io::Result<usize> {
     let mut n = 0_usize;
-    let mut data_copy = data;
-    while !data_copy.is_empty() {
-        let chunk: &[u8];
-        if DATA_MAX_SIZE < data.len() {
-            chunk = &data[..DATA_MAX_SIZE];
-            data_copy = &data_copy[DATA_MAX_SIZE..];
-        } else {
-            chunk = data_copy;
-            data_copy = &[0_u8; 0];
-        }
-        let sealed_frame = &mut [0_u8; TAG_SIZE + TOTAL_FRAME_SIZE];
-        encrypt(chunk, &send_state.cipher, &send_state.nonce, sealed_frame)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-        send_state.nonce.increment();
-        // end encryption
-
+    for chunk in data.chunks(DATA_MAX_SIZE) {
+        let sealed_frame = seal_frame(send_state, chunk)?;
         io_handler.write_all(&sealed_frame[..])?;
         n = n
             .checked_add(chunk.len())
@@ -558,6 +548,25 @@ fn encrypt_and_write<IoHandler: Write>(
     Ok(n)
 }
 
+/// Encrypts the chunk into a frame of `TAG_SIZE` + `TOTAL_FRAME_SIZE` bytes,
+/// and advances the sending nonce.
+fn seal_frame(
+    send_state: &mut SendState,
+    chunk: &[u8],
+) -> io::Result<[u8; TAG_SIZE + TOTAL_FRAME_SIZE]> {
+    let mut sealed_frame = [0_u8; TAG_SIZE + TOTAL_FRAME_SIZE];
+    encrypt(
+        chunk,
+        &send_state.cipher,
+        &send_state.nonce,
+        &mut sealed_frame,
+    )
+    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    send_state.nonce.increment();
+
+    Ok(sealed_frame)
+}
+
 /// Decrypt AEAD authenticated data
 fn decrypt(
     ciphertext: &[u8],
@@ -596,30 +605,29 @@ fn read_and_decrypt<IoHandler: Read>(
     recv_state: &mut ReceiveState,
     data: &mut [u8],
 ) -> io::Result<usize> {
-    if !recv_state.buffer.is_empty() {
-        let n = cmp::min(data.len(), recv_state.buffer.len());
-        data.copy_from_slice(&recv_state.buffer[..n]);
-        let mut leftover_portion = vec![
-            0;
-            recv_state
-                .buffer
-                .len()
-                .checked_sub(n)
-                .expect("leftover calculation failed")
-        ];
-        leftover_portion.clone_from_slice(&recv_state.buffer[n..]);
-        recv_state.buffer = leftover_portion;
-
-        return Ok(n);
+    if recv_state.buffer.is_empty() {
+        let mut sealed_frame = [0_u8; TAG_SIZE + TOTAL_FRAME_SIZE];
+        io_handler.read_exact(&mut sealed_frame)?;
+        recv_state.buffer = open_frame(recv_state, &sealed_frame)?;
     }
 
-    let mut sealed_frame = [0_u8; TAG_SIZE + TOTAL_FRAME_SIZE];
-    io_handler.read_exact(&mut sealed_frame)?;
+    let n = cmp::min(data.len(), recv_state.buffer.len());
+    data[..n].copy_from_slice(&recv_state.buffer[..n]);
+    recv_state.buffer.drain(..n);
+
+    Ok(n)
+}
 
+/// Decrypts a frame of `TAG_SIZE` + `TOTAL_FRAME_SIZE` bytes, advances the
+/// receiving nonce, and returns the chunk of data carried by the frame.
+fn open_frame(
+    recv_state: &mut ReceiveState,
+    sealed_frame: &[u8; TAG_SIZE + TOTAL_FRAME_SIZE],
+) -> io::Result<Vec<u8>> {
     // decrypt the frame
     let mut frame = [0_u8; TOTAL_FRAME_SIZE];
     let res = decrypt(
-        &sealed_frame,
+        sealed_frame,
         &recv_state.cipher,
         &recv_state.nonce,
         &mut frame,
@@ -649,9 +657,5 @@ fn read_and_decrypt<IoHandler: Read>(
                 .expect("chunk size addition overflow"))],
     );
 
-    let n = cmp::min(data.len(), chunk.len());
-    data[..n].copy_from_slice(&chunk[..n]);
-    recv_state.buffer.copy_from_slice(&chunk[n..]);
-
-    Ok(n)
+    Ok(chunk)
 }
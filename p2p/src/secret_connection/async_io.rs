@@ -0,0 +1,375 @@
+//! Asynchronous `SecretConnection`, on top of [`tokio`] I/O.
+
+use std::{
+    cmp, io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use x25519_dalek::PublicKey as EphemeralPublic;
+
+use super::{
+    open_frame, seal_frame, Handshake, Nonce, PublicKey, ReceiveState, SendState, Version,
+    DATA_MAX_SIZE, TAG_SIZE, TOTAL_FRAME_SIZE,
+};
+use crate::error::Error;
+
+const SEALED_FRAME_SIZE: usize = TAG_SIZE + TOTAL_FRAME_SIZE;
+
+// Asynchronous counterpart of `checked_io!`: fails if the connection was
+// terminated by a previous error, and terminates the connection if the given
+// operation fails.
+macro_rules! checked_poll {
+    ($term:expr, $f:expr) => {{
+        if $term.load(Ordering::SeqCst) {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "secret connection was terminated elsewhere by previous error",
+            )));
+        }
+        let result = { $f };
+        if matches!(result, Poll::Ready(Err(_))) {
+            $term.store(true, Ordering::SeqCst);
+        }
+        result
+    }};
+}
+
+/// Encrypted connection between peers in a Tendermint network, over
+/// asynchronous I/O.
+///
+/// The connection is established over the separate reading and writing halves
+/// of an underlying connection, such as those produced by
+/// [`tokio::net::TcpStream::into_split`] or [`tokio::io::split`]. It can in
+/// turn be split into its sending and receiving halves through
+/// [`AsyncSecretConnection::split`], which can then be used from separate
+/// tasks.
+///
+/// As with [`SecretConnection`](super::SecretConnection), a read or write
+/// failure on either half terminates the whole connection.
+pub struct AsyncSecretConnection<R, W> {
+    sender: AsyncSender<W>,
+    receiver: AsyncReceiver<R>,
+}
+
+impl<R, W> AsyncSecretConnection<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    /// Performs a handshake over the given halves of a connection, and
+    /// returns a new `AsyncSecretConnection`.
+    ///
+    /// # Errors
+    ///
+    /// * if sharing of the pubkey fails
+    /// * if sharing of the signature fails
+    /// * if receiving the signature fails
+    pub async fn new(
+        mut reader: R,
+        mut writer: W,
+        local_privkey: ed25519_consensus::SigningKey,
+        protocol_version: Version,
+    ) -> Result<Self, Error> {
+        // Start a handshake process.
+        let local_pubkey = local_privkey.verification_key();
+        let (mut h, local_eph_pubkey) = Handshake::new(local_privkey, protocol_version);
+
+        // Write local ephemeral pubkey and receive one too.
+        let remote_eph_pubkey = share_eph_pubkey(
+            &mut reader,
+            &mut writer,
+            &local_eph_pubkey,
+            protocol_version,
+        )
+        .await?;
+
+        // Compute a local signature (also recv_cipher & send_cipher)
+        let mut h = h.got_key(remote_eph_pubkey)?;
+
+        let mut send_state = SendState {
+            cipher: h.state.send_cipher.clone(),
+            nonce: Nonce::default(),
+        };
+        let mut recv_state = ReceiveState {
+            cipher: h.state.recv_cipher.clone(),
+            nonce: Nonce::default(),
+            buffer: vec![],
+        };
+
+        // Share each other's pubkey & challenge signature.
+        // NOTE: the data must be encrypted/decrypted using ciphers.
+        let buf = protocol_version.encode_auth_signature(&local_pubkey, &h.state.local_signature);
+        let response_len = protocol_version.auth_sig_msg_response_len();
+        let ((), mut remote_buf) = tokio::try_join!(
+            async {
+                for chunk in buf.chunks(DATA_MAX_SIZE) {
+                    writer
+                        .write_all(&seal_frame(&mut send_state, chunk)?)
+                        .await?;
+                }
+                writer.flush().await
+            },
+            async {
+                let mut remote_buf = vec![];
+                let mut sealed_frame = [0_u8; SEALED_FRAME_SIZE];
+                while remote_buf.len() < response_len {
+                    reader.read_exact(&mut sealed_frame).await?;
+                    remote_buf.extend(open_frame(&mut recv_state, &sealed_frame)?);
+                }
+                Ok::<_, io::Error>(remote_buf)
+            },
+        )?;
+        // Data sent right after the signature is kept for the first read.
+        recv_state.buffer = remote_buf.split_off(response_len);
+        let auth_sig_msg = protocol_version.decode_auth_signature(&remote_buf)?;
+
+        // Authenticate remote pubkey.
+        let remote_pubkey = h.got_signature(auth_sig_msg)?;
+
+        // All good!
+        let terminate = Arc::new(AtomicBool::new(false));
+        Ok(Self {
+            sender: AsyncSender {
+                io_handler: writer,
+                remote_pubkey,
+                state: send_state,
+                sealed_frame: [0_u8; SEALED_FRAME_SIZE],
+                pending: 0..0,
+                terminate: terminate.clone(),
+            },
+            receiver: AsyncReceiver {
+                io_handler: reader,
+                remote_pubkey,
+                state: recv_state,
+                sealed_frame: [0_u8; SEALED_FRAME_SIZE],
+                filled: 0,
+                terminate,
+            },
+        })
+    }
+}
+
+impl<R, W> AsyncSecretConnection<R, W> {
+    /// Returns the remote pubkey.
+    pub const fn remote_pubkey(&self) -> PublicKey {
+        self.sender.remote_pubkey
+    }
+
+    /// Splits the connection into its sending and receiving halves, which
+    /// can be used from separate tasks.
+    pub fn split(self) -> (AsyncSender<W>, AsyncReceiver<R>) {
+        (self.sender, self.receiver)
+    }
+}
+
+impl<R: AsyncRead + Unpin, W: Unpin> AsyncRead for AsyncSecretConnection<R, W> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().receiver).poll_read(cx, buf)
+    }
+}
+
+impl<R: Unpin, W: AsyncWrite + Unpin> AsyncWrite for AsyncSecretConnection<R, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().sender).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().sender).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().sender).poll_shutdown(cx)
+    }
+}
+
+/// The sending end of an [`AsyncSecretConnection`].
+///
+/// Each write encrypts up to [`DATA_MAX_SIZE`] bytes into a frame, which is
+/// written to the underlying connection by subsequent writes or flushes.
+pub struct AsyncSender<W> {
+    io_handler: W,
+    remote_pubkey: PublicKey,
+    state: SendState,
+    sealed_frame: [u8; SEALED_FRAME_SIZE],
+    /// The part of the sealed frame which remains to be written.
+    pending: std::ops::Range<usize>,
+    terminate: Arc<AtomicBool>,
+}
+
+impl<W> AsyncSender<W> {
+    /// Returns the remote pubkey.
+    pub const fn remote_pubkey(&self) -> PublicKey {
+        self.remote_pubkey
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncSender<W> {
+    /// Writes the pending part of the sealed frame.
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let n = ready!(Pin::new(&mut self.io_handler)
+                .poll_write(cx, &self.sealed_frame[self.pending.clone()]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.start = self.pending.start.saturating_add(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_encrypt_and_write(
+        &mut self,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_write_pending(cx))?;
+        if data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let chunk = &data[..cmp::min(data.len(), DATA_MAX_SIZE)];
+        self.sealed_frame = seal_frame(&mut self.state, chunk)?;
+        self.pending = 0..SEALED_FRAME_SIZE;
+        Poll::Ready(Ok(chunk.len()))
+    }
+
+    fn poll_flush_frames(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_pending(cx))?;
+        Pin::new(&mut self.io_handler).poll_flush(cx)
+    }
+
+    fn poll_flush_and_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_pending(cx))?;
+        Pin::new(&mut self.io_handler).poll_shutdown(cx)
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncSender<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        checked_poll!(this.terminate, this.poll_encrypt_and_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        checked_poll!(this.terminate, this.poll_flush_frames(cx))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        checked_poll!(this.terminate, this.poll_flush_and_shutdown(cx))
+    }
+}
+
+/// The receiving end of an [`AsyncSecretConnection`].
+pub struct AsyncReceiver<R> {
+    io_handler: R,
+    remote_pubkey: PublicKey,
+    state: ReceiveState,
+    sealed_frame: [u8; SEALED_FRAME_SIZE],
+    /// The size of the part of the sealed frame read so far.
+    filled: usize,
+    terminate: Arc<AtomicBool>,
+}
+
+impl<R> AsyncReceiver<R> {
+    /// Returns the remote pubkey.
+    pub const fn remote_pubkey(&self) -> PublicKey {
+        self.remote_pubkey
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncReceiver<R> {
+    /// Reads the next sealed frame. Returns `false` if the underlying
+    /// connection was closed cleanly before the frame.
+    fn poll_read_frame(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        while self.filled < SEALED_FRAME_SIZE {
+            let mut buf = ReadBuf::new(&mut self.sealed_frame[self.filled..]);
+            ready!(Pin::new(&mut self.io_handler).poll_read(cx, &mut buf))?;
+            let n = buf.filled().len();
+            if n == 0 {
+                return Poll::Ready(if self.filled == 0 {
+                    Ok(false)
+                } else {
+                    Err(io::ErrorKind::UnexpectedEof.into())
+                });
+            }
+            self.filled = self.filled.saturating_add(n);
+        }
+        self.filled = 0;
+        Poll::Ready(Ok(true))
+    }
+
+    fn poll_read_and_decrypt(
+        &mut self,
+        cx: &mut Context<'_>,
+        data: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.state.buffer.is_empty() {
+            if !ready!(self.poll_read_frame(cx))? {
+                return Poll::Ready(Ok(()));
+            }
+            self.state.buffer = open_frame(&mut self.state, &self.sealed_frame)?;
+        }
+        let n = cmp::min(data.remaining(), self.state.buffer.len());
+        data.put_slice(&self.state.buffer[..n]);
+        self.state.buffer.drain(..n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncReceiver<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        checked_poll!(this.terminate, this.poll_read_and_decrypt(cx, buf))
+    }
+}
+
+/// Returns `remote_eph_pubkey`
+async fn share_eph_pubkey<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    local_eph_pubkey: &EphemeralPublic,
+    protocol_version: Version,
+) -> Result<EphemeralPublic, Error>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    // Send our pubkey and receive theirs in tandem.
+    let buf = protocol_version.encode_initial_handshake(local_eph_pubkey);
+    let ((), remote_buf) = tokio::try_join!(
+        async {
+            writer.write_all(&buf).await?;
+            writer.flush().await
+        },
+        async {
+            let response_len = reader.read_u8().await?;
+            let mut buf = vec![0; usize::from(response_len)];
+            reader.read_exact(&mut buf).await?;
+            Ok(buf)
+        },
+    )?;
+    protocol_version.decode_initial_handshake(&remote_buf)
+}
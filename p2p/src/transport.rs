@@ -6,6 +6,11 @@ use std::net::{SocketAddr, ToSocketAddrs};
 use eyre::Result;
 use tendermint::{node, public_key::PublicKey};
 
+#[cfg(any(feature = "blocksync", feature = "pex"))]
+use crate::{error::Error, secret_connection};
+
+pub mod tcp;
+
 /// Information which resources to bind to and how to identify on the network.
 pub struct BindInfo<A>
 where
@@ -41,6 +46,8 @@ where
 pub enum StreamId {
     /// Stream to exchange message concerning Peer Exchange.
     Pex,
+    /// Stream to exchange blocks with peers catching up with the chain.
+    BlockSync,
 }
 
 /// Envelope to trace the original direction of an established connection.
@@ -51,6 +58,22 @@ pub enum Direction<Conn> {
     Outgoing(Conn),
 }
 
+impl<Conn> Direction<Conn> {
+    /// Returns a reference to the connection.
+    pub const fn inner(&self) -> &Conn {
+        match self {
+            Self::Incoming(conn) | Self::Outgoing(conn) => conn,
+        }
+    }
+
+    /// Unwraps the connection.
+    pub fn into_inner(self) -> Conn {
+        match self {
+            Self::Incoming(conn) | Self::Outgoing(conn) => conn,
+        }
+    }
+}
+
 /// Trait that describes the send end of a stream.
 pub trait StreamSend {
     /// Sends the message to the peer over the open stream. `msg` should be a valid and properly
@@ -61,7 +84,7 @@ pub trait StreamSend {
     /// * If the underlying I/O operations fail.
     /// * If the stream is closed.
     /// * If the peer is gone
-    fn send<B: AsRef<[u8]>>(msg: B) -> Result<()>;
+    fn send<B: AsRef<[u8]>>(&self, msg: B) -> Result<()>;
 }
 
 /// Trait which describes the core concept of a connection between two peers established by
@@ -102,6 +125,42 @@ pub trait Connection: Send {
     fn remote_addr(&self) -> SocketAddr;
 }
 
+impl<Conn> Connection for Direction<Conn>
+where
+    Conn: Connection,
+{
+    type Error = Conn::Error;
+    type StreamRead = Conn::StreamRead;
+    type StreamSend = Conn::StreamSend;
+
+    fn advertised_addrs(&self) -> Vec<SocketAddr> {
+        self.inner().advertised_addrs()
+    }
+
+    fn close(&self) -> Result<()> {
+        self.inner().close()
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.inner().local_addr()
+    }
+
+    fn open_bidirectional(
+        &self,
+        stream_id: StreamId,
+    ) -> Result<(Self::StreamRead, Self::StreamSend), Self::Error> {
+        self.inner().open_bidirectional(stream_id)
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.inner().public_key()
+    }
+
+    fn remote_addr(&self) -> SocketAddr {
+        self.inner().remote_addr()
+    }
+}
+
 /// Local handle on a resource which allows connecting to remote peers.
 pub trait Endpoint<A>: Send
 where
@@ -141,3 +200,18 @@ where
     /// * If resource allocation fails for lack of privileges or being not available.
     fn bind(self, bind_info: BindInfo<A>) -> Result<(Self::Endpoint, Self::Incoming)>;
 }
+
+/// Derives the node identifier of a peer from its public key.
+#[cfg(any(feature = "blocksync", feature = "pex"))]
+pub(crate) fn peer_id(public_key: PublicKey) -> Option<node::Id> {
+    let public_key = public_key.ed25519()?;
+    secret_connection::PublicKey::from_raw_ed25519(public_key.as_bytes())
+        .ok()
+        .map(secret_connection::PublicKey::peer_id)
+}
+
+#[cfg(any(feature = "blocksync", feature = "pex"))]
+#[allow(clippy::needless_pass_by_value)]
+pub(crate) fn transport_error(e: eyre::Report) -> Error {
+    Error::transport(e.to_string())
+}
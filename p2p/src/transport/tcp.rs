@@ -0,0 +1,462 @@
+//! Transport over TCP.
+//!
+//! Every connection, incoming or outgoing, goes through the same steps: a
+//! [`SecretConnection`] handshake authenticates the remote peer, the peers
+//! exchange their [`node::Info`], and an [`MConnection`] then multiplexes the
+//! streams over the secret connection.
+
+use std::{
+    io,
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::Duration,
+};
+
+use eyre::{Report, Result};
+use tendermint::{node, public_key::PublicKey};
+#[cfg(feature = "tendermint-config")]
+use tendermint_config::P2PConfig;
+
+use super::{
+    BindInfo, ConnectInfo, Connection, Direction, Endpoint, StreamId, StreamSend, Transport,
+};
+use crate::{
+    error::Error,
+    mconnection::{
+        ChannelDescriptor, ChannelId, ChannelReceiver, ChannelSender, ConnectionStatus,
+        MConnConfig, MConnection,
+    },
+    node_info,
+    secret_connection::{self, SecretConnection, Version},
+};
+
+/// Identifier of the channel carrying [`StreamId::Pex`].
+pub const PEX_CHANNEL: ChannelId = 0x00;
+
+/// Identifier of the channel carrying [`StreamId::BlockSync`].
+pub const BLOCKSYNC_CHANNEL: ChannelId = 0x40;
+
+/// Default time to wait for a connection to a remote peer to be established.
+pub const DEFAULT_DIAL_TIMEOUT: Duration = Duration::new(3, 0);
+
+/// Default time to wait for the handshake and node information exchange to
+/// complete.
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::new(20, 0);
+
+/// Time between two polls of a listener with no pending connection, and
+/// hence the time it may take for the listeners to close once the endpoint
+/// is dropped.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Capacity of the send queue of the PEX channel.
+const PEX_SEND_QUEUE_CAPACITY: usize = 10;
+
+/// Capacity of the send queue of the blocksync channel.
+const BLOCKSYNC_SEND_QUEUE_CAPACITY: usize = 1000;
+
+/// Initial capacity of the receive buffer of the blocksync channel.
+const BLOCKSYNC_RECV_BUFFER_CAPACITY: usize = 50 * 4096;
+
+/// Maximum size of a blocksync message: a `BlockResponse` carrying a block
+/// of the maximum size.
+const BLOCKSYNC_RECV_MESSAGE_CAPACITY: usize = 104_857_600 + 5;
+
+/// Configuration of a [`TcpTransport`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TcpConfig {
+    /// Time to wait for a connection to a remote peer to be established.
+    pub dial_timeout: Duration,
+    /// Time to wait for the secret connection handshake and node information
+    /// exchange to complete.
+    pub handshake_timeout: Duration,
+    /// Configuration of the multiplexed connections.
+    pub mconn: MConnConfig,
+}
+
+impl Default for TcpConfig {
+    fn default() -> Self {
+        Self {
+            dial_timeout: DEFAULT_DIAL_TIMEOUT,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            mconn: MConnConfig::default(),
+        }
+    }
+}
+
+#[cfg(feature = "tendermint-config")]
+impl From<&P2PConfig> for TcpConfig {
+    fn from(config: &P2PConfig) -> Self {
+        Self {
+            dial_timeout: *config.dial_timeout,
+            handshake_timeout: *config.handshake_timeout,
+            mconn: MConnConfig::from(config),
+        }
+    }
+}
+
+/// Transport establishing connections over TCP.
+///
+/// The node identifier and listening address advertised to remote peers
+/// are derived from the private key, and from the addresses given to
+/// [`Transport::bind`] respectively.
+pub struct TcpTransport {
+    private_key: ed25519_consensus::SigningKey,
+    node_info: node::Info,
+    config: TcpConfig,
+}
+
+impl TcpTransport {
+    /// Creates a transport identified by the given private key, which
+    /// advertises the given node information to remote peers.
+    #[must_use]
+    pub const fn new(
+        private_key: ed25519_consensus::SigningKey,
+        node_info: node::Info,
+        config: TcpConfig,
+    ) -> Self {
+        Self {
+            private_key,
+            node_info,
+            config,
+        }
+    }
+}
+
+impl<A> Transport<A> for TcpTransport
+where
+    A: ToSocketAddrs,
+{
+    type Connection = Direction<TcpConnection>;
+    type Endpoint = TcpEndpoint;
+    type Incoming = TcpIncoming;
+
+    fn bind(self, bind_info: BindInfo<A>) -> Result<(Self::Endpoint, Self::Incoming)> {
+        if Some(bind_info.public_key) != public_key(&self.private_key.verification_key()) {
+            return Err(Report::msg(Error::invalid_key()));
+        }
+
+        let mut node_info = self.node_info;
+        node_info.id = secret_connection::PublicKey::from(&self.private_key).peer_id();
+        if let Some(addr) = bind_info.advertise_addrs.to_socket_addrs()?.next() {
+            node_info.listen_addr = node::info::ListenAddress::new(format!("tcp://{addr}"));
+        }
+        let local = Arc::new(Local {
+            private_key: self.private_key,
+            node_info,
+            config: self.config,
+        });
+
+        let listeners = bind_info
+            .bind_addrs
+            .to_socket_addrs()?
+            .map(|addr| {
+                // Listeners are polled, such that they notice when closed.
+                let listener = TcpListener::bind(addr)?;
+                listener.set_nonblocking(true)?;
+                Ok(listener)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let listen_addrs = listeners
+            .iter()
+            .map(TcpListener::local_addr)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let (incoming_tx, incoming_rx) = flume::unbounded();
+        for listener in listeners {
+            let local = local.clone();
+            let closed = closed.clone();
+            let incoming_tx = incoming_tx.clone();
+            thread::spawn(move || accept(&listener, &local, &closed, &incoming_tx));
+        }
+
+        Ok((
+            TcpEndpoint {
+                local,
+                listen_addrs,
+                closed,
+            },
+            TcpIncoming {
+                connections: incoming_rx,
+            },
+        ))
+    }
+}
+
+/// Identity and configuration of the local node.
+struct Local {
+    private_key: ed25519_consensus::SigningKey,
+    node_info: node::Info,
+    config: TcpConfig,
+}
+
+/// Accepts connections until the endpoint is dropped, or the incoming
+/// connections are no longer consumed.
+fn accept(
+    listener: &TcpListener,
+    local: &Arc<Local>,
+    closed: &AtomicBool,
+    incoming: &flume::Sender<Result<Direction<TcpConnection>>>,
+) {
+    while !closed.load(Ordering::SeqCst) && !incoming.is_disconnected() {
+        // Accepted streams may inherit the non-blocking mode of the listener.
+        match listener
+            .accept()
+            .and_then(|(stream, _)| stream.set_nonblocking(false).map(|()| stream))
+        {
+            Ok(stream) => {
+                let local = local.clone();
+                let incoming = incoming.clone();
+                thread::spawn(move || {
+                    let conn = TcpConnection::new(stream, &local).map(Direction::Incoming);
+                    let _ = incoming.send(conn.map_err(Report::msg));
+                });
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+            },
+            Err(e) => {
+                let _ = incoming.send(Err(Report::msg(Error::io(e))));
+            },
+        }
+    }
+}
+
+/// Handle on a bound [`TcpTransport`], to connect to remote peers.
+///
+/// Dropping the endpoint closes the listeners, and ends the stream of
+/// incoming connections.
+pub struct TcpEndpoint {
+    local: Arc<Local>,
+    listen_addrs: Vec<SocketAddr>,
+    closed: Arc<AtomicBool>,
+}
+
+impl<A> Endpoint<A> for TcpEndpoint
+where
+    A: ToSocketAddrs,
+{
+    type Connection = Direction<TcpConnection>;
+
+    fn connect(&self, info: ConnectInfo<A>) -> Result<Self::Connection> {
+        // Failing addresses are skipped, and the last failure reported if
+        // none of them succeeds.
+        let mut last_error = None;
+        for addr in info.addrs.to_socket_addrs()? {
+            let stream = match TcpStream::connect_timeout(&addr, self.local.config.dial_timeout) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    last_error = Some(Error::io(e));
+                    continue;
+                },
+            };
+            let conn = match TcpConnection::new(stream, &self.local) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    last_error = Some(e);
+                    continue;
+                },
+            };
+            let authenticated = conn.node_info.id;
+            if authenticated != info.id {
+                let _ = conn.close();
+                last_error = Some(Error::unexpected_peer_id(info.id, authenticated));
+                continue;
+            }
+            return Ok(Direction::Outgoing(conn));
+        }
+        Err(Report::msg(last_error.unwrap_or_else(Error::no_address)))
+    }
+
+    fn listen_addrs(&self) -> Vec<SocketAddr> {
+        self.listen_addrs.clone()
+    }
+}
+
+impl Drop for TcpEndpoint {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Stream of the connections accepted by a [`TcpEndpoint`].
+///
+/// The stream ends once the endpoint is dropped.
+pub struct TcpIncoming {
+    connections: flume::Receiver<Result<Direction<TcpConnection>>>,
+}
+
+impl Iterator for TcpIncoming {
+    type Item = Result<Direction<TcpConnection>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.connections.recv().ok()
+    }
+}
+
+/// Connection to a remote peer, over TCP.
+pub struct TcpConnection {
+    stream: TcpStream,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    public_key: PublicKey,
+    node_info: node::Info,
+    mconn: Mutex<Option<MConnection>>,
+}
+
+impl TcpConnection {
+    fn new(stream: TcpStream, local: &Local) -> Result<Self, Error> {
+        let local_addr = stream.local_addr()?;
+        let remote_addr = stream.peer_addr()?;
+        let timeout = Some(local.config.handshake_timeout);
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+
+        let mut conn = SecretConnection::new(
+            stream.try_clone()?,
+            local.private_key.clone(),
+            Version::V0_34,
+        )?;
+        let node_info = node_info::exchange(&mut conn, &local.node_info)?;
+        let remote_pubkey = conn.remote_pubkey();
+        let public_key = remote_pubkey
+            .ed25519()
+            .and_then(|pk| public_key(&pk))
+            .ok_or_else(Error::invalid_key)?;
+
+        stream.set_read_timeout(None)?;
+        stream.set_write_timeout(None)?;
+        let pex = ChannelDescriptor {
+            send_queue_capacity: PEX_SEND_QUEUE_CAPACITY,
+            ..ChannelDescriptor::new(PEX_CHANNEL, 1)
+        };
+        let blocksync = ChannelDescriptor {
+            send_queue_capacity: BLOCKSYNC_SEND_QUEUE_CAPACITY,
+            recv_buffer_capacity: BLOCKSYNC_RECV_BUFFER_CAPACITY,
+            recv_message_capacity: BLOCKSYNC_RECV_MESSAGE_CAPACITY,
+            ..ChannelDescriptor::new(BLOCKSYNC_CHANNEL, 5)
+        };
+        let mconn =
+            MConnection::from_secret_connection(conn, &[pex, blocksync], local.config.mconn)?;
+
+        Ok(Self {
+            stream,
+            local_addr,
+            remote_addr,
+            public_key,
+            node_info,
+            mconn: Mutex::new(Some(mconn)),
+        })
+    }
+
+    /// Returns the information the remote peer sent about itself.
+    #[must_use]
+    pub const fn node_info(&self) -> &node::Info {
+        &self.node_info
+    }
+
+    /// Returns the statistics of the data sent and received over the
+    /// connection, unless it is closed.
+    #[must_use]
+    pub fn status(&self) -> Option<ConnectionStatus> {
+        self.mconn().as_ref().map(MConnection::status)
+    }
+
+    fn mconn(&self) -> MutexGuard<'_, Option<MConnection>> {
+        // The connection is only ever taken out of the mutex.
+        self.mconn
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl Connection for TcpConnection {
+    type Error = Error;
+    type StreamRead = TcpStreamRead;
+    type StreamSend = TcpStreamSend;
+
+    fn advertised_addrs(&self) -> Vec<SocketAddr> {
+        let listen_addr = self.node_info.listen_addr.as_str();
+        let listen_addr = listen_addr.strip_prefix("tcp://").unwrap_or(listen_addr);
+        listen_addr
+            .to_socket_addrs()
+            .map(Iterator::collect)
+            .unwrap_or_default()
+    }
+
+    fn close(&self) -> Result<()> {
+        let result = self.mconn().take().map_or(Ok(()), MConnection::stop);
+        // Also stop receiving.
+        let _ = self.stream.shutdown(Shutdown::Both);
+        result.map_err(Report::msg)
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    fn open_bidirectional(
+        &self,
+        stream_id: StreamId,
+    ) -> Result<(Self::StreamRead, Self::StreamSend), Self::Error> {
+        let channel_id = match stream_id {
+            StreamId::Pex => PEX_CHANNEL,
+            StreamId::BlockSync => BLOCKSYNC_CHANNEL,
+        };
+        let (receiver, sender) = self
+            .mconn()
+            .as_ref()
+            .map(|mconn| (mconn.receiver(channel_id), mconn.sender(channel_id)))
+            .ok_or_else(Error::connection_closed)?;
+        match (receiver, sender) {
+            (Some(receiver), Some(sender)) => {
+                Ok((TcpStreamRead { receiver }, TcpStreamSend { sender }))
+            },
+            _ => Err(Error::unknown_channel(channel_id.into())),
+        }
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+}
+
+/// Read end of a stream carried by a [`TcpConnection`].
+///
+/// Each item is a message sent by the remote peer. The stream ends when the
+/// connection is closed.
+pub struct TcpStreamRead {
+    receiver: ChannelReceiver,
+}
+
+impl Iterator for TcpStreamRead {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.next().map(Ok)
+    }
+}
+
+/// Send end of a stream carried by a [`TcpConnection`].
+pub struct TcpStreamSend {
+    sender: ChannelSender,
+}
+
+impl StreamSend for TcpStreamSend {
+    fn send<B: AsRef<[u8]>>(&self, msg: B) -> Result<()> {
+        self.sender.send(msg.as_ref().to_vec()).map_err(Report::msg)
+    }
+}
+
+/// Converts a secret connection key into a `tendermint` one.
+fn public_key(key: &ed25519_consensus::VerificationKey) -> Option<PublicKey> {
+    PublicKey::from_raw_ed25519(key.as_bytes())
+}
@@ -1,15 +1,18 @@
 /// NewRoundStep is sent for every step taken in the ConsensusState.
 /// For every height/round/step transition
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NewRoundStep {
     #[prost(int64, tag = "1")]
+    #[serde(with = "crate::serializers::from_str")]
     pub height: i64,
     #[prost(int32, tag = "2")]
     pub round: i32,
     #[prost(uint32, tag = "3")]
     pub step: u32,
     #[prost(int64, tag = "4")]
+    #[serde(with = "crate::serializers::from_str")]
     pub seconds_since_start_time: i64,
     #[prost(int32, tag = "5")]
     pub last_commit_round: i32,
@@ -17,10 +20,12 @@ pub struct NewRoundStep {
 /// NewValidBlock is sent when a validator observes a valid block B in some round r,
 /// i.e., there is a Proposal for block B and 2/3+ prevotes for the block B in the round r.
 /// In case the block is also committed, then IsCommit flag is set to true.
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NewValidBlock {
     #[prost(int64, tag = "1")]
+    #[serde(with = "crate::serializers::from_str")]
     pub height: i64,
     #[prost(int32, tag = "2")]
     pub round: i32,
@@ -32,6 +37,7 @@ pub struct NewValidBlock {
     pub is_commit: bool,
 }
 /// Proposal is sent when a new block is proposed.
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Proposal {
@@ -39,10 +45,12 @@ pub struct Proposal {
     pub proposal: ::core::option::Option<super::types::Proposal>,
 }
 /// ProposalPOL is sent when a previous proposal is re-proposed.
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ProposalPol {
     #[prost(int64, tag = "1")]
+    #[serde(with = "crate::serializers::from_str")]
     pub height: i64,
     #[prost(int32, tag = "2")]
     pub proposal_pol_round: i32,
@@ -50,10 +58,12 @@ pub struct ProposalPol {
     pub proposal_pol: ::core::option::Option<super::libs::bits::BitArray>,
 }
 /// BlockPart is sent when gossipping a piece of the proposed block.
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BlockPart {
     #[prost(int64, tag = "1")]
+    #[serde(with = "crate::serializers::from_str")]
     pub height: i64,
     #[prost(int32, tag = "2")]
     pub round: i32,
@@ -61,6 +71,7 @@ pub struct BlockPart {
     pub part: ::core::option::Option<super::types::Part>,
 }
 /// Vote is sent when voting for a proposal (or lack thereof).
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Vote {
@@ -68,10 +79,12 @@ pub struct Vote {
     pub vote: ::core::option::Option<super::types::Vote>,
 }
 /// HasVote is sent to indicate that a particular vote has been received.
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HasVote {
     #[prost(int64, tag = "1")]
+    #[serde(with = "crate::serializers::from_str")]
     pub height: i64,
     #[prost(int32, tag = "2")]
     pub round: i32,
@@ -81,10 +94,12 @@ pub struct HasVote {
     pub index: i32,
 }
 /// VoteSetMaj23 is sent to indicate that a given BlockID has seen +2/3 votes.
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct VoteSetMaj23 {
     #[prost(int64, tag = "1")]
+    #[serde(with = "crate::serializers::from_str")]
     pub height: i64,
     #[prost(int32, tag = "2")]
     pub round: i32,
@@ -94,10 +109,12 @@ pub struct VoteSetMaj23 {
     pub block_id: ::core::option::Option<super::types::BlockId>,
 }
 /// VoteSetBits is sent to communicate the bit-array of votes seen for the BlockID.
+#[derive(::serde::Deserialize, ::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct VoteSetBits {
     #[prost(int64, tag = "1")]
+    #[serde(with = "crate::serializers::from_str")]
     pub height: i64,
     #[prost(int32, tag = "2")]
     pub round: i32,
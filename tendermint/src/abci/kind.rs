@@ -7,6 +7,7 @@
 ///
 /// This enum breaks out the `Flush` method as a distinct category, since it is
 /// used to control the execution of other methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum MethodKind {
     /// A consensus method, driven by the consensus protocol and responsible for
     /// block execution.
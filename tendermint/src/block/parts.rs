@@ -1,9 +1,15 @@
 //! Block parts
 
 use serde::{Deserialize, Serialize};
-use tendermint_proto::v0_37::types::PartSetHeader as RawPartSetHeader;
+use tendermint_proto::v0_37::types::{Part as RawPart, PartSetHeader as RawPartSetHeader};
 
-use crate::{error::Error, prelude::*, Hash};
+use crate::{error::Error, merkle, prelude::*, Hash};
+
+/// Maximum size of a block part, in bytes
+pub const BLOCK_PART_SIZE_BYTES: usize = 65536;
+
+/// Maximum number of aunts in the Merkle proof of a block part
+pub const MAX_AUNTS: usize = 100;
 
 /// Block parts header
 #[derive(
@@ -19,9 +25,24 @@ pub struct Header {
     pub hash: Hash,
 }
 
+/// Block part, along with the proof of its inclusion in the block
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawPart", into = "RawPart")]
+pub struct Part {
+    /// Index of this part in the block
+    pub index: u32,
+
+    /// Content of this part
+    pub bytes: Vec<u8>,
+
+    /// Merkle proof of this part, against the hash of the part set header
+    pub proof: merkle::Proof,
+}
+
 tendermint_pb_modules! {
     use pb::types::{
-        CanonicalPartSetHeader as RawCanonicalPartSetHeader, PartSetHeader as RawPartSetHeader,
+        CanonicalPartSetHeader as RawCanonicalPartSetHeader, Part as RawPart,
+        PartSetHeader as RawPartSetHeader,
     };
     use crate::{
         error::Error,
@@ -29,7 +50,7 @@ tendermint_pb_modules! {
         prelude::*,
         Hash,
     };
-    use super::Header;
+    use super::{Header, Part, BLOCK_PART_SIZE_BYTES, MAX_AUNTS};
 
     impl Protobuf<RawPartSetHeader> for Header {}
 
@@ -78,6 +99,57 @@ tendermint_pb_modules! {
             }
         }
     }
+
+    impl Protobuf<RawPart> for Part {}
+
+    impl TryFrom<RawPart> for Part {
+        type Error = Error;
+
+        fn try_from(value: RawPart) -> Result<Self, Self::Error> {
+            if value.bytes.len() > BLOCK_PART_SIZE_BYTES {
+                return Err(Error::invalid_block_part(format!(
+                    "{} bytes, more than the maximum of {}",
+                    value.bytes.len(),
+                    BLOCK_PART_SIZE_BYTES
+                )));
+            }
+            let proof: crate::merkle::Proof = value
+                .proof
+                .ok_or_else(|| Error::invalid_block_part("missing proof".to_string()))?
+                .try_into()?;
+            if proof.index != u64::from(value.index) {
+                return Err(Error::invalid_block_part(format!(
+                    "proof index {} does not match part index {}",
+                    proof.index, value.index
+                )));
+            }
+            if proof.leaf_hash == Hash::None || proof.aunts.contains(&Hash::None) {
+                return Err(Error::invalid_block_part("empty hash in proof".to_string()));
+            }
+            if proof.aunts.len() > MAX_AUNTS {
+                return Err(Error::invalid_block_part(format!(
+                    "{} aunts in proof, more than the maximum of {}",
+                    proof.aunts.len(),
+                    MAX_AUNTS
+                )));
+            }
+            Ok(Self {
+                index: value.index,
+                bytes: value.bytes,
+                proof,
+            })
+        }
+    }
+
+    impl From<Part> for RawPart {
+        fn from(value: Part) -> Self {
+            RawPart {
+                index: value.index,
+                bytes: value.bytes,
+                proof: Some(value.proof.into()),
+            }
+        }
+    }
 }
 
 impl Header {
@@ -33,12 +33,32 @@ pub struct Channel {
     pub recently_sent: u64,
 }
 
-/// Channel collections
+/// Channel collections, as the identifiers of the channels a node supports
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, Default)]
-pub struct Channels(String);
+pub struct Channels(#[serde(with = "serializers::bytes::hexstring")] Vec<u8>);
+
+impl Channels {
+    /// Construct `Channels` from channel identifiers
+    pub fn new(ids: Vec<u8>) -> Channels {
+        Channels(ids)
+    }
+
+    /// Borrow the channel identifiers
+    pub fn ids(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Whether the given channel is among the channels
+    pub fn contains(&self, id: u8) -> bool {
+        self.0.contains(&id)
+    }
+}
 
 impl Display for Channels {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        for id in &self.0 {
+            write!(f, "{id:02X}")?;
+        }
+        Ok(())
     }
 }
@@ -1,5 +1,6 @@
 //! Tendermint consensus
 
+pub mod message;
 pub mod params;
 pub mod state;
 
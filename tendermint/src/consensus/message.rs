@@ -0,0 +1,882 @@
+//! Messages of the consensus reactor
+//!
+//! Domain types of the messages gossiped by validators and full nodes on the
+//! state, data, vote and vote set bits channels of the consensus reactor.
+//! They are validated when converted from their Protobuf counterparts, like
+//! Tendermint Core does upon receipt.
+
+mod bit_array;
+
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+use tendermint_proto::v0_37::consensus::{
+    BlockPart as RawBlockPart, HasVote as RawHasVote, NewRoundStep as RawNewRoundStep,
+    NewValidBlock as RawNewValidBlock, Proposal as RawProposal, ProposalPol as RawProposalPol,
+    Vote as RawVote, VoteSetBits as RawVoteSetBits, VoteSetMaj23 as RawVoteSetMaj23,
+};
+
+pub use self::bit_array::BitArray;
+use crate::{block, error::Error, prelude::*, proposal, vote, Hash};
+
+/// Maximum number of parts of a block: the maximum size of a block, 100 MiB,
+/// divided by the size of a part, plus one
+pub const MAX_BLOCK_PARTS_COUNT: u32 = 1601;
+
+/// Maximum number of votes in a vote set, which bounds the size of the bit
+/// arrays of votes
+pub const MAX_VOTES_COUNT: usize = 10000;
+
+/// Step of a consensus round
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(try_from = "u32", into = "u32")]
+#[repr(u8)]
+pub enum RoundStep {
+    /// Waiting for the commit timeout of the previous height to elapse
+    NewHeight = 1,
+    /// Setting up a new round
+    NewRound = 2,
+    /// Waiting for the proposal
+    Propose = 3,
+    /// Prevoting
+    Prevote = 4,
+    /// Waiting for more prevotes, after +2/3 of any prevotes
+    PrevoteWait = 5,
+    /// Precommitting
+    Precommit = 6,
+    /// Waiting for more precommits, after +2/3 of any precommits
+    PrecommitWait = 7,
+    /// Committing the block, after +2/3 of precommits for it
+    Commit = 8,
+}
+
+impl TryFrom<u32> for RoundStep {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::NewHeight),
+            2 => Ok(Self::NewRound),
+            3 => Ok(Self::Propose),
+            4 => Ok(Self::Prevote),
+            5 => Ok(Self::PrevoteWait),
+            6 => Ok(Self::Precommit),
+            7 => Ok(Self::PrecommitWait),
+            8 => Ok(Self::Commit),
+            _ => Err(Error::invalid_round_step(value)),
+        }
+    }
+}
+
+impl From<RoundStep> for u32 {
+    fn from(value: RoundStep) -> Self {
+        value as u32
+    }
+}
+
+impl fmt::Display for RoundStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::NewHeight => "RoundStepNewHeight",
+            Self::NewRound => "RoundStepNewRound",
+            Self::Propose => "RoundStepPropose",
+            Self::Prevote => "RoundStepPrevote",
+            Self::PrevoteWait => "RoundStepPrevoteWait",
+            Self::Precommit => "RoundStepPrecommit",
+            Self::PrecommitWait => "RoundStepPrecommitWait",
+            Self::Commit => "RoundStepCommit",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Sent for every step taken in a round, and when a peer is added
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawNewRoundStep", into = "RawNewRoundStep")]
+pub struct NewRoundStep {
+    /// Height of the sender
+    pub height: block::Height,
+    /// Round of the sender
+    pub round: block::Round,
+    /// Step of the sender
+    pub step: RoundStep,
+    /// Seconds since the start of the height, which may be negative
+    pub seconds_since_start_time: i64,
+    /// Round of the last commit, or `None` at the initial height
+    pub last_commit_round: Option<block::Round>,
+}
+
+impl NewRoundStep {
+    /// Check the height and the round of the last commit against the initial
+    /// height of the chain
+    pub fn validate_height(&self, initial_height: block::Height) -> Result<(), Error> {
+        if self.height < initial_height {
+            return Err(Error::invalid_consensus_message(format!(
+                "height {} lower than the initial height {}",
+                self.height, initial_height
+            )));
+        }
+        match self.last_commit_round {
+            Some(round) if self.height == initial_height => Err(Error::invalid_consensus_message(
+                format!("last commit round {round} at the initial height {initial_height}"),
+            )),
+            None if self.height > initial_height => Err(Error::invalid_consensus_message(
+                "missing last commit round after the initial height".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Sent when a validator observes a valid block in the current round, that
+/// is a block with +2/3 of prevotes, or when it commits a block
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawNewValidBlock", into = "RawNewValidBlock")]
+pub struct NewValidBlock {
+    /// Height of the block
+    pub height: block::Height,
+    /// Round in which the block is valid
+    pub round: block::Round,
+    /// Header of the parts of the block
+    pub block_part_set_header: block::parts::Header,
+    /// Parts of the block the sender has
+    pub block_parts: BitArray,
+    /// Whether the block is committed
+    pub is_commit: bool,
+}
+
+impl NewValidBlock {
+    fn validate(&self) -> Result<(), Error> {
+        let total = self.block_part_set_header.total;
+        if self.block_parts.is_empty() {
+            return Err(Error::invalid_consensus_message(
+                "empty bit array of block parts".to_string(),
+            ));
+        }
+        if self.block_parts.len() != total as usize {
+            return Err(Error::invalid_consensus_message(format!(
+                "bit array of {} block parts, for {} parts in the header",
+                self.block_parts.len(),
+                total
+            )));
+        }
+        if total > MAX_BLOCK_PARTS_COUNT {
+            return Err(Error::invalid_consensus_message(format!(
+                "{total} block parts, more than the maximum of {MAX_BLOCK_PARTS_COUNT}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Proposal of a block for the current round
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawProposal", into = "RawProposal")]
+pub struct Proposal {
+    /// The signed proposal
+    pub proposal: proposal::Proposal,
+}
+
+impl Proposal {
+    fn validate(&self) -> Result<(), Error> {
+        let proposal = &self.proposal;
+        if let Some(pol_round) = proposal.pol_round {
+            if pol_round >= proposal.round {
+                return Err(Error::invalid_consensus_message(format!(
+                    "POL round {} not lower than the round {}",
+                    pol_round, proposal.round
+                )));
+            }
+        }
+        if !proposal.block_id.as_ref().is_some_and(is_complete) {
+            return Err(Error::invalid_consensus_message(
+                "proposal for an incomplete block ID".to_string(),
+            ));
+        }
+        if proposal.signature.is_none() {
+            return Err(Error::invalid_consensus_message(
+                "unsigned proposal".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Prevotes for the proof-of-lock round of a proposal
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawProposalPol", into = "RawProposalPol")]
+pub struct ProposalPol {
+    /// Height of the proposal
+    pub height: block::Height,
+    /// Proof-of-lock round of the proposal
+    pub proposal_pol_round: block::Round,
+    /// Validators whose prevotes the sender has for that round
+    pub proposal_pol: BitArray,
+}
+
+impl ProposalPol {
+    fn validate(&self) -> Result<(), Error> {
+        if self.proposal_pol.is_empty() {
+            return Err(Error::invalid_consensus_message(
+                "empty bit array of POL prevotes".to_string(),
+            ));
+        }
+        validate_votes_count(&self.proposal_pol)
+    }
+}
+
+/// Part of the block proposed in a round
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawBlockPart", into = "RawBlockPart")]
+pub struct BlockPart {
+    /// Height of the block
+    pub height: block::Height,
+    /// Round of the proposal
+    pub round: block::Round,
+    /// The part of the block
+    pub part: block::parts::Part,
+}
+
+/// Prevote or precommit of a validator
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawVote", into = "RawVote")]
+pub struct Vote {
+    /// The signed vote
+    pub vote: vote::Vote,
+}
+
+impl Vote {
+    fn validate(&self) -> Result<(), Error> {
+        if !self.vote.block_id.as_ref().is_none_or(is_complete) {
+            return Err(Error::invalid_consensus_message(
+                "vote for an incomplete block ID".to_string(),
+            ));
+        }
+        if self.vote.signature.is_none() {
+            return Err(Error::invalid_consensus_message(
+                "unsigned vote".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Sent to tell peers that the sender has a vote
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawHasVote", into = "RawHasVote")]
+pub struct HasVote {
+    /// Height of the vote
+    pub height: block::Height,
+    /// Round of the vote
+    pub round: block::Round,
+    /// Type of the vote
+    pub vote_type: vote::Type,
+    /// Index of the validator who cast the vote
+    pub index: vote::ValidatorIndex,
+}
+
+/// Sent to tell peers that the sender has +2/3 of the votes for a block
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawVoteSetMaj23", into = "RawVoteSetMaj23")]
+pub struct VoteSetMaj23 {
+    /// Height of the votes
+    pub height: block::Height,
+    /// Round of the votes
+    pub round: block::Round,
+    /// Type of the votes
+    pub vote_type: vote::Type,
+    /// Block voted for, or `None` for nil
+    pub block_id: Option<block::Id>,
+}
+
+/// Answer to a [`VoteSetMaj23`], with the votes for the block the sender has
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawVoteSetBits", into = "RawVoteSetBits")]
+pub struct VoteSetBits {
+    /// Height of the votes
+    pub height: block::Height,
+    /// Round of the votes
+    pub round: block::Round,
+    /// Type of the votes
+    pub vote_type: vote::Type,
+    /// Block voted for, or `None` for nil
+    pub block_id: Option<block::Id>,
+    /// Validators whose votes the sender has
+    pub votes: BitArray,
+}
+
+/// Message of the consensus reactor
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value")]
+#[allow(clippy::large_enum_variant)]
+pub enum Message {
+    /// See [`NewRoundStep`]
+    NewRoundStep(NewRoundStep),
+    /// See [`NewValidBlock`]
+    NewValidBlock(NewValidBlock),
+    /// See [`Proposal`]
+    Proposal(Proposal),
+    /// See [`ProposalPol`]
+    ProposalPol(ProposalPol),
+    /// See [`BlockPart`]
+    BlockPart(BlockPart),
+    /// See [`Vote`]
+    Vote(Vote),
+    /// See [`HasVote`]
+    HasVote(HasVote),
+    /// See [`VoteSetMaj23`]
+    VoteSetMaj23(VoteSetMaj23),
+    /// See [`VoteSetBits`]
+    VoteSetBits(VoteSetBits),
+}
+
+/// Whether the block ID has both a hash and a part set header
+fn is_complete(block_id: &block::Id) -> bool {
+    block_id.hash != Hash::None
+        && block_id.part_set_header.total > 0
+        && block_id.part_set_header.hash != Hash::None
+}
+
+fn validate_votes_count(votes: &BitArray) -> Result<(), Error> {
+    if votes.len() > MAX_VOTES_COUNT {
+        return Err(Error::invalid_consensus_message(format!(
+            "bit array of {} votes, more than the maximum of {}",
+            votes.len(),
+            MAX_VOTES_COUNT
+        )));
+    }
+    Ok(())
+}
+
+/// Block ID of a vote set, which is the zero one for nil
+fn nil_block_id(block_id: Option<block::Id>) -> Option<block::Id> {
+    block_id.filter(|block_id| block_id != &block::Id::default())
+}
+
+// =============================================================================
+// Protobuf conversions
+// =============================================================================
+
+tendermint_pb_modules! {
+    use super::{
+        nil_block_id, validate_votes_count, BitArray, BlockPart, HasVote, Message,
+        NewRoundStep, NewValidBlock, Proposal, ProposalPol, Vote, VoteSetBits, VoteSetMaj23,
+    };
+    use crate::{block, error::Error, prelude::*};
+    use pb::consensus::{
+        message::Sum, BlockPart as RawBlockPart, HasVote as RawHasVote, Message as RawMessage,
+        NewRoundStep as RawNewRoundStep, NewValidBlock as RawNewValidBlock,
+        Proposal as RawProposal, ProposalPol as RawProposalPol, Vote as RawVote,
+        VoteSetBits as RawVoteSetBits, VoteSetMaj23 as RawVoteSetMaj23,
+    };
+
+    fn bit_array(value: Option<pb::libs::bits::BitArray>) -> Result<BitArray, Error> {
+        // A missing bit array is an empty one, like in Tendermint Core.
+        Ok(value.map(TryInto::try_into).transpose()?.unwrap_or_default())
+    }
+
+    fn vote_set_block_id(
+        value: Option<pb::types::BlockId>,
+    ) -> Result<Option<block::Id>, Error> {
+        Ok(nil_block_id(value.map(TryInto::try_into).transpose()?))
+    }
+
+    impl Protobuf<RawNewRoundStep> for NewRoundStep {}
+
+    impl TryFrom<RawNewRoundStep> for NewRoundStep {
+        type Error = Error;
+
+        fn try_from(value: RawNewRoundStep) -> Result<Self, Self::Error> {
+            let last_commit_round = match value.last_commit_round {
+                -1 => None,
+                round => Some(round.try_into()?),
+            };
+            Ok(Self {
+                height: value.height.try_into()?,
+                round: value.round.try_into()?,
+                step: value.step.try_into()?,
+                seconds_since_start_time: value.seconds_since_start_time,
+                last_commit_round,
+            })
+        }
+    }
+
+    impl From<NewRoundStep> for RawNewRoundStep {
+        fn from(value: NewRoundStep) -> Self {
+            RawNewRoundStep {
+                height: value.height.into(),
+                round: value.round.into(),
+                step: value.step.into(),
+                seconds_since_start_time: value.seconds_since_start_time,
+                last_commit_round: value.last_commit_round.map_or(-1, Into::into),
+            }
+        }
+    }
+
+    impl Protobuf<RawNewValidBlock> for NewValidBlock {}
+
+    impl TryFrom<RawNewValidBlock> for NewValidBlock {
+        type Error = Error;
+
+        fn try_from(value: RawNewValidBlock) -> Result<Self, Self::Error> {
+            let message = Self {
+                height: value.height.try_into()?,
+                round: value.round.try_into()?,
+                block_part_set_header: value
+                    .block_part_set_header
+                    .ok_or_else(|| Error::invalid_part_set_header("missing".to_string()))?
+                    .try_into()?,
+                block_parts: bit_array(value.block_parts)?,
+                is_commit: value.is_commit,
+            };
+            message.validate()?;
+            Ok(message)
+        }
+    }
+
+    impl From<NewValidBlock> for RawNewValidBlock {
+        fn from(value: NewValidBlock) -> Self {
+            RawNewValidBlock {
+                height: value.height.into(),
+                round: value.round.into(),
+                block_part_set_header: Some(value.block_part_set_header.into()),
+                block_parts: Some(value.block_parts.into()),
+                is_commit: value.is_commit,
+            }
+        }
+    }
+
+    impl Protobuf<RawProposal> for Proposal {}
+
+    impl TryFrom<RawProposal> for Proposal {
+        type Error = Error;
+
+        fn try_from(value: RawProposal) -> Result<Self, Self::Error> {
+            let message = Self {
+                proposal: value.proposal.ok_or_else(Error::no_proposal_found)?.try_into()?,
+            };
+            message.validate()?;
+            Ok(message)
+        }
+    }
+
+    impl From<Proposal> for RawProposal {
+        fn from(value: Proposal) -> Self {
+            RawProposal {
+                proposal: Some(value.proposal.into()),
+            }
+        }
+    }
+
+    impl Protobuf<RawProposalPol> for ProposalPol {}
+
+    impl TryFrom<RawProposalPol> for ProposalPol {
+        type Error = Error;
+
+        fn try_from(value: RawProposalPol) -> Result<Self, Self::Error> {
+            let message = Self {
+                height: value.height.try_into()?,
+                proposal_pol_round: value.proposal_pol_round.try_into()?,
+                proposal_pol: bit_array(value.proposal_pol)?,
+            };
+            message.validate()?;
+            Ok(message)
+        }
+    }
+
+    impl From<ProposalPol> for RawProposalPol {
+        fn from(value: ProposalPol) -> Self {
+            RawProposalPol {
+                height: value.height.into(),
+                proposal_pol_round: value.proposal_pol_round.into(),
+                proposal_pol: Some(value.proposal_pol.into()),
+            }
+        }
+    }
+
+    impl Protobuf<RawBlockPart> for BlockPart {}
+
+    impl TryFrom<RawBlockPart> for BlockPart {
+        type Error = Error;
+
+        fn try_from(value: RawBlockPart) -> Result<Self, Self::Error> {
+            Ok(Self {
+                height: value.height.try_into()?,
+                round: value.round.try_into()?,
+                part: value
+                    .part
+                    .ok_or_else(|| Error::invalid_block_part("missing".to_string()))?
+                    .try_into()?,
+            })
+        }
+    }
+
+    impl From<BlockPart> for RawBlockPart {
+        fn from(value: BlockPart) -> Self {
+            RawBlockPart {
+                height: value.height.into(),
+                round: value.round.into(),
+                part: Some(value.part.into()),
+            }
+        }
+    }
+
+    impl Protobuf<RawVote> for Vote {}
+
+    impl TryFrom<RawVote> for Vote {
+        type Error = Error;
+
+        fn try_from(value: RawVote) -> Result<Self, Self::Error> {
+            let message = Self {
+                vote: value.vote.ok_or_else(Error::no_vote_found)?.try_into()?,
+            };
+            message.validate()?;
+            Ok(message)
+        }
+    }
+
+    impl From<Vote> for RawVote {
+        fn from(value: Vote) -> Self {
+            RawVote {
+                vote: Some(value.vote.into()),
+            }
+        }
+    }
+
+    impl Protobuf<RawHasVote> for HasVote {}
+
+    impl TryFrom<RawHasVote> for HasVote {
+        type Error = Error;
+
+        fn try_from(value: RawHasVote) -> Result<Self, Self::Error> {
+            Ok(Self {
+                height: value.height.try_into()?,
+                round: value.round.try_into()?,
+                vote_type: value.r#type.try_into()?,
+                index: value.index.try_into()?,
+            })
+        }
+    }
+
+    impl From<HasVote> for RawHasVote {
+        fn from(value: HasVote) -> Self {
+            RawHasVote {
+                height: value.height.into(),
+                round: value.round.into(),
+                r#type: value.vote_type.into(),
+                index: value.index.into(),
+            }
+        }
+    }
+
+    impl Protobuf<RawVoteSetMaj23> for VoteSetMaj23 {}
+
+    impl TryFrom<RawVoteSetMaj23> for VoteSetMaj23 {
+        type Error = Error;
+
+        fn try_from(value: RawVoteSetMaj23) -> Result<Self, Self::Error> {
+            Ok(Self {
+                height: value.height.try_into()?,
+                round: value.round.try_into()?,
+                vote_type: value.r#type.try_into()?,
+                block_id: vote_set_block_id(value.block_id)?,
+            })
+        }
+    }
+
+    impl From<VoteSetMaj23> for RawVoteSetMaj23 {
+        fn from(value: VoteSetMaj23) -> Self {
+            RawVoteSetMaj23 {
+                height: value.height.into(),
+                round: value.round.into(),
+                r#type: value.vote_type.into(),
+                block_id: Some(value.block_id.unwrap_or_default().into()),
+            }
+        }
+    }
+
+    impl Protobuf<RawVoteSetBits> for VoteSetBits {}
+
+    impl TryFrom<RawVoteSetBits> for VoteSetBits {
+        type Error = Error;
+
+        fn try_from(value: RawVoteSetBits) -> Result<Self, Self::Error> {
+            let votes = bit_array(value.votes)?;
+            validate_votes_count(&votes)?;
+            Ok(Self {
+                height: value.height.try_into()?,
+                round: value.round.try_into()?,
+                vote_type: value.r#type.try_into()?,
+                block_id: vote_set_block_id(value.block_id)?,
+                votes,
+            })
+        }
+    }
+
+    impl From<VoteSetBits> for RawVoteSetBits {
+        fn from(value: VoteSetBits) -> Self {
+            RawVoteSetBits {
+                height: value.height.into(),
+                round: value.round.into(),
+                r#type: value.vote_type.into(),
+                block_id: Some(value.block_id.unwrap_or_default().into()),
+                votes: Some(value.votes.into()),
+            }
+        }
+    }
+
+    impl Protobuf<RawMessage> for Message {}
+
+    impl TryFrom<RawMessage> for Message {
+        type Error = Error;
+
+        fn try_from(value: RawMessage) -> Result<Self, Self::Error> {
+            let sum = value.sum.ok_or_else(|| {
+                Error::invalid_consensus_message("missing content".to_string())
+            })?;
+            Ok(match sum {
+                Sum::NewRoundStep(m) => Self::NewRoundStep(m.try_into()?),
+                Sum::NewValidBlock(m) => Self::NewValidBlock(m.try_into()?),
+                Sum::Proposal(m) => Self::Proposal(m.try_into()?),
+                Sum::ProposalPol(m) => Self::ProposalPol(m.try_into()?),
+                Sum::BlockPart(m) => Self::BlockPart(m.try_into()?),
+                Sum::Vote(m) => Self::Vote(m.try_into()?),
+                Sum::HasVote(m) => Self::HasVote(m.try_into()?),
+                Sum::VoteSetMaj23(m) => Self::VoteSetMaj23(m.try_into()?),
+                Sum::VoteSetBits(m) => Self::VoteSetBits(m.try_into()?),
+            })
+        }
+    }
+
+    impl From<Message> for RawMessage {
+        fn from(value: Message) -> Self {
+            let sum = match value {
+                Message::NewRoundStep(m) => Sum::NewRoundStep(m.into()),
+                Message::NewValidBlock(m) => Sum::NewValidBlock(m.into()),
+                Message::Proposal(m) => Sum::Proposal(m.into()),
+                Message::ProposalPol(m) => Sum::ProposalPol(m.into()),
+                Message::BlockPart(m) => Sum::BlockPart(m.into()),
+                Message::Vote(m) => Sum::Vote(m.into()),
+                Message::HasVote(m) => Sum::HasVote(m.into()),
+                Message::VoteSetMaj23(m) => Sum::VoteSetMaj23(m.into()),
+                Message::VoteSetBits(m) => Sum::VoteSetBits(m.into()),
+            };
+            RawMessage { sum: Some(sum) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tendermint_proto::{v0_37::consensus::Message as RawMessage, Protobuf};
+
+    use super::*;
+    use crate::{merkle, Signature, Time};
+
+    fn block_id() -> block::Id {
+        block::Id {
+            hash: Hash::Sha256([0xab; 32]),
+            part_set_header: block::parts::Header::new(2, Hash::Sha256([0xcd; 32])).unwrap(),
+        }
+    }
+
+    fn bits(pattern: &str) -> BitArray {
+        serde_json::from_str(&format!("\"{pattern}\"")).unwrap()
+    }
+
+    fn roundtrip(message: Message) {
+        let bytes = Protobuf::<RawMessage>::encode_vec(&message).unwrap();
+        let decoded: Message = Protobuf::<RawMessage>::decode_vec(&bytes).unwrap();
+        assert_eq!(decoded, message);
+
+        let json = serde_json::to_string(&message).unwrap();
+        let decoded: Message = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[test]
+    fn messages_roundtrip() {
+        let vote = vote::Vote {
+            block_id: Some(block_id()),
+            timestamp: Some(Time::from_unix_timestamp(1_600_000_000, 0).unwrap()),
+            signature: Signature::new(vec![1; 64]).unwrap(),
+            ..vote::Vote::default()
+        };
+        let messages = [
+            Message::NewRoundStep(NewRoundStep {
+                height: 2_u32.into(),
+                round: 1_u16.into(),
+                step: RoundStep::PrevoteWait,
+                seconds_since_start_time: -3,
+                last_commit_round: Some(0_u16.into()),
+            }),
+            Message::NewValidBlock(NewValidBlock {
+                height: 2_u32.into(),
+                round: 1_u16.into(),
+                block_part_set_header: block_id().part_set_header,
+                block_parts: bits("x_"),
+                is_commit: true,
+            }),
+            Message::Proposal(Proposal {
+                proposal: proposal::Proposal {
+                    msg_type: proposal::Type::Proposal,
+                    height: 2_u32.into(),
+                    round: 1_u16.into(),
+                    pol_round: Some(0_u16.into()),
+                    block_id: Some(block_id()),
+                    timestamp: Some(Time::from_unix_timestamp(1_600_000_000, 0).unwrap()),
+                    signature: Signature::new(vec![2; 64]).unwrap(),
+                },
+            }),
+            Message::ProposalPol(ProposalPol {
+                height: 2_u32.into(),
+                proposal_pol_round: 0_u16.into(),
+                proposal_pol: bits("_x_x"),
+            }),
+            Message::BlockPart(BlockPart {
+                height: 2_u32.into(),
+                round: 1_u16.into(),
+                part: block::parts::Part {
+                    index: 1,
+                    bytes: vec![0xef; 16],
+                    proof: merkle::Proof {
+                        total: 2,
+                        index: 1,
+                        leaf_hash: Hash::Sha256([0x01; 32]),
+                        aunts: vec![Hash::Sha256([0x02; 32])],
+                    },
+                },
+            }),
+            Message::Vote(Vote { vote }),
+            Message::HasVote(HasVote {
+                height: 2_u32.into(),
+                round: 1_u16.into(),
+                vote_type: vote::Type::Precommit,
+                index: 3_u32.try_into().unwrap(),
+            }),
+            Message::VoteSetMaj23(VoteSetMaj23 {
+                height: 2_u32.into(),
+                round: 1_u16.into(),
+                vote_type: vote::Type::Prevote,
+                block_id: None,
+            }),
+            Message::VoteSetBits(VoteSetBits {
+                height: 2_u32.into(),
+                round: 1_u16.into(),
+                vote_type: vote::Type::Prevote,
+                block_id: Some(block_id()),
+                votes: bits(&"x".repeat(65)),
+            }),
+        ];
+        for message in messages {
+            roundtrip(message);
+        }
+    }
+
+    #[test]
+    fn bit_array_serialization() {
+        let mut array = BitArray::new(70);
+        assert!(array.set(0, true));
+        assert!(array.set(69, true));
+        assert!(!array.set(70, true));
+        assert_eq!(array.count_ones(), 2);
+        assert_eq!(array.get(69), Some(true));
+        assert_eq!(array.get(70), None);
+
+        let json = serde_json::to_string(&array).unwrap();
+        assert_eq!(json, format!("\"x{}x\"", "_".repeat(68)));
+        assert_eq!(serde_json::from_str::<BitArray>(&json).unwrap(), array);
+        assert!(serde_json::from_str::<BitArray>("\"x-\"").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_bit_arrays() {
+        use tendermint_proto::v0_37::libs::bits::BitArray as RawBitArray;
+
+        let too_few_elems = RawBitArray {
+            bits: 65,
+            elems: vec![0],
+        };
+        assert!(BitArray::try_from(too_few_elems).is_err());
+
+        let bits_beyond_size = RawBitArray {
+            bits: 3,
+            elems: vec![0b1000],
+        };
+        assert!(BitArray::try_from(bits_beyond_size).is_err());
+
+        let negative_size = RawBitArray {
+            bits: -1,
+            elems: vec![],
+        };
+        assert!(BitArray::try_from(negative_size).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_messages() {
+        let step = RawNewRoundStep {
+            height: 1,
+            step: 9,
+            last_commit_round: -1,
+            ..Default::default()
+        };
+        assert!(NewRoundStep::try_from(step).is_err());
+
+        let last_commit_round = RawNewRoundStep {
+            height: 1,
+            step: 1,
+            last_commit_round: -2,
+            ..Default::default()
+        };
+        assert!(NewRoundStep::try_from(last_commit_round).is_err());
+
+        let parts_mismatch = RawNewValidBlock {
+            height: 1,
+            block_part_set_header: Some(block_id().part_set_header.into()),
+            block_parts: Some(bits("xxx").into()),
+            ..Default::default()
+        };
+        assert!(NewValidBlock::try_from(parts_mismatch).is_err());
+
+        let too_many_votes = RawVoteSetBits {
+            height: 1,
+            r#type: 1,
+            votes: Some(BitArray::new(MAX_VOTES_COUNT + 1).into()),
+            ..Default::default()
+        };
+        assert!(VoteSetBits::try_from(too_many_votes).is_err());
+
+        let unsigned_vote = vote::Vote {
+            block_id: Some(block_id()),
+            signature: None,
+            ..vote::Vote::default()
+        };
+        let raw = RawVote {
+            vote: Some(unsigned_vote.into()),
+        };
+        assert!(Vote::try_from(raw).is_err());
+
+        assert!(Message::try_from(RawMessage { sum: None }).is_err());
+    }
+
+    #[test]
+    fn new_round_step_height_validation() {
+        let mut step = NewRoundStep {
+            height: 1_u32.into(),
+            round: block::Round::default(),
+            step: RoundStep::NewHeight,
+            seconds_since_start_time: 0,
+            last_commit_round: None,
+        };
+        assert!(step.validate_height(1_u32.into()).is_ok());
+        assert!(step.validate_height(2_u32.into()).is_err());
+
+        step.last_commit_round = Some(0_u16.into());
+        assert!(step.validate_height(1_u32.into()).is_err());
+
+        step.height = 2_u32.into();
+        assert!(step.validate_height(1_u32.into()).is_ok());
+        step.last_commit_round = None;
+        assert!(step.validate_height(1_u32.into()).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Bit arrays, telling which block parts or votes a peer has
+
+use core::fmt;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::prelude::*;
+
+const BITS_PER_ELEM: usize = 64;
+
+/// Array of bits of a given size, as exchanged by the consensus reactor.
+///
+/// Serialized in JSON as a string of `x` (set) and `_` (unset) characters,
+/// like Tendermint Core does.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct BitArray {
+    size: usize,
+    elems: Vec<u64>,
+}
+
+impl BitArray {
+    /// Create an array of the given size, with all bits unset
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            elems: vec![0; elem_count(size)],
+        }
+    }
+
+    /// Number of bits in the array
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the array has no bits
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Value of the bit at the given index, or `None` if out of bounds
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.size {
+            return None;
+        }
+        Some(self.elems[index / BITS_PER_ELEM] & (1 << (index % BITS_PER_ELEM)) != 0)
+    }
+
+    /// Set the bit at the given index, returning `false` if out of bounds
+    pub fn set(&mut self, index: usize, value: bool) -> bool {
+        if index >= self.size {
+            return false;
+        }
+        let mask = 1 << (index % BITS_PER_ELEM);
+        if value {
+            self.elems[index / BITS_PER_ELEM] |= mask;
+        } else {
+            self.elems[index / BITS_PER_ELEM] &= !mask;
+        }
+        true
+    }
+
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.elems
+            .iter()
+            .map(|elem| elem.count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterate over the bits of the array
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.size).map(|index| self.get(index).unwrap_or_default())
+    }
+}
+
+impl fmt::Display for BitArray {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter()
+            .try_for_each(|bit| f.write_str(if bit { "x" } else { "_" }))
+    }
+}
+
+impl Serialize for BitArray {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BitArray {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = String::deserialize(deserializer)?;
+        let mut array = Self::new(bits.len());
+        for (index, bit) in bits.chars().enumerate() {
+            match bit {
+                'x' => {
+                    array.set(index, true);
+                },
+                '_' => {},
+                _ => return Err(D::Error::custom(format!("invalid bit array: {bits}"))),
+            }
+        }
+        Ok(array)
+    }
+}
+
+fn elem_count(size: usize) -> usize {
+    size.div_ceil(BITS_PER_ELEM)
+}
+
+tendermint_pb_modules! {
+    use super::{elem_count, BitArray, BITS_PER_ELEM};
+    use crate::{error::Error, prelude::*};
+    use pb::libs::bits::BitArray as RawBitArray;
+
+    impl Protobuf<RawBitArray> for BitArray {}
+
+    impl TryFrom<RawBitArray> for BitArray {
+        type Error = Error;
+
+        fn try_from(value: RawBitArray) -> Result<Self, Self::Error> {
+            let size = usize::try_from(value.bits)
+                .map_err(|_| Error::invalid_bit_array(format!("invalid size {}", value.bits)))?;
+            if value.elems.len() != elem_count(size) {
+                return Err(Error::invalid_bit_array(format!(
+                    "{} elements for {} bits",
+                    value.elems.len(),
+                    size
+                )));
+            }
+            let unused_bits = value.elems.last().map_or(0, |last| match size % BITS_PER_ELEM {
+                0 => 0,
+                used => last >> used,
+            });
+            if unused_bits != 0 {
+                return Err(Error::invalid_bit_array(format!(
+                    "bits set beyond the size of {size}"
+                )));
+            }
+            Ok(Self {
+                size,
+                elems: value.elems,
+            })
+        }
+    }
+
+    impl From<BitArray> for RawBitArray {
+        fn from(value: BitArray) -> Self {
+            Self {
+                // The size of the array is bounded by the memory.
+                bits: value.size as i64,
+                elems: value.elems,
+            }
+        }
+    }
+}
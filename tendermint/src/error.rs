@@ -230,6 +230,39 @@ define_error! {
         NegativeProofIndex
             [ DisplayOnly<TryFromIntError> ]
             |_| { "negative item index in proof" },
+
+        UnsupportedProofOp
+            { op_type: String }
+            |e| { format_args!("unsupported proof operation type: {}", e.op_type) },
+
+        InvalidProofOp
+            [ DisplayOnly<prost::DecodeError> ]
+            |_| { "invalid proof operation" },
+
+        ProofKeyMismatch
+            |_| { "proof keys do not match the expected keys" },
+
+        InvalidProofLeaf
+            |_| { "proof leaf hash does not match the proven value" },
+
+        ProofRootMismatch
+            |_| { "proof does not lead to the expected root hash" },
+
+        InvalidBitArray
+            { reason: String }
+            |e| { format_args!("invalid bit array: {}", e.reason) },
+
+        InvalidBlockPart
+            { reason: String }
+            |e| { format_args!("invalid block part: {}", e.reason) },
+
+        InvalidRoundStep
+            { step: u32 }
+            |e| { format_args!("invalid round step: {}", e.step) },
+
+        InvalidConsensusMessage
+            { reason: String }
+            |e| { format_args!("invalid consensus message: {}", e.reason) },
     }
 }
 
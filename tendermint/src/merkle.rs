@@ -2,17 +2,14 @@
 
 pub mod proof;
 
-pub use proof::Proof;
-
 use core::marker::PhantomData;
 
 use digest::{consts::U32, Digest, FixedOutputReset};
-
-use crate::crypto::Sha256;
-use crate::prelude::*;
+pub use proof::Proof;
 
 /// Size of Merkle root hash
 pub use crate::crypto::sha256::HASH_SIZE;
+use crate::{crypto::Sha256, prelude::*};
 
 /// Hash is the output of the cryptographic digest function
 pub type Hash = [u8; HASH_SIZE];
@@ -28,6 +25,71 @@ where
     hasher.hash_byte_vectors(byte_vecs)
 }
 
+/// Compute a simple Merkle root from vectors of arbitrary byte vectors, along
+/// with a proof of inclusion for each of them.
+pub fn proofs_from_byte_vectors<H>(byte_vecs: &[Vec<u8>]) -> (Hash, Vec<Proof>)
+where
+    H: MerkleHash + Default,
+{
+    let mut hasher = H::default();
+    let (root, trails) = hasher.hash_trails(byte_vecs);
+    let total = byte_vecs.len() as u64;
+    let proofs = trails
+        .into_iter()
+        .enumerate()
+        .map(|(index, (leaf_hash, aunts))| Proof {
+            total,
+            index: index as u64,
+            leaf_hash: crate::Hash::Sha256(leaf_hash),
+            aunts: aunts.into_iter().map(crate::Hash::Sha256).collect(),
+        })
+        .collect();
+    (root, proofs)
+}
+
+/// Compute a simple Merkle root from the hashes of its leaves, as computed by
+/// [`MerkleHash::leaf_hash`].
+pub fn simple_hash_from_leaf_hashes<H>(leaf_hashes: &[Hash]) -> Hash
+where
+    H: MerkleHash + Default,
+{
+    let mut hasher = H::default();
+    hasher.hash_leaf_hashes(leaf_hashes)
+}
+
+/// Compute the proof of inclusion of the leaf at the given index, from the
+/// hashes of all the leaves of the tree.
+///
+/// Returns `None` if the index is out of range.
+pub fn proof_from_leaf_hashes<H>(leaf_hashes: &[Hash], index: usize) -> Option<Proof>
+where
+    H: MerkleHash + Default,
+{
+    let leaf_hash = *leaf_hashes.get(index)?;
+    let mut hasher = H::default();
+    let aunts = hasher.leaf_aunts(leaf_hashes, index);
+    Some(Proof {
+        total: leaf_hashes.len() as u64,
+        index: index as u64,
+        leaf_hash: crate::Hash::Sha256(leaf_hash),
+        aunts: aunts.into_iter().map(crate::Hash::Sha256).collect(),
+    })
+}
+
+/// Encode a key/value pair as a leaf of a simple Merkle map, as proven by
+/// [`ProofOp`](proof::ProofOp)s of type [`VALUE_OP_TYPE`](proof::VALUE_OP_TYPE).
+///
+/// The key and the hash of the value are each prefixed with their length.
+pub fn value_leaf<H: Sha256>(key: &[u8], value: &[u8]) -> Vec<u8> {
+    let value_hash = H::digest(value);
+    let mut leaf = Vec::with_capacity(key.len() + value_hash.len() + 2);
+    for field in [key, &value_hash[..]] {
+        prost::encoding::encode_varint(field.len() as u64, &mut leaf);
+        leaf.extend_from_slice(field);
+    }
+    leaf
+}
+
 /// Implementation of Merkle tree hashing for Tendermint.
 pub trait MerkleHash {
     // tmhash({})
@@ -61,6 +123,77 @@ pub trait MerkleHash {
             },
         }
     }
+
+    // Same as `hash_byte_vectors`, from the hashes of the leaves.
+    // Pre and post-conditions: the hasher is in the reset state
+    // before and after calling this function.
+    fn hash_leaf_hashes(&mut self, leaf_hashes: &[Hash]) -> Hash {
+        let length = leaf_hashes.len();
+        match length {
+            0 => self.empty_hash(),
+            1 => leaf_hashes[0],
+            _ => {
+                let split = length.next_power_of_two() / 2;
+                let left = self.hash_leaf_hashes(&leaf_hashes[..split]);
+                let right = self.hash_leaf_hashes(&leaf_hashes[split..]);
+                self.inner_hash(left, right)
+            },
+        }
+    }
+
+    // Computes the aunts of the leaf at the given index (from the leaf's
+    // sibling up to a child of the root), from the hashes of the leaves.
+    // Pre and post-conditions: the hasher is in the reset state
+    // before and after calling this function.
+    fn leaf_aunts(&mut self, leaf_hashes: &[Hash], index: usize) -> Vec<Hash> {
+        let length = leaf_hashes.len();
+        if length <= 1 {
+            return vec![];
+        }
+        let split = length.next_power_of_two() / 2;
+        let (mut aunts, aunt) = if index < split {
+            (
+                self.leaf_aunts(&leaf_hashes[..split], index),
+                self.hash_leaf_hashes(&leaf_hashes[split..]),
+            )
+        } else {
+            (
+                self.leaf_aunts(&leaf_hashes[split..], index - split),
+                self.hash_leaf_hashes(&leaf_hashes[..split]),
+            )
+        };
+        aunts.push(aunt);
+        aunts
+    }
+
+    // Computes the root hash, along with the leaf hash and the aunts (from
+    // the leaf's sibling up to a child of the root) of each leaf.
+    // Pre and post-conditions: the hasher is in the reset state
+    // before and after calling this function.
+    #[allow(clippy::type_complexity)]
+    fn hash_trails(&mut self, byte_vecs: &[Vec<u8>]) -> (Hash, Vec<(Hash, Vec<Hash>)>) {
+        let length = byte_vecs.len();
+        match length {
+            0 => (self.empty_hash(), vec![]),
+            1 => {
+                let leaf_hash = self.leaf_hash(&byte_vecs[0]);
+                (leaf_hash, vec![(leaf_hash, vec![])])
+            },
+            _ => {
+                let split = length.next_power_of_two() / 2;
+                let (left, mut left_trails) = self.hash_trails(&byte_vecs[..split]);
+                let (right, mut right_trails) = self.hash_trails(&byte_vecs[split..]);
+                for (_, aunts) in left_trails.iter_mut() {
+                    aunts.push(right);
+                }
+                for (_, aunts) in right_trails.iter_mut() {
+                    aunts.push(left);
+                }
+                left_trails.append(&mut right_trails);
+                (self.inner_hash(left, right), left_trails)
+            },
+        }
+    }
 }
 
 // A helper to copy GenericArray into the human-friendly Hash type.
@@ -192,6 +325,71 @@ mod tests {
         assert_eq!(node_hash, &hash);
     }
 
+    #[test]
+    fn proofs_lead_to_root() {
+        for total in 1..=9_u8 {
+            let items: Vec<Vec<u8>> = (0..total).map(|i| vec![i; i as usize]).collect();
+            let (root, proofs) = proofs_from_byte_vectors::<Sha256>(&items);
+            assert_eq!(root, simple_hash_from_byte_vectors::<Sha256>(&items));
+            assert_eq!(proofs.len(), items.len());
+            for (proof, item) in proofs.iter().zip(items.iter()) {
+                proof.verify::<Sha256>(&root, item).unwrap();
+            }
+            // A proof does not hold for any other item.
+            if total > 1 {
+                assert!(proofs[0].verify::<Sha256>(&root, &items[1]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn proofs_from_leaf_hashes() {
+        for total in 0..=9_u8 {
+            let items: Vec<Vec<u8>> = (0..total).map(|i| vec![i; i as usize]).collect();
+            let leaf_hashes: Vec<Hash> = items
+                .iter()
+                .map(|item| Sha256::new().leaf_hash(item))
+                .collect();
+            let (root, proofs) = proofs_from_byte_vectors::<Sha256>(&items);
+            assert_eq!(root, simple_hash_from_leaf_hashes::<Sha256>(&leaf_hashes));
+            for (index, proof) in proofs.into_iter().enumerate() {
+                assert_eq!(
+                    proof_from_leaf_hashes::<Sha256>(&leaf_hashes, index),
+                    Some(proof)
+                );
+            }
+            assert_eq!(
+                proof_from_leaf_hashes::<Sha256>(&leaf_hashes, total as usize),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn value_op_proves_map_entry() {
+        let entries = [("a", "1"), ("b", "2"), ("c", "3")];
+        let leaves: Vec<Vec<u8>> = entries
+            .iter()
+            .map(|(k, v)| value_leaf::<Sha256>(k.as_bytes(), v.as_bytes()))
+            .collect();
+        let (root, mut proofs) = proofs_from_byte_vectors::<Sha256>(&leaves);
+        let ops = proof::ProofOps {
+            ops: vec![proof::ProofOp::value(b"b".to_vec(), proofs.remove(1))],
+        };
+
+        ops.verify_value::<Sha256>(&root, &[b"b".as_slice()], b"2")
+            .unwrap();
+        assert!(ops
+            .verify_value::<Sha256>(&root, &[b"b".as_slice()], b"3")
+            .is_err());
+        assert!(ops
+            .verify_value::<Sha256>(&root, &[b"c".as_slice()], b"2")
+            .is_err());
+        assert!(ops
+            .verify_value::<Sha256>(&[0; HASH_SIZE], &[b"b".as_slice()], b"2")
+            .is_err());
+    }
+
     mod non_incremental {
         use super::*;
 
@@ -1,9 +1,15 @@
 //! Merkle proofs
 
+use prost::Message;
 use serde::{Deserialize, Serialize};
-use tendermint_proto::v0_37::crypto::Proof as RawProof;
+use tendermint_proto::v0_37::crypto::{Proof as RawProof, ValueOp as RawValueOp};
 
-use crate::{prelude::*, serializers, Hash};
+use super::{value_leaf, MerkleHash};
+use crate::{crypto::Sha256, error::Error, prelude::*, serializers, Hash};
+
+/// Type of the [`ProofOp`] proving the value associated with a key in a
+/// simple Merkle map, whose leaves are given by [`value_leaf`].
+pub const VALUE_OP_TYPE: &str = "simple:v";
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(try_from = "RawProof", into = "RawProof")]
@@ -43,6 +49,144 @@ pub struct ProofOp {
     pub data: Vec<u8>,
 }
 
+impl Proof {
+    /// Compute the root hash of the Merkle tree from the leaf hash and the
+    /// aunts of this proof, or `None` if the proof is malformed.
+    pub fn compute_root_hash<H>(&self) -> Option<super::Hash>
+    where
+        H: MerkleHash + Default,
+    {
+        let leaf_hash = sha256_bytes(&self.leaf_hash)?;
+        let aunts = self
+            .aunts
+            .iter()
+            .map(sha256_bytes)
+            .collect::<Option<Vec<_>>>()?;
+        compute_hash_from_aunts(&mut H::default(), self.index, self.total, leaf_hash, &aunts)
+    }
+
+    /// Verify that this proof establishes the inclusion of the given leaf in
+    /// the Merkle tree with the given root hash.
+    pub fn verify<H>(&self, root_hash: &super::Hash, leaf: &[u8]) -> Result<(), Error>
+    where
+        H: MerkleHash + Default,
+    {
+        if sha256_bytes(&self.leaf_hash) != Some(H::default().leaf_hash(leaf)) {
+            return Err(Error::invalid_proof_leaf());
+        }
+        match self.compute_root_hash::<H>() {
+            Some(computed) if &computed == root_hash => Ok(()),
+            _ => Err(Error::proof_root_mismatch()),
+        }
+    }
+}
+
+fn sha256_bytes(hash: &Hash) -> Option<super::Hash> {
+    match hash {
+        Hash::Sha256(bytes) => Some(*bytes),
+        Hash::None => None,
+    }
+}
+
+// Recursively compute the root hash from the aunts, which are ordered from
+// the leaf's sibling up to a child of the root.
+fn compute_hash_from_aunts<H: MerkleHash>(
+    hasher: &mut H,
+    index: u64,
+    total: u64,
+    leaf_hash: super::Hash,
+    aunts: &[super::Hash],
+) -> Option<super::Hash> {
+    if index >= total {
+        return None;
+    }
+    if total == 1 {
+        return aunts.is_empty().then_some(leaf_hash);
+    }
+    let (last, inner_aunts) = aunts.split_last()?;
+    let split = total.next_power_of_two() / 2;
+    if index < split {
+        let left = compute_hash_from_aunts(hasher, index, split, leaf_hash, inner_aunts)?;
+        Some(hasher.inner_hash(left, *last))
+    } else {
+        let right =
+            compute_hash_from_aunts(hasher, index - split, total - split, leaf_hash, inner_aunts)?;
+        Some(hasher.inner_hash(*last, right))
+    }
+}
+
+impl ProofOp {
+    /// A [`VALUE_OP_TYPE`] operation, proving the value associated with the
+    /// given key.
+    pub fn value(key: Vec<u8>, proof: Proof) -> Self {
+        Self {
+            field_type: VALUE_OP_TYPE.to_string(),
+            data: RawValueOp {
+                key: key.clone(),
+                proof: Some(proof.into()),
+            }
+            .encode_to_vec(),
+            key,
+        }
+    }
+
+    /// Run this [`VALUE_OP_TYPE`] operation against the given value, which
+    /// produces the root hash of the Merkle map proven to contain it.
+    pub fn run_value<H>(&self, value: &[u8]) -> Result<super::Hash, Error>
+    where
+        H: MerkleHash + Sha256 + Default,
+    {
+        if self.field_type != VALUE_OP_TYPE {
+            return Err(Error::unsupported_proof_op(self.field_type.clone()));
+        }
+        let op = RawValueOp::decode(self.data.as_slice()).map_err(Error::invalid_proof_op)?;
+        if op.key != self.key {
+            return Err(Error::proof_key_mismatch());
+        }
+        let proof = Proof::try_from(op.proof.unwrap_or_default())?;
+        let leaf = value_leaf::<H>(&self.key, value);
+        if sha256_bytes(&proof.leaf_hash) != Some(H::default().leaf_hash(&leaf)) {
+            return Err(Error::invalid_proof_leaf());
+        }
+        proof
+            .compute_root_hash::<H>()
+            .ok_or_else(Error::proof_root_mismatch)
+    }
+}
+
+impl ProofOps {
+    /// Verify that these operations prove the given value to be found at the
+    /// given path of keys, starting from the outermost key, under the given
+    /// root hash.
+    ///
+    /// Only [`VALUE_OP_TYPE`] operations are supported. The operations are
+    /// applied in order, each to the root hash produced by the previous one.
+    pub fn verify_value<H>(
+        &self,
+        root_hash: &super::Hash,
+        keys: &[&[u8]],
+        value: &[u8],
+    ) -> Result<(), Error>
+    where
+        H: MerkleHash + Sha256 + Default,
+    {
+        if self.ops.len() != keys.len() {
+            return Err(Error::proof_key_mismatch());
+        }
+        let mut value = value.to_vec();
+        for (op, key) in self.ops.iter().zip(keys.iter().rev()) {
+            if op.key != *key {
+                return Err(Error::proof_key_mismatch());
+            }
+            value = op.run_value::<H>(&value)?.to_vec();
+        }
+        if value != root_hash {
+            return Err(Error::proof_root_mismatch());
+        }
+        Ok(())
+    }
+}
+
 // =============================================================================
 // Protobuf conversions
 // =============================================================================
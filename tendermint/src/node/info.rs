@@ -102,3 +102,170 @@ impl From<TxIndexStatus> for bool {
         }
     }
 }
+
+tendermint_pb_modules! {
+    use pb::p2p::{
+        DefaultNodeInfo as RawDefaultNodeInfo, DefaultNodeInfoOther as RawDefaultNodeInfoOther,
+        ProtocolVersion as RawProtocolVersion,
+    };
+    use super::{Info, ListenAddress, OtherInfo, ProtocolVersionInfo, TxIndexStatus};
+    use crate::{channel::Channels, error::Error, prelude::*, Version};
+
+    impl Protobuf<RawDefaultNodeInfo> for Info {}
+
+    impl TryFrom<RawDefaultNodeInfo> for Info {
+        type Error = Error;
+
+        fn try_from(value: RawDefaultNodeInfo) -> Result<Self, Self::Error> {
+            Ok(Self {
+                protocol_version: value
+                    .protocol_version
+                    .ok_or_else(Error::missing_data)?
+                    .into(),
+                id: value.default_node_id.parse()?,
+                listen_addr: ListenAddress::new(value.listen_addr),
+                network: value.network.try_into()?,
+                version: Version::from(value.version),
+                channels: Channels::new(value.channels),
+                moniker: value.moniker.parse()?,
+                other: value.other.unwrap_or_default().try_into()?,
+            })
+        }
+    }
+
+    impl From<Info> for RawDefaultNodeInfo {
+        fn from(value: Info) -> Self {
+            RawDefaultNodeInfo {
+                protocol_version: Some(value.protocol_version.into()),
+                default_node_id: value.id.to_string(),
+                listen_addr: value.listen_addr.to_string(),
+                network: value.network.into(),
+                version: value.version.into(),
+                channels: value.channels.ids().to_vec(),
+                moniker: value.moniker.to_string(),
+                other: Some(value.other.into()),
+            }
+        }
+    }
+
+    impl From<RawProtocolVersion> for ProtocolVersionInfo {
+        fn from(value: RawProtocolVersion) -> Self {
+            Self {
+                p2p: value.p2p,
+                block: value.block,
+                app: value.app,
+            }
+        }
+    }
+
+    impl From<ProtocolVersionInfo> for RawProtocolVersion {
+        fn from(value: ProtocolVersionInfo) -> Self {
+            RawProtocolVersion {
+                p2p: value.p2p,
+                block: value.block,
+                app: value.app,
+            }
+        }
+    }
+
+    impl TryFrom<RawDefaultNodeInfoOther> for OtherInfo {
+        type Error = Error;
+
+        fn try_from(value: RawDefaultNodeInfoOther) -> Result<Self, Self::Error> {
+            let tx_index = match value.tx_index.as_str() {
+                "on" => TxIndexStatus::On,
+                "off" | "" => TxIndexStatus::Off,
+                _ => return Err(Error::parse(value.tx_index)),
+            };
+            Ok(Self {
+                tx_index,
+                rpc_address: value.rpc_address,
+            })
+        }
+    }
+
+    impl From<OtherInfo> for RawDefaultNodeInfoOther {
+        fn from(value: OtherInfo) -> Self {
+            let tx_index = match value.tx_index {
+                TxIndexStatus::On => "on",
+                TxIndexStatus::Off => "off",
+            };
+            RawDefaultNodeInfoOther {
+                tx_index: tx_index.to_string(),
+                rpc_address: value.rpc_address,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tendermint_proto::v0_37::p2p::{
+        DefaultNodeInfo as RawDefaultNodeInfo, DefaultNodeInfoOther as RawDefaultNodeInfoOther,
+        ProtocolVersion as RawProtocolVersion,
+    };
+
+    use super::*;
+
+    fn raw_node_info() -> RawDefaultNodeInfo {
+        RawDefaultNodeInfo {
+            protocol_version: Some(RawProtocolVersion {
+                p2p: 8,
+                block: 11,
+                app: 1,
+            }),
+            default_node_id: "6b0bb5ff0c4f2e4bc2d4bd5ad3ea3bc53ab2d5e9".to_string(),
+            listen_addr: "tcp://0.0.0.0:26656".to_string(),
+            network: "dockerchain".to_string(),
+            version: "0.37.0".to_string(),
+            channels: vec![0x40, 0x20, 0x21, 0x22, 0x23, 0x30, 0x38, 0x60, 0x61, 0x00],
+            moniker: "node0".to_string(),
+            other: Some(RawDefaultNodeInfoOther {
+                tx_index: "on".to_string(),
+                rpc_address: "tcp://0.0.0.0:26657".to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn converts_raw_node_info() {
+        let info = Info::try_from(raw_node_info()).unwrap();
+        assert_eq!(info.protocol_version.block, 11);
+        assert_eq!(info.network.as_str(), "dockerchain");
+        assert_eq!(info.channels.to_string(), "40202122233038606100");
+        assert!(info.channels.contains(0x30));
+        assert_eq!(info.other.tx_index, TxIndexStatus::On);
+        assert_eq!(RawDefaultNodeInfo::from(info), raw_node_info());
+    }
+
+    #[test]
+    fn rejects_invalid_raw_node_info() {
+        let raw = RawDefaultNodeInfo {
+            protocol_version: None,
+            ..raw_node_info()
+        };
+        assert!(Info::try_from(raw).is_err());
+
+        let raw = RawDefaultNodeInfo {
+            default_node_id: "not-a-node-id".to_string(),
+            ..raw_node_info()
+        };
+        assert!(Info::try_from(raw).is_err());
+
+        let raw = RawDefaultNodeInfo {
+            other: Some(RawDefaultNodeInfoOther {
+                tx_index: "maybe".to_string(),
+                rpc_address: String::new(),
+            }),
+            ..raw_node_info()
+        };
+        assert!(Info::try_from(raw).is_err());
+    }
+
+    #[test]
+    fn channels_serialize_as_hex() {
+        let channels: Channels = serde_json::from_str("\"4020ab\"").unwrap();
+        assert_eq!(channels.ids(), &[0x40, 0x20, 0xab]);
+        assert_eq!(serde_json::to_string(&channels).unwrap(), "\"4020AB\"");
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(test)]
+pub mod p2p;
+
 #[cfg(test)]
 pub mod pipe;
 
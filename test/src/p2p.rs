@@ -0,0 +1,79 @@
+//! Nodes listening on the TCP transport, for tests of the p2p layer
+
+use std::net::SocketAddr;
+
+use ed25519_consensus::SigningKey;
+use rand_core::OsRng;
+use tendermint::node;
+use tendermint_p2p::{
+    secret_connection,
+    transport::{
+        tcp::{TcpConfig, TcpEndpoint, TcpIncoming, TcpTransport},
+        BindInfo, Endpoint, Transport,
+    },
+};
+use tendermint_proto::v0_37::p2p::{DefaultNodeInfo, DefaultNodeInfoOther, ProtocolVersion};
+
+/// Bind a node with a fresh key to a local port, returning its endpoint,
+/// incoming connections and id.
+pub fn bind(moniker: &str, network: &str, channels: &[u8]) -> (TcpEndpoint, TcpIncoming, node::Id) {
+    let key = SigningKey::new(OsRng);
+    let id = secret_connection::PublicKey::from(&key).peer_id();
+    let (endpoint, incoming) = TcpTransport::new(
+        key.clone(),
+        node_info(moniker, network, channels),
+        TcpConfig::default(),
+    )
+    .bind(bind_info(&key, moniker))
+    .unwrap();
+    (endpoint, incoming, id)
+}
+
+/// Local address the given endpoint listens on.
+pub fn listen_addr(endpoint: &TcpEndpoint) -> SocketAddr {
+    Endpoint::<SocketAddr>::listen_addrs(endpoint)[0]
+}
+
+/// Binding to a local port, advertising the address of the given node.
+pub fn bind_info(key: &SigningKey, moniker: &str) -> BindInfo<SocketAddr> {
+    BindInfo {
+        advertise_addrs: advertise_addr(moniker),
+        bind_addrs: "127.0.0.1:0".parse().unwrap(),
+        public_key: tendermint::PublicKey::from_raw_ed25519(key.verification_key().as_bytes())
+            .unwrap(),
+    }
+}
+
+/// Address advertised by the given node.
+pub fn advertise_addr(moniker: &str) -> SocketAddr {
+    match moniker {
+        "node1" => "10.0.0.1:26656",
+        _ => "10.0.0.2:26656",
+    }
+    .parse()
+    .unwrap()
+}
+
+/// Information of a v0.37 node on the given network and channels.
+pub fn node_info(moniker: &str, network: &str, channels: &[u8]) -> node::Info {
+    DefaultNodeInfo {
+        protocol_version: Some(ProtocolVersion {
+            p2p: 8,
+            block: 11,
+            app: 0,
+        }),
+        // Replaced by the transport.
+        default_node_id: node::Id::new([0; 20]).to_string(),
+        listen_addr: String::new(),
+        network: network.to_string(),
+        version: "0.37.0".to_string(),
+        channels: channels.to_vec(),
+        moniker: moniker.to_string(),
+        other: Some(DefaultNodeInfoOther {
+            tx_index: "off".to_string(),
+            rpc_address: String::new(),
+        }),
+    }
+    .try_into()
+    .unwrap()
+}
@@ -23,9 +23,11 @@
 use std::{
     cmp::min,
     io::{self, BufRead, Read, Write},
+    sync::{Arc, Mutex},
 };
 
 use flume::{self, Receiver, SendError, Sender, TrySendError};
+use tendermint_std_ext::TryClone;
 
 // value for libstd
 const DEFAULT_BUF_SIZE: usize = 8 * 1024;
@@ -74,6 +76,57 @@ pub fn async_bipipe_buffered() -> (
     ((r1, w2).into(), (r2, w1).into())
 }
 
+/// One end of a bidirectional pipe (see `async_bipipe_cloneable()`), which can be cloned in order
+/// to read from and write to it from separate threads.
+pub struct CloneableEnd {
+    reader: Arc<Mutex<Reader>>,
+    writer: Arc<Mutex<BufWriter>>,
+}
+
+/// Creates a pair of pipes for bidirectional communication using buffered writer, whose ends can
+/// be cloned, a bit like TCP streams.
+pub fn async_bipipe_cloneable() -> (CloneableEnd, CloneableEnd) {
+    let (r1, w1) = async_pipe_buffered();
+    let (r2, w2) = async_pipe_buffered();
+    (CloneableEnd::new(r1, w2), CloneableEnd::new(r2, w1))
+}
+
+impl CloneableEnd {
+    fn new(reader: Reader, writer: BufWriter) -> Self {
+        Self {
+            reader: Arc::new(Mutex::new(reader)),
+            writer: Arc::new(Mutex::new(writer)),
+        }
+    }
+}
+
+impl Read for CloneableEnd {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.lock().expect("reader lock poisoned").read(buf)
+    }
+}
+
+impl Write for CloneableEnd {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.lock().expect("writer lock poisoned").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.lock().expect("writer lock poisoned").flush()
+    }
+}
+
+impl TryClone for CloneableEnd {
+    type Error = io::Error;
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            reader: self.reader.clone(),
+            writer: self.writer.clone(),
+        })
+    }
+}
+
 fn epipe() -> io::Error {
     io::Error::new(io::ErrorKind::BrokenPipe, "pipe reader has been dropped")
 }
@@ -1 +1,8 @@
+mod async_secret_connection;
+mod blocksync;
+mod mconnection;
+mod node_info;
+mod pex;
+mod privval;
 mod secret_connection;
+mod transport;
@@ -0,0 +1,143 @@
+use std::{
+    io::{Read as _, Write as _},
+    net, thread,
+};
+
+use rand_core::OsRng;
+use tendermint_p2p::secret_connection::{AsyncSecretConnection, SecretConnection, Version};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+#[tokio::test]
+async fn test_handshake_and_messages() {
+    let (stream1, stream2) = tokio::io::duplex(64);
+    let (reader1, writer1) = tokio::io::split(stream1);
+    let (reader2, writer2) = tokio::io::split(stream2);
+
+    let key1 = ed25519_consensus::SigningKey::new(OsRng);
+    let key2 = ed25519_consensus::SigningKey::new(OsRng);
+    let pubkey1 = key1.verification_key();
+    let pubkey2 = key2.verification_key();
+    let (conn1, conn2) = tokio::try_join!(
+        AsyncSecretConnection::new(reader1, writer1, key1, Version::V0_34),
+        AsyncSecretConnection::new(reader2, writer2, key2, Version::V0_34),
+    )
+    .expect("handshake to succeed");
+    assert_eq!(conn1.remote_pubkey().ed25519(), Some(pubkey2));
+    assert_eq!(conn2.remote_pubkey().ed25519(), Some(pubkey1));
+
+    // Messages larger than a frame go through a pipe smaller than a frame.
+    let message: Vec<u8> = (0..5000_u32).map(|i| (i % 251) as u8).collect();
+    let (mut conn1, mut conn2) = (conn1, conn2);
+    let expected = message.clone();
+    let (_, received) = tokio::join!(
+        async {
+            conn1.write_all(&message).await.unwrap();
+            conn1.write_all(b"Queen's Gambit").await.unwrap();
+            conn1.flush().await.unwrap();
+        },
+        async {
+            let mut buf = vec![0; expected.len() + 14];
+            conn2.read_exact(&mut buf).await.unwrap();
+            buf
+        },
+    );
+    assert_eq!(&received[..expected.len()], expected.as_slice());
+    assert_eq!(&received[expected.len()..], b"Queen's Gambit");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_split_halves_in_separate_tasks() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // The remote peer echoes everything back.
+    let echo = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, writer) = stream.into_split();
+        let conn = new_peer_conn(reader, writer).await;
+        let (mut sender, mut receiver) = conn.split();
+        tokio::io::copy(&mut receiver, &mut sender).await.unwrap();
+        sender.shutdown().await.unwrap();
+    });
+
+    let (reader, writer) = TcpStream::connect(addr).await.unwrap().into_split();
+    let (mut sender, mut receiver) = new_peer_conn(reader, writer).await.split();
+    let write = tokio::spawn(async move {
+        for i in 0..100_u8 {
+            sender.write_all(&[i; 100]).await.unwrap();
+        }
+        sender.shutdown().await.unwrap();
+    });
+    let read = tokio::spawn(async move {
+        let mut buf = vec![];
+        receiver.read_to_end(&mut buf).await.unwrap();
+        buf
+    });
+
+    write.await.expect("writing task has panicked");
+    let received = read.await.expect("reading task has panicked");
+    echo.await.expect("echoing task has panicked");
+    let expected: Vec<u8> = (0..100_u8).flat_map(|i| [i; 100]).collect();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn test_interop_with_sync_connection() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let peer = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let privkey = ed25519_consensus::SigningKey::new(OsRng);
+        let mut conn =
+            SecretConnection::new(stream, privkey, Version::V0_34).expect("handshake to succeed");
+        let mut buf = [0; 2048];
+        conn.read_exact(&mut buf).unwrap();
+        conn.write_all(&buf).unwrap();
+    });
+
+    let (reader, writer) = TcpStream::connect(addr).await.unwrap().into_split();
+    let mut conn = new_peer_conn(reader, writer).await;
+    let message = [7; 2048];
+    conn.write_all(&message).await.unwrap();
+    conn.flush().await.unwrap();
+    let mut buf = [0; 2048];
+    conn.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, message);
+
+    peer.join().expect("peer thread has panicked");
+}
+
+#[tokio::test]
+async fn test_end_of_stream() {
+    let (stream1, stream2) = tokio::io::duplex(4096);
+    let (reader1, writer1) = tokio::io::split(stream1);
+    let (reader2, writer2) = tokio::io::split(stream2);
+    let (conn1, conn2) = tokio::join!(
+        new_peer_conn(reader1, writer1),
+        new_peer_conn(reader2, writer2),
+    );
+
+    // Shutting down the sending half between frames cleanly ends the stream.
+    let (mut sender, _) = conn1.split();
+    let (_, mut receiver) = conn2.split();
+    sender.write_all(b"bye").await.unwrap();
+    sender.shutdown().await.unwrap();
+    let mut buf = vec![];
+    receiver.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"bye");
+}
+
+async fn new_peer_conn<R, W>(reader: R, writer: W) -> AsyncSecretConnection<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let privkey = ed25519_consensus::SigningKey::new(OsRng);
+    AsyncSecretConnection::new(reader, writer, privkey, Version::V0_34)
+        .await
+        .expect("handshake to succeed")
+}
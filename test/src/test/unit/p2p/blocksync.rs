@@ -0,0 +1,406 @@
+use std::{
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use ed25519_consensus::SigningKey;
+use prost::Message as _;
+use rand_core::OsRng;
+use tendermint::{
+    account,
+    block::{self, parts, Commit, CommitSig, Header},
+    chain,
+    crypto::{default::Sha256, Sha256 as _},
+    evidence, merkle, node, validator, vote, AppHash, Block, Hash, PublicKey, Signature, Time,
+};
+use tendermint_p2p::{
+    blocksync::{BlocksyncClient, BlocksyncConfig, BlocksyncMessage},
+    error::ErrorDetail,
+    transport::{ConnectInfo, Connection, Endpoint, StreamId, StreamSend},
+};
+use tendermint_proto::v0_37::types::CommitSig as RawCommitSig;
+
+use crate::p2p::{bind, listen_addr};
+
+const CHAIN_ID: &str = "test-chain";
+const CHANNELS: &[u8] = &[0x00, 0x40];
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn test_message_round_trip() {
+    let keys = keys(1);
+    let blocks = chain(2, &keys, &keys);
+    let messages = [
+        BlocksyncMessage::BlockRequest(3_u32.into()),
+        BlocksyncMessage::NoBlockResponse(3_u32.into()),
+        BlocksyncMessage::BlockResponse(Box::new(blocks[1].clone())),
+        BlocksyncMessage::StatusRequest,
+        BlocksyncMessage::StatusResponse {
+            base: 1_u32.into(),
+            height: 2_u32.into(),
+        },
+    ];
+    for msg in messages {
+        assert_eq!(BlocksyncMessage::decode(&msg.encode()).unwrap(), msg);
+    }
+
+    let err = BlocksyncMessage::decode(&[]).unwrap_err();
+    assert!(matches!(
+        err.detail(),
+        ErrorDetail::MalformedBlocksyncMessage(_)
+    ));
+}
+
+#[test]
+fn test_download_from_several_peers() {
+    let keys = keys(3);
+    let blocks = chain(8, &keys, &keys);
+    let client = client(
+        validator_set(&keys),
+        BlocksyncConfig {
+            max_pending_requests_per_peer: 2,
+            ..BlocksyncConfig::default()
+        },
+    );
+    let peers = [
+        fake_peer(&client, 1, blocks.clone()),
+        fake_peer(&client, 2, blocks[..5].to_vec()),
+    ];
+
+    // The last block cannot be verified until the next one is committed.
+    for expected in &blocks[..7] {
+        let block = client.next_block(TIMEOUT).unwrap().unwrap();
+        assert_eq!(&block, expected);
+    }
+    assert_eq!(client.height(), 8_u32.into());
+    assert!(client.is_caught_up());
+    assert!(client
+        .next_block(Duration::from_millis(100))
+        .unwrap()
+        .is_none());
+
+    for (id, _) in &peers {
+        client.remove_peer(id);
+    }
+    for (_, handle) in peers {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn test_rejects_forged_commits() {
+    let keys = keys(3);
+    let forgers = self::keys(3);
+    let blocks = chain(4, &keys, &forgers);
+    let client = client(validator_set(&keys), BlocksyncConfig::default());
+    let (id, _handle) = fake_peer(&client, 1, blocks);
+
+    let err = client.next_block(TIMEOUT).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::InvalidSyncedBlock(_)));
+
+    // The peer is dropped from the pool.
+    let status = BlocksyncMessage::StatusResponse {
+        base: 1_u32.into(),
+        height: 4_u32.into(),
+    };
+    let err = client.receive(&id, &status.encode()).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::UnknownBlocksyncPeer(_)));
+}
+
+#[test]
+fn test_rejects_forged_transactions() {
+    let keys = keys(1);
+    let mut blocks = chain(4, &keys, &keys);
+    // The header, and therefore the commit, are genuine.
+    blocks[0].data[0] = b"forged".to_vec();
+    let client = client(validator_set(&keys), BlocksyncConfig::default());
+    let (_id, _handle) = fake_peer(&client, 1, blocks);
+
+    let err = client.next_block(TIMEOUT).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::InvalidSyncedBlock(_)));
+}
+
+#[test]
+fn test_validator_set_change() {
+    let keys = keys(2);
+    // The second validator joins at height 3.
+    let blocks = build_chain(4, |height| {
+        let keys = if height < 3 { &keys[..1] } else { &keys[..] };
+        (keys, keys)
+    });
+    let client = client(validator_set(&keys[..1]), BlocksyncConfig::default());
+    let (_id, _handle) = fake_peer(&client, 1, blocks.clone());
+
+    for expected in &blocks[..2] {
+        assert_eq!(&client.next_block(TIMEOUT).unwrap().unwrap(), expected);
+    }
+    let err = client.next_block(TIMEOUT).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::ValidatorSetChanged(_)));
+
+    client.update_validators(validator_set(&keys));
+    assert_eq!(client.next_block(TIMEOUT).unwrap().unwrap(), blocks[2]);
+}
+
+#[test]
+fn test_rejects_unannounced_validators() {
+    let keys = keys(2);
+    let blocks = chain(3, &keys, &keys);
+    let client = client(validator_set(&keys[..1]), BlocksyncConfig::default());
+    let (id, _handle) = fake_peer(&client, 1, blocks);
+
+    // No verified header vouches for the validators of the first block.
+    let err = client.next_block(TIMEOUT).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::InvalidSyncedBlock(_)));
+    let status = BlocksyncMessage::StatusResponse {
+        base: 1_u32.into(),
+        height: 3_u32.into(),
+    };
+    let err = client.receive(&id, &status.encode()).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::UnknownBlocksyncPeer(_)));
+}
+
+#[test]
+fn test_receive_unexpected_messages() {
+    let keys = keys(1);
+    let blocks = chain(2, &keys, &keys);
+    let client = client(validator_set(&keys), BlocksyncConfig::default());
+    let (tx, rx) = flume::unbounded();
+    let id = node::Id::new([1; 20]);
+
+    let status = BlocksyncMessage::StatusResponse {
+        base: 2_u32.into(),
+        height: 1_u32.into(),
+    };
+    let err = client.receive(&id, &status.encode()).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::UnknownBlocksyncPeer(_)));
+
+    client
+        .add_peer(id, move |msg| {
+            let _ = tx.send(msg);
+            Ok(())
+        })
+        .unwrap();
+    let sent = BlocksyncMessage::decode(&rx.recv().unwrap()).unwrap();
+    assert_eq!(sent, BlocksyncMessage::StatusRequest);
+
+    let err = client.receive(&id, &status.encode()).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::InvalidPeerStatus(_)));
+
+    let block = BlocksyncMessage::BlockResponse(Box::new(blocks[0].clone()));
+    let err = client.receive(&id, &block.encode()).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::UnsolicitedBlock(_)));
+
+    // The client has no blocks to serve.
+    let request = BlocksyncMessage::BlockRequest(1_u32.into());
+    let response = client.receive(&id, &request.encode()).unwrap().unwrap();
+    assert_eq!(
+        BlocksyncMessage::decode(&response).unwrap(),
+        BlocksyncMessage::NoBlockResponse(1_u32.into())
+    );
+}
+
+#[test]
+fn test_download_over_transport() {
+    let keys = keys(1);
+    let blocks = chain(4, &keys, &keys);
+    let (endpoint1, _incoming1, _) = bind("node1", CHAIN_ID, CHANNELS);
+    let (endpoint2, mut incoming2, id2) = bind("node2", CHAIN_ID, CHANNELS);
+
+    let conn1 = endpoint1
+        .connect(ConnectInfo {
+            addrs: listen_addr(&endpoint2),
+            id: id2,
+        })
+        .unwrap();
+    let conn2 = incoming2.next().unwrap().unwrap();
+
+    // The second node serves its blocks.
+    let (read, send) = conn2.open_bidirectional(StreamId::BlockSync).unwrap();
+    let served = blocks.clone();
+    let _server = thread::spawn(move || {
+        for msg in read {
+            let response = match BlocksyncMessage::decode(&msg.unwrap()).unwrap() {
+                BlocksyncMessage::StatusRequest => BlocksyncMessage::StatusResponse {
+                    base: 1_u32.into(),
+                    height: 4_u32.into(),
+                },
+                BlocksyncMessage::BlockRequest(height) => BlocksyncMessage::BlockResponse(
+                    Box::new(served[height.value() as usize - 1].clone()),
+                ),
+                _ => continue,
+            };
+            send.send(response.encode()).unwrap();
+        }
+    });
+
+    let client = client(validator_set(&keys), BlocksyncConfig::default());
+    let _handle = client.serve(&conn1).unwrap();
+    for expected in &blocks[..3] {
+        assert_eq!(&client.next_block(TIMEOUT).unwrap().unwrap(), expected);
+    }
+}
+
+fn client(validators: validator::Set, config: BlocksyncConfig) -> BlocksyncClient {
+    BlocksyncClient::new(
+        CHAIN_ID.parse().unwrap(),
+        block::Height::default(),
+        validators,
+        config,
+    )
+}
+
+/// Adds a peer to the pool of the client, serving the given blocks from
+/// the first one, until it is removed from the pool.
+fn fake_peer(client: &BlocksyncClient, id: u8, blocks: Vec<Block>) -> (node::Id, JoinHandle<()>) {
+    let id = node::Id::new([id; 20]);
+    let (tx, rx) = flume::unbounded();
+    client
+        .add_peer(id, move |msg| {
+            let _ = tx.send(msg);
+            Ok(())
+        })
+        .unwrap();
+    let client = client.clone();
+    let handle = thread::spawn(move || {
+        for msg in rx.iter() {
+            let response = match BlocksyncMessage::decode(&msg).unwrap() {
+                BlocksyncMessage::StatusRequest => BlocksyncMessage::StatusResponse {
+                    base: 1_u32.into(),
+                    height: (blocks.len() as u32).into(),
+                },
+                BlocksyncMessage::BlockRequest(height) => {
+                    match blocks.get(height.value() as usize - 1) {
+                        Some(block) => BlocksyncMessage::BlockResponse(Box::new(block.clone())),
+                        None => BlocksyncMessage::NoBlockResponse(height),
+                    }
+                },
+                _ => continue,
+            };
+            if client.receive(&id, &response.encode()).is_err() {
+                break;
+            }
+        }
+    });
+    (id, handle)
+}
+
+fn keys(count: usize) -> Vec<SigningKey> {
+    (0..count).map(|_| SigningKey::new(OsRng)).collect()
+}
+
+fn public_key(key: &SigningKey) -> PublicKey {
+    PublicKey::from_raw_ed25519(key.verification_key().as_bytes()).unwrap()
+}
+
+fn validator_set(keys: &[SigningKey]) -> validator::Set {
+    validator::Set::without_proposer(
+        keys.iter()
+            .map(|key| validator::Info::new(public_key(key), vote::Power::from(10_u32)))
+            .collect(),
+    )
+}
+
+/// Builds a chain of blocks of the validators with the given keys, whose
+/// commits are signed with the given signing keys.
+fn chain(len: u32, keys: &[SigningKey], signers: &[SigningKey]) -> Vec<Block> {
+    build_chain(len, |_| (keys, signers))
+}
+
+/// Builds a chain of blocks, given the keys of the validators at each height
+/// and the keys signing their commits.
+fn build_chain<'a>(
+    len: u32,
+    keys_at: impl Fn(u32) -> (&'a [SigningKey], &'a [SigningKey]),
+) -> Vec<Block> {
+    let chain_id: chain::Id = CHAIN_ID.parse().unwrap();
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut last_commit = None;
+    for height in 1..=len {
+        let (keys, signers) = keys_at(height);
+        let txs = vec![format!("tx{height}").into_bytes()];
+        let tx_hashes: Vec<_> = txs.iter().map(|tx| Sha256::digest(tx).to_vec()).collect();
+        let signatures: Vec<_> = last_commit
+            .iter()
+            .flat_map(|commit: &Commit| commit.signatures.iter())
+            .map(|sig| RawCommitSig::from(sig.clone()).encode_to_vec())
+            .collect();
+        let header = Header {
+            version: block::header::Version { block: 11, app: 0 },
+            chain_id: chain_id.clone(),
+            height: height.into(),
+            time: time(height),
+            last_block_id: last_commit.as_ref().map(|commit| commit.block_id),
+            last_commit_hash: Some(merkle_hash(&signatures)),
+            data_hash: Some(merkle_hash(&tx_hashes)),
+            validators_hash: validator_set(keys).hash(),
+            next_validators_hash: validator_set(keys_at(height + 1).0).hash(),
+            consensus_hash: Hash::Sha256([0x01; 32]),
+            app_hash: AppHash::default(),
+            last_results_hash: None,
+            evidence_hash: Some(merkle_hash(&[])),
+            proposer_address: account::Id::from(public_key(&keys[0])),
+        };
+        let block_id = block::Id {
+            hash: header.hash(),
+            part_set_header: parts::Header::new(1, Hash::Sha256([height as u8; 32])).unwrap(),
+        };
+        blocks.push(
+            Block::new(
+                header,
+                txs,
+                evidence::Data::new(Vec::new()),
+                last_commit.take(),
+            )
+            .unwrap(),
+        );
+        last_commit = Some(commit(&chain_id, height, block_id, keys, signers));
+    }
+    blocks
+}
+
+fn merkle_hash(leaves: &[Vec<u8>]) -> Hash {
+    Hash::Sha256(merkle::simple_hash_from_byte_vectors::<Sha256>(leaves))
+}
+
+fn commit(
+    chain_id: &chain::Id,
+    height: u32,
+    block_id: block::Id,
+    keys: &[SigningKey],
+    signers: &[SigningKey],
+) -> Commit {
+    let signatures = keys
+        .iter()
+        .zip(signers)
+        .enumerate()
+        .map(|(index, (key, signer))| {
+            let vote = vote::Vote {
+                vote_type: vote::Type::Precommit,
+                height: height.into(),
+                round: block::Round::default(),
+                block_id: Some(block_id),
+                timestamp: Some(time(height + 1)),
+                validator_address: account::Id::from(public_key(key)),
+                validator_index: (index as u32).try_into().unwrap(),
+                signature: None,
+            };
+            let sign_bytes = vote.to_signable_vec(chain_id.clone()).unwrap();
+            let signature = signer.sign(&sign_bytes);
+            CommitSig::BlockIdFlagCommit {
+                validator_address: vote.validator_address,
+                timestamp: time(height + 1),
+                signature: Signature::new(signature.to_bytes()).unwrap(),
+            }
+        })
+        .collect();
+    Commit {
+        height: height.into(),
+        round: block::Round::default(),
+        block_id,
+        signatures,
+    }
+}
+
+fn time(height: u32) -> Time {
+    Time::from_unix_timestamp(1_600_000_000 + i64::from(height), 0).unwrap()
+}
@@ -0,0 +1,381 @@
+use std::{
+    io::{Read, Write},
+    slice, thread,
+    time::{Duration, Instant},
+};
+
+use prost::Message as _;
+use rand_core::OsRng;
+use tendermint_config::TendermintConfig;
+use tendermint_p2p::{
+    error::{Error, ErrorDetail},
+    mconnection::{ChannelDescriptor, MConnConfig, MConnection},
+    secret_connection::{SecretConnection, Version},
+    transport::tcp::TcpConfig,
+};
+use tendermint_proto::v0_37::p2p::{packet::Sum, Packet};
+
+use crate::pipe;
+
+const CONSENSUS: u8 = 0x20;
+const MEMPOOL: u8 = 0x30;
+
+#[test]
+fn test_multiplexed_messages() {
+    let channels = [
+        ChannelDescriptor::new(CONSENSUS, 10),
+        ChannelDescriptor::new(MEMPOOL, 5),
+    ];
+    let (conn1, conn2) = new_mconn_pair(&channels, &channels, MConnConfig::default());
+
+    // Spans many packets and secret connection frames.
+    let large: Vec<u8> = (0..100_000_u32).map(|i| (i % 251) as u8).collect();
+    let sender = conn1.sender(MEMPOOL).unwrap();
+    let large_copy = large.clone();
+    let mempool = thread::spawn(move || {
+        sender.send(large_copy).unwrap();
+        sender.send(b"tx".to_vec()).unwrap();
+    });
+    let sender = conn1.sender(CONSENSUS).unwrap();
+    for i in 0..10_u8 {
+        sender.send(vec![i; usize::from(i)]).unwrap();
+    }
+    mempool.join().expect("mempool sender thread has panicked");
+
+    let consensus = conn2.receiver(CONSENSUS).unwrap();
+    for i in 0..10_u8 {
+        assert_eq!(consensus.recv().unwrap(), vec![i; usize::from(i)]);
+    }
+    let mempool = conn2.receiver(MEMPOOL).unwrap();
+    assert_eq!(mempool.recv().unwrap(), large);
+    assert_eq!(mempool.recv().unwrap(), b"tx");
+    assert_eq!(
+        mempool.recv_timeout(Duration::from_millis(10)).unwrap(),
+        None
+    );
+
+    // Messages flow both ways.
+    conn2
+        .sender(CONSENSUS)
+        .unwrap()
+        .send(b"vote".to_vec())
+        .unwrap();
+    assert_eq!(conn1.receiver(CONSENSUS).unwrap().recv().unwrap(), b"vote");
+
+    conn1.stop().unwrap();
+    conn2.stop().unwrap();
+}
+
+#[test]
+fn test_pings_are_answered() {
+    let channels = [ChannelDescriptor::new(CONSENSUS, 1)];
+    let config = MConnConfig {
+        ping_interval: Duration::from_millis(10),
+        pong_timeout: Duration::from_millis(100),
+        ..MConnConfig::default()
+    };
+    let (conn1, conn2) = new_mconn_pair(&channels, &channels, config);
+
+    thread::sleep(Duration::from_millis(300));
+    conn1
+        .sender(CONSENSUS)
+        .unwrap()
+        .send(b"still there?".to_vec())
+        .unwrap();
+    assert_eq!(
+        conn2.receiver(CONSENSUS).unwrap().recv().unwrap(),
+        b"still there?"
+    );
+
+    conn1.stop().unwrap();
+    conn2.stop().unwrap();
+}
+
+#[test]
+fn test_pong_timeout() {
+    let (reader, _unused_writer) = pipe::async_pipe_buffered();
+    let (mut peer_reader, writer) = pipe::async_pipe_buffered();
+    let config = MConnConfig {
+        ping_interval: Duration::from_millis(10),
+        pong_timeout: Duration::from_millis(50),
+        ..MConnConfig::default()
+    };
+    let conn = MConnection::new(
+        reader,
+        writer,
+        &[ChannelDescriptor::new(CONSENSUS, 1)],
+        config,
+    )
+    .unwrap();
+
+    // The silent peer receives a ping, which it never answers.
+    assert!(matches!(read_packet(&mut peer_reader), Sum::PacketPing(_)));
+    thread::sleep(Duration::from_millis(200));
+    let err = conn.stop().unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::PongTimeout(_)));
+}
+
+#[test]
+fn test_oversized_message() {
+    let channels1 = [ChannelDescriptor::new(CONSENSUS, 1)];
+    let channels2 = [ChannelDescriptor {
+        recv_message_capacity: 2048,
+        ..ChannelDescriptor::new(CONSENSUS, 1)
+    }];
+    let (conn1, conn2) = new_mconn_pair(&channels1, &channels2, MConnConfig::default());
+
+    conn1
+        .sender(CONSENSUS)
+        .unwrap()
+        .send(vec![0; 2048])
+        .unwrap();
+    conn1
+        .sender(CONSENSUS)
+        .unwrap()
+        .send(vec![0; 2049])
+        .unwrap();
+    let receiver = conn2.receiver(CONSENSUS).unwrap();
+    assert_eq!(receiver.recv().unwrap().len(), 2048);
+    assert!(receiver.recv().is_err());
+
+    let err = conn2.stop().unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::MessageTooLarge(_)));
+}
+
+#[test]
+fn test_oversized_packet() {
+    // The length prefix alone announces a packet larger than allowed.
+    let mut prefix = Vec::new();
+    prost::encoding::encode_varint(1 << 20, &mut prefix);
+    let err = receive_raw(&prefix);
+    assert!(matches!(err.detail(), ErrorDetail::PacketTooLarge(_)));
+}
+
+#[test]
+fn test_overlong_packet_prefix() {
+    let err = receive_raw(&[0x80; 11]);
+    assert!(matches!(err.detail(), ErrorDetail::PacketDecode(_)));
+}
+
+#[test]
+fn test_unknown_channel() {
+    let channels1 = [
+        ChannelDescriptor::new(CONSENSUS, 1),
+        ChannelDescriptor::new(MEMPOOL, 1),
+    ];
+    let channels2 = [ChannelDescriptor::new(CONSENSUS, 1)];
+    let (conn1, conn2) = new_mconn_pair(&channels1, &channels2, MConnConfig::default());
+
+    conn1.sender(MEMPOOL).unwrap().send(b"tx".to_vec()).unwrap();
+    assert!(conn2.receiver(CONSENSUS).unwrap().recv().is_err());
+
+    let err = conn2.stop().unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::UnknownChannel(_)));
+}
+
+#[test]
+fn test_duplicate_channel() {
+    let (reader, writer) = pipe::async_pipe_buffered();
+    let res = MConnection::new(
+        reader,
+        writer,
+        &[
+            ChannelDescriptor::new(CONSENSUS, 1),
+            ChannelDescriptor::new(CONSENSUS, 2),
+        ],
+        MConnConfig::default(),
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_priorities() {
+    let (reader, _unused_writer) = pipe::async_pipe_buffered();
+    let (mut peer_reader, writer) = pipe::async_pipe_buffered();
+    let conn = MConnection::new(
+        reader,
+        writer,
+        &[
+            ChannelDescriptor::new(CONSENSUS, 10),
+            ChannelDescriptor::new(MEMPOOL, 1),
+        ],
+        MConnConfig::default(),
+    )
+    .unwrap();
+
+    // Both channels have 1000 packets to send.
+    conn.sender(CONSENSUS)
+        .unwrap()
+        .send(vec![0; 1024 * 1000])
+        .unwrap();
+    conn.sender(MEMPOOL)
+        .unwrap()
+        .send(vec![0; 1024 * 1000])
+        .unwrap();
+
+    // The mempool channel gets about a tenth of the bandwidth until the
+    // consensus channel is done.
+    let mut mempool_packets = 0;
+    loop {
+        match read_packet(&mut peer_reader) {
+            Sum::PacketMsg(msg) if msg.channel_id == i32::from(CONSENSUS) => {
+                if msg.eof {
+                    break;
+                }
+            },
+            Sum::PacketMsg(_) => mempool_packets += 1,
+            packet => panic!("unexpected packet: {packet:?}"),
+        }
+    }
+    assert!(mempool_packets > 0);
+    assert!(mempool_packets < 200);
+
+    conn.stop().unwrap();
+}
+
+#[test]
+fn test_status() {
+    let channels = [ChannelDescriptor::new(CONSENSUS, 1)];
+    let (conn1, conn2) = new_mconn_pair(&channels, &channels, MConnConfig::default());
+
+    conn1
+        .sender(CONSENSUS)
+        .unwrap()
+        .send(vec![1; 10_000])
+        .unwrap();
+    assert_eq!(
+        conn2.receiver(CONSENSUS).unwrap().recv().unwrap().len(),
+        10_000
+    );
+
+    wait_until(|| conn2.status().recv.bytes == conn1.status().send.bytes);
+    let (status1, status2) = (conn1.status(), conn2.status());
+    assert!(status1.send.bytes >= 10_000);
+    assert!(status1.send.current_rate > 0);
+    assert!(status1.send.average_rate > 0);
+    assert_eq!(status1.send.limit, MConnConfig::default().send_rate);
+    assert_eq!(status2.recv.bytes, status1.send.bytes);
+    assert_eq!(status2.send.bytes, 0);
+
+    conn1.stop().unwrap();
+    conn2.stop().unwrap();
+}
+
+#[test]
+fn test_send_rate_limit() {
+    let (reader, _unused_writer) = pipe::async_pipe_buffered();
+    let (mut peer_reader, writer) = pipe::async_pipe_buffered();
+    let config = MConnConfig {
+        send_rate: 50_000,
+        ..MConnConfig::default()
+    };
+    let conn = MConnection::new(
+        reader,
+        writer,
+        &[ChannelDescriptor::new(CONSENSUS, 1)],
+        config,
+    )
+    .unwrap();
+
+    // The first second worth of data goes out at once, the rest at the
+    // configured rate.
+    let start = Instant::now();
+    conn.sender(CONSENSUS)
+        .unwrap()
+        .send(vec![0; 100_000])
+        .unwrap();
+    let mut received = 0;
+    while received < 100_000 {
+        match read_packet(&mut peer_reader) {
+            Sum::PacketMsg(msg) => received += msg.data.len(),
+            packet => panic!("unexpected packet: {packet:?}"),
+        }
+    }
+    assert!(start.elapsed() >= Duration::from_millis(900));
+    wait_until(|| conn.status().send.bytes > 100_000);
+
+    conn.stop().unwrap();
+}
+
+#[test]
+fn test_config_from_p2p_config() {
+    let config = TendermintConfig::parse_toml(include_str!(
+        "../../../../../config/tests/support/config/config.toml"
+    ))
+    .unwrap();
+    let p2p = config.p2p;
+
+    let tcp = TcpConfig::from(&p2p);
+    assert_eq!(tcp.dial_timeout, Duration::from_secs(3));
+    assert_eq!(tcp.handshake_timeout, Duration::from_secs(20));
+    assert_eq!(tcp.mconn.max_packet_msg_payload_size, 1024);
+    assert_eq!(tcp.mconn.send_rate, p2p.send_rate.bytes_per_sec());
+    assert_eq!(tcp.mconn.recv_rate, p2p.recv_rate.bytes_per_sec());
+}
+
+/// Feeds the given bytes to a connection, returning the error it fails with.
+fn receive_raw(bytes: &[u8]) -> Error {
+    let (reader, mut peer_writer) = pipe::async_pipe_buffered();
+    let (_peer_reader, writer) = pipe::async_pipe_buffered();
+    let conn = MConnection::new(
+        reader,
+        writer,
+        &[ChannelDescriptor::new(CONSENSUS, 1)],
+        MConnConfig::default(),
+    )
+    .unwrap();
+
+    peer_writer.write_all(bytes).unwrap();
+    assert!(conn.receiver(CONSENSUS).unwrap().recv().is_err());
+    conn.stop().unwrap_err()
+}
+
+fn new_mconn_pair(
+    channels1: &[ChannelDescriptor],
+    channels2: &[ChannelDescriptor],
+    config: MConnConfig,
+) -> (MConnection, MConnection) {
+    let (pipe1, pipe2) = pipe::async_bipipe_cloneable();
+
+    let channels2 = channels2.to_vec();
+    let peer2 = thread::spawn(move || {
+        let conn = new_peer_conn(pipe2);
+        MConnection::from_secret_connection(conn, &channels2, config).unwrap()
+    });
+    let conn1 =
+        MConnection::from_secret_connection(new_peer_conn(pipe1), channels1, config).unwrap();
+    let conn2 = peer2.join().expect("peer2 thread has panicked");
+    (conn1, conn2)
+}
+
+fn new_peer_conn(io_handler: pipe::CloneableEnd) -> SecretConnection<pipe::CloneableEnd> {
+    let privkey = ed25519_consensus::SigningKey::new(OsRng);
+    SecretConnection::new(io_handler, privkey, Version::V0_34).expect("handshake to succeed")
+}
+
+/// Waits for the given condition to hold, panicking after a few seconds.
+///
+/// Bytes are counted once written, which may complete after the peer has
+/// received them, hence the counters are only eventually up to date.
+fn wait_until(mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !condition() {
+        assert!(Instant::now() < deadline, "condition not met in time");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn read_packet<R: Read>(reader: &mut R) -> Sum {
+    let mut len = 0_usize;
+    for shift in (0..).step_by(7) {
+        let mut byte = 0_u8;
+        reader.read_exact(slice::from_mut(&mut byte)).unwrap();
+        len |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf).unwrap();
+    Packet::decode(buf.as_slice()).unwrap().sum.unwrap()
+}
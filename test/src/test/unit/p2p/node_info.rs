@@ -0,0 +1,144 @@
+use std::thread;
+
+use rand_core::OsRng;
+use tendermint::node;
+use tendermint_p2p::{
+    error::ErrorDetail,
+    node_info::{self, MAX_NUM_CHANNELS},
+    secret_connection::{PublicKey, SecretConnection, Version},
+};
+use tendermint_proto::v0_37::p2p::{DefaultNodeInfo, DefaultNodeInfoOther, ProtocolVersion};
+
+use crate::pipe;
+
+#[test]
+fn test_exchange() {
+    let (pipe1, pipe2) = pipe::async_bipipe_buffered();
+    let key1 = ed25519_consensus::SigningKey::new(OsRng);
+    let key2 = ed25519_consensus::SigningKey::new(OsRng);
+    let info1 = node_info(&key1, "node1");
+    let info2 = node_info(&key2, "node2");
+
+    let (expected1, expected2) = (info1.clone(), info2.clone());
+    let peer = thread::spawn(move || {
+        let mut conn = SecretConnection::new(pipe2, key2, Version::V0_34).unwrap();
+        let remote = node_info::exchange(&mut conn, &info2).unwrap();
+        assert_eq!(remote, expected1);
+    });
+    let mut conn = SecretConnection::new(pipe1, key1, Version::V0_34).unwrap();
+    let remote = node_info::exchange(&mut conn, &info1).unwrap();
+    assert_eq!(remote, expected2);
+
+    peer.join().expect("peer thread has panicked");
+}
+
+#[test]
+fn test_exchange_with_impostor() {
+    let (pipe1, pipe2) = pipe::async_bipipe_buffered();
+    let key1 = ed25519_consensus::SigningKey::new(OsRng);
+    let key2 = ed25519_consensus::SigningKey::new(OsRng);
+    let info1 = node_info(&key1, "node1");
+    // The remote peer claims the identity of the local node.
+    let impostor = node_info(&key1, "node2");
+
+    let peer = thread::spawn(move || {
+        let mut conn = SecretConnection::new(pipe2, key2, Version::V0_34).unwrap();
+        let _ = node_info::exchange(&mut conn, &impostor);
+    });
+    let mut conn = SecretConnection::new(pipe1, key1, Version::V0_34).unwrap();
+    let err = node_info::exchange(&mut conn, &info1).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::PeerIdMismatch(_)));
+
+    peer.join().expect("peer thread has panicked");
+}
+
+#[test]
+fn test_validate() {
+    let key = ed25519_consensus::SigningKey::new(OsRng);
+    let info = node_info(&key, "node");
+    node_info::validate(&info).unwrap();
+
+    let invalid = with_raw(&info, |raw| raw.moniker = " ".to_string());
+    let err = node_info::validate(&invalid).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::EmptyMoniker(_)));
+
+    let invalid = with_raw(&info, |raw| raw.channels = vec![0x20, 0x30, 0x20]);
+    let err = node_info::validate(&invalid).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::DuplicateChannel(_)));
+
+    let invalid = with_raw(&info, |raw| {
+        raw.channels = (0..=MAX_NUM_CHANNELS as u8).collect();
+    });
+    let err = node_info::validate(&invalid).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::TooManyChannels(_)));
+}
+
+#[test]
+fn test_check_compatible() {
+    let key1 = ed25519_consensus::SigningKey::new(OsRng);
+    let key2 = ed25519_consensus::SigningKey::new(OsRng);
+    let local = node_info(&key1, "node1");
+    let remote = node_info(&key2, "node2");
+    node_info::check_compatible(&local, &remote).unwrap();
+
+    let other = with_raw(&remote, |raw| {
+        raw.protocol_version = Some(ProtocolVersion {
+            p2p: 8,
+            block: 10,
+            app: 0,
+        });
+    });
+    let err = node_info::check_compatible(&local, &other).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::BlockVersionMismatch(_)));
+
+    // Neither the p2p nor the application version matters.
+    let other = with_raw(&remote, |raw| {
+        raw.protocol_version = Some(ProtocolVersion {
+            p2p: 7,
+            block: 11,
+            app: 42,
+        });
+    });
+    node_info::check_compatible(&local, &other).unwrap();
+
+    let other = with_raw(&remote, |raw| raw.network = "other-chain".to_string());
+    let err = node_info::check_compatible(&local, &other).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::NetworkMismatch(_)));
+
+    let other = with_raw(&remote, |raw| raw.channels = vec![0x60]);
+    let err = node_info::check_compatible(&local, &other).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::NoCommonChannels(_)));
+
+    // A node without channels is compatible with any other node.
+    let crawler = with_raw(&local, |raw| raw.channels = vec![]);
+    node_info::check_compatible(&crawler, &other).unwrap();
+}
+
+fn node_info(key: &ed25519_consensus::SigningKey, moniker: &str) -> node::Info {
+    let id = PublicKey::from(key).peer_id();
+    DefaultNodeInfo {
+        protocol_version: Some(ProtocolVersion {
+            p2p: 8,
+            block: 11,
+            app: 0,
+        }),
+        default_node_id: id.to_string(),
+        listen_addr: "tcp://127.0.0.1:26656".to_string(),
+        network: "test-chain".to_string(),
+        version: "0.37.0".to_string(),
+        channels: vec![0x20, 0x30, 0x40],
+        moniker: moniker.to_string(),
+        other: Some(DefaultNodeInfoOther {
+            tx_index: "on".to_string(),
+            rpc_address: "tcp://127.0.0.1:26657".to_string(),
+        }),
+    }
+    .try_into()
+    .unwrap()
+}
+
+fn with_raw(info: &node::Info, f: impl FnOnce(&mut DefaultNodeInfo)) -> node::Info {
+    let mut raw = DefaultNodeInfo::from(info.clone());
+    f(&mut raw);
+    raw.try_into().unwrap()
+}
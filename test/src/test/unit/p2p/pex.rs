@@ -0,0 +1,260 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand_core::{OsRng, RngCore};
+use tendermint::node;
+use tendermint_p2p::{
+    error::ErrorDetail,
+    pex::{AddrBook, BucketType, PeerAddress, PexConfig, PexMessage, PexReactor},
+};
+
+use crate::p2p::{bind, listen_addr};
+
+const NETWORK: &str = "test-chain";
+const CHANNELS: &[u8] = &[0x00];
+
+#[test]
+fn test_message_round_trip() {
+    let request = PexMessage::Request;
+    assert_eq!(PexMessage::decode(&request.encode()).unwrap(), request);
+
+    let addrs = PexMessage::Addrs(vec![peer(1, "1.2.3.4:26656"), peer(2, "[::1]:26656")]);
+    assert_eq!(PexMessage::decode(&addrs.encode()).unwrap(), addrs);
+}
+
+#[test]
+fn test_decode_invalid_message() {
+    assert!(PexMessage::decode(&[0xff, 0xff]).is_err());
+    // A `tendermint.p2p.Message` without content.
+    let err = PexMessage::decode(&[]).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::MalformedPexMessage(_)));
+}
+
+#[test]
+fn test_peer_address_string() {
+    let addr = peer(0xab, "1.2.3.4:26656");
+    let s = addr.to_string();
+    assert_eq!(s, format!("{}@1.2.3.4:26656", node::Id::new([0xab; 20])));
+    assert_eq!(s.parse::<PeerAddress>().unwrap(), addr);
+
+    for invalid in ["1.2.3.4:26656", "abcd@1.2.3.4:26656", "@", ""] {
+        let err = invalid.parse::<PeerAddress>().unwrap_err();
+        assert!(matches!(err.detail(), ErrorDetail::InvalidPeerAddress(_)));
+    }
+}
+
+#[test]
+fn test_addr_book_add_and_mark() {
+    let mut book = AddrBook::new();
+    let src = peer(1, "1.2.3.4:26656");
+    let addr = peer(2, "5.6.7.8:26656");
+    book.add_our_id(node::Id::new([3; 20]));
+
+    assert!(book.add_address(addr, src));
+    assert!(!book.add_address(addr, src));
+    assert!(!book.add_address(peer(3, "9.9.9.9:26656"), src));
+    assert_eq!(book.len(), 1);
+    assert_eq!(book.new_count(), 1);
+    assert_eq!(book.pick_address(100), Some(addr));
+
+    book.mark_attempt(&addr.id);
+    book.mark_good(&addr.id);
+    let known = book.get(&addr.id).unwrap();
+    assert_eq!(known.bucket_type, BucketType::Tried);
+    assert_eq!(known.attempts, 0);
+    assert!(known.last_success.is_some());
+    assert_eq!((book.new_count(), book.tried_count()), (0, 1));
+    assert_eq!(book.pick_address(100), Some(addr));
+
+    book.remove(&addr.id);
+    assert!(book.is_empty());
+    assert_eq!(book.pick_address(0), None);
+}
+
+#[test]
+fn test_addr_book_drops_unreachable_address() {
+    let mut book = AddrBook::new();
+    let addr = peer(2, "5.6.7.8:26656");
+    book.add_address(addr, peer(1, "1.2.3.4:26656"));
+    for _ in 0..3 {
+        assert!(book.get(&addr.id).is_some());
+        book.mark_attempt(&addr.id);
+    }
+    assert!(book.get(&addr.id).is_none());
+}
+
+#[test]
+fn test_addr_book_selection() {
+    let mut book = AddrBook::new();
+    let src = peer(0, "1.2.3.4:26656");
+    for i in 1..=100 {
+        let addr = format!("{}.{}.1.1:26656", i, 200 - i);
+        book.add_address(peer(i, &addr), src);
+    }
+    assert_eq!(book.len(), 100);
+
+    let selection = book.selection(250);
+    assert_eq!(selection.len(), 32);
+    assert!(selection.iter().all(|addr| book.get(&addr.id).is_some()));
+    assert_eq!(book.selection(10).len(), 10);
+}
+
+#[test]
+fn test_addr_book_persistence() {
+    let path = std::env::temp_dir().join(format!("addrbook-{}.json", OsRng.next_u64()));
+    assert!(AddrBook::load(&path).unwrap().is_empty());
+
+    let mut book = AddrBook::new();
+    let (tried, new) = (peer(1, "1.2.3.4:26656"), peer(2, "5.6.7.8:26656"));
+    book.add_address(tried, tried);
+    book.add_address(new, tried);
+    book.mark_good(&tried.id);
+    book.save(&path).unwrap();
+
+    let loaded = AddrBook::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded.get(&tried.id), book.get(&tried.id));
+    assert_eq!(loaded.get(&new.id), book.get(&new.id));
+    assert_eq!((loaded.new_count(), loaded.tried_count()), (1, 1));
+}
+
+#[test]
+fn test_addr_book_load_skips_duplicate_ids() {
+    let path = std::env::temp_dir().join(format!("addrbook-{}.json", OsRng.next_u64()));
+    let mut book = AddrBook::new();
+    let addr = peer(1, "1.2.3.4:26656");
+    book.add_address(addr, addr);
+    let new = saved_addrs(&book, &path);
+    book.mark_good(&addr.id);
+    let tried = saved_addrs(&book, &path);
+
+    // The same identifier appears in both a new and a tried bucket.
+    let json = std::fs::read_to_string(&path).unwrap();
+    let json = json.replacen(&tried, &format!("{new},{tried}"), 1);
+    std::fs::write(&path, json).unwrap();
+    let loaded = AddrBook::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded.get(&addr.id).unwrap().bucket_type, BucketType::New);
+    assert_eq!((loaded.new_count(), loaded.tried_count()), (1, 0));
+}
+
+#[test]
+fn test_request_rate_limit() {
+    let reactor = PexReactor::new(AddrBook::new(), PexConfig::default());
+    let remote = peer(1, "1.2.3.4:26656");
+    let request = PexMessage::Request.encode();
+
+    let response = reactor.receive(&remote, &request).unwrap().unwrap();
+    assert_eq!(
+        PexMessage::decode(&response).unwrap(),
+        PexMessage::Addrs(vec![])
+    );
+    let err = reactor.receive(&remote, &request).unwrap_err();
+    assert!(matches!(
+        err.detail(),
+        ErrorDetail::PexRequestTooFrequent(_)
+    ));
+
+    // Other peers are not affected.
+    assert!(reactor.receive(&peer(2, "1.2.3.5:26656"), &request).is_ok());
+}
+
+#[test]
+fn test_receive_addrs() {
+    let config = PexConfig {
+        max_addrs_per_message: 2,
+        ..PexConfig::default()
+    };
+    let reactor = PexReactor::new(AddrBook::new(), config);
+    let remote = peer(1, "1.2.3.4:26656");
+    let addrs = vec![peer(2, "5.6.7.8:26656"), peer(3, "9.10.11.12:26656")];
+    let msg = PexMessage::Addrs(addrs.clone()).encode();
+
+    let err = reactor.receive(&remote, &msg).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::UnsolicitedPexAddrs(_)));
+
+    let _ = reactor.request(&remote.id);
+    assert!(reactor.receive(&remote, &msg).unwrap().is_none());
+    for addr in &addrs {
+        assert_eq!(reactor.addr_book().get(&addr.id).unwrap().src, remote);
+    }
+    // Only one response per request.
+    assert!(reactor.receive(&remote, &msg).is_err());
+
+    let _ = reactor.request(&remote.id);
+    let too_many = PexMessage::Addrs(vec![peer(4, "1.1.1.1:1"); 3]).encode();
+    let err = reactor.receive(&remote, &too_many).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::TooManyPexAddrs(_)));
+}
+
+#[test]
+fn test_exchange_addrs_over_transport() {
+    let (endpoint1, _incoming1, id1) = bind("node1", NETWORK, CHANNELS);
+    let (endpoint2, mut incoming2, id2) = bind("node2", NETWORK, CHANNELS);
+
+    // The first node only knows about the second one.
+    let mut book1 = AddrBook::new();
+    book1.add_our_id(id1);
+    let addr2 = PeerAddress {
+        id: id2,
+        addr: listen_addr(&endpoint2),
+    };
+    book1.add_address(addr2, addr2);
+    let reactor1 = PexReactor::new(book1, PexConfig::default());
+
+    // The second node knows about a few others.
+    let mut book2 = AddrBook::new();
+    book2.add_our_id(id2);
+    let others = [
+        peer(0x11, "1.2.3.4:26656"),
+        peer(0x12, "5.6.7.8:26656"),
+        peer(0x13, "9.10.11.12:26656"),
+    ];
+    for addr in others {
+        book2.add_address(addr, addr);
+    }
+    let reactor2 = PexReactor::new(book2, PexConfig::default());
+
+    let conns1 = reactor1.ensure_peers(&endpoint1, &[], 1);
+    assert_eq!(conns1.len(), 1);
+    assert_eq!(
+        reactor1.addr_book().get(&id2).unwrap().bucket_type,
+        BucketType::Tried
+    );
+    assert!(reactor1.dial_candidates(1, &[id2]).is_empty());
+    let conn2 = incoming2.next().unwrap().unwrap();
+
+    let _handle1 = reactor1.serve(&conns1[0]).unwrap();
+    let _handle2 = reactor2.serve(&conn2).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while reactor1.addr_book().len() < 4 {
+        assert!(Instant::now() < deadline, "addresses not received in time");
+        thread::sleep(Duration::from_millis(10));
+    }
+    for addr in others {
+        assert_eq!(reactor1.addr_book().get(&addr.id).unwrap().src.id, id2);
+    }
+    // The first node advertised its address to the second one.
+    assert!(reactor2.addr_book().get(&id1).is_some());
+}
+
+/// Saves the book to the given file, returning the JSON of its addresses.
+fn saved_addrs(book: &AddrBook, path: &std::path::Path) -> String {
+    book.save(path).unwrap();
+    let json = std::fs::read_to_string(path).unwrap();
+    let start = json.find('[').unwrap() + 1;
+    let end = json.rfind(']').unwrap();
+    json[start..end].to_string()
+}
+
+fn peer(id: u8, addr: &str) -> PeerAddress {
+    PeerAddress {
+        id: node::Id::new([id; 20]),
+        addr: addr.parse().unwrap(),
+    }
+}
@@ -0,0 +1,499 @@
+use std::{net::TcpListener, path::PathBuf, thread, time::Duration};
+
+use rand_core::{OsRng, RngCore};
+use tendermint::{
+    block, chain,
+    private_key::{self, PrivateKey},
+    proposal::{self, Proposal, SignProposalRequest},
+    public_key::{PubKeyRequest, PubKeyResponse, PublicKey},
+    vote::{self, SignVoteRequest, SignedVoteResponse, Vote},
+    Hash, Signature, Time,
+};
+use tendermint_config::{net, PrivValidatorState};
+use tendermint_p2p::{
+    error::ErrorDetail,
+    privval::{Request, Response, Signer, SignerClient, SignerConnection, SignerListener},
+    secret_connection::{self, SecretConnection, Version},
+};
+
+const CHAIN_ID: &str = "test-chain";
+
+#[test]
+fn test_pub_key() {
+    let (key, public_key) = private_key();
+    let mut signer = new_signer(&key, state_file());
+
+    match signer.handle(Request::PubKey(PubKeyRequest {
+        chain_id: chain_id(),
+    })) {
+        Response::PubKey(res) => {
+            assert_eq!(res.pub_key.unwrap().to_bytes(), public_key.as_bytes());
+            assert!(res.error.is_none());
+        },
+        res => panic!("unexpected response: {res:?}"),
+    }
+    match signer.handle(Request::PubKey(PubKeyRequest {
+        chain_id: "other-chain".parse().unwrap(),
+    })) {
+        Response::PubKey(res) => {
+            assert!(res.pub_key.is_none());
+            assert!(res.error.is_some());
+        },
+        res => panic!("unexpected response: {res:?}"),
+    }
+    assert_eq!(signer.handle(Request::Ping), Response::Ping);
+}
+
+#[test]
+fn test_sign_vote() {
+    let (key, public_key) = private_key();
+    let state_file = state_file();
+    let mut signer = new_signer(&key, state_file.clone());
+
+    let vote = vote(10, 1, 0);
+    let signed = sign_vote(&mut signer, vote.clone()).unwrap();
+    let signature = signed.signature.clone().unwrap();
+    verify(
+        &public_key,
+        &vote.to_signable_vec(chain_id()).unwrap(),
+        &signature,
+    );
+    assert_eq!(signed.timestamp, vote.timestamp);
+
+    let state = PrivValidatorState::load_json_file(&state_file).unwrap();
+    assert_eq!(&state, signer.state());
+    assert_eq!(state.height, block::Height::from(10_u32));
+    assert_eq!(state.round, block::Round::from(1_u16));
+    assert_eq!(state.step, PrivValidatorState::STEP_PRECOMMIT);
+    assert_eq!(state.signature, signature.as_bytes());
+    std::fs::remove_file(state_file).unwrap();
+}
+
+#[test]
+fn test_sign_same_vote_again() {
+    let (key, _) = private_key();
+    let state_file = state_file();
+    let mut signer = new_signer(&key, state_file.clone());
+
+    let first = sign_vote(&mut signer, vote(10, 1, 0)).unwrap();
+    let again = sign_vote(&mut signer, vote(10, 1, 0)).unwrap();
+    assert_eq!(again, first);
+
+    // Only the timestamp differs: the vote is signed with the first one.
+    let later = sign_vote(&mut signer, vote(10, 1, 5)).unwrap();
+    assert_eq!(later, first);
+    std::fs::remove_file(state_file).unwrap();
+}
+
+#[test]
+fn test_refuse_double_sign() {
+    let (key, _) = private_key();
+    let state_file = state_file();
+    let mut signer = new_signer(&key, state_file.clone());
+    sign_vote(&mut signer, vote(10, 1, 0)).unwrap();
+
+    // Another block at the same height, round and step.
+    let mut conflicting = vote(10, 1, 0);
+    conflicting.block_id = None;
+    let err = sign_vote(&mut signer, conflicting).unwrap_err();
+    assert!(err.contains("conflicting"), "{err}");
+
+    // Lower round, or lower step.
+    assert!(sign_vote(&mut signer, vote(10, 0, 0)).is_err());
+    let mut prevote = vote(10, 1, 0);
+    prevote.vote_type = vote::Type::Prevote;
+    assert!(sign_vote(&mut signer, prevote).is_err());
+
+    // The protection holds across restarts.
+    drop(signer);
+    let mut signer = new_signer(&key, state_file.clone());
+    assert!(sign_vote(&mut signer, vote(9, 0, 0)).is_err());
+    assert!(sign_vote(&mut signer, vote(11, 0, 0)).is_ok());
+    std::fs::remove_file(state_file).unwrap();
+}
+
+#[test]
+fn test_sign_proposal() {
+    let (key, public_key) = private_key();
+    let state_file = state_file();
+    let mut signer = new_signer(&key, state_file.clone());
+
+    let proposal = proposal(10, 0);
+    let req = SignProposalRequest {
+        proposal: proposal.clone(),
+        chain_id: chain_id(),
+    };
+    let signed = match signer.handle(Request::SignProposal(req.clone())) {
+        Response::SignedProposal(res) => res.proposal.unwrap(),
+        res => panic!("unexpected response: {res:?}"),
+    };
+    verify(
+        &public_key,
+        &proposal.to_signable_vec(chain_id()).unwrap(),
+        &signed.signature.unwrap(),
+    );
+
+    // Votes of the same round come after the proposal.
+    assert!(sign_vote(&mut signer, vote(10, 0, 0)).is_ok());
+    match signer.handle(Request::SignProposal(req)) {
+        Response::SignedProposal(res) => assert!(res.error.is_some()),
+        res => panic!("unexpected response: {res:?}"),
+    }
+    std::fs::remove_file(state_file).unwrap();
+}
+
+#[test]
+fn test_serve_over_tcp() {
+    let (key, public_key) = private_key();
+    let node_key = ed25519_consensus::SigningKey::new(OsRng);
+    let node_id = secret_connection::PublicKey::from(&node_key).peer_id();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = net::Address::Tcp {
+        peer_id: Some(node_id),
+        host: "127.0.0.1".to_string(),
+        port: listener.local_addr().unwrap().port(),
+    };
+
+    let node = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = SecretConnection::new(stream, node_key, Version::V0_34).unwrap();
+        Request::Ping.write_to(&mut conn).unwrap();
+        assert_eq!(Response::read_from(&mut conn).unwrap(), Response::Ping);
+        Request::PubKey(PubKeyRequest {
+            chain_id: chain_id(),
+        })
+        .write_to(&mut conn)
+        .unwrap();
+        match Response::read_from(&mut conn).unwrap() {
+            Response::PubKey(res) => res.pub_key.unwrap(),
+            res => panic!("unexpected response: {res:?}"),
+        }
+    });
+
+    let conn = SignerConnection::connect(
+        &address,
+        ed25519_consensus::SigningKey::new(OsRng),
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let state_file = state_file();
+    Signer::new(conn, &key, chain_id(), &state_file)
+        .unwrap()
+        .serve()
+        .unwrap();
+    let node_pub_key = node.join().expect("node thread has panicked");
+    assert_eq!(node_pub_key.to_bytes(), public_key.as_bytes());
+}
+
+#[test]
+fn test_connect_to_unexpected_node() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = net::Address::Tcp {
+        peer_id: Some(tendermint::node::Id::new([0xab; 20])),
+        host: "127.0.0.1".to_string(),
+        port: listener.local_addr().unwrap().port(),
+    };
+    let node = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let key = ed25519_consensus::SigningKey::new(OsRng);
+        let _ = SecretConnection::new(stream, key, Version::V0_34);
+    });
+
+    let err = SignerConnection::connect(
+        &address,
+        ed25519_consensus::SigningKey::new(OsRng),
+        Duration::from_secs(1),
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(err.detail(), ErrorDetail::UnexpectedPeerId(_)));
+    node.join().expect("node thread has panicked");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_serve_over_unix_socket() {
+    use std::os::unix::net::UnixListener;
+
+    let (key, _) = private_key();
+    let path = std::env::temp_dir().join(format!("privval-{}.sock", OsRng.next_u64()));
+    let listener = UnixListener::bind(&path).unwrap();
+    let address = net::Address::Unix {
+        path: path.to_str().unwrap().to_string(),
+    };
+
+    let node = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        Request::SignVote(SignVoteRequest {
+            vote: vote(1, 0, 0),
+            chain_id: chain_id(),
+        })
+        .write_to(&mut stream)
+        .unwrap();
+        Response::read_from(&mut stream).unwrap()
+    });
+
+    let conn = SignerConnection::connect(
+        &address,
+        ed25519_consensus::SigningKey::new(OsRng),
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let state_file = state_file();
+    Signer::new(conn, &key, chain_id(), &state_file)
+        .unwrap()
+        .serve()
+        .unwrap();
+    match node.join().expect("node thread has panicked") {
+        Response::SignedVote(res) => assert!(res.vote.unwrap().signature.is_some()),
+        res => panic!("unexpected response: {res:?}"),
+    }
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(state_file).unwrap();
+}
+
+#[test]
+fn test_client_over_tcp() {
+    let (key, public_key) = private_key();
+    let listener = SignerListener::bind(
+        &"tcp://127.0.0.1:0".parse().unwrap(),
+        ed25519_consensus::SigningKey::new(OsRng),
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let address = listener.local_addr().unwrap();
+    let state_file = state_file();
+    let signer_state_file = state_file.clone();
+    let signer = thread::spawn(move || {
+        let conn = SignerConnection::connect(
+            &address,
+            ed25519_consensus::SigningKey::new(OsRng),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        Signer::new(conn, &key, chain_id(), signer_state_file)
+            .unwrap()
+            .serve()
+            .unwrap();
+    });
+
+    let mut client = SignerClient::new(listener.accept().unwrap(), chain_id()).unwrap();
+    assert_eq!(client.public_key().to_bytes(), public_key.as_bytes());
+    client.ping().unwrap();
+    let signed = client.sign_proposal(&proposal(10, 0)).unwrap();
+    assert!(signed.signature.is_some());
+    let signed = client.sign_vote(&vote(10, 0, 0)).unwrap();
+    verify(
+        &public_key,
+        &vote(10, 0, 0).to_signable_vec(chain_id()).unwrap(),
+        &signed.signature.unwrap(),
+    );
+    let err = client.sign_vote(&vote(9, 0, 0)).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::RemoteSigner(_)));
+
+    drop(client);
+    signer.join().expect("signer thread has panicked");
+    std::fs::remove_file(state_file).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_client_conformance() {
+    let (key, _) = private_key();
+    let path = std::env::temp_dir().join(format!("privval-{}.sock", OsRng.next_u64()));
+    let address = net::Address::Unix {
+        path: path.to_str().unwrap().to_string(),
+    };
+    let listener = SignerListener::bind(
+        &address,
+        ed25519_consensus::SigningKey::new(OsRng),
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let state_file = state_file();
+    let signer_state_file = state_file.clone();
+    let signer = thread::spawn(move || {
+        let conn = SignerConnection::connect(
+            &address,
+            ed25519_consensus::SigningKey::new(OsRng),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        Signer::new(conn, &key, chain_id(), signer_state_file)
+            .unwrap()
+            .serve()
+            .unwrap();
+    });
+
+    let mut client = SignerClient::new(listener.accept().unwrap(), chain_id()).unwrap();
+    client.check_conformance(100_u32.into()).unwrap();
+
+    drop(client);
+    signer.join().expect("signer thread has panicked");
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(state_file).unwrap();
+}
+
+#[test]
+fn test_client_rejects_bad_signatures() {
+    let signing_key = ed25519_consensus::SigningKey::new(OsRng);
+    let public_key = PublicKey::from_raw_ed25519(signing_key.verification_key().as_bytes());
+    let (mut client, signer) = fake_signer(move |request| match request {
+        Request::PubKey(_) => Response::PubKey(PubKeyResponse {
+            pub_key: public_key,
+            error: None,
+        }),
+        Request::SignVote(req) => {
+            let mut vote = req.vote;
+            let signature = if vote.height.value() == 1 {
+                // Signs another chain.
+                signing_key.sign(
+                    &vote
+                        .to_signable_vec("other-chain".parse().unwrap())
+                        .unwrap(),
+                )
+            } else {
+                // Signs another round.
+                vote.round = vote.round.increment();
+                signing_key.sign(&vote.to_signable_vec(req.chain_id).unwrap())
+            };
+            vote.signature = Some(signature.into());
+            Response::SignedVote(SignedVoteResponse {
+                vote: Some(vote),
+                error: None,
+            })
+        },
+        request => panic!("unexpected request: {request:?}"),
+    });
+
+    assert_eq!(Some(client.public_key()), public_key);
+    let err = client.sign_vote(&vote(1, 0, 0)).unwrap_err();
+    assert!(matches!(
+        err.detail(),
+        ErrorDetail::InvalidSignerSignature(_)
+    ));
+    let err = client.sign_vote(&vote(2, 0, 0)).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::SignedDataMismatch(_)));
+
+    drop(client);
+    signer.join().expect("signer thread has panicked");
+}
+
+#[test]
+fn test_client_detects_non_conformant_signer() {
+    let (key, _) = private_key();
+    let (mut client, signer) = fake_signer(move |request| match request {
+        // Ignores the chain ID.
+        Request::PubKey(_) => Response::PubKey(PubKeyResponse {
+            pub_key: Some(key.public_key()),
+            error: None,
+        }),
+        Request::Ping => Response::Ping,
+        request => panic!("unexpected request: {request:?}"),
+    });
+
+    let err = client.check_conformance(1_u32.into()).unwrap_err();
+    assert!(matches!(err.detail(), ErrorDetail::NonConformantSigner(_)));
+
+    drop(client);
+    signer.join().expect("signer thread has panicked");
+}
+
+/// Spawns a signer answering requests with the given function, and returns
+/// a client connected to it.
+fn fake_signer(
+    answer: impl Fn(Request) -> Response + Send + 'static,
+) -> (SignerClient<SignerConnection>, thread::JoinHandle<()>) {
+    let listener = SignerListener::bind(
+        &"tcp://127.0.0.1:0".parse().unwrap(),
+        ed25519_consensus::SigningKey::new(OsRng),
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let address = listener.local_addr().unwrap();
+    let signer = thread::spawn(move || {
+        let mut conn = SignerConnection::connect(
+            &address,
+            ed25519_consensus::SigningKey::new(OsRng),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        while let Some(request) = Request::read_from(&mut conn).unwrap() {
+            answer(request).write_to(&mut conn).unwrap();
+        }
+    });
+    let client = SignerClient::new(listener.accept().unwrap(), chain_id()).unwrap();
+    (client, signer)
+}
+
+fn new_signer(key: &PrivateKey, state_file: PathBuf) -> Signer<std::io::Cursor<Vec<u8>>> {
+    Signer::new(std::io::Cursor::new(vec![]), key, chain_id(), state_file).unwrap()
+}
+
+fn sign_vote<C>(signer: &mut Signer<C>, vote: Vote) -> Result<Vote, String>
+where
+    C: std::io::Read + std::io::Write,
+{
+    match signer.handle(Request::SignVote(SignVoteRequest {
+        vote,
+        chain_id: chain_id(),
+    })) {
+        Response::SignedVote(res) => match (res.vote, res.error) {
+            (Some(vote), None) => Ok(vote),
+            (_, Some(error)) => Err(error.description),
+            (None, None) => panic!("empty response"),
+        },
+        res => panic!("unexpected response: {res:?}"),
+    }
+}
+
+fn vote(height: u32, round: u16, seconds: i64) -> Vote {
+    Vote {
+        vote_type: vote::Type::Precommit,
+        height: height.into(),
+        round: round.into(),
+        block_id: Some(block_id()),
+        timestamp: Some(Time::from_unix_timestamp(1_600_000_000 + seconds, 0).unwrap()),
+        signature: None,
+        ..Vote::default()
+    }
+}
+
+fn proposal(height: u32, round: u16) -> Proposal {
+    Proposal {
+        msg_type: proposal::Type::Proposal,
+        height: height.into(),
+        round: round.into(),
+        pol_round: None,
+        block_id: Some(block_id()),
+        timestamp: Some(Time::from_unix_timestamp(1_600_000_000, 0).unwrap()),
+        signature: None,
+    }
+}
+
+fn block_id() -> block::Id {
+    block::Id {
+        hash: Hash::Sha256([1; 32]),
+        part_set_header: block::parts::Header::new(1, Hash::Sha256([2; 32])).unwrap(),
+    }
+}
+
+fn verify(public_key: &ed25519_consensus::VerificationKey, msg: &[u8], signature: &Signature) {
+    let signature = ed25519_consensus::Signature::try_from(signature.as_bytes()).unwrap();
+    public_key.verify(&signature, msg).unwrap();
+}
+
+fn private_key() -> (PrivateKey, ed25519_consensus::VerificationKey) {
+    let key = ed25519_consensus::SigningKey::new(OsRng);
+    let private_key =
+        PrivateKey::Ed25519(private_key::Ed25519::try_from(key.as_bytes().as_slice()).unwrap());
+    (private_key, key.verification_key())
+}
+
+fn state_file() -> PathBuf {
+    std::env::temp_dir().join(format!("priv_validator_state-{}.json", OsRng.next_u64()))
+}
+
+fn chain_id() -> chain::Id {
+    CHAIN_ID.parse().unwrap()
+}
@@ -0,0 +1,128 @@
+use rand_core::OsRng;
+use tendermint::node;
+use tendermint_p2p::{
+    error::{Error, ErrorDetail},
+    secret_connection,
+    transport::{
+        tcp::{TcpConfig, TcpTransport},
+        ConnectInfo, Connection, Direction, Endpoint, StreamId, StreamSend, Transport,
+    },
+};
+
+use crate::p2p::{advertise_addr, bind, bind_info, listen_addr, node_info};
+
+const NETWORK: &str = "test-chain";
+const CHANNELS: &[u8] = &[0x00];
+
+#[test]
+fn test_connect_and_accept() {
+    let (endpoint1, _incoming1, id1) = bind("node1", NETWORK, CHANNELS);
+    let (endpoint2, mut incoming2, id2) = bind("node2", NETWORK, CHANNELS);
+
+    let conn1 = endpoint1
+        .connect(ConnectInfo {
+            addrs: listen_addr(&endpoint2),
+            id: id2,
+        })
+        .unwrap();
+    let conn2 = incoming2.next().unwrap().unwrap();
+    assert!(matches!(conn1, Direction::Outgoing(_)));
+    assert!(matches!(conn2, Direction::Incoming(_)));
+
+    assert_eq!(conn1.inner().node_info().moniker.to_string(), "node2");
+    assert_eq!(conn2.inner().node_info().moniker.to_string(), "node1");
+    assert_eq!(peer_id(&conn2), id1);
+    assert_eq!(conn1.remote_addr(), conn2.local_addr());
+    assert_eq!(conn1.local_addr(), conn2.remote_addr());
+    assert_eq!(conn2.advertised_addrs(), vec![advertise_addr("node1")]);
+
+    let (mut read1, send1) = conn1.open_bidirectional(StreamId::Pex).unwrap();
+    let (mut read2, send2) = conn2.open_bidirectional(StreamId::Pex).unwrap();
+    send1.send(b"ping").unwrap();
+    assert_eq!(read2.next().unwrap().unwrap(), b"ping");
+    send2.send(vec![7; 10_000]).unwrap();
+    assert_eq!(read1.next().unwrap().unwrap(), vec![7; 10_000]);
+
+    conn1.close().unwrap();
+    assert!(read2.next().is_none());
+    assert!(send1.send(b"ping").is_err());
+    assert!(conn1.open_bidirectional(StreamId::Pex).is_err());
+}
+
+#[test]
+fn test_connect_to_unexpected_peer() {
+    let (endpoint1, _incoming1, _) = bind("node1", NETWORK, CHANNELS);
+    let (endpoint2, _incoming2, _) = bind("node2", NETWORK, CHANNELS);
+
+    let err = endpoint1
+        .connect(ConnectInfo {
+            addrs: listen_addr(&endpoint2),
+            id: node::Id::new([0xab; 20]),
+        })
+        .err()
+        .unwrap();
+    let err = err.downcast_ref::<Error>().unwrap();
+    assert!(matches!(err.detail(), ErrorDetail::UnexpectedPeerId(_)));
+}
+
+#[test]
+fn test_incompatible_peer() {
+    let (endpoint1, _incoming1, _) = bind("node1", NETWORK, CHANNELS);
+    let (endpoint2, mut incoming2, id) = bind("node2", "other-chain", CHANNELS);
+
+    let err = endpoint1
+        .connect(ConnectInfo {
+            addrs: listen_addr(&endpoint2),
+            id,
+        })
+        .err()
+        .unwrap();
+    let err = err.downcast_ref::<Error>().unwrap();
+    assert!(matches!(err.detail(), ErrorDetail::NetworkMismatch(_)));
+    assert!(incoming2.next().unwrap().is_err());
+}
+
+#[test]
+fn test_connect_tries_remaining_addrs() {
+    let (endpoint1, _incoming1, _) = bind("node1", NETWORK, CHANNELS);
+    let (other_chain, _incoming2, _) = bind("node2", "other-chain", CHANNELS);
+    let (endpoint3, mut incoming3, id3) = bind("node3", NETWORK, CHANNELS);
+
+    // The first address fails the handshake, the second one succeeds.
+    let addrs = [listen_addr(&other_chain), listen_addr(&endpoint3)];
+    let conn1 = endpoint1
+        .connect(ConnectInfo {
+            addrs: &addrs[..],
+            id: id3,
+        })
+        .unwrap();
+    let conn3 = incoming3.next().unwrap().unwrap();
+    assert_eq!(conn1.remote_addr(), conn3.local_addr());
+}
+
+#[test]
+fn test_bind_with_other_key() {
+    let key = ed25519_consensus::SigningKey::new(OsRng);
+    let other_key = ed25519_consensus::SigningKey::new(OsRng);
+    let res = TcpTransport::new(
+        key,
+        node_info("node", NETWORK, CHANNELS),
+        TcpConfig::default(),
+    )
+    .bind(bind_info(&other_key, "node"));
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_dropping_endpoint_ends_incoming() {
+    let (endpoint, mut incoming, _) = bind("node", NETWORK, CHANNELS);
+    drop(endpoint);
+    assert!(incoming.next().is_none());
+}
+
+fn peer_id<C: Connection>(conn: &C) -> node::Id {
+    let public_key = conn.public_key().ed25519().unwrap();
+    secret_connection::PublicKey::from_raw_ed25519(public_key.as_bytes())
+        .unwrap()
+        .peer_id()
+}
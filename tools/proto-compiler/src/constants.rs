@@ -97,6 +97,17 @@ pub static CUSTOM_TYPE_ATTRIBUTES: &[(&str, &str)] = &[
     (".tendermint.types.BlockMeta", SERIALIZED),
     (".tendermint.types.TxProof", SERIALIZED),
     (".tendermint.crypto.Proof", SERIALIZED),
+    (".tendermint.types.Proposal", SERIALIZED),
+    (".tendermint.types.Part", SERIALIZED),
+    (".tendermint.consensus.NewRoundStep", SERIALIZED),
+    (".tendermint.consensus.NewValidBlock", SERIALIZED),
+    (".tendermint.consensus.Proposal", SERIALIZED),
+    (".tendermint.consensus.ProposalPOL", SERIALIZED),
+    (".tendermint.consensus.BlockPart", SERIALIZED),
+    (".tendermint.consensus.Vote", SERIALIZED),
+    (".tendermint.consensus.HasVote", SERIALIZED),
+    (".tendermint.consensus.VoteSetMaj23", SERIALIZED),
+    (".tendermint.consensus.VoteSetBits", SERIALIZED),
 ];
 
 /// Custom field attributes applied on top of protobuf fields in (a) struct(s)
@@ -202,4 +213,19 @@ pub static CUSTOM_FIELD_ATTRIBUTES: &[(&str, &str)] = &[
     (".tendermint.crypto.Proof.total", QUOTED),
     (".tendermint.crypto.Proof.aunts", VEC_BASE64STRING),
     (".tendermint.crypto.Proof.leaf_hash", BASE64STRING),
+    (".tendermint.types.Proposal.height", QUOTED),
+    (".tendermint.types.Proposal.timestamp", OPTIONAL),
+    (".tendermint.types.Proposal.signature", BASE64STRING),
+    (".tendermint.types.Part.bytes", HEXSTRING),
+    (".tendermint.consensus.NewRoundStep.height", QUOTED),
+    (
+        ".tendermint.consensus.NewRoundStep.seconds_since_start_time",
+        QUOTED,
+    ),
+    (".tendermint.consensus.NewValidBlock.height", QUOTED),
+    (".tendermint.consensus.ProposalPOL.height", QUOTED),
+    (".tendermint.consensus.BlockPart.height", QUOTED),
+    (".tendermint.consensus.HasVote.height", QUOTED),
+    (".tendermint.consensus.VoteSetMaj23.height", QUOTED),
+    (".tendermint.consensus.VoteSetBits.height", QUOTED),
 ];